@@ -0,0 +1,184 @@
+//! Conversion of chunky pixel data between color models and sample byte orders.
+//!
+//! The functions operate on flat byte slices holding whole pixels, regardless of
+//! where rows start, so they can be applied to any chunk of the content read from
+//! a decoder before writing it to an encoder. The color model conversions expect
+//! colors of 8 bits, and both slices must hold the same number of pixels, otherwise
+//! they panic.
+//!
+//! [`unpack_bits`] is the exception, as packed pixels of less than 8 bits are only
+//! aligned to bytes at the start of each line, so it operates on whole lines.
+
+/// Converts RGB pixels to CMYK pixels, using the darkest possible black.
+pub fn rgb_to_cmyk(src: &[u8], dst: &mut [u8]) {
+    convert(src, dst, |src: &[u8; 3], dst: &mut [u8; 4]| {
+        *dst = rgb_pixel_to_cmyk(*src)
+    });
+}
+
+/// Converts CMYK pixels to RGB pixels.
+pub fn cmyk_to_rgb(src: &[u8], dst: &mut [u8]) {
+    convert(src, dst, |src: &[u8; 4], dst: &mut [u8; 3]| {
+        *dst = cmyk_pixel_to_rgb(*src)
+    });
+}
+
+/// Converts gray pixels to RGB pixels.
+pub fn gray_to_rgb(src: &[u8], dst: &mut [u8]) {
+    convert(src, dst, |src: &[u8; 1], dst: &mut [u8; 3]| {
+        *dst = [src[0]; 3]
+    });
+}
+
+/// Converts RGB pixels to gray pixels, weighting the channels by their luminance.
+pub fn rgb_to_gray(src: &[u8], dst: &mut [u8]) {
+    convert(src, dst, |src: &[u8; 3], dst: &mut [u8; 1]| {
+        dst[0] = luminance(*src)
+    });
+}
+
+fn convert<const N: usize, const M: usize>(
+    src: &[u8],
+    dst: &mut [u8],
+    f: impl Fn(&[u8; N], &mut [u8; M]),
+) {
+    assert!(
+        src.len().is_multiple_of(N)
+            && dst.len().is_multiple_of(M)
+            && src.len() / N == dst.len() / M,
+        "source and destination must hold the same number of whole pixels"
+    );
+    for (src, dst) in src.chunks_exact(N).zip(dst.chunks_exact_mut(M)) {
+        f(src.try_into().unwrap(), dst.try_into().unwrap());
+    }
+}
+
+/// Swaps the bytes of each 16-bit sample, e.g. to convert the colors of 16 bits read
+/// from a stream of one byte order to the other. A trailing odd byte is left untouched.
+pub fn swap_samples_16(buf: &mut [u8]) {
+    for sample in buf.chunks_exact_mut(2) {
+        sample.swap(0, 1);
+    }
+}
+
+/// Expands packed samples of 1, 2 or 4 bits to a byte each, appending them to `out`,
+/// e.g. to hand 1-bit gray pixels to a crate working on whole bytes.
+///
+/// `src` holds whole lines of `width` samples, each line starting on a byte boundary,
+/// i.e. lines of `(width * bits_per_pixel).div_ceil(8)` bytes as in a packed CUPS raster
+/// line. The padding bits at the end of each line are skipped. Samples are stored from
+/// the most significant bit and keep their value, e.g. 0 or 1 for 1 bit. A
+/// `bits_per_pixel` of 8 copies the lines as they are.
+///
+/// Panics if `bits_per_pixel` is not 1, 2, 4 or 8, or if `src` doesn't hold whole lines.
+pub fn unpack_bits(src: &[u8], bits_per_pixel: u8, width: u32, out: &mut Vec<u8>) {
+    assert!(
+        matches!(bits_per_pixel, 1 | 2 | 4 | 8),
+        "bits_per_pixel must be 1, 2, 4 or 8"
+    );
+    let bytes_per_line = (width as usize * bits_per_pixel as usize).div_ceil(8);
+    if bytes_per_line == 0 {
+        return;
+    }
+    assert!(
+        src.len().is_multiple_of(bytes_per_line),
+        "source must hold whole lines"
+    );
+    let samples_per_byte = 8 / bits_per_pixel as usize;
+    let mask = ((1u16 << bits_per_pixel) - 1) as u8;
+    out.reserve(src.len() / bytes_per_line * width as usize);
+    for line in src.chunks_exact(bytes_per_line) {
+        let samples = line.iter().flat_map(|&byte| {
+            (0..samples_per_byte).map(move |i| {
+                let shift = 8 - bits_per_pixel as usize * (i + 1);
+                (byte >> shift) & mask
+            })
+        });
+        out.extend(samples.take(width as usize));
+    }
+}
+
+pub(crate) fn luminance([r, g, b]: [u8; 3]) -> u8 {
+    ((r as u32 * 77 + g as u32 * 150 + b as u32 * 29 + 128) >> 8) as u8
+}
+
+pub(crate) fn cmyk_pixel_to_rgb([c, m, y, k]: [u8; 4]) -> [u8; 3] {
+    let k = !k as u32;
+    let f = |c: u8| ((!c as u32 * k + 127) / 255) as u8;
+    [f(c), f(m), f(y)]
+}
+
+pub(crate) fn rgb_pixel_to_cmyk(rgb: [u8; 3]) -> [u8; 4] {
+    let [r, g, b] = rgb;
+    let k = !r.max(g).max(b);
+    let w = !k as u32;
+    let f = |c: u8| ((w - c as u32) * 255).checked_div(w).unwrap_or(0) as u8;
+    [f(r), f(g), f(b), k]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cmyk_to_rgb, gray_to_rgb, rgb_to_cmyk, rgb_to_gray, swap_samples_16, unpack_bits};
+
+    #[test]
+    fn test_convert() {
+        let rgb = [
+            0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0x80, 0x40, 0x00,
+        ];
+        let mut cmyk = [0; 16];
+        rgb_to_cmyk(&rgb, &mut cmyk);
+        assert_eq!(
+            cmyk,
+            [0, 0, 0, 0, 0, 0, 0, 0xff, 0, 0xff, 0xff, 0, 0, 0x7f, 0xff, 0x7f]
+        );
+        let mut back = [0; 12];
+        cmyk_to_rgb(&cmyk, &mut back);
+        assert_eq!(back, rgb);
+
+        let mut gray = [0; 4];
+        rgb_to_gray(&rgb, &mut gray);
+        assert_eq!(gray, [255, 0, 77, 76]);
+        let mut rgb = [0; 12];
+        gray_to_rgb(&gray, &mut rgb);
+        assert_eq!(rgb[9..], [76; 3]);
+    }
+
+    #[test]
+    fn test_swap_samples_16() {
+        let mut buf = [0x12, 0x34, 0xab, 0xcd, 0xff];
+        swap_samples_16(&mut buf);
+        assert_eq!(buf, [0x34, 0x12, 0xcd, 0xab, 0xff]);
+    }
+
+    #[test]
+    fn test_unpack_bits() {
+        // lines of 5 pixels, the padding bits are set to check they are skipped
+        let mut out = Vec::new();
+        unpack_bits(&[0b1010_0111, 0b1011_1111], 1, 5, &mut out);
+        assert_eq!(out, [1, 0, 1, 0, 0, 1, 0, 1, 1, 1]);
+
+        let mut out = vec![9];
+        unpack_bits(&[0b0001_1011, 0b1101_1111], 2, 5, &mut out);
+        assert_eq!(out, [9, 0, 1, 2, 3, 3]);
+
+        let mut out = Vec::new();
+        unpack_bits(&[0x12, 0x3f, 0x45, 0x6f], 4, 3, &mut out);
+        assert_eq!(out, [1, 2, 3, 4, 5, 6]);
+
+        let mut out = Vec::new();
+        unpack_bits(&[7, 8, 9], 8, 3, &mut out);
+        assert_eq!(out, [7, 8, 9]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_unpack_bits_partial_line() {
+        unpack_bits(&[0; 3], 4, 3, &mut Vec::new());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_convert_length_mismatch() {
+        rgb_to_cmyk(&[0; 6], &mut [0; 4]);
+    }
+}
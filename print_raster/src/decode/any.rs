@@ -0,0 +1,169 @@
+use super::{CompressedRasterDecoder, CupsRasterUnifiedDecoder, UncompressedRasterDecoder};
+use crate::decode::RasterDecoder;
+use derive_more::From;
+use futures::AsyncRead;
+use pin_project::pin_project;
+use std::{ops::DerefMut, pin::Pin};
+
+/// A decoder of the page content of either a CUPS raster or a URF stream,
+/// for consumers that don't depend on the format.
+///
+/// A bare [`CompressedRasterDecoder`] converts to the [`Urf`](Self::Urf) variant and a
+/// bare [`UncompressedRasterDecoder`] to the [`Cups`](Self::Cups) one, as URF content
+/// is always compressed.
+#[pin_project(project = AnyRasterDecoderProj)]
+#[derive(From)]
+pub enum AnyRasterDecoder<R> {
+    Cups(#[pin] CupsRasterUnifiedDecoder<R>),
+    Urf(#[pin] CompressedRasterDecoder<R>),
+}
+
+impl<R> From<UncompressedRasterDecoder<R>> for AnyRasterDecoder<R> {
+    fn from(decoder: UncompressedRasterDecoder<R>) -> Self {
+        AnyRasterDecoder::Cups(decoder.into())
+    }
+}
+
+impl<R> RasterDecoder<R> for AnyRasterDecoder<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    fn bytes_remaining(&self) -> u64 {
+        match self {
+            AnyRasterDecoder::Cups(decoder) => decoder.bytes_remaining(),
+            AnyRasterDecoder::Urf(decoder) => decoder.bytes_remaining(),
+        }
+    }
+
+    fn bytes_total(&self) -> u64 {
+        match self {
+            AnyRasterDecoder::Cups(decoder) => decoder.bytes_total(),
+            AnyRasterDecoder::Urf(decoder) => decoder.bytes_total(),
+        }
+    }
+
+    fn encoded_bytes_read(&self) -> u64 {
+        match self {
+            AnyRasterDecoder::Cups(decoder) => decoder.encoded_bytes_read(),
+            AnyRasterDecoder::Urf(decoder) => decoder.encoded_bytes_read(),
+        }
+    }
+
+    fn min_encoded_bytes_remaining(&self) -> u64 {
+        match self {
+            AnyRasterDecoder::Cups(decoder) => decoder.min_encoded_bytes_remaining(),
+            AnyRasterDecoder::Urf(decoder) => decoder.min_encoded_bytes_remaining(),
+        }
+    }
+
+    fn poll_skip(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        match self.project() {
+            AnyRasterDecoderProj::Cups(decoder) => decoder.poll_skip(cx),
+            AnyRasterDecoderProj::Urf(decoder) => decoder.poll_skip(cx),
+        }
+    }
+
+    fn poll_peek(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        n: usize,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        match self.project() {
+            AnyRasterDecoderProj::Cups(decoder) => decoder.poll_peek(cx, n),
+            AnyRasterDecoderProj::Urf(decoder) => decoder.poll_peek(cx, n),
+        }
+    }
+
+    fn peeked(&self) -> &[u8] {
+        match self {
+            AnyRasterDecoder::Cups(decoder) => decoder.peeked(),
+            AnyRasterDecoder::Urf(decoder) => decoder.peeked(),
+        }
+    }
+
+    fn clean_page_boundary(&self) -> bool {
+        match self {
+            AnyRasterDecoder::Cups(decoder) => decoder.clean_page_boundary(),
+            AnyRasterDecoder::Urf(decoder) => decoder.clean_page_boundary(),
+        }
+    }
+
+    fn set_fill_byte(&mut self, fill_byte: u8) {
+        match self {
+            AnyRasterDecoder::Cups(decoder) => decoder.set_fill_byte(fill_byte),
+            AnyRasterDecoder::Urf(decoder) => decoder.set_fill_byte(fill_byte),
+        }
+    }
+
+    fn into_pin_mut(self) -> Pin<R> {
+        match self {
+            AnyRasterDecoder::Cups(decoder) => decoder.into_pin_mut(),
+            AnyRasterDecoder::Urf(decoder) => decoder.into_pin_mut(),
+        }
+    }
+}
+
+impl<R> AsyncRead for AnyRasterDecoder<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    fn poll_read(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context,
+        buf: &mut [u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        match self.project() {
+            AnyRasterDecoderProj::Cups(decoder) => decoder.poll_read(cx, buf),
+            AnyRasterDecoderProj::Urf(decoder) => decoder.poll_read(cx, buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AnyRasterDecoder;
+    use crate::decode::{
+        CompressedRasterDecoder, Limits, RasterDecoder, UncompressedRasterDecoder,
+    };
+    use futures::AsyncReadExt;
+    use std::pin::Pin;
+
+    async fn read_all<R>(mut decoder: AnyRasterDecoder<R>) -> Vec<u8>
+    where
+        R: std::ops::DerefMut<Target: futures::AsyncRead>,
+        AnyRasterDecoder<R>: Unpin,
+    {
+        let mut content = Vec::new();
+        decoder.read_to_end(&mut content).await.unwrap();
+        assert_eq!(decoder.bytes_remaining(), 0);
+        content
+    }
+
+    #[tokio::test]
+    async fn test_any_decoder() {
+        let mut reader = futures::io::Cursor::new([1u8, 2, 3, 4]);
+        let decoder =
+            UncompressedRasterDecoder::new(Pin::new(&mut reader), Limits::NO_LIMITS, 4).unwrap();
+        let decoder = AnyRasterDecoder::from(decoder);
+        assert!(matches!(decoder, AnyRasterDecoder::Cups(_)));
+        assert_eq!(read_all(decoder).await, [1, 2, 3, 4]);
+
+        let mut reader = futures::io::Cursor::new([0x00, 0x03, 0x12]);
+        let decoder = CompressedRasterDecoder::new(
+            Pin::new(&mut reader),
+            Limits::NO_LIMITS,
+            1,
+            4,
+            4,
+            0xff,
+            true,
+        )
+        .unwrap();
+        let decoder = AnyRasterDecoder::from(decoder);
+        assert!(matches!(decoder, AnyRasterDecoder::Urf(_)));
+        assert_eq!(read_all(decoder).await, [0x12; 4]);
+    }
+}
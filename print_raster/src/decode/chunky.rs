@@ -0,0 +1,331 @@
+use super::peek::PeekBuffer;
+use super::RasterDecoder;
+use crate::model::cups::{CupsColorOrder, CupsPageHeaderV2};
+use futures::ready;
+use futures::task::Context;
+use futures::task::Poll;
+use futures::AsyncRead;
+use pin_project::pin_project;
+use std::io;
+use std::ops::DerefMut;
+use std::pin::Pin;
+
+/// A decoder converting banded or planar pixels to chunky pixels as they are read.
+///
+/// Banded pixels are converted line by line. Planar pixels need all planes but the last
+/// to be buffered before the first line can be produced. Chunky pixels are passed through.
+///
+/// Only colors of 8 or 16 bits are supported, as sub-byte colors of several planes
+/// would have to be merged into a single byte.
+#[pin_project]
+pub struct ToChunkyDecoder<D> {
+    #[pin]
+    inner: D,
+    color_order: CupsColorOrder,
+    bytes_per_color: usize,
+    num_colors: usize,
+    /// The size of a line of a single color.
+    band_len: usize,
+    height: u64,
+    lines_done: u64,
+    /// All planes but the last one, if planar.
+    planes: Vec<u8>,
+    planes_filled: usize,
+    /// A source line, or a line of the last plane if planar.
+    line: Vec<u8>,
+    line_filled: usize,
+    out: Vec<u8>,
+    out_pos: usize,
+    peek: PeekBuffer,
+}
+
+impl<D> ToChunkyDecoder<D> {
+    pub fn new(inner: D, header: &CupsPageHeaderV2) -> io::Result<Self> {
+        let color_order = header.v1.color_order;
+        let bits_per_color = header.v1.bits_per_color;
+        let num_colors = header.num_colors() as usize;
+        let height = header.v1.height as u64;
+        let bytes_per_line = header.v1.bytes_per_line as usize;
+        let unsupported = |msg: &str| io::Error::new(io::ErrorKind::Unsupported, msg);
+        if color_order != CupsColorOrder::Chunky && !matches!(bits_per_color, 8 | 16) {
+            return Err(unsupported(
+                "only 8 or 16 bits per color can be converted to chunky pixels",
+            ));
+        }
+        let bytes_per_color = bits_per_color as usize / 8;
+        let (band_len, planes_len, line_len) = match color_order {
+            CupsColorOrder::Chunky => (0, 0, 0),
+            CupsColorOrder::Banded => {
+                if num_colors == 0 || !bytes_per_line.is_multiple_of(num_colors) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "bytes_per_line must be a multiple of the number of colors",
+                    ));
+                }
+                let band_len = bytes_per_line / num_colors;
+                (band_len, 0, bytes_per_line)
+            }
+            CupsColorOrder::Planar => {
+                let planes_len = usize::try_from(height)
+                    .ok()
+                    .and_then(|height| height.checked_mul(bytes_per_line))
+                    .and_then(|plane_len| plane_len.checked_mul(num_colors.saturating_sub(1)))
+                    .ok_or_else(|| unsupported("planes are too large to be buffered"))?;
+                (bytes_per_line, planes_len, bytes_per_line)
+            }
+        };
+        if !band_len.is_multiple_of(bytes_per_color.max(1)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "bytes_per_line must be a multiple of the color size",
+            ));
+        }
+        let mut planes = Vec::new();
+        planes.try_reserve_exact(planes_len)?;
+        planes.resize(planes_len, 0);
+        let out_len = band_len * num_colors;
+        Ok(Self {
+            inner,
+            color_order,
+            bytes_per_color,
+            num_colors,
+            band_len,
+            height,
+            lines_done: 0,
+            planes,
+            planes_filled: 0,
+            line: vec![0; line_len],
+            line_filled: 0,
+            out: vec![0; out_len],
+            out_pos: out_len,
+            peek: PeekBuffer::default(),
+        })
+    }
+
+    pub fn get_ref(&self) -> &D {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+/// Reads from `reader` until `target[*filled..]` is full.
+fn poll_fill<D>(
+    mut reader: Pin<&mut D>,
+    cx: &mut Context<'_>,
+    target: &mut [u8],
+    filled: &mut usize,
+) -> Poll<io::Result<()>>
+where
+    D: AsyncRead,
+{
+    while *filled < target.len() {
+        let num_read = ready!(reader.as_mut().poll_read(cx, &mut target[*filled..]))?;
+        if num_read == 0 {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "unexpected eof, more data of raster page is expected",
+            )));
+        }
+        *filled += num_read;
+    }
+    Poll::Ready(Ok(()))
+}
+
+impl<D> ToChunkyDecoder<D>
+where
+    D: AsyncRead,
+{
+    fn poll_convert(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut this = self.project();
+        if *this.color_order == CupsColorOrder::Chunky {
+            return this.inner.poll_read(cx, buf);
+        }
+        loop {
+            if *this.out_pos < this.out.len() {
+                let n = buf.len().min(this.out.len() - *this.out_pos);
+                buf[..n].copy_from_slice(&this.out[*this.out_pos..*this.out_pos + n]);
+                *this.out_pos += n;
+                return Poll::Ready(Ok(n));
+            }
+            if *this.lines_done >= *this.height || buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            ready!(poll_fill(
+                this.inner.as_mut(),
+                cx,
+                this.planes,
+                this.planes_filled
+            ))?;
+            ready!(poll_fill(
+                this.inner.as_mut(),
+                cx,
+                this.line,
+                this.line_filled
+            ))?;
+            let band_len = *this.band_len;
+            let plane_len = band_len * *this.height as usize;
+            let line_index = *this.lines_done as usize;
+            let band = |color: usize| -> &[u8] {
+                match this.color_order {
+                    CupsColorOrder::Planar if color + 1 < *this.num_colors => {
+                        let start = color * plane_len + line_index * band_len;
+                        &this.planes[start..start + band_len]
+                    }
+                    CupsColorOrder::Planar => &this.line[..],
+                    _ => &this.line[color * band_len..(color + 1) * band_len],
+                }
+            };
+            let bytes_per_color = *this.bytes_per_color;
+            let pixel_len = bytes_per_color * *this.num_colors;
+            for color in 0..*this.num_colors {
+                let band = band(color);
+                for (pixel, value) in band.chunks_exact(bytes_per_color).enumerate() {
+                    let start = pixel * pixel_len + color * bytes_per_color;
+                    this.out[start..start + bytes_per_color].copy_from_slice(value);
+                }
+            }
+            *this.out_pos = 0;
+            *this.line_filled = 0;
+            *this.lines_done += 1;
+        }
+    }
+}
+
+impl<D, R> RasterDecoder<R> for ToChunkyDecoder<D>
+where
+    D: RasterDecoder<R>,
+    R: DerefMut<Target: AsyncRead>,
+{
+    fn bytes_remaining(&self) -> u64 {
+        let converted = match self.color_order {
+            CupsColorOrder::Chunky => self.inner.bytes_remaining(),
+            _ => {
+                (self.height - self.lines_done) * self.out.len() as u64
+                    + (self.out.len() - self.out_pos) as u64
+            }
+        };
+        converted + self.peek.len() as u64
+    }
+
+    fn bytes_total(&self) -> u64 {
+        match self.color_order {
+            CupsColorOrder::Chunky => self.inner.bytes_total(),
+            _ => self.height * self.out.len() as u64,
+        }
+    }
+
+    fn encoded_bytes_read(&self) -> u64 {
+        self.inner.encoded_bytes_read()
+    }
+
+    fn min_encoded_bytes_remaining(&self) -> u64 {
+        self.inner.min_encoded_bytes_remaining()
+    }
+
+    fn clean_page_boundary(&self) -> bool {
+        self.bytes_remaining() == 0 && self.inner.clean_page_boundary()
+    }
+
+    fn poll_skip(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.project();
+        this.peek.clear();
+        *this.lines_done = *this.height;
+        *this.out_pos = this.out.len();
+        this.inner.poll_skip(cx)
+    }
+
+    fn poll_peek(mut self: Pin<&mut Self>, cx: &mut Context<'_>, n: usize) -> Poll<io::Result<()>> {
+        let mut peek = std::mem::take(self.as_mut().project().peek);
+        let result = peek.poll_fill(n, |buf| self.as_mut().poll_convert(cx, buf));
+        *self.project().peek = peek;
+        result
+    }
+
+    fn peeked(&self) -> &[u8] {
+        self.peek.as_slice()
+    }
+
+    fn set_fill_byte(&mut self, fill_byte: u8) {
+        self.inner.set_fill_byte(fill_byte);
+    }
+
+    fn into_pin_mut(self) -> Pin<R> {
+        self.inner.into_pin_mut()
+    }
+}
+
+impl<D> AsyncRead for ToChunkyDecoder<D>
+where
+    D: AsyncRead,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if !self.peek.is_empty() {
+            return Poll::Ready(Ok(self.project().peek.read(buf)));
+        }
+        self.poll_convert(cx, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ToChunkyDecoder;
+    use crate::decode::{Limits, RasterDecoder, UncompressedRasterDecoder};
+    use crate::model::cups::{CupsColorOrder, CupsPageHeaderV2};
+    use futures::AsyncReadExt;
+    use std::pin::Pin;
+
+    async fn to_chunky(color_order: CupsColorOrder, data: &[u8]) -> Vec<u8> {
+        let header = CupsPageHeaderV2::builder()
+            .width(2)
+            .height(2)
+            .color_order(color_order)
+            .build();
+        let mut reader = futures::io::Cursor::new(data);
+        let decoder = UncompressedRasterDecoder::new(
+            Pin::new(&mut reader),
+            Limits::NO_LIMITS,
+            data.len() as u64,
+        )
+        .unwrap();
+        let mut decoder = ToChunkyDecoder::new(decoder, &header).unwrap();
+        assert_eq!(decoder.bytes_remaining(), 12);
+        let mut chunky = Vec::new();
+        decoder.read_to_end(&mut chunky).await.unwrap();
+        assert_eq!(decoder.bytes_remaining(), 0);
+        assert_eq!(decoder.bytes_total(), 12);
+        chunky
+    }
+
+    #[tokio::test]
+    async fn test_to_chunky() {
+        const CHUNKY: &[u8] = &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+        assert_eq!(to_chunky(CupsColorOrder::Chunky, CHUNKY).await, CHUNKY);
+        assert_eq!(
+            to_chunky(
+                CupsColorOrder::Banded,
+                &[1, 4, 2, 5, 3, 6, 7, 10, 8, 11, 9, 12]
+            )
+            .await,
+            CHUNKY
+        );
+        assert_eq!(
+            to_chunky(
+                CupsColorOrder::Planar,
+                &[1, 4, 7, 10, 2, 5, 8, 11, 3, 6, 9, 12]
+            )
+            .await,
+            CHUNKY
+        );
+    }
+}
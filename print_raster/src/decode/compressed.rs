@@ -1,9 +1,11 @@
 use super::decoder::RasterDecoder;
+use super::peek::PeekBuffer;
+use super::read_ahead::{BufferedSource, EncodedSource, ReadAhead};
 use super::Limits;
 use futures::ready;
 use futures::task::Context;
 use futures::task::Poll;
-use futures::AsyncRead;
+use futures::{AsyncBufRead, AsyncRead};
 use pin_project::pin_project;
 use std::io;
 use std::ops::DerefMut;
@@ -26,19 +28,67 @@ enum CompressedRasterDecoderState {
     },
 }
 
+/// A decoder of the run-length encoding of CUPS Raster V2 and URF.
+///
+/// Runs repeat whole pixels of `chunk_size` bytes, which are copied as is, so the byte
+/// order of colors of 16 bits is the same as in the stream, regardless of the encoding.
+///
+/// The encoded bytes are taken from `reader` through `S`, see [`EncodedSource`].
 #[pin_project]
-pub struct CompressedRasterDecoder<R> {
+pub struct CompressedRasterDecoder<R, S = ReadAhead> {
     reader: Pin<R>,
     chunk_size: u8,
     bytes_per_line: u64,
     fill_byte: u8,
+    allow_fill_to_end: bool,
     line_buffer: Vec<u8>,
     line_repeat: u8,
     state: CompressedRasterDecoderState,
     bytes_remaining: u64,
+    bytes_total: u64,
+    encoded_bytes_read: u64,
+    source: S,
+    peek: PeekBuffer,
+}
+
+/// Returns the least number of encoded bytes needed for the remaining content, which
+/// bounds how far the underlying reader may be read without passing the end of the page.
+fn min_encoded_bytes_remaining(
+    state: &CompressedRasterDecoderState,
+    line_repeat: u8,
+    bytes_per_line: u64,
+    bytes_remaining: u64,
+) -> u64 {
+    if bytes_remaining == 0 {
+        return 0;
+    }
+    // bytes still needed to finish the current line, and the bytes that may be
+    // produced by it (including its repetitions) at most
+    let (current, covered) = match *state {
+        CompressedRasterDecoderState::Begin => (0, 0),
+        CompressedRasterDecoderState::BeginInlineBlock { .. } => {
+            (1, (line_repeat as u64 + 1) * bytes_per_line)
+        }
+        CompressedRasterDecoderState::ReadInlineBlock { remaining, .. } => {
+            (remaining as u64, (line_repeat as u64 + 1) * bytes_per_line)
+        }
+        CompressedRasterDecoderState::UseBuffer { .. } => {
+            (0, (line_repeat as u64 + 1) * bytes_per_line)
+        }
+    };
+    // every following run of up to 256 lines takes at least a line repeat code and a block code
+    let lines = bytes_remaining.saturating_sub(covered) / bytes_per_line;
+    current + lines.div_ceil(256) * 2
 }
 
 impl<R> CompressedRasterDecoder<R> {
+    /// Creates a decoder of `num_bytes` bytes of lines of `bytes_per_line` bytes.
+    ///
+    /// `allow_fill_to_end` enables the block code `0x80` of URF, filling the rest of the
+    /// line with `fill_byte`. Otherwise the code is rejected as invalid data.
+    ///
+    /// Besides the sizes of a line and of the page, the number of lines is checked
+    /// against [`Limits::max_height`].
     pub fn new(
         reader: Pin<R>,
         limits: &Limits,
@@ -46,6 +96,7 @@ pub fn new(
         bytes_per_line: u64,
         num_bytes: u64,
         fill_byte: u8,
+        allow_fill_to_end: bool,
     ) -> io::Result<Self> {
         if bytes_per_line > limits.bytes_per_line {
             return Err(io::Error::new(
@@ -59,18 +110,27 @@ pub fn new(
                 "num_bytes exceeds limit",
             ));
         }
-        if bytes_per_line != 0 && (chunk_size == 0 || bytes_per_line % chunk_size as u64 != 0) {
+        if bytes_per_line != 0
+            && (chunk_size == 0 || !bytes_per_line.is_multiple_of(chunk_size as u64))
+        {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
                 "bytes_per_line must be multiple of chunk_size",
             ));
         }
-        if (num_bytes != 0) && (bytes_per_line == 0 || num_bytes % bytes_per_line != 0) {
+        if (num_bytes != 0) && (bytes_per_line == 0 || !num_bytes.is_multiple_of(bytes_per_line)) {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
                 "num_bytes must be multiple of bytes_per_line",
             ));
         }
+        // bounds the total output along with the line buffer
+        if num_bytes != 0 && num_bytes / bytes_per_line > limits.max_height as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "number of lines exceeds limit",
+            ));
+        }
         // note: when `num_bytes` = 0, `bytes_per_line` can be any value, but `line_buffer_size` must be 0
         let line_buffer_size = usize::try_from(bytes_per_line.min(num_bytes)).map_err(|_| {
             io::Error::new(io::ErrorKind::InvalidData, "bytes_per_line is too large")
@@ -87,20 +147,268 @@ pub fn new(
             chunk_size,
             bytes_per_line,
             fill_byte,
+            allow_fill_to_end,
             line_buffer,
             line_repeat: 0,
             state: CompressedRasterDecoderState::Begin,
             bytes_remaining: num_bytes,
+            bytes_total: num_bytes,
+            encoded_bytes_read: 0,
+            source: ReadAhead::default(),
+            peek: PeekBuffer::default(),
         })
     }
+
+    /// Switches to another source of encoded bytes, before anything is read.
+    pub(crate) fn with_source<T: Default>(self) -> CompressedRasterDecoder<R, T> {
+        CompressedRasterDecoder {
+            reader: self.reader,
+            chunk_size: self.chunk_size,
+            bytes_per_line: self.bytes_per_line,
+            fill_byte: self.fill_byte,
+            allow_fill_to_end: self.allow_fill_to_end,
+            line_buffer: self.line_buffer,
+            line_repeat: self.line_repeat,
+            state: self.state,
+            bytes_remaining: self.bytes_remaining,
+            bytes_total: self.bytes_total,
+            encoded_bytes_read: self.encoded_bytes_read,
+            source: T::default(),
+            peek: self.peek,
+        }
+    }
+}
+
+impl<R> From<CompressedRasterDecoder<R>> for CompressedRasterDecoder<R, BufferedSource>
+where
+    R: DerefMut<Target: AsyncBufRead>,
+{
+    /// Reads the encoded bytes of a decoder that has not read anything yet directly
+    /// from the buffer of `R`.
+    fn from(decoder: CompressedRasterDecoder<R>) -> Self {
+        debug_assert_eq!(decoder.encoded_bytes_read, 0);
+        decoder.with_source()
+    }
+}
+
+impl<R, S> CompressedRasterDecoder<R, S> {
+    /// Returns the size in bytes of a chunk (pixel) used by the run-length encoding.
+    pub fn chunk_size(&self) -> u8 {
+        self.chunk_size
+    }
+
+    /// Returns the size in bytes of a decoded line.
+    pub fn bytes_per_line(&self) -> u64 {
+        self.bytes_per_line
+    }
+
+    /// Returns the byte that fills the rest of a line on a fill-to-end block.
+    pub fn fill_byte(&self) -> u8 {
+        self.fill_byte
+    }
+
+    /// Returns the index of the line the next byte read belongs to, e.g. to report
+    /// where a page is malformed.
+    pub fn current_row(&self) -> u64 {
+        let bytes_read = self.bytes_total - self.bytes_remaining - self.peek.len() as u64;
+        bytes_read.checked_div(self.bytes_per_line).unwrap_or(0)
+    }
+}
+
+impl<R, S> CompressedRasterDecoder<R, S>
+where
+    R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
+{
+    /// Reads the block headers of the remaining content and skips the pixels they
+    /// describe, without expanding repeated pixels or lines.
+    fn poll_skip_content(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.project();
+        let reader = this.reader;
+        let chunk_size = *this.chunk_size as usize;
+        let line_len = this.line_buffer.len();
+        let unexpected_eof = || {
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "unexpected eof, more data of raster page is expected",
+            )
+        };
+        let invalid_block_header =
+            || io::Error::new(io::ErrorKind::InvalidData, "invalid block header");
+        // `bytes_remaining` is reduced as if the skipped bytes were read,
+        // so that the state stays consistent when the skip is resumed
+        loop {
+            if *this.bytes_remaining == 0 {
+                return Poll::Ready(Ok(()));
+            }
+            let limit = min_encoded_bytes_remaining(
+                this.state,
+                *this.line_repeat,
+                *this.bytes_per_line,
+                *this.bytes_remaining,
+            );
+            let end_of_line = match this.state {
+                CompressedRasterDecoderState::Begin => {
+                    let mut code = 0u8;
+                    if ready!(this.source.poll_read_encoded(
+                        reader,
+                        cx,
+                        slice::from_mut(&mut code),
+                        limit
+                    ))? == 0
+                    {
+                        return Poll::Ready(Err(unexpected_eof()));
+                    }
+                    *this.encoded_bytes_read += 1;
+                    *this.line_repeat = code;
+                    *this.state = CompressedRasterDecoderState::BeginInlineBlock { start: 0 };
+                    None
+                }
+                CompressedRasterDecoderState::BeginInlineBlock { start } => {
+                    let mut code = 0u8;
+                    if ready!(this.source.poll_read_encoded(
+                        reader,
+                        cx,
+                        slice::from_mut(&mut code),
+                        limit
+                    ))? == 0
+                    {
+                        return Poll::Ready(Err(unexpected_eof()));
+                    }
+                    *this.encoded_bytes_read += 1;
+                    if code == 0x80 && !*this.allow_fill_to_end {
+                        return Poll::Ready(Err(invalid_block_header()));
+                    }
+                    let (repeat_last, length) = match code {
+                        0x00..=0x7F => (code, (code as usize + 1) * chunk_size),
+                        0x80 => (0, line_len - *start),
+                        _ => (0, (!code as usize + 2) * chunk_size),
+                    };
+                    if line_len - *start < length {
+                        return Poll::Ready(Err(invalid_block_header()));
+                    }
+                    if code == 0x80 {
+                        *this.bytes_remaining = this.bytes_remaining.saturating_sub(length as u64);
+                        Some(line_len)
+                    } else {
+                        *this.state = CompressedRasterDecoderState::ReadInlineBlock {
+                            repeat_last,
+                            start: *start,
+                            remaining: length / (repeat_last as usize + 1),
+                        };
+                        None
+                    }
+                }
+                CompressedRasterDecoderState::ReadInlineBlock {
+                    repeat_last,
+                    start,
+                    remaining,
+                } => {
+                    // the line buffer serves as scratch space for the skipped bytes
+                    let n = ready!(this.source.poll_read_encoded(
+                        reader,
+                        cx,
+                        &mut this.line_buffer[*start..*start + *remaining],
+                        limit
+                    ))?;
+                    if n == 0 {
+                        return Poll::Ready(Err(unexpected_eof()));
+                    }
+                    *this.encoded_bytes_read += n as u64;
+                    *this.bytes_remaining = this.bytes_remaining.saturating_sub(n as u64);
+                    *start += n;
+                    *remaining -= n;
+                    if *remaining == 0 {
+                        let repeated = *repeat_last as usize * chunk_size;
+                        *this.bytes_remaining =
+                            this.bytes_remaining.saturating_sub(repeated as u64);
+                        Some(*start + repeated)
+                    } else {
+                        None
+                    }
+                }
+                CompressedRasterDecoderState::UseBuffer { start, remaining } => {
+                    *this.bytes_remaining = this.bytes_remaining.saturating_sub(*remaining as u64);
+                    Some(*start + *remaining)
+                }
+            };
+            match end_of_line {
+                Some(end) if end == line_len => {
+                    let repeated = *this.line_repeat as u64 * line_len as u64;
+                    if repeated > *this.bytes_remaining {
+                        // the line is repeated past the end of the page, keep the
+                        // pending repeat to report an unclean page boundary
+                        *this.bytes_remaining = 0;
+                        *this.state = CompressedRasterDecoderState::UseBuffer {
+                            start: line_len,
+                            remaining: 0,
+                        };
+                    } else {
+                        *this.bytes_remaining -= repeated;
+                        *this.line_repeat = 0;
+                        *this.state = CompressedRasterDecoderState::Begin;
+                    }
+                }
+                Some(end) => {
+                    *this.state = CompressedRasterDecoderState::BeginInlineBlock { start: end };
+                }
+                None => {}
+            }
+        }
+    }
 }
 
-impl<R> RasterDecoder<R> for CompressedRasterDecoder<R>
+impl<R, S> RasterDecoder<R> for CompressedRasterDecoder<R, S>
 where
     R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
 {
     fn bytes_remaining(&self) -> u64 {
-        self.bytes_remaining
+        self.bytes_remaining + self.peek.len() as u64
+    }
+
+    fn bytes_total(&self) -> u64 {
+        self.bytes_total
+    }
+
+    fn encoded_bytes_read(&self) -> u64 {
+        self.encoded_bytes_read
+    }
+
+    fn min_encoded_bytes_remaining(&self) -> u64 {
+        // bytes read ahead are no longer in the underlying reader
+        min_encoded_bytes_remaining(
+            &self.state,
+            self.line_repeat,
+            self.bytes_per_line,
+            self.bytes_remaining,
+        )
+        .saturating_sub(self.source.buffered_len() as u64)
+    }
+
+    fn poll_skip(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.as_mut().project().peek.clear();
+        self.poll_skip_content(cx)
+    }
+
+    fn poll_peek(mut self: Pin<&mut Self>, cx: &mut Context<'_>, n: usize) -> Poll<io::Result<()>> {
+        let mut peek = std::mem::take(self.as_mut().project().peek);
+        let result = peek.poll_fill(n, |buf| self.as_mut().poll_decode(cx, buf));
+        *self.project().peek = peek;
+        result
+    }
+
+    fn peeked(&self) -> &[u8] {
+        self.peek.as_slice()
+    }
+
+    fn clean_page_boundary(&self) -> bool {
+        // a well-formed page ends with its last line, leaving no repeat pending
+        self.bytes_remaining() == 0 && matches!(self.state, CompressedRasterDecoderState::Begin)
+    }
+
+    fn set_fill_byte(&mut self, fill_byte: u8) {
+        self.fill_byte = fill_byte;
     }
 
     fn into_pin_mut(self) -> Pin<R> {
@@ -108,11 +416,29 @@ fn into_pin_mut(self) -> Pin<R> {
     }
 }
 
-impl<R> AsyncRead for CompressedRasterDecoder<R>
+impl<R, S> AsyncRead for CompressedRasterDecoder<R, S>
 where
     R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
 {
     fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if !self.peek.is_empty() {
+            return Poll::Ready(Ok(self.project().peek.read(buf)));
+        }
+        self.poll_decode(cx, buf)
+    }
+}
+
+impl<R, S> CompressedRasterDecoder<R, S>
+where
+    R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
+{
+    fn poll_decode(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         mut buf: &mut [u8],
@@ -127,11 +453,21 @@ fn poll_read(
         }
         let mut total_read: usize = 0;
         loop {
+            let limit = min_encoded_bytes_remaining(
+                this.state,
+                *this.line_repeat,
+                *this.bytes_per_line,
+                *this.bytes_remaining - total_read as u64,
+            );
             match this.state {
                 CompressedRasterDecoderState::Begin => {
                     let mut code = 0u8;
-                    let read_code =
-                        ready!(reader.as_mut().poll_read(cx, slice::from_mut(&mut code)));
+                    let read_code = ready!(this.source.poll_read_encoded(
+                        reader,
+                        cx,
+                        slice::from_mut(&mut code),
+                        limit
+                    ));
                     match read_code {
                         Ok(0) => {
                             *this.bytes_remaining =
@@ -139,6 +475,7 @@ fn poll_read(
                             return Poll::Ready(Ok(total_read));
                         }
                         Ok(_) => {
+                            *this.encoded_bytes_read += 1;
                             *this.line_repeat = code;
                             *this.state =
                                 CompressedRasterDecoderState::BeginInlineBlock { start: 0 };
@@ -148,8 +485,12 @@ fn poll_read(
                 }
                 CompressedRasterDecoderState::BeginInlineBlock { start } => {
                     let mut code = 0u8;
-                    let read_code =
-                        ready!(reader.as_mut().poll_read(cx, slice::from_mut(&mut code)));
+                    let read_code = ready!(this.source.poll_read_encoded(
+                        reader,
+                        cx,
+                        slice::from_mut(&mut code),
+                        limit
+                    ));
                     match read_code {
                         Ok(0) => {
                             return Poll::Ready(Err(io::Error::new(
@@ -158,6 +499,7 @@ fn poll_read(
                             )))
                         }
                         Ok(_) => {
+                            *this.encoded_bytes_read += 1;
                             match code {
                                 0x00..=0x7F => {
                                     // repeat single pixel
@@ -176,6 +518,12 @@ fn poll_read(
                                         remaining: chunk_size as usize,
                                     }
                                 }
+                                0x80 if !*this.allow_fill_to_end => {
+                                    return Poll::Ready(Err(io::Error::new(
+                                        io::ErrorKind::InvalidData,
+                                        "invalid block header",
+                                    )));
+                                }
                                 0x80 => {
                                     // reset all remaining pixels to white (apple-specific)
                                     this.line_buffer[*start..].fill(*this.fill_byte);
@@ -213,9 +561,12 @@ fn poll_read(
                 } => {
                     let start_cur = *start;
                     let n_read = buf.len().min(*remaining);
-                    let read_exact = ready!(reader
-                        .as_mut()
-                        .poll_read(cx, &mut this.line_buffer[start_cur..start_cur + n_read]));
+                    let read_exact = ready!(this.source.poll_read_encoded(
+                        reader,
+                        cx,
+                        &mut this.line_buffer[start_cur..start_cur + n_read],
+                        limit
+                    ));
                     match read_exact {
                         Ok(0) => {
                             return Poll::Ready(Err(io::Error::new(
@@ -224,6 +575,7 @@ fn poll_read(
                             )))
                         }
                         Ok(n) => {
+                            *this.encoded_bytes_read += n as u64;
                             *start += n;
                             *remaining -= n;
 
@@ -328,6 +680,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_decompress() {
+        use crate::decode::RasterDecoder;
+
         const UNCOMPRESSED_DATA: &[u8] = &[
             0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0xff, 0xff, 0x00, 0xff, 0xff, 0x00, 0xff, 0xff,
             0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00,
@@ -361,11 +715,100 @@ async fn test_decompress() {
             3 * 8,
             3 * 8 * 8,
             0,
+            true,
         )
         .unwrap();
         let mut uncompressed = Vec::new();
+        assert_eq!(decoder.bytes_total(), UNCOMPRESSED_DATA.len() as u64);
         decoder.read_to_end(&mut uncompressed).await.unwrap();
         assert_eq!(uncompressed, UNCOMPRESSED_DATA);
+        assert_eq!(decoder.bytes_total(), UNCOMPRESSED_DATA.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_read_ahead() {
+        use futures::task::{Context, Poll};
+        use futures::AsyncRead;
+        use std::io;
+
+        struct CountingReader<'a> {
+            data: &'a [u8],
+            reads: usize,
+        }
+
+        impl AsyncRead for CountingReader<'_> {
+            fn poll_read(
+                mut self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+                buf: &mut [u8],
+            ) -> Poll<io::Result<usize>> {
+                self.reads += 1;
+                let n = buf.len().min(self.data.len());
+                buf[..n].copy_from_slice(&self.data[..n]);
+                self.data = &self.data[n..];
+                Poll::Ready(Ok(n))
+            }
+        }
+
+        // 16 lines of alternating single pixels, followed by the next page
+        let line = [
+            0x00, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00,
+        ];
+        let mut data = line.repeat(16);
+        let page_len = data.len();
+        data.extend_from_slice(&[0xaa; 16]);
+        let mut reader = CountingReader {
+            data: &data,
+            reads: 0,
+        };
+        let mut decoder = super::CompressedRasterDecoder::new(
+            Pin::new(&mut reader),
+            Limits::NO_LIMITS,
+            1,
+            5,
+            80,
+            0,
+            true,
+        )
+        .unwrap();
+        let mut uncompressed = Vec::new();
+        decoder.read_to_end(&mut uncompressed).await.unwrap();
+        assert_eq!(uncompressed, [0x00, 0xff, 0x00, 0xff, 0x00].repeat(16));
+        assert_eq!(reader.data.len(), data.len() - page_len);
+        assert!(reader.reads < page_len / 2);
+    }
+
+    #[tokio::test]
+    async fn test_buffered_source() {
+        use crate::decode::RasterDecoder;
+
+        // 16 lines of alternating single pixels, followed by the next page
+        let line = [
+            0x00, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00,
+        ];
+        let mut data = line.repeat(16);
+        let page_len = data.len();
+        data.extend_from_slice(&[0xaa; 16]);
+        let mut reader = futures::io::BufReader::with_capacity(7, &data[..]);
+        let decoder = super::CompressedRasterDecoder::new(
+            Pin::new(&mut reader),
+            Limits::NO_LIMITS,
+            1,
+            5,
+            80,
+            0,
+            true,
+        )
+        .unwrap();
+        let mut decoder: super::CompressedRasterDecoder<_, super::BufferedSource> = decoder.into();
+        let mut uncompressed = Vec::new();
+        decoder.read_to_end(&mut uncompressed).await.unwrap();
+        assert_eq!(uncompressed, [0x00, 0xff, 0x00, 0xff, 0x00].repeat(16));
+        assert_eq!(decoder.encoded_bytes_read(), page_len as u64);
+        // nothing past the end of the page is consumed
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, [0xaa; 16]);
     }
 
     #[tokio::test]
@@ -386,6 +829,7 @@ async fn test_uncompress_highly_repetitive_data() {
             WIDTH * 3,
             WIDTH * HEIGHT * 3,
             0,
+            true,
         )
         .unwrap();
         let mut uncompressed = Vec::new();
@@ -405,10 +849,176 @@ async fn test_uncompress_zero() {
             0,
             0,
             0,
+            true,
         )
         .unwrap();
         let mut uncompressed = Vec::new();
         decoder.read_to_end(&mut uncompressed).await.unwrap();
         assert_eq!(uncompressed, UNCOMPRESSED_DATA);
     }
+
+    #[tokio::test]
+    async fn test_clean_page_boundary() {
+        use crate::decode::RasterDecoder;
+
+        async fn decode(data: &[u8]) -> bool {
+            let mut reader = futures::io::Cursor::new(data);
+            let mut decoder = super::CompressedRasterDecoder::new(
+                Pin::new(&mut reader),
+                Limits::NO_LIMITS,
+                3,
+                3,
+                3,
+                0,
+                true,
+            )
+            .unwrap();
+            let mut uncompressed = Vec::new();
+            decoder.read_to_end(&mut uncompressed).await.unwrap();
+            assert_eq!(uncompressed, [0x01, 0x02, 0x03]);
+            decoder.clean_page_boundary()
+        }
+
+        assert!(decode(&[0x00, 0x00, 0x01, 0x02, 0x03]).await);
+        // the line is repeated past the end of the page
+        assert!(!decode(&[0x01, 0x00, 0x01, 0x02, 0x03]).await);
+    }
+
+    #[tokio::test]
+    async fn test_skip() {
+        use crate::decode::RasterDecoder;
+
+        // two lines of 4 pixels repeated once, then a line reset to the fill byte
+        const COMPRESSED_DATA: &[u8] = &[
+            0x01, 0x01, 0xaa, 0xbb, 0xcc, 0xff, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x00, 0x80,
+        ];
+        for num_read in 0..=36 {
+            let mut reader = futures::io::Cursor::new(COMPRESSED_DATA);
+            let mut decoder = super::CompressedRasterDecoder::new(
+                Pin::new(&mut reader),
+                Limits::NO_LIMITS,
+                3,
+                3 * 4,
+                3 * 4 * 3,
+                0xff,
+                true,
+            )
+            .unwrap();
+            let mut buf = vec![0; num_read];
+            decoder.read_exact(&mut buf).await.unwrap();
+            futures::future::poll_fn(|cx| Pin::new(&mut decoder).poll_skip(cx))
+                .await
+                .unwrap();
+            assert_eq!(decoder.bytes_remaining(), 0);
+            assert_eq!(decoder.encoded_bytes_read(), COMPRESSED_DATA.len() as u64);
+            assert!(decoder.clean_page_boundary());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fill_to_end() {
+        use crate::decode::RasterDecoder;
+
+        // a pixel followed by a fill to the end of the line
+        const COMPRESSED_DATA: &[u8] = &[0x00, 0x00, 0xaa, 0x80];
+        for allow_fill_to_end in [true, false] {
+            let mut reader = futures::io::Cursor::new(COMPRESSED_DATA);
+            let mut decoder = super::CompressedRasterDecoder::new(
+                Pin::new(&mut reader),
+                Limits::NO_LIMITS,
+                1,
+                4,
+                4,
+                0xff,
+                allow_fill_to_end,
+            )
+            .unwrap();
+            let mut decoded = Vec::new();
+            let result = decoder.read_to_end(&mut decoded).await;
+            if allow_fill_to_end {
+                assert_eq!(decoded, [0xaa, 0xff, 0xff, 0xff]);
+            } else {
+                let err = result.unwrap_err();
+                assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+            }
+
+            let mut reader = futures::io::Cursor::new(COMPRESSED_DATA);
+            let mut decoder = super::CompressedRasterDecoder::new(
+                Pin::new(&mut reader),
+                Limits::NO_LIMITS,
+                1,
+                4,
+                4,
+                0xff,
+                allow_fill_to_end,
+            )
+            .unwrap();
+            let skipped = futures::future::poll_fn(|cx| Pin::new(&mut decoder).poll_skip(cx)).await;
+            assert_eq!(skipped.is_ok(), allow_fill_to_end);
+        }
+    }
+
+    #[test]
+    fn test_max_height() {
+        let limits = Limits::builder().max_height(4).build();
+        let mut reader = futures::io::empty();
+        let mut new = |num_bytes| {
+            super::CompressedRasterDecoder::new(
+                Pin::new(&mut reader),
+                &limits,
+                1,
+                16,
+                num_bytes,
+                0,
+                false,
+            )
+            .map(|_| ())
+        };
+        assert!(new(16 * 4).is_ok());
+        assert_eq!(
+            new(16 * 5).err().unwrap().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+    }
+
+    #[tokio::test]
+    async fn test_current_row() {
+        use crate::decode::{RasterDecoderExt, UncompressedRasterDecoder};
+
+        // 3 lines of 2 bytes, each a run of the same byte
+        const COMPRESSED_DATA: &[u8] = &[0x00, 0x01, 0xaa, 0x00, 0x01, 0xbb, 0x00, 0x01, 0xcc];
+        let mut reader = futures::io::Cursor::new(COMPRESSED_DATA);
+        let mut decoder = super::CompressedRasterDecoder::new(
+            Pin::new(&mut reader),
+            Limits::NO_LIMITS,
+            1,
+            2,
+            6,
+            0,
+            true,
+        )
+        .unwrap();
+        let mut buf = [0u8; 3];
+        assert_eq!(decoder.current_row(), 0);
+        decoder.read_exact(&mut buf[..1]).await.unwrap();
+        assert_eq!(decoder.current_row(), 0);
+        decoder.read_exact(&mut buf[1..]).await.unwrap();
+        assert_eq!(decoder.current_row(), 1);
+        // peeked bytes are not read yet
+        decoder.peek(3).await.unwrap();
+        assert_eq!(decoder.current_row(), 1);
+        decoder.read_exact(&mut buf).await.unwrap();
+        assert_eq!(decoder.current_row(), 3);
+
+        let mut reader = futures::io::Cursor::new([0u8; 6]);
+        let mut decoder =
+            UncompressedRasterDecoder::new(Pin::new(&mut reader), Limits::NO_LIMITS, 6).unwrap();
+        decoder.read_exact(&mut buf).await.unwrap();
+        // the size of a line is unknown
+        assert_eq!(decoder.current_row(), 0);
+        let mut decoder = decoder.with_bytes_per_line(2);
+        assert_eq!(decoder.current_row(), 1);
+        decoder.read_exact(&mut buf[..1]).await.unwrap();
+        assert_eq!(decoder.current_row(), 2);
+    }
 }
@@ -1,20 +1,48 @@
-use super::{CompressedRasterDecoder, UncompressedRasterDecoder};
+use super::{CompressedRasterDecoder, EncodedSource, ReadAhead, UncompressedRasterDecoder};
 use crate::decode::RasterDecoder;
-use derive_more::From;
 use futures::AsyncRead;
 use pin_project::pin_project;
 use std::{ops::DerefMut, pin::Pin};
 
+/// A decoder of the content of a CUPS Raster page, compressed (V2) or not (V1 and V3).
+///
+/// The compressed content is read through `S`, see [`EncodedSource`].
 #[pin_project(project = CupsRasterDecoderProj)]
-#[derive(From)]
-pub enum CupsRasterUnifiedDecoder<R> {
+pub enum CupsRasterUnifiedDecoder<R, S = ReadAhead> {
     Uncompressed(#[pin] UncompressedRasterDecoder<R>),
-    Compressed(#[pin] CompressedRasterDecoder<R>),
+    Compressed(#[pin] CompressedRasterDecoder<R, S>),
 }
 
-impl<R> RasterDecoder<R> for CupsRasterUnifiedDecoder<R>
+impl<R, S> From<UncompressedRasterDecoder<R>> for CupsRasterUnifiedDecoder<R, S> {
+    fn from(decoder: UncompressedRasterDecoder<R>) -> Self {
+        CupsRasterUnifiedDecoder::Uncompressed(decoder)
+    }
+}
+
+impl<R, S> From<CompressedRasterDecoder<R>> for CupsRasterUnifiedDecoder<R, S>
+where
+    S: Default,
+{
+    fn from(decoder: CompressedRasterDecoder<R>) -> Self {
+        CupsRasterUnifiedDecoder::Compressed(decoder.with_source())
+    }
+}
+
+impl<R, S> CupsRasterUnifiedDecoder<R, S> {
+    /// Returns the index of the line the next byte read belongs to,
+    /// see [`CompressedRasterDecoder::current_row`].
+    pub fn current_row(&self) -> u64 {
+        match self {
+            CupsRasterUnifiedDecoder::Uncompressed(decoder) => decoder.current_row(),
+            CupsRasterUnifiedDecoder::Compressed(decoder) => decoder.current_row(),
+        }
+    }
+}
+
+impl<R, S> RasterDecoder<R> for CupsRasterUnifiedDecoder<R, S>
 where
     R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
 {
     fn bytes_remaining(&self) -> u64 {
         match self {
@@ -23,6 +51,71 @@ fn bytes_remaining(&self) -> u64 {
         }
     }
 
+    fn bytes_total(&self) -> u64 {
+        match self {
+            CupsRasterUnifiedDecoder::Uncompressed(decoder) => decoder.bytes_total(),
+            CupsRasterUnifiedDecoder::Compressed(decoder) => decoder.bytes_total(),
+        }
+    }
+
+    fn encoded_bytes_read(&self) -> u64 {
+        match self {
+            CupsRasterUnifiedDecoder::Uncompressed(decoder) => decoder.encoded_bytes_read(),
+            CupsRasterUnifiedDecoder::Compressed(decoder) => decoder.encoded_bytes_read(),
+        }
+    }
+
+    fn min_encoded_bytes_remaining(&self) -> u64 {
+        match self {
+            CupsRasterUnifiedDecoder::Uncompressed(decoder) => {
+                decoder.min_encoded_bytes_remaining()
+            }
+            CupsRasterUnifiedDecoder::Compressed(decoder) => decoder.min_encoded_bytes_remaining(),
+        }
+    }
+
+    fn poll_skip(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        match self.project() {
+            CupsRasterDecoderProj::Uncompressed(decoder) => decoder.poll_skip(cx),
+            CupsRasterDecoderProj::Compressed(decoder) => decoder.poll_skip(cx),
+        }
+    }
+
+    fn poll_peek(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        n: usize,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        match self.project() {
+            CupsRasterDecoderProj::Uncompressed(decoder) => decoder.poll_peek(cx, n),
+            CupsRasterDecoderProj::Compressed(decoder) => decoder.poll_peek(cx, n),
+        }
+    }
+
+    fn peeked(&self) -> &[u8] {
+        match self {
+            CupsRasterUnifiedDecoder::Uncompressed(decoder) => decoder.peeked(),
+            CupsRasterUnifiedDecoder::Compressed(decoder) => decoder.peeked(),
+        }
+    }
+
+    fn clean_page_boundary(&self) -> bool {
+        match self {
+            CupsRasterUnifiedDecoder::Uncompressed(decoder) => decoder.clean_page_boundary(),
+            CupsRasterUnifiedDecoder::Compressed(decoder) => decoder.clean_page_boundary(),
+        }
+    }
+
+    fn set_fill_byte(&mut self, fill_byte: u8) {
+        match self {
+            CupsRasterUnifiedDecoder::Uncompressed(decoder) => decoder.set_fill_byte(fill_byte),
+            CupsRasterUnifiedDecoder::Compressed(decoder) => decoder.set_fill_byte(fill_byte),
+        }
+    }
+
     fn into_pin_mut(self) -> Pin<R> {
         match self {
             CupsRasterUnifiedDecoder::Uncompressed(decoder) => decoder.into_pin_mut(),
@@ -31,9 +124,10 @@ fn into_pin_mut(self) -> Pin<R> {
     }
 }
 
-impl<R> AsyncRead for CupsRasterUnifiedDecoder<R>
+impl<R, S> AsyncRead for CupsRasterUnifiedDecoder<R, S>
 where
     R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
 {
     fn poll_read(
         self: std::pin::Pin<&mut Self>,
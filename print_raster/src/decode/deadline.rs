@@ -0,0 +1,142 @@
+use super::RasterDecoder;
+use futures::task::Context;
+use futures::task::Poll;
+use futures::AsyncRead;
+use pin_project::pin_project;
+use std::io;
+use std::ops::DerefMut;
+use std::pin::Pin;
+use std::time::Instant;
+
+/// A decoder that fails with [`io::ErrorKind::TimedOut`] once a deadline has passed.
+///
+/// The deadline is checked whenever the decoder is polled, so a read that is still
+/// pending at the deadline fails on its next poll. No timer is registered to wake the
+/// task, which makes this a guard against slow transports rather than a replacement
+/// for a runtime timeout.
+#[pin_project]
+pub struct DeadlineDecoder<D> {
+    #[pin]
+    inner: D,
+    deadline: Instant,
+}
+
+impl<D> DeadlineDecoder<D> {
+    pub fn new(inner: D, deadline: Instant) -> Self {
+        Self { inner, deadline }
+    }
+
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        self.deadline = deadline;
+    }
+
+    pub fn get_ref(&self) -> &D {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    fn check_deadline(&self) -> io::Result<()> {
+        if Instant::now() >= self.deadline {
+            Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "deadline exceeded while reading page content",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<D, R> RasterDecoder<R> for DeadlineDecoder<D>
+where
+    D: RasterDecoder<R>,
+    R: DerefMut<Target: AsyncRead>,
+{
+    fn bytes_remaining(&self) -> u64 {
+        self.inner.bytes_remaining()
+    }
+
+    fn bytes_total(&self) -> u64 {
+        self.inner.bytes_total()
+    }
+
+    fn encoded_bytes_read(&self) -> u64 {
+        self.inner.encoded_bytes_read()
+    }
+
+    fn min_encoded_bytes_remaining(&self) -> u64 {
+        self.inner.min_encoded_bytes_remaining()
+    }
+
+    fn clean_page_boundary(&self) -> bool {
+        self.inner.clean_page_boundary()
+    }
+
+    fn poll_skip(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.check_deadline()?;
+        self.project().inner.poll_skip(cx)
+    }
+
+    fn poll_peek(self: Pin<&mut Self>, cx: &mut Context<'_>, n: usize) -> Poll<io::Result<()>> {
+        self.check_deadline()?;
+        self.project().inner.poll_peek(cx, n)
+    }
+
+    fn peeked(&self) -> &[u8] {
+        self.inner.peeked()
+    }
+
+    fn set_fill_byte(&mut self, fill_byte: u8) {
+        self.inner.set_fill_byte(fill_byte);
+    }
+
+    fn into_pin_mut(self) -> Pin<R> {
+        self.inner.into_pin_mut()
+    }
+}
+
+impl<D> AsyncRead for DeadlineDecoder<D>
+where
+    D: AsyncRead,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.check_deadline()?;
+        self.project().inner.poll_read(cx, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeadlineDecoder;
+    use crate::decode::{Limits, UncompressedRasterDecoder};
+    use futures::AsyncReadExt;
+    use std::io;
+    use std::pin::Pin;
+    use std::time::{Duration, Instant};
+
+    #[tokio::test]
+    async fn test_deadline() {
+        let mut reader = futures::io::Cursor::new([0x12u8; 4]);
+        let decoder =
+            UncompressedRasterDecoder::new(Pin::new(&mut reader), Limits::NO_LIMITS, 4).unwrap();
+        let mut decoder = DeadlineDecoder::new(decoder, Instant::now() + Duration::from_secs(60));
+        let mut buf = [0u8; 2];
+        decoder.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, [0x12, 0x12]);
+
+        decoder.set_deadline(Instant::now());
+        let err = decoder.read_exact(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+}
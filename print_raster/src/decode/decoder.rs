@@ -12,36 +12,83 @@ pub trait RasterDecoder<R>: AsyncRead
     R: DerefMut<Target: AsyncRead>,
 {
     fn bytes_remaining(&self) -> u64;
+    /// Returns the size in bytes of the whole content, as declared by the page header.
+    fn bytes_total(&self) -> u64;
+    /// Returns the number of encoded bytes read from the underlying reader so far.
+    fn encoded_bytes_read(&self) -> u64;
+    /// Returns a lower bound of the encoded bytes still to be read to finish the content.
+    fn min_encoded_bytes_remaining(&self) -> u64;
+    /// Returns `true` if the content is fully read and the encoded data ended exactly
+    /// at the end of the content, i.e. no block or line repeat extends past the page.
+    fn clean_page_boundary(&self) -> bool;
+    /// Advances past the remaining content without producing its bytes.
+    ///
+    /// This reads the encoded data only as far as needed to find the end of the content,
+    /// e.g. block headers of compressed data are parsed but pixels are not expanded.
+    fn poll_skip(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+    /// Decodes ahead until `n` bytes are available in [`peeked`](Self::peeked),
+    /// or fewer if the content ends. The peeked bytes are returned by the following reads.
+    fn poll_peek(self: Pin<&mut Self>, cx: &mut Context<'_>, n: usize) -> Poll<io::Result<()>>;
+    /// Returns the bytes decoded ahead by [`poll_peek`](Self::poll_peek) and not read yet.
+    fn peeked(&self) -> &[u8];
+    /// Sets the byte that fills the rest of a line on a fill-to-end block of compressed
+    /// content. Decoders without such blocks ignore it.
+    fn set_fill_byte(&mut self, _fill_byte: u8) {}
     fn into_pin_mut(self) -> Pin<R>;
 }
 
 #[pin_project]
 pub struct RasterDecoderConsumer<D, R>
+where
+    D: RasterDecoder<R> + Unpin,
+    R: DerefMut<Target: AsyncRead>,
+{
+    #[pin]
+    drain: RasterDecoderDrain<D, R>,
+}
+
+impl<D, R> Future for RasterDecoderConsumer<D, R>
+where
+    D: RasterDecoder<R> + Unpin,
+    R: DerefMut<Target: AsyncRead>,
+{
+    type Output = io::Result<Pin<R>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let content = ready!(self.project().drain.poll(cx))?;
+        Poll::Ready(Ok(content.into_pin_mut()))
+    }
+}
+
+#[pin_project]
+pub struct RasterDecoderDrain<D, R>
 where
     D: RasterDecoder<R> + Unpin,
     R: DerefMut<Target: AsyncRead>,
 {
     content: Option<D>,
     buf: Vec<u8>,
+    skip: bool,
     _phantom: std::marker::PhantomData<R>,
 }
 
-impl<D, R> Future for RasterDecoderConsumer<D, R>
+impl<D, R> Future for RasterDecoderDrain<D, R>
 where
     D: RasterDecoder<R> + Unpin,
     R: DerefMut<Target: AsyncRead>,
 {
-    type Output = io::Result<Pin<R>>;
+    type Output = io::Result<D>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
         let this = self.as_mut().project();
         if this.content.is_none() {
-            return Poll::Ready(Err(io::Error::new(
-                io::ErrorKind::Other,
-                "content is already consumed",
-            )));
+            return Poll::Ready(Err(io::Error::other("content is already consumed")));
         }
         let content = this.content.as_mut().unwrap();
+        if *this.skip {
+            ready!(Pin::new(&mut *content).poll_skip(cx))?;
+            return Poll::Ready(Ok(this.content.take().unwrap()));
+        }
         let mut remaining = content.bytes_remaining();
         if remaining > 0 {
             loop {
@@ -59,7 +106,34 @@ fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
                 }
             }
         }
-        Poll::Ready(Ok(this.content.take().unwrap().into_pin_mut()))
+        Poll::Ready(Ok(this.content.take().unwrap()))
+    }
+}
+
+#[pin_project]
+pub struct RasterDecoderPeek<'a, D, R>
+where
+    D: RasterDecoder<R> + Unpin,
+    R: DerefMut<Target: AsyncRead>,
+{
+    content: &'a mut D,
+    n: usize,
+    _phantom: std::marker::PhantomData<R>,
+}
+
+impl<D, R> Future for RasterDecoderPeek<'_, D, R>
+where
+    D: RasterDecoder<R> + Unpin,
+    R: DerefMut<Target: AsyncRead>,
+{
+    type Output = io::Result<Vec<u8>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+        let n = *this.n;
+        ready!(Pin::new(&mut **this.content).poll_peek(cx, n))?;
+        let peeked = this.content.peeked();
+        Poll::Ready(Ok(peeked[..n.min(peeked.len())].to_vec()))
     }
 }
 
@@ -88,8 +162,49 @@ fn consume(self) -> RasterDecoderConsumer<Self, R>
         Self: Unpin + Sized,
     {
         RasterDecoderConsumer {
+            drain: self.drain(),
+        }
+    }
+
+    /// Consumes the decoder and returns a future that reads all remaining bytes,
+    /// handing the decoder back afterwards.
+    fn drain(self) -> RasterDecoderDrain<Self, R>
+    where
+        Self: Unpin + Sized,
+    {
+        RasterDecoderDrain {
             content: Some(self),
             buf: vec![0; 4096],
+            skip: false,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// Returns a future that resolves to the next `n` bytes of the content without
+    /// consuming them, or fewer if the content ends.
+    fn peek(&mut self, n: usize) -> RasterDecoderPeek<'_, Self, R>
+    where
+        Self: Unpin + Sized,
+    {
+        RasterDecoderPeek {
+            content: self,
+            n,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// Consumes the decoder and returns a future that skips all remaining bytes
+    /// without decoding them, handing the decoder back afterwards.
+    ///
+    /// See [`RasterDecoder::poll_skip`].
+    fn skip_content(self) -> RasterDecoderDrain<Self, R>
+    where
+        Self: Unpin + Sized,
+    {
+        RasterDecoderDrain {
+            content: Some(self),
+            buf: Vec::new(),
+            skip: true,
             _phantom: std::marker::PhantomData,
         }
     }
@@ -4,13 +4,33 @@ pub struct Limits {
     pub bytes_per_line: u64,
     // The maximum number of bytes to decode per page.
     pub bytes_per_page: u64,
+    // The maximum number of pages to read from a stream.
+    pub max_pages: u64,
+    // The maximum width of a page in pixels.
+    pub max_width: u32,
+    // The maximum height of a page in pixels.
+    pub max_height: u32,
 }
 
 impl Limits {
+    /// Returns a builder starting from [`NO_LIMITS`](Self::NO_LIMITS), to set only
+    /// the limits of interest.
+    pub fn builder() -> LimitsBuilder {
+        LimitsBuilder::default()
+    }
+
     pub const NO_LIMITS: &'static Self = &Self {
         bytes_per_line: u64::MAX,
         bytes_per_page: u64::MAX,
+        max_pages: u64::MAX,
+        max_width: u32::MAX,
+        max_height: u32::MAX,
     };
+
+    /// Returns `true` if a page of the given dimensions is within the limits.
+    pub fn allows_dimensions(&self, width: u32, height: u32) -> bool {
+        width <= self.max_width && height <= self.max_height
+    }
 }
 
 impl Default for Limits {
@@ -18,3 +38,46 @@ fn default() -> Self {
         Self::NO_LIMITS.clone()
     }
 }
+
+/// A builder of [`Limits`], where every limit defaults to none.
+#[derive(Clone, Debug, Default)]
+pub struct LimitsBuilder {
+    limits: Limits,
+}
+
+impl LimitsBuilder {
+    /// Sets the maximum number of bytes to decode per line.
+    pub fn max_bytes_per_line(mut self, bytes_per_line: u64) -> Self {
+        self.limits.bytes_per_line = bytes_per_line;
+        self
+    }
+
+    /// Sets the maximum number of bytes to decode per page.
+    pub fn max_bytes_per_page(mut self, bytes_per_page: u64) -> Self {
+        self.limits.bytes_per_page = bytes_per_page;
+        self
+    }
+
+    /// Sets the maximum number of pages to read from a stream.
+    pub fn max_pages(mut self, max_pages: u64) -> Self {
+        self.limits.max_pages = max_pages;
+        self
+    }
+
+    /// Sets the maximum width of a page in pixels.
+    pub fn max_width(mut self, max_width: u32) -> Self {
+        self.limits.max_width = max_width;
+        self
+    }
+
+    /// Sets the maximum height of a page in pixels.
+    pub fn max_height(mut self, max_height: u32) -> Self {
+        self.limits.max_height = max_height;
+        self
+    }
+
+    /// Returns the limits set so far.
+    pub fn build(self) -> Limits {
+        self.limits
+    }
+}
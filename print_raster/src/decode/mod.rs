@@ -1,10 +1,27 @@
+mod any;
+mod chunky;
 mod compressed;
 mod cups;
+mod deadline;
 mod decoder;
 mod limits;
+mod peek;
+mod read_ahead;
+mod repack;
+mod rows;
+#[cfg(feature = "bytes")]
+mod stream;
 mod uncompressed;
+pub use any::*;
+pub use chunky::*;
 pub use compressed::*;
 pub use cups::*;
+pub use deadline::*;
 pub use decoder::*;
 pub use limits::*;
+pub use read_ahead::{BufferedSource, EncodedSource, ReadAhead};
+pub use repack::*;
+pub use rows::*;
+#[cfg(feature = "bytes")]
+pub use stream::*;
 pub use uncompressed::*;
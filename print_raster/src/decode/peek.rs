@@ -0,0 +1,72 @@
+use futures::task::Poll;
+use std::io;
+
+/// Decoded bytes read ahead by a peek, returned by the following reads.
+#[derive(Debug, Default)]
+pub(crate) struct PeekBuffer {
+    data: Vec<u8>,
+    start: usize,
+}
+
+impl PeekBuffer {
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        &self.data[self.start..]
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.data.len() - self.start
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.data.clear();
+        self.start = 0;
+    }
+
+    /// Moves buffered bytes into `buf`, returning the number of bytes moved.
+    pub(crate) fn read(&mut self, buf: &mut [u8]) -> usize {
+        let n = buf.len().min(self.len());
+        buf[..n].copy_from_slice(&self.data[self.start..self.start + n]);
+        self.start += n;
+        if self.start == self.data.len() {
+            self.clear();
+        }
+        n
+    }
+
+    /// Decodes with `decode` until at least `n` bytes are buffered or the content ends.
+    pub(crate) fn poll_fill(
+        &mut self,
+        n: usize,
+        mut decode: impl FnMut(&mut [u8]) -> Poll<io::Result<usize>>,
+    ) -> Poll<io::Result<()>> {
+        if self.start > 0 {
+            self.data.drain(..self.start);
+            self.start = 0;
+        }
+        while self.data.len() < n {
+            let filled = self.data.len();
+            self.data.resize(n, 0);
+            let result = decode(&mut self.data[filled..]);
+            let num_read = match result {
+                Poll::Ready(Ok(num_read)) => num_read,
+                Poll::Ready(Err(e)) => {
+                    self.data.truncate(filled);
+                    return Poll::Ready(Err(e));
+                }
+                Poll::Pending => {
+                    self.data.truncate(filled);
+                    return Poll::Pending;
+                }
+            };
+            self.data.truncate(filled + num_read);
+            if num_read == 0 {
+                break;
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,104 @@
+use futures::ready;
+use futures::task::{Context, Poll};
+use futures::{AsyncBufRead, AsyncRead};
+use std::io;
+use std::ops::DerefMut;
+use std::pin::Pin;
+
+const READ_AHEAD_SIZE: usize = 512;
+
+/// Where a [`CompressedRasterDecoder`](super::CompressedRasterDecoder) takes its
+/// encoded bytes from.
+///
+/// The decoder makes many small reads, e.g. of a single block header, so the source
+/// either buffers the reader itself ([`ReadAhead`]) or uses the buffer of an
+/// `AsyncBufRead` reader ([`BufferedSource`]).
+pub trait EncodedSource<R>: Default {
+    /// Returns the number of bytes taken from the underlying reader but not read yet.
+    fn buffered_len(&self) -> usize;
+
+    /// Reads into `buf`, taking no more than `limit` bytes from `reader` at once.
+    fn poll_read_encoded(
+        &mut self,
+        reader: &mut Pin<R>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+        limit: u64,
+    ) -> Poll<io::Result<usize>>;
+}
+
+/// Encoded bytes read ahead from the underlying reader, returned by the following reads.
+///
+/// Reads ahead no further than the limit given by the caller,
+/// so that no bytes past the end of the page are consumed.
+#[derive(Debug, Default)]
+pub struct ReadAhead {
+    data: Vec<u8>,
+    start: usize,
+    end: usize,
+}
+
+impl<R> EncodedSource<R> for ReadAhead
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    fn buffered_len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Reads into `buf`, reading ahead up to `limit` bytes from `reader` if nothing is buffered.
+    fn poll_read_encoded(
+        &mut self,
+        reader: &mut Pin<R>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+        limit: u64,
+    ) -> Poll<io::Result<usize>> {
+        if self.end == self.start {
+            let fill = limit.min(READ_AHEAD_SIZE as u64) as usize;
+            if fill <= buf.len() {
+                return reader.as_mut().poll_read(cx, buf);
+            }
+            if self.data.is_empty() {
+                self.data = vec![0; READ_AHEAD_SIZE];
+            }
+            let n = ready!(reader.as_mut().poll_read(cx, &mut self.data[..fill]))?;
+            self.start = 0;
+            self.end = n;
+        }
+        let n = buf.len().min(self.end - self.start);
+        buf[..n].copy_from_slice(&self.data[self.start..self.start + n]);
+        self.start += n;
+        Poll::Ready(Ok(n))
+    }
+}
+
+/// Encoded bytes taken directly from the buffer of an `AsyncBufRead` reader.
+///
+/// Only the bytes copied out are consumed, so nothing is read past the end of the page
+/// and no buffer of its own is needed.
+#[derive(Debug, Default)]
+pub struct BufferedSource;
+
+impl<R> EncodedSource<R> for BufferedSource
+where
+    R: DerefMut<Target: AsyncBufRead>,
+{
+    fn buffered_len(&self) -> usize {
+        0
+    }
+
+    fn poll_read_encoded(
+        &mut self,
+        reader: &mut Pin<R>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+        _limit: u64,
+    ) -> Poll<io::Result<usize>> {
+        let available = ready!(reader.as_mut().poll_fill_buf(cx))?;
+        let n = buf.len().min(available.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        reader.as_mut().consume(n);
+        Poll::Ready(Ok(n))
+    }
+}
@@ -0,0 +1,129 @@
+use futures::ready;
+use futures::task::Context;
+use futures::task::Poll;
+use futures::AsyncRead;
+use pin_project::pin_project;
+use std::io;
+use std::pin::Pin;
+
+/// Re-emits lines of `src_bytes_per_line` bytes as lines of `dst_bytes_per_line` bytes,
+/// truncating or zero-padding each line, e.g. to copy the content of a decoder into an
+/// encoder of another format whose lines are padded differently.
+///
+/// Reading fails with [`io::ErrorKind::UnexpectedEof`] if the inner reader ends in the
+/// middle of a line.
+#[pin_project]
+pub struct RowRepack<D> {
+    #[pin]
+    inner: D,
+    src_line: Vec<u8>,
+    src_filled: usize,
+    dst_line: Vec<u8>,
+    dst_pos: usize,
+}
+
+impl<D> RowRepack<D> {
+    /// Fails with [`io::ErrorKind::InvalidInput`] if either line size is zero.
+    pub fn new(inner: D, src_bytes_per_line: usize, dst_bytes_per_line: usize) -> io::Result<Self> {
+        if src_bytes_per_line == 0 || dst_bytes_per_line == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "bytes per line must not be zero",
+            ));
+        }
+        Ok(Self {
+            inner,
+            src_line: vec![0; src_bytes_per_line],
+            src_filled: 0,
+            dst_line: vec![0; dst_bytes_per_line],
+            dst_pos: dst_bytes_per_line,
+        })
+    }
+
+    pub fn get_ref(&self) -> &D {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<D> AsyncRead for RowRepack<D>
+where
+    D: AsyncRead,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut this = self.project();
+        loop {
+            if *this.dst_pos < this.dst_line.len() {
+                let n = buf.len().min(this.dst_line.len() - *this.dst_pos);
+                buf[..n].copy_from_slice(&this.dst_line[*this.dst_pos..*this.dst_pos + n]);
+                *this.dst_pos += n;
+                return Poll::Ready(Ok(n));
+            }
+            if buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            while *this.src_filled < this.src_line.len() {
+                let num_read = ready!(this
+                    .inner
+                    .as_mut()
+                    .poll_read(cx, &mut this.src_line[*this.src_filled..]))?;
+                if num_read == 0 {
+                    if *this.src_filled == 0 {
+                        return Poll::Ready(Ok(0));
+                    }
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "unexpected eof in the middle of a line",
+                    )));
+                }
+                *this.src_filled += num_read;
+            }
+            let len = this.src_line.len().min(this.dst_line.len());
+            this.dst_line[..len].copy_from_slice(&this.src_line[..len]);
+            this.dst_line[len..].fill(0);
+            *this.src_filled = 0;
+            *this.dst_pos = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RowRepack;
+    use futures::AsyncReadExt;
+
+    #[tokio::test]
+    async fn test_repack() {
+        let data = (1..=12).collect::<Vec<u8>>();
+
+        let mut padded = Vec::new();
+        let mut repack = RowRepack::new(&data[..], 4, 6).unwrap();
+        repack.read_to_end(&mut padded).await.unwrap();
+        assert_eq!(
+            padded,
+            [1, 2, 3, 4, 0, 0, 5, 6, 7, 8, 0, 0, 9, 10, 11, 12, 0, 0]
+        );
+
+        let mut truncated = Vec::new();
+        let mut repack = RowRepack::new(&data[..], 4, 3).unwrap();
+        repack.read_to_end(&mut truncated).await.unwrap();
+        assert_eq!(truncated, [1, 2, 3, 5, 6, 7, 9, 10, 11]);
+
+        let mut repack = RowRepack::new(&data[..10], 4, 4).unwrap();
+        assert_eq!(
+            repack
+                .read_to_end(&mut Vec::new())
+                .await
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::UnexpectedEof
+        );
+    }
+}
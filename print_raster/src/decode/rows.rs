@@ -0,0 +1,90 @@
+use super::RasterDecoder;
+use futures::{AsyncRead, AsyncReadExt};
+use std::{io, marker::PhantomData, ops::DerefMut};
+
+/// Reads decoded content one line of `bytes_per_line` bytes at a time.
+pub struct RowReader<D, R> {
+    decoder: D,
+    row: Vec<u8>,
+    _reader: PhantomData<R>,
+}
+
+impl<D, R> RowReader<D, R>
+where
+    D: RasterDecoder<R> + Unpin,
+    R: DerefMut<Target: AsyncRead>,
+{
+    /// Fails with [`io::ErrorKind::InvalidInput`] if `bytes_per_line` is zero.
+    pub fn new(decoder: D, bytes_per_line: usize) -> io::Result<Self> {
+        if bytes_per_line == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "bytes per line must not be zero",
+            ));
+        }
+        Ok(Self {
+            decoder,
+            row: vec![0; bytes_per_line],
+            _reader: PhantomData,
+        })
+    }
+
+    /// Reads the next line, or returns `None` once the content is exhausted.
+    ///
+    /// Fails with [`io::ErrorKind::UnexpectedEof`] if the content ends in the middle of a line.
+    pub async fn next_row(&mut self) -> io::Result<Option<&[u8]>> {
+        if self.decoder.bytes_remaining() == 0 {
+            return Ok(None);
+        }
+        self.decoder.read_exact(&mut self.row).await?;
+        Ok(Some(&self.row))
+    }
+
+    pub fn bytes_per_line(&self) -> usize {
+        self.row.len()
+    }
+
+    pub fn get_ref(&self) -> &D {
+        &self.decoder
+    }
+
+    pub fn into_inner(self) -> D {
+        self.decoder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RowReader;
+    use crate::decode::{Limits, UncompressedRasterDecoder};
+    use std::pin::Pin;
+
+    #[tokio::test]
+    async fn test_next_row() {
+        let data = (0..12).collect::<Vec<u8>>();
+        let mut reader = &data[..];
+        let decoder =
+            UncompressedRasterDecoder::new(Pin::new(&mut reader), Limits::NO_LIMITS, 12).unwrap();
+        let mut rows = RowReader::new(decoder, 4).unwrap();
+        let mut lines = Vec::new();
+        while let Some(row) = rows.next_row().await.unwrap() {
+            lines.push(row.to_vec());
+        }
+        assert_eq!(lines, data.chunks(4).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_next_row_partial() {
+        let data = (0..10).collect::<Vec<u8>>();
+        let mut reader = &data[..];
+        let decoder =
+            UncompressedRasterDecoder::new(Pin::new(&mut reader), Limits::NO_LIMITS, 10).unwrap();
+        let mut rows = RowReader::new(decoder, 4).unwrap();
+        assert!(rows.next_row().await.unwrap().is_some());
+        assert!(rows.next_row().await.unwrap().is_some());
+        assert_eq!(
+            rows.next_row().await.unwrap_err().kind(),
+            std::io::ErrorKind::UnexpectedEof
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use super::RasterDecoder;
+use ::bytes::{Bytes, BytesMut};
+use futures::{AsyncRead, AsyncReadExt, Stream};
+use std::{io, ops::DerefMut};
+
+/// Turns a decoder into a stream of [`Bytes`] chunks of at most `chunk_size` bytes.
+///
+/// The stream ends when the content of the page is exhausted, or after yielding an error.
+pub fn decoder_into_stream<D, R>(
+    decoder: D,
+    chunk_size: usize,
+) -> impl Stream<Item = io::Result<Bytes>>
+where
+    D: RasterDecoder<R> + Unpin,
+    R: DerefMut<Target: AsyncRead>,
+{
+    let chunk_size = chunk_size.max(1);
+    futures::stream::try_unfold(decoder, move |mut decoder| async move {
+        let len = usize::try_from(decoder.bytes_remaining())
+            .unwrap_or(usize::MAX)
+            .min(chunk_size);
+        if len == 0 {
+            return Ok(None);
+        }
+        let mut buf = BytesMut::zeroed(len);
+        let n = decoder.read(&mut buf).await?;
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "unexpected eof, more data of raster page is expected",
+            ));
+        }
+        buf.truncate(n);
+        Ok(Some((buf.freeze(), decoder)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::decode::{Limits, UncompressedRasterDecoder};
+    use futures::TryStreamExt;
+    use std::pin::Pin;
+
+    #[tokio::test]
+    async fn test_decoder_into_stream() {
+        let data = (0..10).collect::<Vec<u8>>();
+        let mut reader = &data[..];
+        let decoder =
+            UncompressedRasterDecoder::new(Pin::new(&mut reader), Limits::NO_LIMITS, 10).unwrap();
+        let chunks = super::decoder_into_stream(decoder, 4)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(
+            chunks.iter().map(|x| x.len()).collect::<Vec<_>>(),
+            vec![4, 4, 2]
+        );
+        assert_eq!(chunks.concat(), data);
+    }
+
+    #[tokio::test]
+    async fn test_decoder_into_stream_truncated() {
+        let data = (0..10).collect::<Vec<u8>>();
+        let mut reader = &data[..];
+        let decoder =
+            UncompressedRasterDecoder::new(Pin::new(&mut reader), Limits::NO_LIMITS, 12).unwrap();
+        let result = super::decoder_into_stream(decoder, 4)
+            .try_collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            result.unwrap_err().kind(),
+            std::io::ErrorKind::UnexpectedEof
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use super::peek::PeekBuffer;
 use super::Limits;
 use super::RasterDecoder;
 use futures::ready;
@@ -13,6 +14,10 @@
 pub struct UncompressedRasterDecoder<R> {
     reader: Pin<R>,
     bytes_remaining: u64,
+    bytes_total: u64,
+    bytes_per_line: u64,
+    encoded_bytes_read: u64,
+    peek: PeekBuffer,
 }
 
 impl<R> UncompressedRasterDecoder<R> {
@@ -26,8 +31,31 @@ pub fn new(reader: Pin<R>, limits: &Limits, num_bytes: u64) -> io::Result<Self>
         Ok(Self {
             reader,
             bytes_remaining: num_bytes,
+            bytes_total: num_bytes,
+            bytes_per_line: 0,
+            encoded_bytes_read: 0,
+            peek: PeekBuffer::default(),
         })
     }
+
+    /// Sets the size in bytes of a line, which is only used to report
+    /// [`current_row`](Self::current_row), as the content is not split into lines.
+    pub fn with_bytes_per_line(mut self, bytes_per_line: u64) -> Self {
+        self.bytes_per_line = bytes_per_line;
+        self
+    }
+
+    /// Returns the size in bytes of a line, or 0 if it is unknown.
+    pub fn bytes_per_line(&self) -> u64 {
+        self.bytes_per_line
+    }
+
+    /// Returns the index of the line the next byte read belongs to,
+    /// or 0 if the size of a line is unknown.
+    pub fn current_row(&self) -> u64 {
+        let bytes_read = self.bytes_total - self.bytes_remaining - self.peek.len() as u64;
+        bytes_read.checked_div(self.bytes_per_line).unwrap_or(0)
+    }
 }
 
 impl<R> RasterDecoder<R> for UncompressedRasterDecoder<R>
@@ -35,18 +63,64 @@ impl<R> RasterDecoder<R> for UncompressedRasterDecoder<R>
     R: DerefMut<Target: AsyncRead>,
 {
     fn bytes_remaining(&self) -> u64 {
+        self.bytes_remaining + self.peek.len() as u64
+    }
+
+    fn bytes_total(&self) -> u64 {
+        self.bytes_total
+    }
+
+    fn encoded_bytes_read(&self) -> u64 {
+        self.encoded_bytes_read
+    }
+
+    fn min_encoded_bytes_remaining(&self) -> u64 {
         self.bytes_remaining
     }
 
+    fn poll_skip(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.project();
+        this.peek.clear();
+        let mut scratch = [0u8; 4096];
+        while *this.bytes_remaining > 0 {
+            let len = (*this.bytes_remaining).min(scratch.len() as u64) as usize;
+            let num_read = ready!(this.reader.as_mut().poll_read(cx, &mut scratch[..len]))?;
+            if num_read == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "unexpected eof, more data of raster page is expected",
+                )));
+            }
+            *this.bytes_remaining -= num_read as u64;
+            *this.encoded_bytes_read += num_read as u64;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_peek(mut self: Pin<&mut Self>, cx: &mut Context<'_>, n: usize) -> Poll<io::Result<()>> {
+        let mut peek = std::mem::take(self.as_mut().project().peek);
+        let result = peek.poll_fill(n, |buf| self.as_mut().poll_decode(cx, buf));
+        *self.project().peek = peek;
+        result
+    }
+
+    fn peeked(&self) -> &[u8] {
+        self.peek.as_slice()
+    }
+
+    fn clean_page_boundary(&self) -> bool {
+        self.bytes_remaining() == 0
+    }
+
     fn into_pin_mut(self) -> Pin<R> {
         self.reader
     }
 }
-impl<R> AsyncRead for UncompressedRasterDecoder<R>
+impl<R> UncompressedRasterDecoder<R>
 where
     R: DerefMut<Target: AsyncRead>,
 {
-    fn poll_read(
+    fn poll_decode(
         self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         mut buf: &mut [u8],
@@ -60,6 +134,23 @@ fn poll_read(
         }
         let total_read = ready!(reader.as_mut().poll_read(cx, buf))?;
         *this.bytes_remaining = this.bytes_remaining.saturating_sub(total_read as u64);
+        *this.encoded_bytes_read += total_read as u64;
         Poll::Ready(Ok(total_read))
     }
 }
+
+impl<R> AsyncRead for UncompressedRasterDecoder<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if !self.peek.is_empty() {
+            return Poll::Ready(Ok(self.project().peek.read(buf)));
+        }
+        self.poll_decode(cx, buf)
+    }
+}
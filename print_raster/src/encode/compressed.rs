@@ -1,4 +1,5 @@
 use super::RasterEncoder;
+use futures::future::poll_fn;
 use futures::ready;
 use futures::task::Context;
 use futures::task::Poll;
@@ -9,6 +10,37 @@
 use std::pin::Pin;
 use std::slice;
 
+/// How a line is split into the blocks of the compressed encoding.
+///
+/// Both policies merge up to 256 identical lines under one line repeat code, and scan
+/// the chunks of each line from the start: two or more equal chunks become a repeat block
+/// of up to 128 chunks, other chunks a literal block ending before the next pair of equal
+/// chunks. They differ in the length of literal blocks only, so the output is deterministic
+/// for a given policy, while either decodes to the same pixels.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EncoderPolicy {
+    /// Literal blocks hold up to 127 chunks, and a literal block reaching the end of the
+    /// line leaves the last chunk to a block of its own.
+    #[default]
+    Greedy,
+    /// Literal blocks hold up to 128 chunks and take the last chunk of the line, as done
+    /// by `cupsRasterWritePixels` of CUPS, so whole lines compare byte-for-byte with
+    /// streams written by the C library.
+    ///
+    /// The lines emitted in parts by
+    /// [`checkpoint`](CompressedRasterEncoder::checkpoint) don't match CUPS.
+    CupsCompatible,
+}
+
+impl EncoderPolicy {
+    fn max_literal_chunks(self) -> u8 {
+        match self {
+            EncoderPolicy::Greedy => 0x7f,
+            EncoderPolicy::CupsCompatible => 0x80,
+        }
+    }
+}
+
 #[derive(Debug)]
 enum FlushLineBufferState {
     None,
@@ -43,11 +75,18 @@ fn is_none(&self) -> bool {
 pub struct CompressedRasterEncoder<W> {
     writer: Pin<W>,
     chunk_size: u8,
+    policy: EncoderPolicy,
     bytes_per_line: u64,
     bytes_remaining: u64,
+    bytes_total: u64,
+    bytes_consumed: u64,
+    bytes_emitted: u64,
+    max_bytes_emitted: u64,
     line_buffer: Vec<u8>,
     line_repeat: Option<u8>,
     pos_in_line: usize,
+    /// The bytes of the current line already emitted by [`checkpoint`](Self::checkpoint).
+    line_emitted: usize,
     flush_line_buffer_state: FlushLineBufferState,
 }
 
@@ -58,13 +97,32 @@ pub fn new(
         bytes_per_line: u64,
         num_bytes: u64,
     ) -> io::Result<Self> {
-        if bytes_per_line != 0 && (chunk_size == 0 || bytes_per_line % chunk_size as u64 != 0) {
+        Self::new_with_policy(
+            writer,
+            chunk_size,
+            bytes_per_line,
+            num_bytes,
+            EncoderPolicy::default(),
+        )
+    }
+
+    /// Like [`new`](Self::new), splitting lines into blocks by `policy`.
+    pub fn new_with_policy(
+        writer: Pin<W>,
+        chunk_size: u8,
+        bytes_per_line: u64,
+        num_bytes: u64,
+        policy: EncoderPolicy,
+    ) -> io::Result<Self> {
+        if bytes_per_line != 0
+            && (chunk_size == 0 || !bytes_per_line.is_multiple_of(chunk_size as u64))
+        {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
                 "bytes_per_line must be multiple of chunk_size",
             ));
         }
-        if (num_bytes != 0) && (bytes_per_line == 0 || num_bytes % bytes_per_line != 0) {
+        if (num_bytes != 0) && (bytes_per_line == 0 || !num_bytes.is_multiple_of(bytes_per_line)) {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
                 "num_bytes must be multiple of bytes_per_line",
@@ -84,14 +142,113 @@ pub fn new(
         Ok(Self {
             writer,
             chunk_size,
+            policy,
             bytes_per_line,
             bytes_remaining: num_bytes,
+            bytes_total: num_bytes,
+            bytes_consumed: 0,
+            bytes_emitted: 0,
+            max_bytes_emitted: CompressedRasterEncoder::max_compressed_size(
+                chunk_size,
+                bytes_per_line,
+                num_bytes,
+            ),
             line_buffer,
             line_repeat: None,
             pos_in_line: 0,
+            line_emitted: 0,
             flush_line_buffer_state: FlushLineBufferState::None,
         })
     }
+
+    /// Returns the number of uncompressed bytes written to the encoder so far.
+    ///
+    /// Together with [`bytes_emitted`](RasterEncoder::bytes_emitted), this gives
+    /// the compression ratio achieved.
+    pub fn bytes_consumed(&self) -> u64 {
+        self.bytes_consumed
+    }
+
+    pub fn policy(&self) -> EncoderPolicy {
+        self.policy
+    }
+}
+
+impl<W> CompressedRasterEncoder<W>
+where
+    W: DerefMut<Target: AsyncWrite>,
+{
+    /// Emits the bytes written so far, without waiting for the current line to be complete,
+    /// e.g. for a streaming producer to get them on the wire.
+    ///
+    /// A buffered line waiting for repetitions is emitted as is, and the whole chunks of
+    /// the current line are emitted as the start of a line that is not repeated. The rest
+    /// of the line is emitted once it is written. Lines emitted this way are not merged
+    /// with identical following lines, which costs some compression.
+    ///
+    /// The underlying writer is not flushed. Fails with [`io::ErrorKind::InvalidInput`]
+    /// if a write is still pending.
+    pub async fn checkpoint(&mut self) -> io::Result<()> {
+        if !self.flush_line_buffer_state.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot checkpoint while a write is pending",
+            ));
+        }
+        if let Some(line_repeat) = self.line_repeat.take() {
+            self.flush_line_buffer_state = FlushLineBufferState::Begin {
+                ret: 0,
+                line_repeat,
+            };
+            poll_fn(|cx| self.poll_flush_line_buffer(cx, self.line_buffer.len())).await?;
+        }
+        let chunk_size = (self.chunk_size as usize).max(1);
+        let end = self.pos_in_line / chunk_size * chunk_size;
+        if end > self.line_emitted {
+            self.flush_line_buffer_state = match self.line_emitted {
+                0 => FlushLineBufferState::Begin {
+                    ret: 0,
+                    line_repeat: 0,
+                },
+                start => FlushLineBufferState::BeginInlineBlock { ret: 0, start },
+            };
+            poll_fn(|cx| self.poll_flush_line_buffer(cx, end)).await?;
+            self.line_emitted = end;
+        }
+        Ok(())
+    }
+
+    fn poll_flush_line_buffer(
+        &mut self,
+        cx: &mut Context<'_>,
+        end: usize,
+    ) -> Poll<io::Result<usize>> {
+        poll_flush_line_buffer(
+            &mut self.flush_line_buffer_state,
+            cx,
+            &mut self.writer,
+            &mut self.bytes_emitted,
+            self.chunk_size,
+            self.policy,
+            &self.line_buffer[..end],
+        )
+    }
+}
+
+impl CompressedRasterEncoder<()> {
+    /// Returns the worst-case size of the compressed output for the given parameters,
+    /// which is one line repeat code per line and one block code per chunk.
+    pub fn max_compressed_size(chunk_size: u8, bytes_per_line: u64, num_bytes: u64) -> u64 {
+        if chunk_size == 0 || bytes_per_line == 0 {
+            return 0;
+        }
+        let num_lines = num_bytes / bytes_per_line;
+        let chunks_per_line = bytes_per_line.div_ceil(chunk_size as u64);
+        num_lines.saturating_mul(
+            1u64.saturating_add(chunks_per_line)
+                .saturating_add(bytes_per_line),
+        )
+    }
 }
 
 impl<W> RasterEncoder<W> for CompressedRasterEncoder<W>
@@ -102,6 +259,18 @@ fn bytes_remaining(&self) -> u64 {
         self.bytes_remaining
     }
 
+    fn bytes_per_line(&self) -> u64 {
+        self.bytes_per_line
+    }
+
+    fn bytes_total(&self) -> u64 {
+        self.bytes_total
+    }
+
+    fn bytes_emitted(&self) -> u64 {
+        self.bytes_emitted
+    }
+
     fn into_pin_mut(self) -> Pin<W> {
         self.writer
     }
@@ -111,7 +280,9 @@ fn poll_flush_line_buffer<W>(
     state: &mut FlushLineBufferState,
     cx: &mut Context<'_>,
     writer: &mut Pin<W>,
+    bytes_emitted: &mut u64,
     chunk_size: u8,
+    policy: EncoderPolicy,
     line_buffer: &[u8],
 ) -> Poll<io::Result<usize>>
 where
@@ -130,6 +301,7 @@ fn poll_flush_line_buffer<W>(
                         "failed to write to writer",
                     )));
                 }
+                *bytes_emitted += n_written as u64;
                 *state = FlushLineBufferState::BeginInlineBlock { ret, start: 0 };
             }
             FlushLineBufferState::BeginInlineBlock { ret, start } => {
@@ -156,17 +328,21 @@ fn poll_flush_line_buffer<W>(
                             end: start + chunk_size as usize * (tag + 1) as usize,
                         };
                     } else {
+                        let max_count = policy.max_literal_chunks();
                         let mut count = 1u8;
                         let mut prev_chunk = second_chunk;
+                        // whether `prev_chunk` is the last chunk of the line
+                        let mut at_end = true;
                         for chunk in chunks {
-                            if chunk == prev_chunk {
+                            if chunk == prev_chunk || count >= max_count {
+                                at_end = false;
                                 break;
                             }
                             count += 1;
                             prev_chunk = chunk;
-                            if count >= 0x7f {
-                                break;
-                            }
+                        }
+                        if policy == EncoderPolicy::CupsCompatible && at_end && count < max_count {
+                            count += 1;
                         }
                         let tag = (!count).wrapping_add(2);
                         *state = FlushLineBufferState::WriteInlineBlock {
@@ -199,6 +375,7 @@ fn poll_flush_line_buffer<W>(
                         "failed to write to writer",
                     )));
                 }
+                *bytes_emitted += n_written as u64;
                 *state = FlushLineBufferState::WriteInlineBlockData { ret, start, end };
             }
             FlushLineBufferState::WriteInlineBlockData { ret, start, end } => {
@@ -209,6 +386,7 @@ fn poll_flush_line_buffer<W>(
                         "failed to write to writer",
                     )));
                 }
+                *bytes_emitted += n_written as u64;
                 if start + n_written >= end {
                     *state = FlushLineBufferState::BeginInlineBlock { ret, start: end };
                 } else {
@@ -235,6 +413,13 @@ fn poll_write(
         let this = self.as_mut().project();
         let writer = this.writer;
         let mut total_write = 0;
+        if *this.bytes_remaining == 0 && !buf.is_empty() {
+            // the excess would be dropped silently otherwise
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "all bytes of the page have been written",
+            )));
+        }
         buf = &buf[..(*this.bytes_remaining).min(buf.len() as u64) as usize];
 
         if !this.flush_line_buffer_state.is_none() {
@@ -242,7 +427,9 @@ fn poll_write(
                 this.flush_line_buffer_state,
                 cx,
                 writer,
+                this.bytes_emitted,
                 *this.chunk_size,
+                *this.policy,
                 this.line_buffer,
             ))?;
             buf = &buf[total_write..];
@@ -264,7 +451,24 @@ fn poll_write(
                         // One line is full, reset the pointer for the next line
                         *this.pos_in_line = 0;
 
-                        if total_write as u64 >= *this.bytes_remaining {
+                        if *this.line_emitted > 0 {
+                            // The start of the line has been emitted by a checkpoint,
+                            // so emit the rest immediately without a line repeat code
+                            *this.flush_line_buffer_state =
+                                FlushLineBufferState::BeginInlineBlock {
+                                    ret: total_write,
+                                    start: std::mem::take(this.line_emitted),
+                                };
+                            total_write = ready!(poll_flush_line_buffer(
+                                this.flush_line_buffer_state,
+                                cx,
+                                writer,
+                                this.bytes_emitted,
+                                *this.chunk_size,
+                                *this.policy,
+                                this.line_buffer
+                            ))?;
+                        } else if total_write as u64 >= *this.bytes_remaining {
                             // Flush immediately if all bytes are written
                             *this.flush_line_buffer_state = FlushLineBufferState::Begin {
                                 ret: total_write,
@@ -274,7 +478,9 @@ fn poll_write(
                                 this.flush_line_buffer_state,
                                 cx,
                                 writer,
+                                this.bytes_emitted,
                                 *this.chunk_size,
+                                *this.policy,
                                 this.line_buffer
                             ))?;
                         } else {
@@ -304,7 +510,9 @@ fn poll_write(
                             this.flush_line_buffer_state,
                             cx,
                             writer,
+                            this.bytes_emitted,
                             *this.chunk_size,
+                            *this.policy,
                             this.line_buffer
                         ))?;
                     } else {
@@ -328,7 +536,9 @@ fn poll_write(
                                     this.flush_line_buffer_state,
                                     cx,
                                     writer,
+                                    this.bytes_emitted,
                                     *this.chunk_size,
+                                    *this.policy,
                                     this.line_buffer
                                 ))?;
                             }
@@ -339,6 +549,11 @@ fn poll_write(
         }
 
         *this.bytes_remaining = this.bytes_remaining.saturating_sub(total_write as u64);
+        *this.bytes_consumed += total_write as u64;
+        debug_assert!(
+            *this.bytes_emitted <= *this.max_bytes_emitted,
+            "compressed output exceeds the worst-case size"
+        );
         Poll::Ready(Ok(total_write))
     }
 
@@ -357,6 +572,7 @@ fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>
 
 #[cfg(test)]
 mod tests {
+    use crate::encode::RasterEncoder;
     use futures::AsyncWriteExt;
     use std::pin::Pin;
 
@@ -416,9 +632,58 @@ async fn test_compress_highly_repetitive_data() {
         .unwrap();
         encoder.write_all(UNCOMPRESSED_DATA).await.unwrap();
         encoder.flush().await.unwrap();
+        assert_eq!(encoder.bytes_consumed(), UNCOMPRESSED_DATA.len() as u64);
+        assert_eq!(encoder.bytes_total(), UNCOMPRESSED_DATA.len() as u64);
+        assert_eq!(encoder.bytes_emitted(), COMPRESSED_DATA.len() as u64);
         assert_eq!(writer, COMPRESSED_DATA);
     }
 
+    #[tokio::test]
+    async fn test_checkpoint() {
+        use crate::decode::{CompressedRasterDecoder, Limits};
+        use futures::AsyncReadExt;
+
+        let data = [[0x11u8; 12], [0x11; 12], [0x22; 12]].concat();
+        let mut writer = Vec::<u8>::new();
+        let mut encoder =
+            super::CompressedRasterEncoder::new(Pin::new(&mut writer), 3, 12, 36).unwrap();
+        // the first line is buffered, waiting for repetitions
+        encoder.write_all(&data[..19]).await.unwrap();
+        assert_eq!(encoder.bytes_emitted(), 0);
+        encoder.checkpoint().await.unwrap();
+        // the first line, then the 2 whole pixels of the second line
+        assert_eq!(encoder.bytes_emitted(), 5 + 5);
+        encoder.checkpoint().await.unwrap();
+        assert_eq!(encoder.bytes_emitted(), 5 + 5);
+        encoder.write_all(&data[19..]).await.unwrap();
+        encoder.flush().await.unwrap();
+        drop(encoder);
+        assert_eq!(
+            writer,
+            [
+                0x00, 0x03, 0x11, 0x11, 0x11, // first line
+                0x00, 0x01, 0x11, 0x11, 0x11, // checkpoint in the second line
+                0x01, 0x11, 0x11, 0x11, // rest of the second line
+                0x00, 0x03, 0x22, 0x22, 0x22, // third line
+            ]
+        );
+
+        let mut decoded = Vec::new();
+        let mut input = writer.as_slice();
+        let mut decoder = CompressedRasterDecoder::new(
+            Pin::new(&mut input),
+            Limits::NO_LIMITS,
+            3,
+            12,
+            36,
+            0,
+            false,
+        )
+        .unwrap();
+        decoder.read_to_end(&mut decoded).await.unwrap();
+        assert_eq!(decoded, data);
+    }
+
     #[tokio::test]
     async fn test_compress_zero() {
         const UNCOMPRESSED_DATA: &[u8] = &[0; 0];
@@ -430,4 +695,102 @@ async fn test_compress_zero() {
         encoder.flush().await.unwrap();
         assert_eq!(writer, COMPRESSED_DATA);
     }
+
+    async fn compress_with_policy(
+        policy: super::EncoderPolicy,
+        bytes_per_line: u64,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut writer = Vec::<u8>::new();
+        let mut encoder = super::CompressedRasterEncoder::new_with_policy(
+            Pin::new(&mut writer),
+            1,
+            bytes_per_line,
+            data.len() as u64,
+            policy,
+        )
+        .unwrap();
+        encoder.write_all(data).await.unwrap();
+        writer
+    }
+
+    #[tokio::test]
+    async fn test_encoder_policy() {
+        use super::EncoderPolicy::{CupsCompatible, Greedy};
+
+        const DATA: &[u8] = &[1, 2, 3, 4, 1, 2, 3, 4, 5, 5, 5, 6];
+        assert_eq!(
+            compress_with_policy(Greedy, 4, DATA).await,
+            [0x01, 0xfe, 1, 2, 3, 0x00, 4, 0x00, 0x02, 5, 0x00, 6]
+        );
+        assert_eq!(
+            compress_with_policy(CupsCompatible, 4, DATA).await,
+            [0x01, 0xfd, 1, 2, 3, 4, 0x00, 0x02, 5, 0x00, 6]
+        );
+
+        // literal blocks longer than the limit of each policy
+        let data = (0..130).collect::<Vec<u8>>();
+        let mut expected = vec![0x00, 0x82];
+        expected.extend(0..127);
+        expected.extend([0xff, 127, 128, 0x00, 129]);
+        assert_eq!(compress_with_policy(Greedy, 130, &data).await, expected);
+        let mut expected = vec![0x00, 0x81];
+        expected.extend(0..128);
+        expected.extend([0xff, 128, 129]);
+        assert_eq!(
+            compress_with_policy(CupsCompatible, 130, &data).await,
+            expected
+        );
+    }
+
+    #[tokio::test]
+    async fn test_overfeed() {
+        let mut writer = Vec::<u8>::new();
+        let mut encoder =
+            super::CompressedRasterEncoder::new(Pin::new(&mut writer), 1, 2, 4).unwrap();
+        let err = encoder.write_all(&[1, 2, 3, 4, 5]).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+        assert_eq!(encoder.bytes_remaining(), 0);
+        encoder.write_all(&[]).await.unwrap();
+
+        let mut writer = Vec::<u8>::new();
+        let mut encoder = crate::encode::UncompressedRasterEncoder::new(Pin::new(&mut writer), 4);
+        let err = encoder.write_all(&[1, 2, 3, 4, 5]).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+        assert_eq!(encoder.bytes_remaining(), 0);
+        assert_eq!(writer, [1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_max_compressed_size() {
+        const CHUNK_SIZE: u8 = 3;
+        const BYTES_PER_LINE: u64 = 3 * 100;
+        const NUM_BYTES: u64 = BYTES_PER_LINE * 4;
+        let patterns: [fn(usize) -> u8; 4] = [
+            |i| (i / 3) as u8,
+            |i| if (i / 3) % 3 == 0 { 0x00 } else { 0xff },
+            |i| ((i / 3) * 7919 % 251) as u8,
+            |_| 0x55,
+        ];
+        let max_size = super::CompressedRasterEncoder::max_compressed_size(
+            CHUNK_SIZE,
+            BYTES_PER_LINE,
+            NUM_BYTES,
+        );
+        assert_eq!(max_size, 4 * (1 + 100 + 300));
+        for pattern in patterns {
+            let data = (0..NUM_BYTES as usize).map(pattern).collect::<Vec<_>>();
+            let mut writer = Vec::<u8>::new();
+            let mut encoder = super::CompressedRasterEncoder::new(
+                Pin::new(&mut writer),
+                CHUNK_SIZE,
+                BYTES_PER_LINE,
+                NUM_BYTES,
+            )
+            .unwrap();
+            encoder.write_all(&data).await.unwrap();
+            encoder.flush().await.unwrap();
+            assert!(writer.len() as u64 <= max_size);
+        }
+    }
 }
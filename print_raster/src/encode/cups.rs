@@ -0,0 +1,89 @@
+use super::{CompressedRasterEncoder, UncompressedRasterEncoder};
+use crate::encode::RasterEncoder;
+use derive_more::From;
+use futures::AsyncWrite;
+use pin_project::pin_project;
+use std::{ops::DerefMut, pin::Pin};
+
+#[pin_project(project = CupsRasterEncoderProj)]
+#[derive(From)]
+pub enum CupsRasterUnifiedEncoder<W> {
+    Uncompressed(#[pin] UncompressedRasterEncoder<W>),
+    Compressed(#[pin] CompressedRasterEncoder<W>),
+}
+
+impl<W> RasterEncoder<W> for CupsRasterUnifiedEncoder<W>
+where
+    W: DerefMut<Target: AsyncWrite>,
+{
+    fn bytes_remaining(&self) -> u64 {
+        match self {
+            CupsRasterUnifiedEncoder::Uncompressed(encoder) => encoder.bytes_remaining(),
+            CupsRasterUnifiedEncoder::Compressed(encoder) => encoder.bytes_remaining(),
+        }
+    }
+
+    fn bytes_per_line(&self) -> u64 {
+        match self {
+            CupsRasterUnifiedEncoder::Uncompressed(encoder) => encoder.bytes_per_line(),
+            CupsRasterUnifiedEncoder::Compressed(encoder) => encoder.bytes_per_line(),
+        }
+    }
+
+    fn bytes_total(&self) -> u64 {
+        match self {
+            CupsRasterUnifiedEncoder::Uncompressed(encoder) => encoder.bytes_total(),
+            CupsRasterUnifiedEncoder::Compressed(encoder) => encoder.bytes_total(),
+        }
+    }
+
+    fn bytes_emitted(&self) -> u64 {
+        match self {
+            CupsRasterUnifiedEncoder::Uncompressed(encoder) => encoder.bytes_emitted(),
+            CupsRasterUnifiedEncoder::Compressed(encoder) => encoder.bytes_emitted(),
+        }
+    }
+
+    fn into_pin_mut(self) -> Pin<W> {
+        match self {
+            CupsRasterUnifiedEncoder::Uncompressed(encoder) => encoder.into_pin_mut(),
+            CupsRasterUnifiedEncoder::Compressed(encoder) => encoder.into_pin_mut(),
+        }
+    }
+}
+
+impl<W> AsyncWrite for CupsRasterUnifiedEncoder<W>
+where
+    W: DerefMut<Target: AsyncWrite>,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        match self.project() {
+            CupsRasterEncoderProj::Uncompressed(encoder) => encoder.poll_write(cx, buf),
+            CupsRasterEncoderProj::Compressed(encoder) => encoder.poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        match self.project() {
+            CupsRasterEncoderProj::Uncompressed(encoder) => encoder.poll_flush(cx),
+            CupsRasterEncoderProj::Compressed(encoder) => encoder.poll_flush(cx),
+        }
+    }
+
+    fn poll_close(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        match self.project() {
+            CupsRasterEncoderProj::Uncompressed(encoder) => encoder.poll_close(cx),
+            CupsRasterEncoderProj::Compressed(encoder) => encoder.poll_close(cx),
+        }
+    }
+}
@@ -9,6 +9,15 @@ pub trait RasterEncoder<W>: AsyncWrite
     W: DerefMut<Target: AsyncWrite>,
 {
     fn bytes_remaining(&self) -> u64;
+    /// Returns the size in bytes of a line, or zero if the encoder is not aware of lines,
+    /// which is the default.
+    fn bytes_per_line(&self) -> u64 {
+        0
+    }
+    /// Returns the size in bytes of the whole content, as declared by the page header.
+    fn bytes_total(&self) -> u64;
+    /// Returns the number of encoded bytes written to the underlying writer so far.
+    fn bytes_emitted(&self) -> u64;
     fn into_pin_mut(self) -> Pin<W>;
 }
 
@@ -0,0 +1,209 @@
+use super::RasterEncoder;
+use futures::task::Context;
+use futures::task::Poll;
+use futures::AsyncWrite;
+use pin_project::pin_project;
+use std::io;
+use std::ops::DerefMut;
+use std::pin::Pin;
+
+/// A hash function fed with the bytes written to a [`HashingEncoder`].
+///
+/// This follows the `update`/`finalize` shape of the hashers of the `digest` crate,
+/// so those are wrapped easily.
+pub trait PixelDigest {
+    type Output;
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self) -> Self::Output;
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// The CRC-32 checksum of IEEE 802.3, as used by zlib and PNG.
+#[derive(Clone, Debug)]
+pub struct Crc32 {
+    state: u32,
+}
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self { state: !0 }
+    }
+}
+
+impl PixelDigest for Crc32 {
+    type Output = u32;
+
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.state =
+                CRC32_TABLE[((self.state ^ byte as u32) & 0xff) as usize] ^ (self.state >> 8);
+        }
+    }
+
+    fn finalize(self) -> u32 {
+        !self.state
+    }
+}
+
+/// An encoder hashing the bytes written to it before they are encoded by `inner`.
+///
+/// The hash covers the pixels rather than their encoding, so it is the same for the
+/// compressed and the uncompressed encoding of a page, e.g. to check that a transcode
+/// kept the content of the pages.
+#[pin_project]
+pub struct HashingEncoder<E, H> {
+    #[pin]
+    inner: E,
+    hasher: H,
+}
+
+impl<E, H> HashingEncoder<E, H>
+where
+    H: PixelDigest,
+{
+    pub fn new(inner: E, hasher: H) -> Self {
+        Self { inner, hasher }
+    }
+
+    pub fn get_ref(&self) -> &E {
+        &self.inner
+    }
+
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    pub fn into_inner(self) -> (E, H) {
+        (self.inner, self.hasher)
+    }
+
+    /// Returns the hash of the bytes written so far, dropping the inner encoder.
+    pub fn finalize_hash(self) -> H::Output {
+        self.hasher.finalize()
+    }
+}
+
+impl<E, H, W> RasterEncoder<W> for HashingEncoder<E, H>
+where
+    E: RasterEncoder<W>,
+    H: PixelDigest,
+    W: DerefMut<Target: AsyncWrite>,
+{
+    fn bytes_remaining(&self) -> u64 {
+        self.inner.bytes_remaining()
+    }
+
+    fn bytes_per_line(&self) -> u64 {
+        self.inner.bytes_per_line()
+    }
+
+    fn bytes_total(&self) -> u64 {
+        self.inner.bytes_total()
+    }
+
+    fn bytes_emitted(&self) -> u64 {
+        self.inner.bytes_emitted()
+    }
+
+    fn into_pin_mut(self) -> Pin<W> {
+        self.inner.into_pin_mut()
+    }
+}
+
+impl<E, H> AsyncWrite for HashingEncoder<E, H>
+where
+    E: AsyncWrite,
+    H: PixelDigest,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        let result = this.inner.poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = result {
+            // only the bytes taken by the encoder are hashed
+            this.hasher.update(&buf[..n]);
+        }
+        result
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Crc32, HashingEncoder, PixelDigest};
+    use crate::encode::{CompressedRasterEncoder, UncompressedRasterEncoder};
+    use futures::AsyncWriteExt;
+    use std::pin::Pin;
+
+    #[test]
+    fn test_crc32() {
+        let mut crc = Crc32::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.finalize(), 0xcbf4_3926);
+        assert_eq!(Crc32::new().finalize(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_hashing_encoder() {
+        let mut pixels = vec![0x10u8; 24];
+        pixels.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+
+        let mut compressed = Vec::new();
+        let encoder =
+            CompressedRasterEncoder::new(Pin::new(&mut compressed), 3, 12, pixels.len() as u64)
+                .unwrap();
+        let mut encoder = HashingEncoder::new(encoder, Crc32::new());
+        encoder.write_all(&pixels).await.unwrap();
+        encoder.flush().await.unwrap();
+        let compressed_hash = encoder.finalize_hash();
+
+        let mut uncompressed = Vec::new();
+        let encoder =
+            UncompressedRasterEncoder::new(Pin::new(&mut uncompressed), pixels.len() as u64);
+        let mut encoder = HashingEncoder::new(encoder, Crc32::new());
+        encoder.write_all(&pixels).await.unwrap();
+        let uncompressed_hash = encoder.finalize_hash();
+
+        assert_ne!(compressed, uncompressed);
+        assert_eq!(compressed_hash, uncompressed_hash);
+        let mut crc = Crc32::new();
+        crc.update(&pixels);
+        assert_eq!(compressed_hash, crc.finalize());
+    }
+}
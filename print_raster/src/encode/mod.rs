@@ -1,6 +1,10 @@
 mod compressed;
+mod cups;
 mod encoder;
+mod hashing;
 mod uncompressed;
 pub use compressed::*;
+pub use cups::*;
 pub use encoder::*;
+pub use hashing::*;
 pub use uncompressed::*;
@@ -11,16 +11,29 @@
 #[pin_project]
 pub struct UncompressedRasterEncoder<W> {
     writer: Pin<W>,
+    bytes_per_line: u64,
     bytes_remaining: u64,
+    bytes_total: u64,
+    bytes_emitted: u64,
 }
 
 impl<W> UncompressedRasterEncoder<W> {
     pub fn new(writer: Pin<W>, num_bytes: u64) -> Self {
         Self {
             writer,
+            bytes_per_line: 0,
             bytes_remaining: num_bytes,
+            bytes_total: num_bytes,
+            bytes_emitted: 0,
         }
     }
+
+    /// Sets the line size reported by [`bytes_per_line`](RasterEncoder::bytes_per_line),
+    /// which is zero by default as the content is written as is.
+    pub fn with_bytes_per_line(mut self, bytes_per_line: u64) -> Self {
+        self.bytes_per_line = bytes_per_line;
+        self
+    }
 }
 
 impl<W> RasterEncoder<W> for UncompressedRasterEncoder<W>
@@ -31,6 +44,18 @@ fn bytes_remaining(&self) -> u64 {
         self.bytes_remaining
     }
 
+    fn bytes_per_line(&self) -> u64 {
+        self.bytes_per_line
+    }
+
+    fn bytes_total(&self) -> u64 {
+        self.bytes_total
+    }
+
+    fn bytes_emitted(&self) -> u64 {
+        self.bytes_emitted
+    }
+
     fn into_pin_mut(self) -> Pin<W> {
         self.writer
     }
@@ -47,13 +72,21 @@ fn poll_write(
     ) -> Poll<io::Result<usize>> {
         let this = self.project();
         let writer = this.writer;
-        let buf_size = (*this.bytes_remaining).min(buf.len() as u64) as usize;
-        buf = &buf[..buf_size];
-        if buf_size == 0 {
+        if buf.is_empty() {
             return Poll::Ready(Ok(0));
         }
+        if *this.bytes_remaining == 0 {
+            // the excess would be dropped silently otherwise
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "all bytes of the page have been written",
+            )));
+        }
+        let buf_size = (*this.bytes_remaining).min(buf.len() as u64) as usize;
+        buf = &buf[..buf_size];
         let total_write = ready!(writer.as_mut().poll_write(cx, buf))?;
         *this.bytes_remaining = this.bytes_remaining.saturating_sub(total_write as u64);
+        *this.bytes_emitted += total_write as u64;
         Poll::Ready(Ok(total_write))
     }
 
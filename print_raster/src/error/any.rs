@@ -0,0 +1,25 @@
+use super::{CupsRasterError, UrfError};
+use thiserror::Error;
+
+/// An error of [`AnyRasterReader`](crate::reader::AnyRasterReader), raised by the reader
+/// of the detected format or while detecting it.
+#[derive(Error, Debug)]
+pub enum AnyRasterError {
+    #[error("IO error")]
+    IoError(#[from] std::io::Error),
+    #[error("CUPS raster error")]
+    Cups(#[from] CupsRasterError),
+    #[error("URF error")]
+    Urf(#[from] UrfError),
+}
+
+impl AnyRasterError {
+    /// Returns `true` if the stream ended in the middle of a header or page content.
+    pub fn is_unexpected_eof(&self) -> bool {
+        match self {
+            Self::IoError(e) => e.kind() == std::io::ErrorKind::UnexpectedEof,
+            Self::Cups(e) => e.is_unexpected_eof(),
+            Self::Urf(e) => e.is_unexpected_eof(),
+        }
+    }
+}
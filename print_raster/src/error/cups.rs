@@ -1,3 +1,5 @@
+use super::{ascii, hex, CommonRasterError};
+use crate::factory::PageGeometry;
 use crate::model::cups::{
     CupsAdvance, CupsColorOrder, CupsColorSpace, CupsCut, CupsJog, CupsLeadingEdge, CupsOrientation,
 };
@@ -9,8 +11,8 @@
 pub enum CupsRasterError {
     #[error("IO error")]
     IoError(#[from] std::io::Error),
-    #[error("Invalid sync word")]
-    InvalidSyncWord,
+    #[error("Invalid sync word: {} ({:?})", hex(.0), ascii(.0))]
+    InvalidSyncWord([u8; 4]),
     #[error("Invalid string")]
     InvalidString(#[from] Utf8Error),
     #[error("Unknown advance media")]
@@ -33,4 +35,61 @@ pub enum CupsRasterError {
     DataLayoutError,
     #[error("Data too large")]
     DataTooLarge,
+    #[error("Invalid page header: {0}")]
+    InvalidHeader(&'static str),
+    #[error("Page header is {actual} bytes long, expected {expected}")]
+    ShortHeader { expected: usize, actual: usize },
+    #[error(
+        "Page declares at least {declared} bytes, but only {remaining} bytes remain in the stream"
+    )]
+    DeclaredSizeExceedsStream { declared: u64, remaining: u64 },
+    #[error("Page geometry changed from {expected:?} to {actual:?}")]
+    GeometryChanged {
+        expected: PageGeometry,
+        actual: PageGeometry,
+    },
+    #[error("Stream has more than {max_pages} pages")]
+    TooManyPages { max_pages: u64 },
+    #[error("Page is incomplete, {bytes_remaining} bytes of content are not written")]
+    IncompletePage { bytes_remaining: u64 },
+    #[error("Page {index} is beyond the end of the stream")]
+    PageOutOfRange { index: usize },
+    #[error("Unsupported pixel layout: {0}")]
+    UnsupportedLayout(&'static str),
+    #[error("Unsupported color conversion from {from:?} to {to:?}")]
+    UnsupportedColorConversion {
+        from: CupsColorSpace,
+        to: CupsColorSpace,
+    },
+    #[cfg(feature = "image")]
+    #[error("Image conversion error")]
+    ImageConversion(#[from] super::ImageConversionError),
+}
+
+impl CupsRasterError {
+    /// Returns `true` if the stream ended in the middle of a header or page content.
+    pub fn is_unexpected_eof(&self) -> bool {
+        matches!(self, Self::IoError(e) if e.kind() == std::io::ErrorKind::UnexpectedEof)
+    }
+}
+
+impl CommonRasterError for CupsRasterError {
+    fn declared_size_exceeds_stream(declared: u64, remaining: u64) -> Self {
+        Self::DeclaredSizeExceedsStream {
+            declared,
+            remaining,
+        }
+    }
+
+    fn geometry_changed(expected: PageGeometry, actual: PageGeometry) -> Self {
+        Self::GeometryChanged { expected, actual }
+    }
+
+    fn too_many_pages(max_pages: u64) -> Self {
+        Self::TooManyPages { max_pages }
+    }
+
+    fn incomplete_page(bytes_remaining: u64) -> Self {
+        Self::IncompletePage { bytes_remaining }
+    }
 }
@@ -0,0 +1,32 @@
+use crate::model::cups::{CupsColorOrder, CupsColorSpace};
+use crate::model::urf::UrfColorSpace;
+use ::image::ColorType;
+use thiserror::Error;
+
+/// An error converting between a page and an image of the `image` crate.
+#[derive(Error, Debug)]
+pub enum ImageConversionError {
+    #[error("Color space {0} cannot be represented as an image")]
+    UnsupportedCupsColorSpace(CupsColorSpace),
+    #[error("Color space {0} cannot be represented as an image")]
+    UnsupportedUrfColorSpace(UrfColorSpace),
+    #[error("Color order {0:?} cannot be represented as an image")]
+    UnsupportedColorOrder(CupsColorOrder),
+    #[error("Colors of {0} bits cannot be represented as an image")]
+    UnsupportedBitsPerColor(u32),
+    #[error("Colors of {0} bits need the byte order of the stream to be read")]
+    UnknownByteOrder(u32),
+    #[error("Lines of {bytes_per_line} bytes cannot hold {min_bytes_per_line} bytes of pixels")]
+    InvalidBytesPerLine {
+        bytes_per_line: u64,
+        min_bytes_per_line: u64,
+    },
+    #[error("Page data is {actual} bytes long, expected {expected}")]
+    DataTooShort { expected: u64, actual: u64 },
+    #[error(
+        "Images of color type {0:?} cannot be written to a page, only L8 and Rgb8 are supported"
+    )]
+    UnsupportedColorType(ColorType),
+    #[error("Resolution of {0} dpi is invalid")]
+    InvalidResolution(u32),
+}
@@ -1,4 +1,44 @@
+mod any;
 mod cups;
+#[cfg(feature = "image")]
+mod image;
 mod urf;
+#[cfg(feature = "image")]
+pub use self::image::ImageConversionError;
+pub use any::AnyRasterError;
 pub use cups::CupsRasterError;
 pub use urf::UrfError;
+
+use crate::factory::PageGeometry;
+
+/// Formats `bytes` as space-separated hex, e.g. for the bytes at the start of a stream.
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Formats `bytes` as ASCII, replacing the bytes that are not printable by `.`.
+fn ascii(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            }
+        })
+        .collect()
+}
+
+/// Errors raised by the format-independent parts of the crate,
+/// implemented by the error type of every raster format.
+pub trait CommonRasterError: std::error::Error + From<std::io::Error> + 'static {
+    fn declared_size_exceeds_stream(declared: u64, remaining: u64) -> Self;
+    fn geometry_changed(expected: PageGeometry, actual: PageGeometry) -> Self;
+    fn too_many_pages(max_pages: u64) -> Self;
+    fn incomplete_page(bytes_remaining: u64) -> Self;
+}
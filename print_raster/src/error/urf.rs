@@ -1,3 +1,6 @@
+use super::{ascii, hex, CommonRasterError};
+use crate::factory::PageGeometry;
+use crate::model::cups::CupsColorSpace;
 use crate::model::urf::{UrfColorSpace, UrfDuplex, UrfMediaPosition, UrfMediaType, UrfQuality};
 use num_enum::TryFromPrimitiveError;
 use thiserror::Error;
@@ -6,8 +9,8 @@
 pub enum UrfError {
     #[error("IO error")]
     IoError(#[from] std::io::Error),
-    #[error("Invalid magic")]
-    InvalidMagic,
+    #[error("Invalid magic: {} ({:?})", hex(.0), ascii(.0))]
+    InvalidMagic([u8; 8]),
     #[error("Unknown color space")]
     UnknownColorSpace(#[from] TryFromPrimitiveError<UrfColorSpace>),
     #[error("Unknown duplex")]
@@ -20,4 +23,63 @@ pub enum UrfError {
     UnknownMediaType(#[from] TryFromPrimitiveError<UrfMediaType>),
     #[error("Data too large")]
     DataTooLarge,
+    #[error("Invalid page header: {0}")]
+    InvalidHeader(&'static str),
+    #[error("{bits_per_pixel} bits per pixel are not colors of 8 or 16 bits of {color_space}")]
+    InvalidBitsPerPixel {
+        bits_per_pixel: u8,
+        color_space: UrfColorSpace,
+    },
+    #[error("Page header is {actual} bytes long, expected {expected}")]
+    ShortHeader { expected: usize, actual: usize },
+    #[error(
+        "Page declares at least {declared} bytes, but only {remaining} bytes remain in the stream"
+    )]
+    DeclaredSizeExceedsStream { declared: u64, remaining: u64 },
+    #[error("Page geometry changed from {expected:?} to {actual:?}")]
+    GeometryChanged {
+        expected: PageGeometry,
+        actual: PageGeometry,
+    },
+    #[error("Stream has more than {max_pages} pages")]
+    TooManyPages { max_pages: u64 },
+    #[error("Page is incomplete, {bytes_remaining} bytes of content are not written")]
+    IncompletePage { bytes_remaining: u64 },
+    #[error("Color space {0:?} cannot be represented in URF")]
+    UnsupportedColorSpace(CupsColorSpace),
+    #[error("Resolution {cross_feed}x{feed} is not the same in both directions")]
+    AnisotropicResolution { cross_feed: u32, feed: u32 },
+    #[error("Header declares {page_count} pages, but the stream can hold at most {max_plausible}")]
+    ImplausiblePageCount { page_count: u32, max_plausible: u64 },
+    #[cfg(feature = "image")]
+    #[error("Image conversion error")]
+    ImageConversion(#[from] super::ImageConversionError),
+}
+
+impl UrfError {
+    /// Returns `true` if the stream ended in the middle of a header or page content.
+    pub fn is_unexpected_eof(&self) -> bool {
+        matches!(self, Self::IoError(e) if e.kind() == std::io::ErrorKind::UnexpectedEof)
+    }
+}
+
+impl CommonRasterError for UrfError {
+    fn declared_size_exceeds_stream(declared: u64, remaining: u64) -> Self {
+        Self::DeclaredSizeExceedsStream {
+            declared,
+            remaining,
+        }
+    }
+
+    fn geometry_changed(expected: PageGeometry, actual: PageGeometry) -> Self {
+        Self::GeometryChanged { expected, actual }
+    }
+
+    fn too_many_pages(max_pages: u64) -> Self {
+        Self::TooManyPages { max_pages }
+    }
+
+    fn incomplete_page(bytes_remaining: u64) -> Self {
+        Self::IncompletePage { bytes_remaining }
+    }
 }
@@ -1,12 +1,13 @@
-use super::RasterPageFactory;
+use super::{PageGeometry, RasterPageFactory};
 use crate::{
     decode::{CompressedRasterDecoder, Limits, UncompressedRasterDecoder},
     encode::{CompressedRasterEncoder, UncompressedRasterEncoder},
     error::CupsRasterError,
     model::cups::{
-        CupsAdvance, CupsColorOrder, CupsColorSpace, CupsCut, CupsImagingBoundingBox, CupsJog,
-        CupsLeadingEdge, CupsMargins, CupsOrientation, CupsPageHeaderV1, CupsPageHeaderV2,
-        CupsPageSize, CupsResolution, CupsSyncWord,
+        check_banded_layout, check_kcmycm_layout, effective_bytes_per_line, CupsAdvance,
+        CupsColorOrder, CupsColorSpace, CupsCut, CupsImagingBoundingBox, CupsJog, CupsLeadingEdge,
+        CupsMargins, CupsOrientation, CupsPageHeaderV1, CupsPageHeaderV2, CupsPageSize,
+        CupsResolution, CupsSyncWord, CUPS_V1_PAGE_HEADER_SIZE, CUPS_V2_PAGE_HEADER_SIZE,
     },
 };
 use byteorder::{BigEndian, ByteOrder, LittleEndian};
@@ -42,10 +43,21 @@ fn write_bool<TOrder>(content: &mut [u8], b: bool)
     TOrder::write_u32(content, if b { 1 } else { 0 });
 }
 
+fn check_header_len(content: &[u8], expected: usize) -> Result<(), CupsRasterError> {
+    if content.len() < expected {
+        return Err(CupsRasterError::ShortHeader {
+            expected,
+            actual: content.len(),
+        });
+    }
+    Ok(())
+}
+
 fn read_page_header_v1<TOrder>(content: &[u8]) -> Result<CupsPageHeaderV1, CupsRasterError>
 where
     TOrder: ByteOrder,
 {
+    check_header_len(content, CUPS_V1_PAGE_HEADER_SIZE)?;
     let header = CupsPageHeaderV1 {
         media_class: read_c_string(&content[0..64])?,
         media_color: read_c_string(&content[64..128])?,
@@ -101,16 +113,18 @@ fn read_page_header_v1<TOrder>(content: &[u8]) -> Result<CupsPageHeaderV1, CupsR
         cups_row_feed: TOrder::read_u32(&content[412..416]),
         cups_row_step: TOrder::read_u32(&content[416..420]),
     };
-    let chunk_size = match header.color_order {
-        CupsColorOrder::Chunky => u8::try_from((header.bits_per_pixel as u64 + 7) / 8)
-            .map_err(|_| CupsRasterError::DataTooLarge)?,
-        CupsColorOrder::Banded | CupsColorOrder::Planar => {
-            u8::try_from((header.bits_per_color as u64 + 7) / 8)
-                .map_err(|_| CupsRasterError::DataTooLarge)?
-        }
+    let chunk_size = chunk_size(&header)?;
+    if header.bytes_per_line != 0 && !header.bytes_per_line.is_multiple_of(chunk_size as u32) {
+        return Err(CupsRasterError::DataLayoutError);
     }
-    .max(1);
-    if header.bytes_per_line != 0 && header.bytes_per_line % chunk_size as u32 != 0 {
+    check_kcmycm_layout(&header)?;
+    // a shorter line would cut off the pixels at the end of each line;
+    // banded and planar lines hold at least one color
+    let bits_per_line_unit = match header.color_order {
+        CupsColorOrder::Chunky => header.bits_per_pixel,
+        CupsColorOrder::Banded | CupsColorOrder::Planar => header.bits_per_color,
+    };
+    if (header.bytes_per_line as u64) * 8 < header.width as u64 * bits_per_line_unit as u64 {
         return Err(CupsRasterError::DataLayoutError);
     }
     Ok(header)
@@ -119,10 +133,12 @@ fn read_page_header_v1<TOrder>(content: &[u8]) -> Result<CupsPageHeaderV1, CupsR
 fn write_page_header_v1<TOrder>(
     content: &mut [u8],
     header: &CupsPageHeaderV1,
+    num_colors: u32,
 ) -> Result<(), CupsRasterError>
 where
     TOrder: ByteOrder,
 {
+    check_kcmycm_layout(header)?;
     write_c_string(&mut content[0..64], &header.media_class)?;
     write_c_string(&mut content[64..128], &header.media_color)?;
     write_c_string(&mut content[128..192], &header.media_type)?;
@@ -161,7 +177,10 @@ fn write_page_header_v1<TOrder>(
     TOrder::write_u32(&mut content[380..384], header.cups_media_type);
     TOrder::write_u32(&mut content[384..388], header.bits_per_color);
     TOrder::write_u32(&mut content[388..392], header.bits_per_pixel);
-    TOrder::write_u32(&mut content[392..396], header.bytes_per_line);
+    TOrder::write_u32(
+        &mut content[392..396],
+        effective_bytes_per_line(header, num_colors),
+    );
     TOrder::write_u32(&mut content[396..400], header.color_order as u32);
     TOrder::write_u32(&mut content[400..404], header.color_space as u32);
     TOrder::write_u32(&mut content[404..408], header.cups_compression);
@@ -175,6 +194,7 @@ fn read_page_header_v2<TOrder>(content: &[u8]) -> Result<CupsPageHeaderV2, CupsR
 where
     TOrder: ByteOrder,
 {
+    check_header_len(content, CUPS_V2_PAGE_HEADER_SIZE)?;
     Ok(CupsPageHeaderV2 {
         v1: read_page_header_v1::<TOrder>(&content[0..420])?,
         num_colors: TOrder::read_u32(&content[420..424]),
@@ -208,7 +228,7 @@ fn write_page_header_v2<TOrder>(
 where
     TOrder: ByteOrder,
 {
-    write_page_header_v1::<TOrder>(&mut content[0..420], &header.v1)?;
+    write_page_header_v1::<TOrder>(&mut content[0..420], &header.v1, header.num_colors())?;
     TOrder::write_u32(&mut content[420..424], header.num_colors);
     TOrder::write_f32(&mut content[424..428], header.borderless_scaling_factor);
     TOrder::write_f32(&mut content[428..432], header.page_size_f32.width);
@@ -258,16 +278,25 @@ impl<TOrder> RasterPageFactory for CupsPageFactoryV1<TOrder>
 {
     type Header = CupsPageHeaderV1;
     type Error = CupsRasterError;
-    const HEADER_SIZE: usize = 420;
+    const HEADER_SIZE: usize = CUPS_V1_PAGE_HEADER_SIZE;
     fn header_from_bytes(content: &[u8]) -> Result<Self::Header, Self::Error> {
         read_page_header_v1::<TOrder>(content)
     }
     fn header_to_bytes(target: &mut [u8], header: &Self::Header) -> Result<(), Self::Error> {
-        write_page_header_v1::<TOrder>(target, header)
+        write_page_header_v1::<TOrder>(target, header, header.num_colors())
+    }
+    fn geometry(header: &Self::Header) -> PageGeometry {
+        PageGeometry {
+            width: header.width,
+            height: header.height,
+            color_space: header.color_space as u32,
+        }
     }
 
-    type Decoder<R> = UncompressedRasterDecoder<R>
-    where R: DerefMut<Target: AsyncRead>;
+    type Decoder<R>
+        = UncompressedRasterDecoder<R>
+    where
+        R: DerefMut<Target: AsyncRead>;
     fn decode<R>(
         header: &Self::Header,
         reader: Pin<R>,
@@ -276,50 +305,78 @@ fn decode<R>(
     where
         R: DerefMut<Target: AsyncRead>,
     {
-        let num_bytes = match header.color_order {
-            CupsColorOrder::Chunky | CupsColorOrder::Banded => {
-                header.bytes_per_line as u64 * header.height as u64
-            }
-            CupsColorOrder::Planar => (header.bytes_per_line as u64 * header.height as u64)
-                .checked_mul(header.num_colors() as u64)
-                .ok_or(CupsRasterError::DataTooLarge)?,
-        };
-        Ok(UncompressedRasterDecoder::new(reader, limits, num_bytes)?)
+        if !limits.allows_dimensions(header.width, header.height) {
+            return Err(CupsRasterError::DataTooLarge);
+        }
+        let num_bytes = header.image_byte_len()?;
+        Ok(UncompressedRasterDecoder::new(reader, limits, num_bytes)?
+            .with_bytes_per_line(header.bytes_per_line as u64))
     }
 
-    type Encoder<W> = UncompressedRasterEncoder<W> where
-    W: DerefMut<Target: AsyncWrite>;
+    type Encoder<W>
+        = UncompressedRasterEncoder<W>
+    where
+        W: DerefMut<Target: AsyncWrite>;
     fn encode<W>(header: &Self::Header, writer: Pin<W>) -> Result<Self::Encoder<W>, Self::Error>
     where
         W: DerefMut<Target: AsyncWrite>,
     {
-        let num_bytes = match header.color_order {
-            CupsColorOrder::Chunky | CupsColorOrder::Banded => {
-                header.bytes_per_line as u64 * header.height as u64
-            }
-            CupsColorOrder::Planar => (header.bytes_per_line as u64 * header.height as u64)
-                .checked_mul(header.num_colors() as u64)
-                .ok_or(CupsRasterError::DataTooLarge)?,
-        };
-        Ok(UncompressedRasterEncoder::new(writer, num_bytes))
+        let bytes_per_line = effective_bytes_per_line(header, header.num_colors()) as u64;
+        let num_bytes = header.image_byte_len()?;
+        Ok(UncompressedRasterEncoder::new(writer, num_bytes).with_bytes_per_line(bytes_per_line))
     }
 }
 
+/// Returns the chunk size of the run-length encoding, i.e. the size in bytes of a pixel
+/// of chunky pages or of a color of banded and planar pages, and at least a byte.
+fn chunk_size(header: &CupsPageHeaderV1) -> Result<u8, CupsRasterError> {
+    let bits_per_chunk = match header.color_order {
+        CupsColorOrder::Chunky => {
+            // pixels of whole bytes are laid out by the color space, unless padded
+            let bytes_per_pixel = u8::try_from(header.bits_per_color)
+                .ok()
+                .and_then(|bits_per_color| header.color_space.bytes_per_pixel(bits_per_color))
+                .filter(|&bytes| bytes as u32 * 8 == header.bits_per_pixel);
+            if let Some(bytes_per_pixel) = bytes_per_pixel {
+                return Ok(bytes_per_pixel);
+            }
+            header.bits_per_pixel
+        }
+        CupsColorOrder::Banded | CupsColorOrder::Planar => header.bits_per_color,
+    };
+    Ok(u8::try_from((bits_per_chunk as u64).div_ceil(8))
+        .map_err(|_| CupsRasterError::DataTooLarge)?
+        .max(1))
+}
+
+/// Returns the chunk size of the run-length encoding and the size in bytes of a line
+/// of the compressed content of a V2 page.
+pub(crate) fn compressed_layout(header: &CupsPageHeaderV2) -> Result<(u8, u64), CupsRasterError> {
+    check_banded_layout(&header.v1, header.num_colors())?;
+    let chunk_size = chunk_size(&header.v1)?;
+    let bytes_per_line = effective_bytes_per_line(&header.v1, header.num_colors()) as u64;
+    Ok((chunk_size, bytes_per_line))
+}
+
 impl<TOrder> RasterPageFactory for CupsPageFactoryV2<TOrder>
 where
     TOrder: ByteOrder,
 {
     type Header = CupsPageHeaderV2;
     type Error = CupsRasterError;
-    const HEADER_SIZE: usize = 1796;
+    const HEADER_SIZE: usize = CUPS_V2_PAGE_HEADER_SIZE;
     fn header_from_bytes(content: &[u8]) -> Result<Self::Header, Self::Error> {
         read_page_header_v2::<TOrder>(content)
     }
     fn header_to_bytes(target: &mut [u8], header: &Self::Header) -> Result<(), Self::Error> {
         write_page_header_v2::<TOrder>(target, header)
     }
+    fn geometry(header: &Self::Header) -> PageGeometry {
+        CupsPageFactoryV1::<TOrder>::geometry(&header.v1)
+    }
 
-    type Decoder<R> = CompressedRasterDecoder<R>
+    type Decoder<R>
+        = CompressedRasterDecoder<R>
     where
         R: DerefMut<Target: AsyncRead>;
     fn decode<R>(
@@ -330,34 +387,20 @@ fn decode<R>(
     where
         R: DerefMut<Target: AsyncRead>,
     {
-        let chunk_size = match header.v1.color_order {
-            CupsColorOrder::Chunky => u8::try_from((header.v1.bits_per_pixel as u64 + 7) / 8)
-                .map_err(|_| CupsRasterError::DataTooLarge)?,
-            CupsColorOrder::Banded | CupsColorOrder::Planar => {
-                u8::try_from((header.v1.bits_per_color as u64 + 7) / 8)
-                    .map_err(|_| CupsRasterError::DataTooLarge)?
-            }
+        if !limits.allows_dimensions(header.v1.width, header.v1.height) {
+            return Err(CupsRasterError::DataTooLarge);
         }
-        .max(1);
-        let bytes_per_line = header.v1.bytes_per_line as u64;
-        let num_bytes = match header.v1.color_order {
-            CupsColorOrder::Chunky | CupsColorOrder::Banded => {
-                header.v1.bytes_per_line as u64 * header.v1.height as u64
-            }
-            CupsColorOrder::Planar => (header.v1.bytes_per_line as u64 * header.v1.height as u64)
-                .checked_mul(header.num_colors() as u64)
-                .ok_or(CupsRasterError::DataTooLarge)?,
-        };
-        let fill_byte = match header.v1.color_space {
-            CupsColorSpace::sGray
-            | CupsColorSpace::sRGB
-            | CupsColorSpace::CIELab
-            | CupsColorSpace::AdobeRGB
-            | CupsColorSpace::Gray
-            | CupsColorSpace::RGB
-            | CupsColorSpace::RGBA
-            | CupsColorSpace::RGBW => 0xffu8,
-            _ => 0u8,
+        let (chunk_size, bytes_per_line) = compressed_layout(header)?;
+        // planar pages are `num_colors` planes of `height` lines, each line compressed on
+        // its own as in CUPS, so a repeated line may run over the end of a plane
+        let num_bytes = header.image_byte_len()?;
+        // the height is checked above, and a planar page has a plane of lines per color
+        let limits = match header.v1.color_order {
+            CupsColorOrder::Planar => &Limits {
+                max_height: limits.max_height.saturating_mul(header.num_colors()),
+                ..limits.clone()
+            },
+            _ => limits,
         };
         Ok(CompressedRasterDecoder::new(
             reader,
@@ -365,35 +408,22 @@ fn decode<R>(
             chunk_size,
             bytes_per_line,
             num_bytes,
-            fill_byte,
+            // unused, CUPS Raster has no fill-to-end block
+            0,
+            false,
         )?)
     }
 
-    type Encoder<W> = CompressedRasterEncoder<W>
+    type Encoder<W>
+        = CompressedRasterEncoder<W>
     where
         W: DerefMut<Target: AsyncWrite>;
     fn encode<W>(header: &Self::Header, writer: Pin<W>) -> Result<Self::Encoder<W>, Self::Error>
     where
         W: DerefMut<Target: AsyncWrite>,
     {
-        let chunk_size = match header.v1.color_order {
-            CupsColorOrder::Chunky => u8::try_from((header.v1.bits_per_pixel as u64 + 7) / 8)
-                .map_err(|_| CupsRasterError::DataTooLarge)?,
-            CupsColorOrder::Banded | CupsColorOrder::Planar => {
-                u8::try_from((header.v1.bits_per_color as u64 + 7) / 8)
-                    .map_err(|_| CupsRasterError::DataTooLarge)?
-            }
-        }
-        .max(1);
-        let bytes_per_line = header.v1.bytes_per_line as u64;
-        let num_bytes = match header.v1.color_order {
-            CupsColorOrder::Chunky | CupsColorOrder::Banded => {
-                header.v1.bytes_per_line as u64 * header.v1.height as u64
-            }
-            CupsColorOrder::Planar => (header.v1.bytes_per_line as u64 * header.v1.height as u64)
-                .checked_mul(header.num_colors() as u64)
-                .ok_or(CupsRasterError::DataTooLarge)?,
-        };
+        let (chunk_size, bytes_per_line) = compressed_layout(header)?;
+        let num_bytes = header.image_byte_len()?;
         Ok(CompressedRasterEncoder::new(
             writer,
             chunk_size,
@@ -409,15 +439,19 @@ impl<TOrder> RasterPageFactory for CupsPageFactoryV3<TOrder>
 {
     type Header = CupsPageHeaderV2;
     type Error = CupsRasterError;
-    const HEADER_SIZE: usize = 1796;
+    const HEADER_SIZE: usize = CUPS_V2_PAGE_HEADER_SIZE;
     fn header_from_bytes(content: &[u8]) -> Result<Self::Header, Self::Error> {
         read_page_header_v2::<TOrder>(content)
     }
     fn header_to_bytes(target: &mut [u8], header: &Self::Header) -> Result<(), Self::Error> {
         write_page_header_v2::<TOrder>(target, header)
     }
+    fn geometry(header: &Self::Header) -> PageGeometry {
+        CupsPageFactoryV1::<TOrder>::geometry(&header.v1)
+    }
 
-    type Decoder<R> = UncompressedRasterDecoder<R>
+    type Decoder<R>
+        = UncompressedRasterDecoder<R>
     where
         R: DerefMut<Target: AsyncRead>;
     fn decode<R>(
@@ -428,32 +462,25 @@ fn decode<R>(
     where
         R: DerefMut<Target: AsyncRead>,
     {
-        let num_bytes = match header.v1.color_order {
-            CupsColorOrder::Chunky | CupsColorOrder::Banded => {
-                header.v1.bytes_per_line as u64 * header.v1.height as u64
-            }
-            CupsColorOrder::Planar => (header.v1.bytes_per_line as u64 * header.v1.height as u64)
-                .checked_mul(header.num_colors() as u64)
-                .ok_or(CupsRasterError::DataTooLarge)?,
-        };
-        Ok(UncompressedRasterDecoder::new(reader, limits, num_bytes)?)
+        if !limits.allows_dimensions(header.v1.width, header.v1.height) {
+            return Err(CupsRasterError::DataTooLarge);
+        }
+        let num_bytes = header.image_byte_len()?;
+        Ok(UncompressedRasterDecoder::new(reader, limits, num_bytes)?
+            .with_bytes_per_line(header.v1.bytes_per_line as u64))
     }
 
-    type Encoder<W> = UncompressedRasterEncoder<W> where
-    W: DerefMut<Target: AsyncWrite>;
+    type Encoder<W>
+        = UncompressedRasterEncoder<W>
+    where
+        W: DerefMut<Target: AsyncWrite>;
     fn encode<W>(header: &Self::Header, writer: Pin<W>) -> Result<Self::Encoder<W>, Self::Error>
     where
         W: DerefMut<Target: AsyncWrite>,
     {
-        let num_bytes = match header.v1.color_order {
-            CupsColorOrder::Chunky | CupsColorOrder::Banded => {
-                header.v1.bytes_per_line as u64 * header.v1.height as u64
-            }
-            CupsColorOrder::Planar => (header.v1.bytes_per_line as u64 * header.v1.height as u64)
-                .checked_mul(header.num_colors() as u64)
-                .ok_or(CupsRasterError::DataTooLarge)?,
-        };
-        Ok(UncompressedRasterEncoder::new(writer, num_bytes))
+        let bytes_per_line = effective_bytes_per_line(&header.v1, header.num_colors()) as u64;
+        let num_bytes = header.image_byte_len()?;
+        Ok(UncompressedRasterEncoder::new(writer, num_bytes).with_bytes_per_line(bytes_per_line))
     }
 }
 
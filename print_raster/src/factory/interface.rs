@@ -5,6 +5,15 @@
 use futures::{AsyncRead, AsyncWrite};
 use std::{ops::DerefMut, pin::Pin};
 
+/// The geometry of a page, used to compare the headers of successive pages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PageGeometry {
+    pub width: u32,
+    pub height: u32,
+    /// The format-specific color space value.
+    pub color_space: u32,
+}
+
 pub trait RasterPageFactory
 where
     Self: Sized,
@@ -12,10 +21,16 @@ pub trait RasterPageFactory
     type Header;
     type Error;
     const HEADER_SIZE: usize;
-    /// Parse the header from the given bytes, the bytes are guaranteed to be `HEADER_SIZE` long.
+    /// Bytes written after the content of every page, for format variants
+    /// that delimit pages explicitly. CUPS Raster and URF have none.
+    const EOP_MARKER: Option<&'static [u8]> = None;
+    /// Parse the header from the given bytes, which should be `HEADER_SIZE` long.
+    /// Shorter input is reported as an error.
     fn header_from_bytes(content: &[u8]) -> Result<Self::Header, Self::Error>;
     /// Convert the header to bytes, the bytes will be `HEADER_SIZE` long.
     fn header_to_bytes(target: &mut [u8], header: &Self::Header) -> Result<(), Self::Error>;
+    /// Extract the page geometry from the header.
+    fn geometry(header: &Self::Header) -> PageGeometry;
 
     type Decoder<R>: RasterDecoder<R>
     where
@@ -1,9 +1,10 @@
-use super::RasterPageFactory;
+use super::{PageGeometry, RasterPageFactory};
 use crate::decode::{CompressedRasterDecoder, Limits};
 use crate::encode::CompressedRasterEncoder;
 use crate::error::UrfError;
 use crate::model::urf::{
     UrfColorSpace, UrfDuplex, UrfMediaPosition, UrfMediaType, UrfPageHeader, UrfQuality,
+    URF_PAGE_HEADER_SIZE,
 };
 use futures::{AsyncRead, AsyncWrite};
 use num_enum::TryFromPrimitive;
@@ -15,8 +16,14 @@ pub enum UrfPageFactory {}
 impl RasterPageFactory for UrfPageFactory {
     type Header = UrfPageHeader;
     type Error = UrfError;
-    const HEADER_SIZE: usize = 32;
+    const HEADER_SIZE: usize = URF_PAGE_HEADER_SIZE;
     fn header_from_bytes(content: &[u8]) -> Result<Self::Header, Self::Error> {
+        if content.len() < Self::HEADER_SIZE {
+            return Err(UrfError::ShortHeader {
+                expected: Self::HEADER_SIZE,
+                actual: content.len(),
+            });
+        }
         Ok(UrfPageHeader {
             bits_per_pixel: content[0],
             color_space: UrfColorSpace::try_from_primitive(content[1])?,
@@ -43,9 +50,18 @@ fn header_to_bytes(target: &mut [u8], header: &Self::Header) -> Result<(), Self:
         target[24..32].fill(0);
         Ok(())
     }
+    fn geometry(header: &Self::Header) -> PageGeometry {
+        PageGeometry {
+            width: header.width,
+            height: header.height,
+            color_space: header.color_space as u32,
+        }
+    }
 
-    type Decoder<R> = CompressedRasterDecoder<R>
-        where R: DerefMut<Target: AsyncRead>;
+    type Decoder<R>
+        = CompressedRasterDecoder<R>
+    where
+        R: DerefMut<Target: AsyncRead>;
     fn decode<R>(
         header: &Self::Header,
         reader: Pin<R>,
@@ -54,12 +70,12 @@ fn decode<R>(
     where
         R: DerefMut<Target: AsyncRead>,
     {
-        // for Apple Raster (urf), chunky pixels are used, so the chunk size is the pixel size.
-        let chunk_size = header.bits_per_pixel / 8;
+        if !limits.allows_dimensions(header.width, header.height) {
+            return Err(UrfError::DataTooLarge);
+        }
+        let chunk_size = header.checked_bytes_per_pixel()?;
         let bytes_per_line = header.width as u64 * chunk_size as u64;
-        let num_bytes = (header.width as u64 * header.height as u64)
-            .checked_mul(chunk_size as u64)
-            .ok_or(UrfError::DataTooLarge)?;
+        let num_bytes = header.image_byte_len()?;
         let fill_byte = match header.color_space {
             UrfColorSpace::sGray
             | UrfColorSpace::sRGB
@@ -76,22 +92,21 @@ fn decode<R>(
             bytes_per_line,
             num_bytes,
             fill_byte,
+            true,
         )?)
     }
 
-    type Encoder<W> = CompressedRasterEncoder<W>
+    type Encoder<W>
+        = CompressedRasterEncoder<W>
     where
         W: DerefMut<Target: AsyncWrite>;
     fn encode<W>(header: &Self::Header, writer: Pin<W>) -> Result<Self::Encoder<W>, Self::Error>
     where
         W: DerefMut<Target: AsyncWrite>,
     {
-        // for Apple Raster (urf), chunky pixels are used, so the chunk size is the pixel size.
-        let chunk_size = header.bits_per_pixel / 8;
+        let chunk_size = header.checked_bytes_per_pixel()?;
         let bytes_per_line = header.width as u64 * chunk_size as u64;
-        let num_bytes = (header.width as u64 * header.height as u64)
-            .checked_mul(chunk_size as u64)
-            .ok_or(UrfError::DataTooLarge)?;
+        let num_bytes = header.image_byte_len()?;
         Ok(CompressedRasterEncoder::new(
             writer,
             chunk_size,
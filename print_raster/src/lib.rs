@@ -19,7 +19,7 @@
 //! let pinned_file_reader = pin!(BufReader::new(file.compat()));
 //! // Here we use CupsRasterUnifiedReader for CUPS Raster V1, V2, and V3.
 //! // You may also use UrfPageReader for URF (Apple Raster).
-//! let reader = CupsRasterUnifiedReader::new(pinned_file_reader).await?;
+//! let reader = CupsRasterUnifiedReader::new_buffered(pinned_file_reader).await?;
 //! let mut page_index = 0;
 //! let mut page_next = reader.next_page().await?;
 //! while let Some(mut page) = page_next {
@@ -41,7 +41,7 @@
 //! # });
 //! ```
 //!
-//! You may notice that the original `AsyncRead` instance is wrapped by `BufReader`. It is a common practice because the process of reading raster images will make small and repeated read calls to the underlying reader, which will cause a significant performance drop if the underlying reader is not buffered.
+//! You may notice that the original `AsyncRead` instance is wrapped by `BufReader`. It is a common practice because the process of reading raster images will make small and repeated read calls to the underlying reader, which will cause a significant performance drop if the underlying reader is not buffered. The `new_buffered` constructors take an `AsyncBufRead` source and decode compressed pages directly from its buffer, while `new` accepts any `AsyncRead`.
 //!
 //! ## Writing
 //! Almost the same as reading, but you need to call `finish` after last page is written.
@@ -107,12 +107,17 @@
 //! # });
 //! ```
 
+pub mod convert;
 pub mod decode;
 pub mod encode;
 pub mod error;
 pub mod factory;
+pub mod metrics;
 pub mod model;
 pub mod reader;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod transcode;
 pub mod writer;
 // Re-export byteorder crate.
 pub use byteorder;
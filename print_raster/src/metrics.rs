@@ -0,0 +1,95 @@
+//! Instrumentation hooks for readers and writers.
+//!
+//! With the `metrics` feature enabled, a `RasterMetrics` implementation can be attached
+//! to readers (see the `metrics` field of [`ReaderOptions`](crate::reader::ReaderOptions)) and
+//! writers (e.g. `UrfWriter::with_metrics`).
+//! Without the feature, all hooks compile to nothing.
+
+#[cfg(feature = "metrics")]
+use std::sync::Arc;
+
+/// Receives events from readers and writers, e.g. to update counters of a print service.
+///
+/// All methods do nothing by default.
+#[cfg(feature = "metrics")]
+pub trait RasterMetrics: std::fmt::Debug + Send + Sync {
+    /// Called when the content of a page has been read,
+    /// with the number of encoded bytes it took, excluding the page header.
+    fn page_read(&self, encoded_bytes: u64) {
+        let _ = encoded_bytes;
+    }
+
+    /// Called when the content of a page has been written,
+    /// with the number of encoded bytes it took, excluding the page header.
+    fn page_written(&self, encoded_bytes: u64) {
+        let _ = encoded_bytes;
+    }
+
+    /// Called when reading fails. The error is a `CupsRasterError` or `UrfError`,
+    /// which can be told apart by downcasting.
+    fn read_error(&self, error: &(dyn std::error::Error + 'static)) {
+        let _ = error;
+    }
+
+    /// Called when writing a page header or finishing a page or the stream fails,
+    /// e.g. on incomplete page content. The error is a `CupsRasterError` or `UrfError`.
+    ///
+    /// Errors of the page content itself are returned by its `AsyncWrite` calls instead.
+    fn write_error(&self, error: &(dyn std::error::Error + 'static)) {
+        let _ = error;
+    }
+}
+
+/// The metrics hook carried by readers and writers, a no-op without the `metrics` feature.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct MetricsHandle {
+    #[cfg(feature = "metrics")]
+    inner: Option<Arc<dyn RasterMetrics>>,
+}
+
+impl MetricsHandle {
+    #[cfg(feature = "metrics")]
+    pub(crate) fn new(inner: Option<Arc<dyn RasterMetrics>>) -> Self {
+        Self { inner }
+    }
+
+    #[inline]
+    pub(crate) fn page_read(&self, encoded_bytes: u64) {
+        #[cfg(feature = "metrics")]
+        if let Some(inner) = &self.inner {
+            inner.page_read(encoded_bytes);
+        }
+        #[cfg(not(feature = "metrics"))]
+        let _ = encoded_bytes;
+    }
+
+    #[inline]
+    pub(crate) fn page_written(&self, encoded_bytes: u64) {
+        #[cfg(feature = "metrics")]
+        if let Some(inner) = &self.inner {
+            inner.page_written(encoded_bytes);
+        }
+        #[cfg(not(feature = "metrics"))]
+        let _ = encoded_bytes;
+    }
+
+    #[inline]
+    pub(crate) fn read_error(&self, error: &(dyn std::error::Error + 'static)) {
+        #[cfg(feature = "metrics")]
+        if let Some(inner) = &self.inner {
+            inner.read_error(error);
+        }
+        #[cfg(not(feature = "metrics"))]
+        let _ = error;
+    }
+
+    #[inline]
+    pub(crate) fn write_error(&self, error: &(dyn std::error::Error + 'static)) {
+        #[cfg(feature = "metrics")]
+        if let Some(inner) = &self.inner {
+            inner.write_error(error);
+        }
+        #[cfg(not(feature = "metrics"))]
+        let _ = error;
+    }
+}
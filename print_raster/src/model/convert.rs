@@ -0,0 +1,104 @@
+//! Mappings between the models of the CUPS and URF formats.
+
+use super::cups::{CupsColorOrder, CupsColorSpace, CupsPageHeaderV2};
+use super::urf::{
+    UrfColorSpace, UrfDuplex, UrfMediaPosition, UrfMediaType, UrfPageHeader, UrfQuality,
+};
+use crate::error::UrfError;
+
+impl UrfColorSpace {
+    /// Returns the URF color space equivalent to `cs`,
+    /// or `None` if URF cannot represent it.
+    pub fn try_from_cups(cs: CupsColorSpace) -> Option<UrfColorSpace> {
+        match cs {
+            CupsColorSpace::sGray => Some(UrfColorSpace::sGray),
+            CupsColorSpace::sRGB => Some(UrfColorSpace::sRGB),
+            CupsColorSpace::CIELab => Some(UrfColorSpace::CIELab),
+            CupsColorSpace::AdobeRGB => Some(UrfColorSpace::AdobeRGB),
+            CupsColorSpace::Gray => Some(UrfColorSpace::Gray),
+            CupsColorSpace::RGB => Some(UrfColorSpace::RGB),
+            CupsColorSpace::CMYK => Some(UrfColorSpace::CMYK),
+            _ => None,
+        }
+    }
+}
+
+impl CupsColorSpace {
+    /// Returns the CUPS color space equivalent to `cs`.
+    ///
+    /// Every URF color space has a CUPS equivalent, so this mapping never fails.
+    pub fn from_urf(cs: UrfColorSpace) -> CupsColorSpace {
+        match cs {
+            UrfColorSpace::sGray => CupsColorSpace::sGray,
+            UrfColorSpace::sRGB => CupsColorSpace::sRGB,
+            UrfColorSpace::CIELab => CupsColorSpace::CIELab,
+            UrfColorSpace::AdobeRGB => CupsColorSpace::AdobeRGB,
+            UrfColorSpace::Gray => CupsColorSpace::Gray,
+            UrfColorSpace::RGB => CupsColorSpace::RGB,
+            UrfColorSpace::CMYK => CupsColorSpace::CMYK,
+        }
+    }
+}
+
+impl TryFrom<&CupsPageHeaderV2> for UrfPageHeader {
+    type Error = UrfError;
+
+    /// Converts the page layout of a CUPS header, leaving the URF-only fields at their defaults.
+    ///
+    /// Fails if the color space has no URF equivalent, if the pixels are not colors of
+    /// 8 or 16 bits, or if the resolution differs between feed and cross feed directions,
+    /// as URF has a single resolution for both.
+    fn try_from(header: &CupsPageHeaderV2) -> Result<Self, Self::Error> {
+        let color_space = UrfColorSpace::try_from_cups(header.v1.color_space)
+            .ok_or(UrfError::UnsupportedColorSpace(header.v1.color_space))?;
+        let resolution = &header.v1.resolution;
+        if resolution.cross_feed != resolution.feed {
+            return Err(UrfError::AnisotropicResolution {
+                cross_feed: resolution.cross_feed,
+                feed: resolution.feed,
+            });
+        }
+        let bits_per_pixel = header
+            .v1
+            .bits_per_pixel
+            .try_into()
+            .map_err(|_| UrfError::InvalidHeader("bits per pixel exceeds 255"))?;
+        let urf = UrfPageHeader {
+            bits_per_pixel,
+            color_space,
+            width: header.v1.width,
+            height: header.v1.height,
+            duplex: UrfDuplex::NoDuplex,
+            quality: UrfQuality::Normal,
+            media_position: UrfMediaPosition::Auto,
+            media_type: UrfMediaType::Auto,
+            dot_per_inch: resolution.cross_feed,
+        };
+        // the same rule as the URF reader and writer, so the page is never mis-sized
+        urf.checked_bytes_per_pixel()?;
+        Ok(urf)
+    }
+}
+
+impl From<&UrfPageHeader> for CupsPageHeaderV2 {
+    /// Converts the page layout of a URF header to chunky pixels of the equivalent color space.
+    ///
+    /// The remaining fields are left at the defaults of [`CupsPageHeaderV2::builder`].
+    fn from(header: &UrfPageHeader) -> Self {
+        let bits_per_pixel = header.bits_per_pixel as u32;
+        let num_colors = header.color_space.num_colors() as u32;
+        let bytes_per_line = (header.width as u64 * bits_per_pixel as u64).div_ceil(8);
+        CupsPageHeaderV2::builder()
+            .width(header.width)
+            .height(header.height)
+            .bits_per_color(bits_per_pixel / num_colors.max(1))
+            .bits_per_pixel(bits_per_pixel)
+            .bytes_per_line(bytes_per_line.try_into().unwrap_or(u32::MAX))
+            .color_order(CupsColorOrder::Chunky)
+            .color_space(CupsColorSpace::from_urf(header.color_space))
+            .resolution(header.dot_per_inch, header.dot_per_inch)
+            .duplex(header.duplex != UrfDuplex::NoDuplex)
+            .tumble(header.duplex == UrfDuplex::ShortSide)
+            .build()
+    }
+}
@@ -1,24 +1,76 @@
-use super::RasterByteOrder;
+use super::{RasterByteOrder, RasterPageMetadata};
+use crate::error::CupsRasterError;
 use num_enum::TryFromPrimitive;
-use std::{array, hash::Hash};
+use std::{array, fmt, hash::Hash};
+
+/// Size in bytes of a CUPS Raster V1 page header, excluding the sync word.
+pub const CUPS_V1_PAGE_HEADER_SIZE: usize = 420;
+/// Size in bytes of a CUPS Raster V2/V3 page header, excluding the sync word.
+pub const CUPS_V2_PAGE_HEADER_SIZE: usize = 1796;
+
+/// Sync word bytes of a big-endian CUPS Raster V1 stream.
+pub const CUPS_SYNC_WORD_V1_BE: [u8; 4] = *b"RaSt";
+/// Sync word bytes of a little-endian CUPS Raster V1 stream.
+pub const CUPS_SYNC_WORD_V1_LE: [u8; 4] = *b"tSaR";
+/// Sync word bytes of a big-endian CUPS Raster V2 stream.
+pub const CUPS_SYNC_WORD_V2_BE: [u8; 4] = *b"RaS2";
+/// Sync word bytes of a little-endian CUPS Raster V2 stream.
+pub const CUPS_SYNC_WORD_V2_LE: [u8; 4] = *b"2SaR";
+/// Sync word bytes of a big-endian CUPS Raster V3 stream.
+pub const CUPS_SYNC_WORD_V3_BE: [u8; 4] = *b"RaS3";
+/// Sync word bytes of a little-endian CUPS Raster V3 stream.
+pub const CUPS_SYNC_WORD_V3_LE: [u8; 4] = *b"3SaR";
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u32)]
 /// The sync word is a 32-bit value that identifies the version and byte order of the raster.
+///
+/// These are all the sync words of CUPS: `RaSt`, `RaS2` and `RaS3` for big-endian V1, V2 and
+/// V3 streams, and the reversed `tSaR`, `2SaR` and `3SaR` for little-endian ones. Streams
+/// starting with anything else are rejected with [`CupsRasterError::InvalidSyncWord`].
 /// # Note
 /// The enum underlying value is in native endianness, so it can be different if you print
 /// it out as a number on different target platforms. But if you convert it to a byte array
 /// using `u32::to_ne_bytes`, it will always be the same.
 pub enum CupsSyncWord {
-    V1BigEndian = u32::from_ne_bytes([b'R', b'a', b'S', b't']),
-    V1LittleEndian = u32::from_ne_bytes([b't', b'S', b'a', b'R']),
-    V2BigEndian = u32::from_ne_bytes([b'R', b'a', b'S', b'2']),
-    V2LittleEndian = u32::from_ne_bytes([b'2', b'S', b'a', b'R']),
-    V3BigEndian = u32::from_ne_bytes([b'R', b'a', b'S', b'3']),
-    V3LittleEndian = u32::from_ne_bytes([b'3', b'S', b'a', b'R']),
+    V1BigEndian = u32::from_ne_bytes(CUPS_SYNC_WORD_V1_BE),
+    V1LittleEndian = u32::from_ne_bytes(CUPS_SYNC_WORD_V1_LE),
+    V2BigEndian = u32::from_ne_bytes(CUPS_SYNC_WORD_V2_BE),
+    V2LittleEndian = u32::from_ne_bytes(CUPS_SYNC_WORD_V2_LE),
+    V3BigEndian = u32::from_ne_bytes(CUPS_SYNC_WORD_V3_BE),
+    V3LittleEndian = u32::from_ne_bytes(CUPS_SYNC_WORD_V3_LE),
 }
 
 impl CupsSyncWord {
+    /// Returns the sync word of the bytes at the start of a stream,
+    /// or `None` if they are not a CUPS sync word.
+    pub fn from_bytes(bytes: &[u8; 4]) -> Option<Self> {
+        match *bytes {
+            CUPS_SYNC_WORD_V1_BE => Some(CupsSyncWord::V1BigEndian),
+            CUPS_SYNC_WORD_V1_LE => Some(CupsSyncWord::V1LittleEndian),
+            CUPS_SYNC_WORD_V2_BE => Some(CupsSyncWord::V2BigEndian),
+            CUPS_SYNC_WORD_V2_LE => Some(CupsSyncWord::V2LittleEndian),
+            CUPS_SYNC_WORD_V3_BE => Some(CupsSyncWord::V3BigEndian),
+            CUPS_SYNC_WORD_V3_LE => Some(CupsSyncWord::V3LittleEndian),
+            _ => None,
+        }
+    }
+
+    /// Returns the bytes of the sync word as written at the start of a stream.
+    pub fn to_bytes(&self) -> [u8; 4] {
+        (*self as u32).to_ne_bytes()
+    }
+
+    /// Returns the version of the stream, 1, 2 or 3.
+    pub fn version(&self) -> u8 {
+        match self {
+            CupsSyncWord::V1BigEndian | CupsSyncWord::V1LittleEndian => 1,
+            CupsSyncWord::V2BigEndian | CupsSyncWord::V2LittleEndian => 2,
+            CupsSyncWord::V3BigEndian | CupsSyncWord::V3LittleEndian => 3,
+        }
+    }
+
     pub fn byte_order(&self) -> RasterByteOrder {
         match self {
             CupsSyncWord::V1BigEndian | CupsSyncWord::V2BigEndian | CupsSyncWord::V3BigEndian => {
@@ -29,9 +81,32 @@ pub fn byte_order(&self) -> RasterByteOrder {
             | CupsSyncWord::V3LittleEndian => RasterByteOrder::LittleEndian,
         }
     }
+
+    /// Returns `true` if the pixel data of the stream is run-length encoded.
+    ///
+    /// Only V2 streams are compressed, regardless of the driver-specific
+    /// [`cups_compression`](CupsPageHeaderV1::cups_compression) field of the pages.
+    pub fn is_compressed(&self) -> bool {
+        matches!(
+            self,
+            CupsSyncWord::V2BigEndian | CupsSyncWord::V2LittleEndian
+        )
+    }
+
+    /// Returns the sync word of a stream with V2 page headers in the given byte order,
+    /// which is V2 for compressed pixel data and V3 for uncompressed pixel data.
+    pub fn with_compression(byte_order: RasterByteOrder, compressed: bool) -> Self {
+        match (byte_order, compressed) {
+            (RasterByteOrder::BigEndian, true) => CupsSyncWord::V2BigEndian,
+            (RasterByteOrder::LittleEndian, true) => CupsSyncWord::V2LittleEndian,
+            (RasterByteOrder::BigEndian, false) => CupsSyncWord::V3BigEndian,
+            (RasterByteOrder::LittleEndian, false) => CupsSyncWord::V3LittleEndian,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u32)]
 pub enum CupsAdvance {
     Never = 0,
@@ -42,6 +117,7 @@ pub enum CupsAdvance {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u32)]
 pub enum CupsCut {
     Never = 0,
@@ -52,6 +128,7 @@ pub enum CupsCut {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u32)]
 pub enum CupsJog {
     Never = 0,
@@ -61,6 +138,7 @@ pub enum CupsJog {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u32)]
 pub enum CupsLeadingEdge {
     Top = 0,
@@ -70,6 +148,7 @@ pub enum CupsLeadingEdge {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u32)]
 pub enum CupsColorOrder {
     /// Chunky pixels (CMYK CMYK CMYK)
@@ -80,7 +159,18 @@ pub enum CupsColorOrder {
     Planar = 2,
 }
 
+impl fmt::Display for CupsColorOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CupsColorOrder::Chunky => "Chunky",
+            CupsColorOrder::Banded => "Banded",
+            CupsColorOrder::Planar => "Planar",
+        })
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u32)]
 pub enum CupsColorSpace {
     /// Luminance (DeviceGray, gamma 2.2 by default)
@@ -197,7 +287,168 @@ pub enum CupsColorSpace {
     DeviceF,
 }
 
+impl CupsColorSpace {
+    /// Returns the number of colors of the color space, where KCMYcm has 6 colors
+    /// for pixels of less than 8 bits and 4 colors otherwise, as in CUPS.
+    fn num_colors(&self, bits_per_pixel: u32) -> u32 {
+        match self {
+            CupsColorSpace::Gray
+            | CupsColorSpace::White
+            | CupsColorSpace::Black
+            | CupsColorSpace::Gold
+            | CupsColorSpace::Silver
+            | CupsColorSpace::sGray => 1,
+            CupsColorSpace::RGB
+            | CupsColorSpace::CMY
+            | CupsColorSpace::YMC
+            | CupsColorSpace::CIEXYZ
+            | CupsColorSpace::CIELab
+            | CupsColorSpace::sRGB
+            | CupsColorSpace::AdobeRGB
+            | CupsColorSpace::Icc1
+            | CupsColorSpace::Icc2
+            | CupsColorSpace::Icc3
+            | CupsColorSpace::Icc4
+            | CupsColorSpace::Icc5
+            | CupsColorSpace::Icc6
+            | CupsColorSpace::Icc7
+            | CupsColorSpace::Icc8
+            | CupsColorSpace::Icc9
+            | CupsColorSpace::IccA
+            | CupsColorSpace::IccB
+            | CupsColorSpace::IccC
+            | CupsColorSpace::IccD
+            | CupsColorSpace::IccE
+            | CupsColorSpace::IccF => 3,
+            CupsColorSpace::RGBA
+            | CupsColorSpace::RGBW
+            | CupsColorSpace::CMYK
+            | CupsColorSpace::YMCK
+            | CupsColorSpace::KCMY
+            | CupsColorSpace::GMCK
+            | CupsColorSpace::GMCS => 4,
+            CupsColorSpace::KCMYcm => {
+                if bits_per_pixel < 8 {
+                    6
+                } else {
+                    4
+                }
+            }
+            CupsColorSpace::Device1 => 1,
+            CupsColorSpace::Device2 => 2,
+            CupsColorSpace::Device3 => 3,
+            CupsColorSpace::Device4 => 4,
+            CupsColorSpace::Device5 => 5,
+            CupsColorSpace::Device6 => 6,
+            CupsColorSpace::Device7 => 7,
+            CupsColorSpace::Device8 => 8,
+            CupsColorSpace::Device9 => 9,
+            CupsColorSpace::DeviceA => 10,
+            CupsColorSpace::DeviceB => 11,
+            CupsColorSpace::DeviceC => 12,
+            CupsColorSpace::DeviceD => 13,
+            CupsColorSpace::DeviceE => 14,
+            CupsColorSpace::DeviceF => 15,
+        }
+    }
+
+    /// Returns the number of colors hinted by `Icc1` to `IccF`, or `None` for other color spaces.
+    pub fn icc_color_hint(&self) -> Option<u8> {
+        let value = *self as u32;
+        (CupsColorSpace::Icc1 as u32..=CupsColorSpace::IccF as u32)
+            .contains(&value)
+            .then(|| (value - CupsColorSpace::Icc1 as u32 + 1) as u8)
+    }
+
+    /// Returns the number of colorants of `Device1` to `DeviceF`, or `None` for other color spaces.
+    pub fn device_colorant_count(&self) -> Option<u8> {
+        let value = *self as u32;
+        (CupsColorSpace::Device1 as u32..=CupsColorSpace::DeviceF as u32)
+            .contains(&value)
+            .then(|| (value - CupsColorSpace::Device1 as u32 + 1) as u8)
+    }
+
+    /// Returns the size in bytes of a chunky pixel with `bits_per_color` bits per color,
+    /// or `None` if a pixel is not a whole number of bytes, i.e. `bits_per_color` is
+    /// neither 8 nor 16.
+    pub fn bytes_per_pixel(&self, bits_per_color: u8) -> Option<u8> {
+        match bits_per_color {
+            8 | 16 => u8::try_from(self.num_colors(bits_per_color as u32))
+                .ok()?
+                .checked_mul(bits_per_color / 8),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CupsColorSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CupsColorSpace::Gray => "Gray",
+            CupsColorSpace::RGB => "RGB",
+            CupsColorSpace::RGBA => "RGBA",
+            CupsColorSpace::Black => "Black",
+            CupsColorSpace::CMY => "CMY",
+            CupsColorSpace::YMC => "YMC",
+            CupsColorSpace::CMYK => "CMYK",
+            CupsColorSpace::YMCK => "YMCK",
+            CupsColorSpace::KCMY => "KCMY",
+            CupsColorSpace::KCMYcm => "KCMYcm",
+            CupsColorSpace::GMCK => "GMCK",
+            CupsColorSpace::GMCS => "GMCS",
+            CupsColorSpace::White => "White",
+            CupsColorSpace::Gold => "Gold",
+            CupsColorSpace::Silver => "Silver",
+            CupsColorSpace::CIEXYZ => "CIE XYZ",
+            CupsColorSpace::CIELab => "CIE Lab",
+            CupsColorSpace::RGBW => "RGBW",
+            CupsColorSpace::sGray => "sGray",
+            CupsColorSpace::sRGB => "sRGB",
+            CupsColorSpace::AdobeRGB => "Adobe RGB",
+            // the ICC and device color spaces are numbered by their colors
+            CupsColorSpace::Icc1
+            | CupsColorSpace::Icc2
+            | CupsColorSpace::Icc3
+            | CupsColorSpace::Icc4
+            | CupsColorSpace::Icc5
+            | CupsColorSpace::Icc6
+            | CupsColorSpace::Icc7
+            | CupsColorSpace::Icc8
+            | CupsColorSpace::Icc9
+            | CupsColorSpace::IccA
+            | CupsColorSpace::IccB
+            | CupsColorSpace::IccC
+            | CupsColorSpace::IccD
+            | CupsColorSpace::IccE
+            | CupsColorSpace::IccF => {
+                let n = *self as u32 - CupsColorSpace::Icc1 as u32 + 1;
+                return write!(f, "ICC {}", n);
+            }
+            CupsColorSpace::Device1
+            | CupsColorSpace::Device2
+            | CupsColorSpace::Device3
+            | CupsColorSpace::Device4
+            | CupsColorSpace::Device5
+            | CupsColorSpace::Device6
+            | CupsColorSpace::Device7
+            | CupsColorSpace::Device8
+            | CupsColorSpace::Device9
+            | CupsColorSpace::DeviceA
+            | CupsColorSpace::DeviceB
+            | CupsColorSpace::DeviceC
+            | CupsColorSpace::DeviceD
+            | CupsColorSpace::DeviceE
+            | CupsColorSpace::DeviceF => {
+                let n = *self as u32 - CupsColorSpace::Device1 as u32 + 1;
+                return write!(f, "Device {}", n);
+            }
+        };
+        f.write_str(name)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u32)]
 pub enum CupsOrientation {
     Portrait = 0,
@@ -206,13 +457,26 @@ pub enum CupsOrientation {
     ReverseLandscape = 3,
 }
 
+impl fmt::Display for CupsOrientation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CupsOrientation::Portrait => "Portrait",
+            CupsOrientation::Landscape => "Landscape",
+            CupsOrientation::ReversePortrait => "Reverse Portrait",
+            CupsOrientation::ReverseLandscape => "Reverse Landscape",
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CupsResolution {
     pub cross_feed: u32,
     pub feed: u32,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CupsImagingBoundingBox<T> {
     pub left: T,
     pub bottom: T,
@@ -221,18 +485,21 @@ pub struct CupsImagingBoundingBox<T> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CupsMargins {
     pub left: u32,
     pub bottom: u32,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CupsPageSize<T> {
     pub width: T,
     pub height: T,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CupsPageHeaderV1 {
     pub media_class: String,
     pub media_color: String,
@@ -240,6 +507,7 @@ pub struct CupsPageHeaderV1 {
     pub output_type: String,
     pub advance_distance: u32,
     pub advance_media: CupsAdvance,
+    /// Collate copies, see [`expand_copies`]
     pub collate: bool,
     pub cut_media: CupsCut,
     pub duplex: bool,
@@ -260,7 +528,7 @@ pub struct CupsPageHeaderV1 {
     pub mirror_print: bool,
     /// Invert prints
     pub negative_print: bool,
-    /// 0 = printer default
+    /// 0 = printer default, see [`expand_copies`]
     pub num_copies: u32,
     pub orientation: CupsOrientation,
     /// `false` = Output face down, `true`` = Output face up
@@ -279,12 +547,18 @@ pub struct CupsPageHeaderV1 {
     pub height: u32,
     /// Driver-specific
     pub cups_media_type: u32,
+    /// Colors of 16 bits are stored in the byte order of the stream,
+    /// see [`CupsSyncWord::byte_order`] and [`crate::convert::swap_samples_16`].
     pub bits_per_color: u32,
     pub bits_per_pixel: u32,
+    /// When writing, `0` is replaced by the size of a packed line
     pub bytes_per_line: u32,
     pub color_order: CupsColorOrder,
     pub color_space: CupsColorSpace,
     /// Driver-specific
+    ///
+    /// Whether the pixel data is compressed is determined by the
+    /// [`CupsSyncWord`] of the stream instead, see [`CupsSyncWord::is_compressed`].
     pub cups_compression: u32,
     /// Driver-specific
     pub cups_row_count: u32,
@@ -294,71 +568,184 @@ pub struct CupsPageHeaderV1 {
     pub cups_row_step: u32,
 }
 
+impl Default for CupsPageHeaderV1 {
+    /// Returns a header with empty strings, zero numeric fields, chunky sRGB pixels
+    /// and the first variant of the other enumerations.
+    fn default() -> Self {
+        CupsPageHeaderV1 {
+            media_class: String::new(),
+            media_color: String::new(),
+            media_type: String::new(),
+            output_type: String::new(),
+            advance_distance: 0,
+            advance_media: CupsAdvance::Never,
+            collate: false,
+            cut_media: CupsCut::Never,
+            duplex: false,
+            resolution: CupsResolution {
+                cross_feed: 0,
+                feed: 0,
+            },
+            imaging_bbox: CupsImagingBoundingBox {
+                left: 0,
+                bottom: 0,
+                right: 0,
+                top: 0,
+            },
+            insert_sheet: false,
+            jog: CupsJog::Never,
+            leading_edge: CupsLeadingEdge::Top,
+            margins: CupsMargins { left: 0, bottom: 0 },
+            manual_feed: false,
+            media_position: 0,
+            media_weight: 0,
+            mirror_print: false,
+            negative_print: false,
+            num_copies: 0,
+            orientation: CupsOrientation::Portrait,
+            output_face_up: false,
+            page_size: CupsPageSize {
+                width: 0,
+                height: 0,
+            },
+            separations: false,
+            tray_switch: false,
+            tumble: false,
+            width: 0,
+            height: 0,
+            cups_media_type: 0,
+            bits_per_color: 0,
+            bits_per_pixel: 0,
+            bytes_per_line: 0,
+            color_order: CupsColorOrder::Chunky,
+            color_space: CupsColorSpace::sRGB,
+            cups_compression: 0,
+            cups_row_count: 0,
+            cups_row_feed: 0,
+            cups_row_step: 0,
+        }
+    }
+}
+
 impl CupsPageHeaderV1 {
     pub fn num_colors(&self) -> u32 {
-        match self.color_space {
-            CupsColorSpace::Gray
-            | CupsColorSpace::White
-            | CupsColorSpace::Black
-            | CupsColorSpace::Gold
-            | CupsColorSpace::Silver
-            | CupsColorSpace::sGray => 1,
-            CupsColorSpace::RGB
-            | CupsColorSpace::CMY
-            | CupsColorSpace::YMC
-            | CupsColorSpace::CIEXYZ
-            | CupsColorSpace::CIELab
-            | CupsColorSpace::sRGB
-            | CupsColorSpace::AdobeRGB
-            | CupsColorSpace::Icc1
-            | CupsColorSpace::Icc2
-            | CupsColorSpace::Icc3
-            | CupsColorSpace::Icc4
-            | CupsColorSpace::Icc5
-            | CupsColorSpace::Icc6
-            | CupsColorSpace::Icc7
-            | CupsColorSpace::Icc8
-            | CupsColorSpace::Icc9
-            | CupsColorSpace::IccA
-            | CupsColorSpace::IccB
-            | CupsColorSpace::IccC
-            | CupsColorSpace::IccD
-            | CupsColorSpace::IccE
-            | CupsColorSpace::IccF => 3,
-            CupsColorSpace::RGBA
-            | CupsColorSpace::RGBW
-            | CupsColorSpace::CMYK
-            | CupsColorSpace::YMCK
-            | CupsColorSpace::KCMY
-            | CupsColorSpace::GMCK
-            | CupsColorSpace::GMCS => 4,
-            CupsColorSpace::KCMYcm => {
-                if self.bits_per_pixel < 8 {
-                    6
-                } else {
-                    4
-                }
-            }
-            CupsColorSpace::Device1 => 1,
-            CupsColorSpace::Device2 => 2,
-            CupsColorSpace::Device3 => 3,
-            CupsColorSpace::Device4 => 4,
-            CupsColorSpace::Device5 => 5,
-            CupsColorSpace::Device6 => 6,
-            CupsColorSpace::Device7 => 7,
-            CupsColorSpace::Device8 => 8,
-            CupsColorSpace::Device9 => 9,
-            CupsColorSpace::DeviceA => 10,
-            CupsColorSpace::DeviceB => 11,
-            CupsColorSpace::DeviceC => 12,
-            CupsColorSpace::DeviceD => 13,
-            CupsColorSpace::DeviceE => 14,
-            CupsColorSpace::DeviceF => 15,
-        }
+        self.color_space.num_colors(self.bits_per_pixel)
+    }
+
+    /// Checks the consistency of the pixel layout fields, so that a constructed header
+    /// can be sanitized before it is handed to a writer.
+    ///
+    /// - `bits_per_color` must be 1, 2, 4, 8 or 16.
+    /// - `bits_per_pixel` must be `bits_per_color` times the number of colors for chunky
+    ///   pixels (or times 4 for 3 colors of less than 8 bits, or 8 for KCMYcm of 1 bit
+    ///   per color, padded as `00KCMYcm`), and `bits_per_color` otherwise.
+    /// - `bytes_per_line` must be a multiple of the chunk (pixel or color) size,
+    ///   and large enough to hold `width` pixels. Zero is taken as the size of a packed
+    ///   line, as when writing.
+    /// - For banded pixels, `bytes_per_line` must be a multiple of the number of colors
+    ///   times the color size, i.e. hold one band of whole colors per color.
+    pub fn validate(&self) -> Result<(), CupsRasterError> {
+        validate_layout(self, self.num_colors())
+    }
+
+    /// Returns the size in bytes of the page image, i.e. `bytes_per_line * height`,
+    /// times the number of colors for planar pixels.
+    ///
+    /// A zero `bytes_per_line` is taken as the size of a packed line, as when writing.
+    pub fn image_byte_len(&self) -> Result<u64, CupsRasterError> {
+        image_byte_len(self, self.num_colors())
     }
+
+    /// Returns the PWG `media-source` keyword of [`media_position`](Self::media_position),
+    /// or `None` if the value is driver-specific.
+    ///
+    /// Well-known values follow the numbering of the PWG Raster `MediaPosition` field,
+    /// which is also used by [`UrfMediaPosition`](super::urf::UrfMediaPosition).
+    pub fn media_position_name(&self) -> Option<&'static str> {
+        const NAMES: [&str; 50] = [
+            "auto",
+            "main",
+            "alternate",
+            "large-capacity",
+            "manual",
+            "envelope",
+            "disc",
+            "photo",
+            "hagaki",
+            "main-roll",
+            "alternate-roll",
+            "top",
+            "middle",
+            "bottom",
+            "side",
+            "left",
+            "right",
+            "center",
+            "rear",
+            "by-pass-tray",
+            "tray-1",
+            "tray-2",
+            "tray-3",
+            "tray-4",
+            "tray-5",
+            "tray-6",
+            "tray-7",
+            "tray-8",
+            "tray-9",
+            "tray-10",
+            "tray-11",
+            "tray-12",
+            "tray-13",
+            "tray-14",
+            "tray-15",
+            "tray-16",
+            "tray-17",
+            "tray-18",
+            "tray-19",
+            "tray-20",
+            "roll-1",
+            "roll-2",
+            "roll-3",
+            "roll-4",
+            "roll-5",
+            "roll-6",
+            "roll-7",
+            "roll-8",
+            "roll-9",
+            "roll-10",
+        ];
+        NAMES.get(self.media_position as usize).copied()
+    }
+}
+
+/// Expands `pages` into the physical output order of `num_copies` copies.
+///
+/// Collated copies repeat the whole sequence (1, 2, 3, 1, 2, 3), while uncollated
+/// copies repeat each page in place (1, 1, 2, 2, 3, 3). A `num_copies` of 0 means
+/// the printer default and is treated as a single copy.
+///
+/// Collated output has to revisit earlier pages, so the pages (or whatever identifies
+/// them, e.g. indices or decoded content) must be kept around by the caller.
+/// To expand the pages of a reader instead, see
+/// [`read_expanded_copies`](crate::reader::read_expanded_copies).
+pub fn expand_copies<T>(
+    pages: &[T],
+    num_copies: u32,
+    collate: bool,
+) -> impl Iterator<Item = &T> + Clone {
+    let copies = num_copies.max(1) as usize;
+    let len = pages.len();
+    (0..len * copies).map(move |i| {
+        if collate {
+            &pages[i % len]
+        } else {
+            &pages[i / copies]
+        }
+    })
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CupsPageHeaderV2 {
     pub v1: CupsPageHeaderV1,
     pub num_colors: u32,
@@ -373,13 +760,231 @@ pub struct CupsPageHeaderV2 {
     pub page_size_name: String,
 }
 
+/// Returns the bytes needed for a line of `width` pixels without padding.
+fn packed_bytes_per_line(header: &CupsPageHeaderV1, num_colors: u32) -> u64 {
+    let width = header.width as u64;
+    match header.color_order {
+        CupsColorOrder::Chunky => (width * header.bits_per_pixel as u64).div_ceil(8),
+        // a line holds one band per color
+        CupsColorOrder::Banded => {
+            (width * header.bits_per_color as u64).div_ceil(8) * num_colors as u64
+        }
+        // a line holds a single color
+        CupsColorOrder::Planar => (width * header.bits_per_color as u64).div_ceil(8),
+    }
+}
+
+/// Returns `bytes_per_line`, or if it is zero, the size of a packed line
+/// rounded up to the chunk (pixel or color) size.
+pub(crate) fn effective_bytes_per_line(header: &CupsPageHeaderV1, num_colors: u32) -> u32 {
+    if header.bytes_per_line != 0 {
+        return header.bytes_per_line;
+    }
+    let chunk_bits = match header.color_order {
+        CupsColorOrder::Chunky => header.bits_per_pixel,
+        CupsColorOrder::Banded | CupsColorOrder::Planar => header.bits_per_color,
+    };
+    let chunk_size = (chunk_bits as u64).div_ceil(8).max(1);
+    packed_bytes_per_line(header, num_colors)
+        .next_multiple_of(chunk_size)
+        .try_into()
+        .unwrap_or(u32::MAX)
+}
+
+/// Checks that a banded line holds one band per color, each of whole colors, so that
+/// run-length chunks of one color never straddle two bands.
+pub(crate) fn check_banded_layout(
+    header: &CupsPageHeaderV1,
+    num_colors: u32,
+) -> Result<(), CupsRasterError> {
+    if header.color_order != CupsColorOrder::Banded {
+        return Ok(());
+    }
+    let bytes_per_color = (header.bits_per_color as u64).div_ceil(8).max(1);
+    let band_unit = bytes_per_color * num_colors as u64;
+    let bytes_per_line = effective_bytes_per_line(header, num_colors) as u64;
+    if band_unit == 0 || !bytes_per_line.is_multiple_of(band_unit) {
+        return Err(CupsRasterError::InvalidHeader(
+            "bytes_per_line of banded pixels must be a multiple of the number of colors times the color size",
+        ));
+    }
+    Ok(())
+}
+
+/// Returns `true` for chunky KCMYcm pixels of 6 colors of 1 bit padded to a byte,
+/// as written by CUPS, i.e. `00KCMYcm`.
+///
+/// CUPS counts 4 colors for these pixels of 8 bits, see [`CupsPageHeaderV1::num_colors`],
+/// so they are only told apart from KCMY here.
+fn is_padded_kcmycm(header: &CupsPageHeaderV1) -> bool {
+    header.color_space == CupsColorSpace::KCMYcm
+        && header.color_order == CupsColorOrder::Chunky
+        && header.bits_per_color == 1
+        && header.bits_per_pixel == 8
+}
+
+/// Rejects chunky KCMYcm pixels of 1 bit per color that are not padded to a byte.
+///
+/// Pixels of 6 bits would straddle bytes, while CUPS only writes a pixel per byte,
+/// so such pages can't be split into pixels or colors reliably.
+pub(crate) fn check_kcmycm_layout(header: &CupsPageHeaderV1) -> Result<(), CupsRasterError> {
+    if header.color_space == CupsColorSpace::KCMYcm
+        && header.color_order == CupsColorOrder::Chunky
+        && header.bits_per_color == 1
+        && !is_padded_kcmycm(header)
+    {
+        return Err(CupsRasterError::UnsupportedLayout(
+            "chunky KCMYcm pixels of 1 bit per color must be padded to 8 bits",
+        ));
+    }
+    Ok(())
+}
+
+fn image_byte_len(header: &CupsPageHeaderV1, num_colors: u32) -> Result<u64, CupsRasterError> {
+    let lines = effective_bytes_per_line(header, num_colors) as u64 * header.height as u64;
+    match header.color_order {
+        CupsColorOrder::Chunky | CupsColorOrder::Banded => Ok(lines),
+        // every color is a plane of `height` lines
+        CupsColorOrder::Planar => lines
+            .checked_mul(num_colors as u64)
+            .ok_or(CupsRasterError::DataTooLarge),
+    }
+}
+
+fn validate_layout(header: &CupsPageHeaderV1, num_colors: u32) -> Result<(), CupsRasterError> {
+    let bits_per_color = header.bits_per_color as u64;
+    let bits_per_pixel = header.bits_per_pixel as u64;
+    if !matches!(bits_per_color, 1 | 2 | 4 | 8 | 16) {
+        return Err(CupsRasterError::InvalidHeader(
+            "bits_per_color must be 1, 2, 4, 8 or 16",
+        ));
+    }
+    let chunk_bits = match header.color_order {
+        CupsColorOrder::Chunky => {
+            check_kcmycm_layout(header)?;
+            let colors = num_colors as u64;
+            let padded =
+                (colors == 3 && bits_per_color < 8 && bits_per_pixel == 4 * bits_per_color)
+                    || is_padded_kcmycm(header);
+            if bits_per_pixel != bits_per_color * colors && !padded {
+                return Err(CupsRasterError::InvalidHeader(
+                    "bits_per_pixel must be bits_per_color times the number of colors",
+                ));
+            }
+            bits_per_pixel
+        }
+        CupsColorOrder::Banded | CupsColorOrder::Planar => {
+            if bits_per_pixel != bits_per_color {
+                return Err(CupsRasterError::InvalidHeader(
+                    "bits_per_pixel must equal bits_per_color for banded and planar pixels",
+                ));
+            }
+            bits_per_color
+        }
+    };
+    let chunk_size = chunk_bits.div_ceil(8);
+    // a zero `bytes_per_line` is computed by the writers
+    let bytes_per_line = effective_bytes_per_line(header, num_colors) as u64;
+    if !bytes_per_line.is_multiple_of(chunk_size) {
+        return Err(CupsRasterError::InvalidHeader(
+            "bytes_per_line must be a multiple of the chunk size",
+        ));
+    }
+    if bytes_per_line < packed_bytes_per_line(header, num_colors) {
+        return Err(CupsRasterError::InvalidHeader(
+            "bytes_per_line is too small for the width",
+        ));
+    }
+    check_banded_layout(header, num_colors)
+}
+
 impl CupsPageHeaderV2 {
+    /// Drops the V2 extension fields, e.g. to write the page to a V1 stream.
+    pub fn into_v1(self) -> CupsPageHeaderV1 {
+        self.v1
+    }
+
     pub fn num_colors(&self) -> u32 {
         match self.num_colors {
             0 => self.v1.num_colors(),
             _ => self.num_colors,
         }
     }
+
+    /// Returns `false` if the explicit `num_colors` field disagrees with the number
+    /// of colors implied by the color space.
+    ///
+    /// A zero `num_colors` field is always consistent, as are the `Icc1` to `IccF`
+    /// color spaces, whose number of colors is only a hint.
+    pub fn colors_consistent(&self) -> bool {
+        self.num_colors == 0
+            || self.v1.color_space.icc_color_hint().is_some()
+            || self.num_colors == self.v1.num_colors()
+    }
+
+    /// Checks the consistency of the pixel layout, see [`CupsPageHeaderV1::validate`].
+    ///
+    /// The number of colors is taken from [`num_colors`](Self::num_colors),
+    /// which must agree with the color space, see [`colors_consistent`](Self::colors_consistent).
+    pub fn validate(&self) -> Result<(), CupsRasterError> {
+        if !self.colors_consistent() {
+            return Err(CupsRasterError::InvalidHeader(
+                "num_colors does not match the color space",
+            ));
+        }
+        validate_layout(&self.v1, self.num_colors())
+    }
+
+    /// Returns the size in bytes of the page image, see [`CupsPageHeaderV1::image_byte_len`].
+    ///
+    /// The number of colors is taken from [`num_colors`](Self::num_colors).
+    pub fn image_byte_len(&self) -> Result<u64, CupsRasterError> {
+        image_byte_len(&self.v1, self.num_colors())
+    }
+
+    /// Compares the float fields within `epsilon` and all other fields exactly,
+    /// e.g. to check a header after a round trip. Two NaN values are considered equal.
+    pub fn approx_eq(&self, other: &Self, epsilon: f32) -> bool {
+        let eq = |a: f32, b: f32| (a.is_nan() && b.is_nan()) || (a - b).abs() <= epsilon;
+        let CupsPageHeaderV2 {
+            v1,
+            num_colors,
+            borderless_scaling_factor,
+            page_size_f32,
+            imaging_bbox_f32,
+            vendor_u32,
+            vendor_f32,
+            vendor_str,
+            marker_type,
+            rendering_intent,
+            page_size_name,
+        } = self;
+        *v1 == other.v1
+            && *num_colors == other.num_colors
+            && eq(*borderless_scaling_factor, other.borderless_scaling_factor)
+            && eq(page_size_f32.width, other.page_size_f32.width)
+            && eq(page_size_f32.height, other.page_size_f32.height)
+            && eq(imaging_bbox_f32.left, other.imaging_bbox_f32.left)
+            && eq(imaging_bbox_f32.bottom, other.imaging_bbox_f32.bottom)
+            && eq(imaging_bbox_f32.right, other.imaging_bbox_f32.right)
+            && eq(imaging_bbox_f32.top, other.imaging_bbox_f32.top)
+            && *vendor_u32 == other.vendor_u32
+            && vendor_f32
+                .iter()
+                .zip(&other.vendor_f32)
+                .all(|(&a, &b)| eq(a, b))
+            && *vendor_str == other.vendor_str
+            && *marker_type == other.marker_type
+            && *rendering_intent == other.rendering_intent
+            && *page_size_name == other.page_size_name
+    }
+}
+
+impl Default for CupsPageHeaderV2 {
+    /// Returns the default V1 header, extended with zeroed or empty V2 fields.
+    fn default() -> Self {
+        CupsPageHeaderV1::default().into()
+    }
 }
 
 impl From<CupsPageHeaderV1> for CupsPageHeaderV2 {
@@ -407,3 +1012,308 @@ fn from(v1: CupsPageHeaderV1) -> Self {
         }
     }
 }
+
+impl CupsPageHeaderV1 {
+    pub fn builder() -> CupsPageHeaderV1Builder {
+        CupsPageHeaderV1Builder::new()
+    }
+}
+
+impl RasterPageMetadata for CupsPageHeaderV1 {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn bits_per_pixel(&self) -> u32 {
+        self.bits_per_pixel
+    }
+
+    fn dpi(&self) -> (u32, u32) {
+        (self.resolution.cross_feed, self.resolution.feed)
+    }
+
+    fn num_colors(&self) -> u32 {
+        CupsPageHeaderV1::num_colors(self)
+    }
+
+    fn content_len(&self) -> Option<u64> {
+        self.image_byte_len().ok()
+    }
+}
+
+impl CupsPageHeaderV2 {
+    pub fn builder() -> CupsPageHeaderV2Builder {
+        CupsPageHeaderV2Builder::new()
+    }
+}
+
+impl RasterPageMetadata for CupsPageHeaderV2 {
+    fn width(&self) -> u32 {
+        self.v1.width
+    }
+
+    fn height(&self) -> u32 {
+        self.v1.height
+    }
+
+    fn bits_per_pixel(&self) -> u32 {
+        self.v1.bits_per_pixel
+    }
+
+    fn dpi(&self) -> (u32, u32) {
+        (self.v1.resolution.cross_feed, self.v1.resolution.feed)
+    }
+
+    fn num_colors(&self) -> u32 {
+        CupsPageHeaderV2::num_colors(self)
+    }
+
+    fn content_len(&self) -> Option<u64> {
+        self.image_byte_len().ok()
+    }
+}
+
+/// A builder of [`CupsPageHeaderV1`].
+///
+/// Starts from empty strings, zero numeric fields, 8 bits per color,
+/// [`CupsColorOrder::Chunky`] and [`CupsColorSpace::sRGB`].
+/// `bits_per_pixel` and `bytes_per_line` are derived from the pixel layout
+/// unless set explicitly.
+#[derive(Debug, Clone)]
+pub struct CupsPageHeaderV1Builder {
+    header: CupsPageHeaderV1,
+    bits_per_pixel: Option<u32>,
+    bytes_per_line: Option<u32>,
+}
+
+impl Default for CupsPageHeaderV1Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CupsPageHeaderV1Builder {
+    pub fn new() -> Self {
+        Self {
+            header: CupsPageHeaderV1 {
+                bits_per_color: 8,
+                ..Default::default()
+            },
+            bits_per_pixel: None,
+            bytes_per_line: None,
+        }
+    }
+
+    /// Sets the width of the page image in pixels.
+    pub fn width(mut self, width: u32) -> Self {
+        self.header.width = width;
+        self
+    }
+
+    /// Sets the height of the page image in pixels.
+    pub fn height(mut self, height: u32) -> Self {
+        self.header.height = height;
+        self
+    }
+
+    pub fn bits_per_color(mut self, bits_per_color: u32) -> Self {
+        self.header.bits_per_color = bits_per_color;
+        self
+    }
+
+    pub fn bits_per_pixel(mut self, bits_per_pixel: u32) -> Self {
+        self.bits_per_pixel = Some(bits_per_pixel);
+        self
+    }
+
+    pub fn bytes_per_line(mut self, bytes_per_line: u32) -> Self {
+        self.bytes_per_line = Some(bytes_per_line);
+        self
+    }
+
+    pub fn color_order(mut self, color_order: CupsColorOrder) -> Self {
+        self.header.color_order = color_order;
+        self
+    }
+
+    pub fn color_space(mut self, color_space: CupsColorSpace) -> Self {
+        self.header.color_space = color_space;
+        self
+    }
+
+    /// Sets the resolution in dots per inch.
+    pub fn resolution(mut self, cross_feed: u32, feed: u32) -> Self {
+        self.header.resolution = CupsResolution { cross_feed, feed };
+        self
+    }
+
+    /// Sets the width and length of the page in points.
+    pub fn page_size(mut self, width: u32, height: u32) -> Self {
+        self.header.page_size = CupsPageSize { width, height };
+        self
+    }
+
+    pub fn duplex(mut self, duplex: bool) -> Self {
+        self.header.duplex = duplex;
+        self
+    }
+
+    pub fn tumble(mut self, tumble: bool) -> Self {
+        self.header.tumble = tumble;
+        self
+    }
+
+    pub fn num_copies(mut self, num_copies: u32) -> Self {
+        self.header.num_copies = num_copies;
+        self
+    }
+
+    pub fn collate(mut self, collate: bool) -> Self {
+        self.header.collate = collate;
+        self
+    }
+
+    pub fn orientation(mut self, orientation: CupsOrientation) -> Self {
+        self.header.orientation = orientation;
+        self
+    }
+
+    pub fn media_position(mut self, media_position: u32) -> Self {
+        self.header.media_position = media_position;
+        self
+    }
+
+    pub fn media_type(mut self, media_type: impl Into<String>) -> Self {
+        self.header.media_type = media_type.into();
+        self
+    }
+
+    pub fn build(self) -> CupsPageHeaderV1 {
+        self.build_with_num_colors(None)
+    }
+
+    fn build_with_num_colors(self, num_colors: Option<u32>) -> CupsPageHeaderV1 {
+        let mut header = self.header;
+        let bits_per_color = header.bits_per_color;
+        header.bits_per_pixel = match self.bits_per_pixel {
+            Some(bits_per_pixel) => bits_per_pixel,
+            None => match header.color_order {
+                CupsColorOrder::Chunky => {
+                    // the number of colors of KCMYcm depends on `bits_per_pixel`,
+                    // which is chosen here
+                    let colors = num_colors.unwrap_or(match header.color_space {
+                        CupsColorSpace::KCMYcm if bits_per_color == 1 => 6,
+                        CupsColorSpace::KCMYcm => 4,
+                        _ => header.num_colors(),
+                    });
+                    match colors {
+                        // 3 colors of less than 8 bits are padded to 4
+                        3 if bits_per_color < 8 => 4 * bits_per_color,
+                        // 6 colors of 1 bit are padded to a byte
+                        6 if header.color_space == CupsColorSpace::KCMYcm => 8,
+                        colors => colors * bits_per_color,
+                    }
+                }
+                CupsColorOrder::Banded | CupsColorOrder::Planar => bits_per_color,
+            },
+        };
+        header.bytes_per_line = match self.bytes_per_line {
+            Some(bytes_per_line) => bytes_per_line,
+            None => {
+                let num_colors = num_colors.unwrap_or_else(|| header.num_colors());
+                packed_bytes_per_line(&header, num_colors)
+                    .try_into()
+                    .unwrap_or(u32::MAX)
+            }
+        };
+        header
+    }
+}
+
+/// A builder of [`CupsPageHeaderV2`], see [`CupsPageHeaderV1Builder`] for the defaults.
+///
+/// The fields introduced by V2 start zeroed or empty, as in `From<CupsPageHeaderV1>`.
+#[derive(Debug, Clone, Default)]
+pub struct CupsPageHeaderV2Builder {
+    v1: CupsPageHeaderV1Builder,
+    num_colors: u32,
+    marker_type: String,
+    rendering_intent: String,
+    page_size_name: String,
+}
+
+macro_rules! forward_v1_setters {
+    ($($name:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            #[doc = concat!("See [`CupsPageHeaderV1Builder::", stringify!($name), "`].")]
+            pub fn $name(mut self, $($arg: $ty),*) -> Self {
+                self.v1 = self.v1.$name($($arg),*);
+                self
+            }
+        )*
+    };
+}
+
+impl CupsPageHeaderV2Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    forward_v1_setters! {
+        width(width: u32);
+        height(height: u32);
+        bits_per_color(bits_per_color: u32);
+        bits_per_pixel(bits_per_pixel: u32);
+        bytes_per_line(bytes_per_line: u32);
+        color_order(color_order: CupsColorOrder);
+        color_space(color_space: CupsColorSpace);
+        resolution(cross_feed: u32, feed: u32);
+        page_size(width: u32, height: u32);
+        duplex(duplex: bool);
+        tumble(tumble: bool);
+        num_copies(num_copies: u32);
+        collate(collate: bool);
+        orientation(orientation: CupsOrientation);
+        media_position(media_position: u32);
+    }
+
+    pub fn media_type(mut self, media_type: impl Into<String>) -> Self {
+        self.v1 = self.v1.media_type(media_type);
+        self
+    }
+
+    /// Sets the number of colors, 0 meaning the number implied by the color space.
+    pub fn num_colors(mut self, num_colors: u32) -> Self {
+        self.num_colors = num_colors;
+        self
+    }
+
+    pub fn marker_type(mut self, marker_type: impl Into<String>) -> Self {
+        self.marker_type = marker_type.into();
+        self
+    }
+
+    pub fn rendering_intent(mut self, rendering_intent: impl Into<String>) -> Self {
+        self.rendering_intent = rendering_intent.into();
+        self
+    }
+
+    pub fn page_size_name(mut self, page_size_name: impl Into<String>) -> Self {
+        self.page_size_name = page_size_name.into();
+        self
+    }
+
+    pub fn build(self) -> CupsPageHeaderV2 {
+        let num_colors = (self.num_colors != 0).then_some(self.num_colors);
+        let mut header = CupsPageHeaderV2::from(self.v1.build_with_num_colors(num_colors));
+        header.num_colors = self.num_colors;
+        header.marker_type = self.marker_type;
+        header.rendering_intent = self.rendering_intent;
+        header.page_size_name = self.page_size_name;
+        header
+    }
+}
@@ -1,8 +1,29 @@
+pub mod convert;
 pub mod cups;
+pub mod pixel;
 pub mod urf;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RasterByteOrder {
     BigEndian,
     LittleEndian,
 }
+
+/// The properties of a page image shared by the page headers of every format,
+/// for tools that don't depend on the format.
+pub trait RasterPageMetadata {
+    /// Returns the width of the page image in pixels.
+    fn width(&self) -> u32;
+    /// Returns the height of the page image in pixels.
+    fn height(&self) -> u32;
+    fn bits_per_pixel(&self) -> u32;
+    /// Returns the horizontal and vertical resolution in dots per inch.
+    fn dpi(&self) -> (u32, u32);
+    fn num_colors(&self) -> u32;
+    /// Returns the size in bytes of the page content declared by the header,
+    /// or `None` if it is unknown or out of range.
+    fn content_len(&self) -> Option<u64> {
+        None
+    }
+}
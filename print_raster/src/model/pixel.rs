@@ -0,0 +1,49 @@
+//! Transforms over decoded pixel data.
+
+use super::cups::CupsPageHeaderV1;
+
+/// Crops or pads decoded chunky pixel data to `target_width` x `target_height` pixels.
+///
+/// Each source line is read at a multiple of `header.bytes_per_line`, and the top-left
+/// corner is kept. Pixels outside the source image, including any missing source data,
+/// are filled with `fill_byte`.
+///
+/// The returned lines are tightly packed, that is `ceil(target_width * bits_per_pixel / 8)`
+/// bytes each. Pixels smaller than a byte are expected to be packed most significant bit first.
+pub fn resize_canvas(
+    data: &[u8],
+    header: &CupsPageHeaderV1,
+    target_width: u32,
+    target_height: u32,
+    fill_byte: u8,
+) -> Vec<u8> {
+    let bits_per_pixel = header.bits_per_pixel as usize;
+    let src_bytes_per_line = header.bytes_per_line as usize;
+    let dst_bytes_per_line = (target_width as usize * bits_per_pixel).div_ceil(8);
+    let copy_bits = header.width.min(target_width) as usize * bits_per_pixel;
+    let (copy_bytes, copy_rem_bits) = (copy_bits / 8, copy_bits % 8);
+
+    let mut output = vec![fill_byte; dst_bytes_per_line * target_height as usize];
+    if dst_bytes_per_line == 0 {
+        return output;
+    }
+    let num_lines = header.height.min(target_height) as usize;
+    for (y, dst) in output
+        .chunks_exact_mut(dst_bytes_per_line)
+        .take(num_lines)
+        .enumerate()
+    {
+        let start = y * src_bytes_per_line;
+        let src = data.get(start..).unwrap_or_default();
+        let src = &src[..src.len().min(src_bytes_per_line)];
+        let n = copy_bytes.min(src.len());
+        dst[..n].copy_from_slice(&src[..n]);
+        if n == copy_bytes && copy_rem_bits != 0 {
+            if let Some(&last) = src.get(copy_bytes) {
+                let mask = 0xffu8 << (8 - copy_rem_bits);
+                dst[copy_bytes] = (last & mask) | (fill_byte & !mask);
+            }
+        }
+    }
+    output
+}
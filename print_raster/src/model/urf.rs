@@ -1,11 +1,25 @@
+use super::RasterPageMetadata;
+use crate::error::UrfError;
 use num_enum::TryFromPrimitive;
+use std::fmt;
+
+/// Magic bytes at the start of every URF (Apple Raster) stream.
+pub const URF_MAGIC: &[u8; 8] = b"UNIRAST\0";
+/// Size in bytes of the URF file header, including the magic bytes.
+pub const URF_HEADER_SIZE: usize = 12;
+/// Size in bytes of a URF page header.
+pub const URF_PAGE_HEADER_SIZE: usize = 32;
+/// Size in bytes of the fields of a URF page header, which are followed by reserved bytes.
+pub(crate) const URF_PAGE_HEADER_FIELDS_SIZE: usize = 24;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UrfHeader {
     pub page_count: u32,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum UrfColorSpace {
     /// Luminance (gamma 2.2)
@@ -37,9 +51,33 @@ pub fn num_colors(&self) -> usize {
             UrfColorSpace::CMYK => 4,
         }
     }
+
+    /// Returns the size in bytes of a pixel with `bits_per_color` bits per color,
+    /// or `None` if `bits_per_color` is neither 8 nor 16.
+    pub fn bytes_per_pixel(&self, bits_per_color: u8) -> Option<u8> {
+        match bits_per_color {
+            8 | 16 => Some(self.num_colors() as u8 * (bits_per_color / 8)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for UrfColorSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            UrfColorSpace::sGray => "sGray",
+            UrfColorSpace::sRGB => "sRGB",
+            UrfColorSpace::CIELab => "CIE Lab",
+            UrfColorSpace::AdobeRGB => "Adobe RGB",
+            UrfColorSpace::Gray => "Gray",
+            UrfColorSpace::RGB => "RGB",
+            UrfColorSpace::CMYK => "CMYK",
+        })
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum UrfMediaType {
     Auto,
@@ -59,6 +97,7 @@ pub enum UrfMediaType {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum UrfDuplex {
     NoDuplex = 1,
@@ -67,6 +106,7 @@ pub enum UrfDuplex {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum UrfQuality {
     Default = 0,
@@ -76,6 +116,7 @@ pub enum UrfQuality {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum UrfMediaPosition {
     Auto = 0,
@@ -131,6 +172,7 @@ pub enum UrfMediaPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UrfPageHeader {
     pub bits_per_pixel: u8,
     pub color_space: UrfColorSpace,
@@ -142,3 +184,176 @@ pub struct UrfPageHeader {
     pub height: u32,
     pub dot_per_inch: u32,
 }
+
+impl UrfPageHeader {
+    pub fn builder() -> UrfPageHeaderBuilder {
+        UrfPageHeaderBuilder::default()
+    }
+
+    /// Returns the size in bytes of the page image, i.e. `width * height` pixels
+    /// of `bits_per_pixel / 8` bytes.
+    pub fn image_byte_len(&self) -> Result<u64, UrfError> {
+        (self.width as u64 * self.height as u64)
+            .checked_mul(self.bits_per_pixel as u64 / 8)
+            .ok_or(UrfError::DataTooLarge)
+    }
+
+    /// Returns the size in bytes of a pixel, which is the chunk of the run-length encoding,
+    /// as URF pixels are chunky.
+    ///
+    /// Fails with [`UrfError::InvalidBitsPerPixel`] unless a pixel holds every color of the
+    /// color space in 8 or 16 bits, so that the lines of a page are never mis-sized.
+    pub(crate) fn checked_bytes_per_pixel(&self) -> Result<u8, UrfError> {
+        let bits_per_color = self.bits_per_pixel / self.color_space.num_colors() as u8;
+        self.color_space
+            .bytes_per_pixel(bits_per_color)
+            .filter(|&bytes| bytes as u32 * 8 == self.bits_per_pixel as u32)
+            .ok_or(UrfError::InvalidBitsPerPixel {
+                bits_per_pixel: self.bits_per_pixel,
+                color_space: self.color_space,
+            })
+    }
+}
+
+impl RasterPageMetadata for UrfPageHeader {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn bits_per_pixel(&self) -> u32 {
+        self.bits_per_pixel as u32
+    }
+
+    /// The resolution of URF pages is the same in both directions.
+    fn dpi(&self) -> (u32, u32) {
+        (self.dot_per_inch, self.dot_per_inch)
+    }
+
+    fn num_colors(&self) -> u32 {
+        self.color_space.num_colors() as u32
+    }
+
+    fn content_len(&self) -> Option<u64> {
+        self.image_byte_len().ok()
+    }
+}
+
+/// A builder of [`UrfPageHeader`].
+///
+/// `width`, `height`, `bits_per_pixel`, `color_space` and `dot_per_inch` are required,
+/// the others default to [`UrfDuplex::NoDuplex`], [`UrfQuality::Normal`],
+/// [`UrfMediaPosition::Auto`] and [`UrfMediaType::Auto`].
+#[derive(Debug, Clone)]
+pub struct UrfPageHeaderBuilder {
+    bits_per_pixel: Option<u8>,
+    color_space: Option<UrfColorSpace>,
+    duplex: UrfDuplex,
+    quality: UrfQuality,
+    media_position: UrfMediaPosition,
+    media_type: UrfMediaType,
+    width: Option<u32>,
+    height: Option<u32>,
+    dot_per_inch: Option<u32>,
+}
+
+impl Default for UrfPageHeaderBuilder {
+    fn default() -> Self {
+        Self {
+            bits_per_pixel: None,
+            color_space: None,
+            duplex: UrfDuplex::NoDuplex,
+            quality: UrfQuality::Normal,
+            media_position: UrfMediaPosition::Auto,
+            media_type: UrfMediaType::Auto,
+            width: None,
+            height: None,
+            dot_per_inch: None,
+        }
+    }
+}
+
+impl UrfPageHeaderBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the width of the page image in pixels.
+    pub fn width(mut self, width: u32) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// Sets the height of the page image in pixels.
+    pub fn height(mut self, height: u32) -> Self {
+        self.height = Some(height);
+        self
+    }
+
+    /// Sets the size of a pixel in bits, which must be whole colors of 8 or 16 bits.
+    pub fn bits_per_pixel(mut self, bits_per_pixel: u8) -> Self {
+        self.bits_per_pixel = Some(bits_per_pixel);
+        self
+    }
+
+    pub fn color_space(mut self, color_space: UrfColorSpace) -> Self {
+        self.color_space = Some(color_space);
+        self
+    }
+
+    pub fn dot_per_inch(mut self, dot_per_inch: u32) -> Self {
+        self.dot_per_inch = Some(dot_per_inch);
+        self
+    }
+
+    pub fn duplex(mut self, duplex: UrfDuplex) -> Self {
+        self.duplex = duplex;
+        self
+    }
+
+    pub fn quality(mut self, quality: UrfQuality) -> Self {
+        self.quality = quality;
+        self
+    }
+
+    pub fn media_position(mut self, media_position: UrfMediaPosition) -> Self {
+        self.media_position = media_position;
+        self
+    }
+
+    pub fn media_type(mut self, media_type: UrfMediaType) -> Self {
+        self.media_type = media_type;
+        self
+    }
+
+    pub fn build(self) -> Result<UrfPageHeader, UrfError> {
+        let bits_per_pixel = self
+            .bits_per_pixel
+            .ok_or(UrfError::InvalidHeader("bits_per_pixel is required"))?;
+        let header = UrfPageHeader {
+            bits_per_pixel,
+            color_space: self
+                .color_space
+                .ok_or(UrfError::InvalidHeader("color_space is required"))?,
+            duplex: self.duplex,
+            quality: self.quality,
+            media_position: self.media_position,
+            media_type: self.media_type,
+            width: self
+                .width
+                .ok_or(UrfError::InvalidHeader("width is required"))?,
+            height: self
+                .height
+                .ok_or(UrfError::InvalidHeader("height is required"))?,
+            dot_per_inch: self
+                .dot_per_inch
+                .ok_or(UrfError::InvalidHeader("dot_per_inch is required"))?,
+        };
+        // the same pixel sizes as accepted when reading and writing
+        header.checked_bytes_per_pixel()?;
+        Ok(header)
+    }
+}
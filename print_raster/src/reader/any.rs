@@ -0,0 +1,339 @@
+use super::common::CommonRasterReaderState;
+use super::cups::unified::{
+    CupsRasterUnifiedNextPage, CupsRasterUnifiedPageReader, CupsRasterUnifiedReader,
+    CupsRasterUnifiedReaderNextPage,
+};
+use super::urf::{UrfPageReader, UrfReader};
+use super::{
+    detect_format, PrefixedReader, RasterFormat, RasterPageReader, RasterPageStream, RasterReader,
+    ReaderOptions,
+};
+use crate::decode::AnyRasterDecoder;
+use crate::error::AnyRasterError;
+use crate::model::cups::CupsPageHeaderV2;
+use crate::model::urf::UrfPageHeader;
+use crate::model::RasterPageMetadata;
+use derive_more::From;
+use futures::AsyncRead;
+use pin_project::pin_project;
+use std::future::Future;
+use std::ops::DerefMut;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// The header of a page of either a CUPS raster or a URF stream.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AnyPageHeader {
+    Cups(Box<CupsPageHeaderV2>),
+    Urf(UrfPageHeader),
+}
+
+impl From<CupsPageHeaderV2> for AnyPageHeader {
+    fn from(header: CupsPageHeaderV2) -> Self {
+        AnyPageHeader::Cups(Box::new(header))
+    }
+}
+
+impl From<UrfPageHeader> for AnyPageHeader {
+    fn from(header: UrfPageHeader) -> Self {
+        AnyPageHeader::Urf(header)
+    }
+}
+
+impl RasterPageMetadata for AnyPageHeader {
+    fn width(&self) -> u32 {
+        match self {
+            AnyPageHeader::Cups(header) => header.width(),
+            AnyPageHeader::Urf(header) => header.width(),
+        }
+    }
+
+    fn height(&self) -> u32 {
+        match self {
+            AnyPageHeader::Cups(header) => header.height(),
+            AnyPageHeader::Urf(header) => header.height(),
+        }
+    }
+
+    fn bits_per_pixel(&self) -> u32 {
+        match self {
+            AnyPageHeader::Cups(header) => header.bits_per_pixel(),
+            AnyPageHeader::Urf(header) => header.bits_per_pixel(),
+        }
+    }
+
+    fn dpi(&self) -> (u32, u32) {
+        match self {
+            AnyPageHeader::Cups(header) => header.dpi(),
+            AnyPageHeader::Urf(header) => header.dpi(),
+        }
+    }
+
+    fn num_colors(&self) -> u32 {
+        match self {
+            AnyPageHeader::Cups(header) => RasterPageMetadata::num_colors(header.as_ref()),
+            AnyPageHeader::Urf(header) => header.num_colors(),
+        }
+    }
+
+    fn content_len(&self) -> Option<u64> {
+        match self {
+            AnyPageHeader::Cups(header) => header.content_len(),
+            AnyPageHeader::Urf(header) => header.content_len(),
+        }
+    }
+}
+
+/// A reader of the pages of either a CUPS raster or a URF stream,
+/// for consumers that don't depend on the format.
+#[derive(From)]
+pub enum AnyRasterReader<R> {
+    Cups(CupsRasterUnifiedReader<R>),
+    Urf(UrfReader<R>),
+}
+
+impl<R> AnyRasterReader<Box<PrefixedReader<R>>>
+where
+    R: DerefMut<Target: AsyncRead> + Unpin,
+{
+    /// Detects the format of the stream, see [`detect_format`], and creates the reader
+    /// of that format.
+    pub async fn new(reader: Pin<R>) -> Result<Self, AnyRasterError> {
+        Self::new_with_options(reader, ReaderOptions::default()).await
+    }
+
+    pub async fn new_with_options(
+        reader: Pin<R>,
+        options: ReaderOptions,
+    ) -> Result<Self, AnyRasterError> {
+        let (format, reader) = detect_format(reader).await?;
+        let reader = Box::pin(reader);
+        Ok(match format {
+            RasterFormat::Cups(_) => CupsRasterUnifiedReader::new_with_options(reader, options)
+                .await?
+                .into(),
+            RasterFormat::Urf => UrfReader::new_with_options(reader, options).await?.into(),
+        })
+    }
+}
+
+impl<T> AnyRasterReader<Box<PrefixedReader<Box<T>>>>
+where
+    T: AsyncRead,
+{
+    /// Creates a reader over an owned source, e.g. a `Cursor`, pinning it on the heap.
+    pub async fn from_reader(source: T) -> Result<Self, AnyRasterError> {
+        Self::new(Box::pin(source)).await
+    }
+}
+
+impl<R> AnyRasterReader<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    pub fn format(&self) -> RasterFormat {
+        match self {
+            AnyRasterReader::Cups(reader) => RasterFormat::Cups(reader.sync_word()),
+            AnyRasterReader::Urf(_) => RasterFormat::Urf,
+        }
+    }
+
+    /// Turns the reader into a [`Stream`](futures::Stream) of its pages.
+    pub fn into_page_stream(self) -> RasterPageStream<Self, R> {
+        RasterPageStream::new(self)
+    }
+}
+
+impl<R> RasterReader<R> for AnyRasterReader<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    type PageHeader = AnyPageHeader;
+    type PageReader = AnyPageReader<R>;
+    type Error = AnyRasterError;
+    type NextPageFuture = AnyRasterReaderNextPage<R>;
+
+    fn next_page(self) -> AnyRasterReaderNextPage<R> {
+        match self {
+            AnyRasterReader::Cups(reader) => AnyRasterReaderNextPage::Cups(reader.next_page()),
+            AnyRasterReader::Urf(reader) => AnyRasterReaderNextPage::Urf(reader.next_page()),
+        }
+    }
+}
+
+#[pin_project(project = AnyRasterReaderNextPageProj)]
+pub enum AnyRasterReaderNextPage<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    Cups(#[pin] CupsRasterUnifiedReaderNextPage<R>),
+    Urf(#[pin] <UrfReader<R> as RasterReader<R>>::NextPageFuture),
+}
+
+impl<R> Future for AnyRasterReaderNextPage<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    type Output = Result<Option<AnyPageReader<R>>, AnyRasterError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            AnyRasterReaderNextPageProj::Cups(reader) => reader.poll(cx).map(|result| {
+                result
+                    .map(|reader| reader.map(AnyPageReader::from))
+                    .map_err(AnyRasterError::from)
+            }),
+            AnyRasterReaderNextPageProj::Urf(reader) => reader.poll(cx).map(|result| {
+                result
+                    .map(|reader| reader.map(AnyPageReader::from))
+                    .map_err(AnyRasterError::from)
+            }),
+        }
+    }
+}
+
+/// A page of either a CUPS raster or a URF stream, read by [`AnyRasterReader`].
+pub struct AnyPageReader<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    format: RasterFormat,
+    header: AnyPageHeader,
+    content: AnyRasterDecoder<R>,
+    /// The state at the start of the content.
+    state: CommonRasterReaderState,
+}
+
+// only lives until the next page is requested
+#[allow(clippy::large_enum_variant)]
+enum FormatPageReader<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    Cups(CupsRasterUnifiedPageReader<R>),
+    Urf(UrfPageReader<R>),
+}
+
+impl<R> From<CupsRasterUnifiedPageReader<R>> for AnyPageReader<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    fn from(page: CupsRasterUnifiedPageReader<R>) -> Self {
+        let format = RasterFormat::Cups(page.sync_word());
+        let (header, content, state) = page.into_raw_parts();
+        AnyPageReader {
+            format,
+            header: header.into(),
+            content: AnyRasterDecoder::Cups(content),
+            state,
+        }
+    }
+}
+
+impl<R> From<UrfPageReader<R>> for AnyPageReader<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    fn from(page: UrfPageReader<R>) -> Self {
+        let (header, content, state) = page.into_raw_parts();
+        AnyPageReader {
+            format: RasterFormat::Urf,
+            header: header.into(),
+            content: AnyRasterDecoder::Urf(content),
+            state,
+        }
+    }
+}
+
+impl<R> AnyPageReader<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    pub fn format(&self) -> RasterFormat {
+        self.format
+    }
+
+    /// Hands the page back to the reader of its format, e.g. to advance to the next page.
+    fn into_format_reader(self) -> FormatPageReader<R> {
+        match (self.format, self.header, self.content) {
+            (
+                RasterFormat::Cups(sync_word),
+                AnyPageHeader::Cups(header),
+                AnyRasterDecoder::Cups(content),
+            ) => FormatPageReader::Cups(CupsRasterUnifiedPageReader::from_raw_parts(
+                sync_word, *header, content, self.state,
+            )),
+            (RasterFormat::Urf, AnyPageHeader::Urf(header), AnyRasterDecoder::Urf(content)) => {
+                FormatPageReader::Urf(UrfPageReader::from_raw_parts(header, content, self.state))
+            }
+            _ => unreachable!("the header and the content are of the format of the page"),
+        }
+    }
+}
+
+impl<R> RasterPageReader<R> for AnyPageReader<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    type Header = AnyPageHeader;
+    type Decoder = AnyRasterDecoder<R>;
+    type Error = AnyRasterError;
+    type NextPageFuture = AnyPageReaderNext<R>;
+
+    fn next_page(self) -> Self::NextPageFuture {
+        match self.into_format_reader() {
+            FormatPageReader::Cups(page) => AnyPageReaderNext::Cups(page.next_page()),
+            FormatPageReader::Urf(page) => AnyPageReaderNext::Urf(page.next_page()),
+        }
+    }
+
+    fn skip_page(self) -> Self::NextPageFuture {
+        match self.into_format_reader() {
+            FormatPageReader::Cups(page) => AnyPageReaderNext::Cups(page.skip_page()),
+            FormatPageReader::Urf(page) => AnyPageReaderNext::Urf(page.skip_page()),
+        }
+    }
+
+    fn header(&self) -> &Self::Header {
+        &self.header
+    }
+
+    fn content_mut(&mut self) -> &mut Self::Decoder {
+        &mut self.content
+    }
+
+    fn into_content(self) -> Self::Decoder {
+        self.content
+    }
+}
+
+#[pin_project(project = AnyPageReaderNextProj)]
+pub enum AnyPageReaderNext<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    Cups(#[pin] CupsRasterUnifiedNextPage<R>),
+    Urf(#[pin] <UrfPageReader<R> as RasterPageReader<R>>::NextPageFuture),
+}
+
+impl<R> Future for AnyPageReaderNext<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    type Output = Result<Option<AnyPageReader<R>>, AnyRasterError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            AnyPageReaderNextProj::Cups(fut) => fut.poll(cx).map(|result| {
+                result
+                    .map(|reader| reader.map(AnyPageReader::from))
+                    .map_err(AnyRasterError::from)
+            }),
+            AnyPageReaderNextProj::Urf(fut) => fut.poll(cx).map(|result| {
+                result
+                    .map(|reader| reader.map(AnyPageReader::from))
+                    .map_err(AnyRasterError::from)
+            }),
+        }
+    }
+}
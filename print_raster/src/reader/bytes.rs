@@ -0,0 +1,23 @@
+use super::RasterPageReader;
+use crate::decode::RasterDecoder;
+use ::bytes::{Bytes, BytesMut};
+use futures::{AsyncRead, AsyncReadExt};
+use std::{io, ops::DerefMut};
+
+/// Reads the remaining content of the page into a [`Bytes`] buffer.
+///
+/// The buffer is allocated once with the size of the remaining content,
+/// so the result can be handed off without another copy.
+pub async fn read_page_into_bytes<P, R>(page: &mut P) -> io::Result<Bytes>
+where
+    P: RasterPageReader<R>,
+    P::Decoder: Unpin,
+    R: DerefMut<Target: AsyncRead>,
+{
+    let content = page.content_mut();
+    let len = usize::try_from(content.bytes_remaining())
+        .map_err(|_| io::Error::new(io::ErrorKind::OutOfMemory, "page is too large"))?;
+    let mut buf = BytesMut::zeroed(len);
+    content.read_exact(&mut buf).await?;
+    Ok(buf.freeze())
+}
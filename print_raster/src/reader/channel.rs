@@ -0,0 +1,53 @@
+use ::bytes::{Buf, Bytes};
+use futures::{channel::mpsc::Receiver, AsyncRead, Stream};
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// An [`AsyncRead`] adapter over a channel of [`Bytes`] chunks.
+///
+/// Chunks are consumed in the order they are sent, and a chunk larger than the read buffer
+/// is kept and continued by the next read, so chunk boundaries are invisible to the reader.
+/// Empty chunks are skipped. Once all senders are dropped and the pending chunks are drained,
+/// reads return `0` (end of stream).
+pub struct ChannelReader {
+    receiver: Receiver<Bytes>,
+    chunk: Bytes,
+}
+
+impl ChannelReader {
+    pub fn new(receiver: Receiver<Bytes>) -> Self {
+        Self {
+            receiver,
+            chunk: Bytes::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> Receiver<Bytes> {
+        self.receiver
+    }
+}
+
+impl AsyncRead for ChannelReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        while self.chunk.is_empty() {
+            match futures::ready!(Pin::new(&mut self.receiver).poll_next(cx)) {
+                Some(chunk) => self.chunk = chunk,
+                None => return Poll::Ready(Ok(0)),
+            }
+        }
+        let n = buf.len().min(self.chunk.len());
+        buf[..n].copy_from_slice(&self.chunk[..n]);
+        self.chunk.advance(n);
+        Poll::Ready(Ok(n))
+    }
+}
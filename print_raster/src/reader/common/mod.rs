@@ -1,16 +1,69 @@
-use crate::decode::{Limits, RasterDecoder, RasterDecoderConsumer, RasterDecoderExt};
-use crate::factory::RasterPageFactory;
-use crate::reader::RasterPageReader;
+use crate::decode::{RasterDecoder, RasterDecoderDrain, RasterDecoderExt};
+use crate::error::CommonRasterError;
+use crate::factory::{PageGeometry, RasterPageFactory};
+use crate::metrics::MetricsHandle;
+use crate::reader::{RasterPageReader, ReaderOptions};
 use futures::ready;
 use futures::task::Context;
-use futures::AsyncRead;
+use futures::{AsyncRead, AsyncReadExt};
 use pin_project::pin_project;
 use std::future::Future;
+use std::io;
 use std::marker::PhantomData;
 use std::ops::DerefMut;
 use std::pin::Pin;
 use std::task::Poll;
 
+/// Reads a page header into `buffer`, returning `false` if the stream ends before it.
+///
+/// Fails with [`io::ErrorKind::UnexpectedEof`] if the stream ends within the header.
+pub(crate) async fn read_header_bytes<R>(reader: &mut Pin<R>, buffer: &mut [u8]) -> io::Result<bool>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    let mut start = 0;
+    while start < buffer.len() {
+        let num_read = reader.as_mut().read(&mut buffer[start..]).await?;
+        if num_read == 0 {
+            if start == 0 {
+                // the stream ends at a page boundary
+                return Ok(false);
+            }
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "unexpected eof, the page header is truncated",
+            ));
+        }
+        start += num_read;
+    }
+    Ok(true)
+}
+
+/// The state carried from one page to the next.
+#[derive(Clone, Debug)]
+pub struct CommonRasterReaderState {
+    pub(crate) options: ReaderOptions,
+    /// The position in the stream, i.e. the number of bytes read so far.
+    pub(crate) position: u64,
+    /// The geometry of the first page, if uniform geometry is expected.
+    pub(crate) first_geometry: Option<PageGeometry>,
+    /// The number of pages read so far.
+    pub(crate) pages_read: u64,
+    pub(crate) metrics: MetricsHandle,
+}
+
+impl CommonRasterReaderState {
+    pub(crate) fn new(options: ReaderOptions, position: u64) -> Self {
+        Self {
+            metrics: options.metrics(),
+            options,
+            position,
+            first_geometry: None,
+            pages_read: 0,
+        }
+    }
+}
+
 /// A common implementation of `RasterPageReader` for all raster formats.
 ///
 /// # Type parameters
@@ -27,7 +80,8 @@ pub struct CommonRasterPageReader<F, HS, DS, R>
 {
     header: HS,
     content: DS,
-    limits: Limits,
+    /// The state at the start of the content.
+    state: CommonRasterReaderState,
     _factory: PhantomData<F>,
     _reader: PhantomData<R>,
 }
@@ -38,20 +92,48 @@ impl<F, HS, DS, R> CommonRasterPageReader<F, HS, DS, R>
     HS: From<<F as RasterPageFactory>::Header>,
     DS: From<<F as RasterPageFactory>::Decoder<R>> + RasterDecoder<R> + Unpin,
     R: DerefMut<Target: AsyncRead>,
-    F::Error: From<std::io::Error>,
+    F::Error: CommonRasterError,
 {
     /// Consumes the header of next page and returns a reader for the next page.
-    pub fn reader_for(reader: Pin<R>, limits: Limits) -> CommonRasterPageReaderFor<F, HS, DS, R> {
+    pub fn reader_for(
+        reader: Pin<R>,
+        options: ReaderOptions,
+    ) -> CommonRasterPageReaderFor<F, HS, DS, R> {
+        Self::reader_for_with_state(reader, CommonRasterReaderState::new(options, 0))
+    }
+
+    pub(crate) fn reader_for_with_state(
+        reader: Pin<R>,
+        state: CommonRasterReaderState,
+    ) -> CommonRasterPageReaderFor<F, HS, DS, R> {
+        let header_size = state.options.header_size_override.unwrap_or(F::HEADER_SIZE);
         CommonRasterPageReaderFor {
             reader: Some(reader),
-            buffer: vec![0; F::HEADER_SIZE],
-            limits,
+            buffer: vec![0; header_size],
+            state,
             start: 0,
             _header_storage: PhantomData,
             _decoder_storage: PhantomData,
             _factory: PhantomData,
         }
     }
+
+    /// Splits the reader into the header, the decoder and the state at the start of
+    /// the content, e.g. to consume the content without decoding it.
+    pub(crate) fn into_raw_parts(self) -> (HS, DS, CommonRasterReaderState) {
+        (self.header, self.content, self.state)
+    }
+
+    /// Reassembles a reader split by [`into_raw_parts`](Self::into_raw_parts).
+    pub(crate) fn from_raw_parts(header: HS, content: DS, state: CommonRasterReaderState) -> Self {
+        CommonRasterPageReader {
+            header,
+            content,
+            state,
+            _factory: PhantomData,
+            _reader: PhantomData,
+        }
+    }
 }
 
 impl<F, HS, DS, R> RasterPageReader<R> for CommonRasterPageReader<F, HS, DS, R>
@@ -60,7 +142,7 @@ impl<F, HS, DS, R> RasterPageReader<R> for CommonRasterPageReader<F, HS, DS, R>
     HS: From<<F as RasterPageFactory>::Header>,
     DS: From<<F as RasterPageFactory>::Decoder<R>> + RasterDecoder<R> + Unpin,
     R: DerefMut<Target: AsyncRead>,
-    F::Error: From<std::io::Error>,
+    F::Error: CommonRasterError,
 {
     type Header = HS;
     type Decoder = DS;
@@ -68,9 +150,13 @@ impl<F, HS, DS, R> RasterPageReader<R> for CommonRasterPageReader<F, HS, DS, R>
     type NextPageFuture = CommonRasterPageReaderNext<F, HS, DS, R>;
 
     fn next_page(self) -> Self::NextPageFuture {
-        let limits = self.limits.clone();
-        let content = self.into_content().consume();
-        CommonRasterPageReaderNext::Consume(content, limits)
+        let content = self.content.drain();
+        CommonRasterPageReaderNext::Consume(content, self.state)
+    }
+
+    fn skip_page(self) -> Self::NextPageFuture {
+        let content = self.content.skip_content();
+        CommonRasterPageReaderNext::Consume(content, self.state)
     }
 
     fn header(&self) -> &Self::Header {
@@ -93,11 +179,11 @@ pub struct CommonRasterPageReaderFor<F, HS, DS, R>
     HS: From<<F as RasterPageFactory>::Header>,
     DS: From<<F as RasterPageFactory>::Decoder<R>> + RasterDecoder<R>,
     R: DerefMut<Target: AsyncRead>,
-    F::Error: From<std::io::Error>,
+    F::Error: CommonRasterError,
 {
     reader: Option<Pin<R>>,
     buffer: Vec<u8>,
-    limits: Limits,
+    state: CommonRasterReaderState,
     start: usize,
     _header_storage: std::marker::PhantomData<HS>,
     _decoder_storage: std::marker::PhantomData<DS>,
@@ -110,12 +196,33 @@ impl<F, HS, DS, R> Future for CommonRasterPageReaderFor<F, HS, DS, R>
     HS: From<<F as RasterPageFactory>::Header>,
     DS: From<<F as RasterPageFactory>::Decoder<R>> + RasterDecoder<R>,
     R: DerefMut<Target: AsyncRead>,
-    F::Error: From<std::io::Error>,
+    F::Error: CommonRasterError,
 {
     type Output = Result<Option<CommonRasterPageReader<F, HS, DS, R>>, F::Error>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-        let this = self.as_mut().project();
+        let result = ready!(self.as_mut().poll_page(cx));
+        if let Err(e) = &result {
+            self.state.metrics.read_error(e);
+        }
+        Poll::Ready(result)
+    }
+}
+
+impl<F, HS, DS, R> CommonRasterPageReaderFor<F, HS, DS, R>
+where
+    F: RasterPageFactory,
+    HS: From<<F as RasterPageFactory>::Header>,
+    DS: From<<F as RasterPageFactory>::Decoder<R>> + RasterDecoder<R>,
+    R: DerefMut<Target: AsyncRead>,
+    F::Error: CommonRasterError,
+{
+    #[allow(clippy::type_complexity)]
+    fn poll_page(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<Option<CommonRasterPageReader<F, HS, DS, R>>, F::Error>> {
+        let this = self.project();
         if this.reader.is_none() {
             return Poll::Ready(Ok(None));
         }
@@ -124,20 +231,59 @@ fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
             let buf = &mut this.buffer[*this.start..];
             let num_read = ready!(reader.as_mut().poll_read(cx, buf))?;
             *this.start += num_read;
-            if *this.start >= F::HEADER_SIZE {
+            if *this.start >= this.buffer.len() {
                 // header is read
                 break;
             }
             if num_read == 0 {
-                return Poll::Ready(Ok(None));
+                if *this.start == 0 {
+                    // the stream ends at a page boundary
+                    return Poll::Ready(Ok(None));
+                }
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "unexpected eof, the page header is truncated",
+                )
+                .into()));
             }
         }
+        let mut state = this.state.clone();
+        let max_pages = state.options.limits.max_pages;
+        if state.pages_read >= max_pages {
+            return Poll::Ready(Err(F::Error::too_many_pages(max_pages)));
+        }
+        state.pages_read += 1;
+        state.position += this.buffer.len() as u64;
+        // tolerate headers of legacy files with a different size
+        this.buffer.resize(F::HEADER_SIZE, 0);
         let header = F::header_from_bytes(this.buffer)?;
-        let content = F::decode(&header, this.reader.take().unwrap(), this.limits)?;
+        if state.options.expect_uniform_geometry {
+            let geometry = F::geometry(&header);
+            match state.first_geometry {
+                Some(expected) if expected != geometry => {
+                    return Poll::Ready(Err(F::Error::geometry_changed(expected, geometry)));
+                }
+                Some(_) => {}
+                None => state.first_geometry = Some(geometry),
+            }
+        }
+        let mut content = F::decode(&header, this.reader.take().unwrap(), &state.options.limits)?;
+        if let Some(fill_byte) = state.options.fill_byte_override {
+            content.set_fill_byte(fill_byte);
+        }
+        if let Some(total_stream_len) = state.options.total_stream_len {
+            let declared = content.min_encoded_bytes_remaining();
+            let remaining = total_stream_len.saturating_sub(state.position);
+            if declared > remaining {
+                return Poll::Ready(Err(F::Error::declared_size_exceeds_stream(
+                    declared, remaining,
+                )));
+            }
+        }
         Poll::Ready(Ok(Some(CommonRasterPageReader {
             header: header.into(),
             content: content.into(),
-            limits: this.limits.clone(),
+            state,
             _factory: PhantomData,
             _reader: PhantomData,
         })))
@@ -151,9 +297,9 @@ pub enum CommonRasterPageReaderNext<F, HS, DS, R>
     HS: From<<F as RasterPageFactory>::Header>,
     DS: From<<F as RasterPageFactory>::Decoder<R>> + RasterDecoder<R> + Unpin,
     R: DerefMut<Target: AsyncRead>,
-    F::Error: From<std::io::Error>,
+    F::Error: CommonRasterError,
 {
-    Consume(#[pin] RasterDecoderConsumer<DS, R>, Limits),
+    Consume(#[pin] RasterDecoderDrain<DS, R>, CommonRasterReaderState),
     ReaderFor(#[pin] CommonRasterPageReaderFor<F, HS, DS, R>),
 }
 
@@ -163,17 +309,37 @@ impl<F, HS, DS, R> Future for CommonRasterPageReaderNext<F, HS, DS, R>
     HS: From<<F as RasterPageFactory>::Header>,
     DS: From<<F as RasterPageFactory>::Decoder<R>> + RasterDecoder<R> + Unpin,
     R: DerefMut<Target: AsyncRead>,
-    F::Error: From<std::io::Error>,
+    F::Error: CommonRasterError,
 {
     type Output = Result<Option<CommonRasterPageReader<F, HS, DS, R>>, F::Error>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
         loop {
             match self.as_mut().project() {
-                CommonRasterPageReaderNextProj::Consume(consumer, limits) => {
-                    let reader = ready!(consumer.poll(cx))?;
-                    let future =
-                        CommonRasterPageReader::<F, HS, DS, R>::reader_for(reader, limits.clone());
+                CommonRasterPageReaderNextProj::Consume(drain, state) => {
+                    let content = match ready!(drain.poll(cx)) {
+                        Ok(content) => content,
+                        Err(e) => {
+                            let e = F::Error::from(e);
+                            state.metrics.read_error(&e);
+                            return Poll::Ready(Err(e));
+                        }
+                    };
+                    if state.options.require_clean_page_boundary && !content.clean_page_boundary() {
+                        let e = F::Error::from(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "encoded data of the page extends past its content",
+                        ));
+                        state.metrics.read_error(&e);
+                        return Poll::Ready(Err(e));
+                    }
+                    state.metrics.page_read(content.encoded_bytes_read());
+                    let mut state = state.clone();
+                    state.position += content.encoded_bytes_read();
+                    let future = CommonRasterPageReader::<F, HS, DS, R>::reader_for_with_state(
+                        content.into_pin_mut(),
+                        state,
+                    );
                     self.set(CommonRasterPageReaderNext::ReaderFor(future));
                 }
                 CommonRasterPageReaderNextProj::ReaderFor(future) => return future.poll(cx),
@@ -1 +1,25 @@
+use crate::error::CupsRasterError;
+use crate::model::cups::CupsPageHeaderV2;
+use futures::AsyncRead;
+use std::ops::DerefMut;
+use std::pin::Pin;
+use unified::CupsRasterUnifiedReader;
+
 pub mod unified;
+
+/// Counts the pages of a CUPS raster stream of any version without decoding their pixels.
+///
+/// Returns the number of pages and the header of each page. The content of each page
+/// is skipped by following its line and block headers, without setting up a decoder.
+pub async fn count_pages<R>(
+    reader: Pin<R>,
+) -> Result<(usize, Vec<CupsPageHeaderV2>), CupsRasterError>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    let headers = CupsRasterUnifiedReader::new(reader)
+        .await?
+        .walk_page_headers()
+        .await?;
+    Ok((headers.len(), headers))
+}
@@ -1,23 +1,47 @@
+#![allow(clippy::enum_variant_names)]
+
 use byteorder::{BigEndian, LittleEndian};
-use futures::AsyncRead;
+use futures::io::SeekFrom;
+use futures::{
+    AsyncBufRead, AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt,
+};
 use pin_project::pin_project;
 use std::io;
+use std::marker::PhantomData;
 use std::task::{Context, Poll};
 use std::{future::Future, ops::DerefMut, pin::Pin};
 mod page;
-use crate::decode::{CupsRasterUnifiedDecoder, Limits};
+use crate::decode::{
+    BufferedSource, CupsRasterUnifiedDecoder, EncodedSource, Limits, RasterDecoder, ReadAhead,
+};
+use crate::encode::CupsRasterUnifiedEncoder;
 use crate::error::CupsRasterError;
-use crate::factory::{CupsPageFactoryV1, CupsPageFactoryV2, CupsPageFactoryV3};
-use crate::model::cups::{CupsPageHeaderV2, CupsSyncWord};
+use crate::factory::{
+    compressed_layout, CupsPageFactoryV1, CupsPageFactoryV2, CupsPageFactoryV3, RasterPageFactory,
+};
+use crate::model::cups::{
+    CupsPageHeaderV2, CupsSyncWord, CUPS_V1_PAGE_HEADER_SIZE, CUPS_V2_PAGE_HEADER_SIZE,
+};
 use crate::model::RasterByteOrder;
-use crate::reader::common::CommonRasterPageReaderFor;
-use crate::reader::RasterReader;
+use crate::reader::common::{
+    read_header_bytes, CommonRasterPageReaderFor, CommonRasterReaderState,
+};
+use crate::reader::{
+    read_page_headers, RasterPageReader, RasterPageStream, RasterReader, ReaderOptions,
+};
+use crate::transcode::copy_compressed_content;
+use crate::writer::cups::unified::CupsRasterUnifiedWriter;
+use crate::writer::{RasterPageWriter, RasterWriter};
 pub use page::*;
 
-pub struct CupsRasterUnifiedReader<R> {
+/// Reads the pages of a CUPS Raster stream of any version.
+///
+/// The compressed content of V2 pages is read through `S`, see [`EncodedSource`].
+pub struct CupsRasterUnifiedReader<R, S = ReadAhead> {
     sync_word: CupsSyncWord,
     reader: Pin<R>,
-    limits: Limits,
+    options: ReaderOptions,
+    _source: PhantomData<S>,
 }
 
 impl<R> CupsRasterUnifiedReader<R>
@@ -25,21 +49,65 @@ impl<R> CupsRasterUnifiedReader<R>
     R: DerefMut<Target: AsyncRead>,
 {
     pub async fn new(reader: Pin<R>) -> Result<Self, CupsRasterError> {
-        Self::new_with_limits(reader, Limits::default()).await
+        Self::new_with_options(reader, ReaderOptions::default()).await
+    }
+
+    pub async fn new_with_limits(reader: Pin<R>, limits: Limits) -> Result<Self, CupsRasterError> {
+        Self::new_with_options(reader, limits.into()).await
     }
 
-    pub async fn new_with_limits(
+    pub async fn new_with_options(
         mut reader: Pin<R>,
-        limits: Limits,
+        options: ReaderOptions,
     ) -> Result<Self, CupsRasterError> {
+        // the fields of every version are laid out as in V1
+        options.check_header_size_override(CUPS_V1_PAGE_HEADER_SIZE)?;
         let sync_word = CupsRasterReaderReadSyncWord::new(reader.as_mut()).await?;
         Ok(CupsRasterUnifiedReader {
             sync_word,
             reader,
-            limits,
+            options,
+            _source: PhantomData,
         })
     }
+}
+
+impl<R> CupsRasterUnifiedReader<R, BufferedSource>
+where
+    R: DerefMut<Target: AsyncBufRead>,
+{
+    /// Creates a reader over a buffered source, e.g. a `futures::io::BufReader`.
+    ///
+    /// The compressed content of V2 pages is decoded from the buffer of the source with
+    /// `poll_fill_buf` and `consume`, instead of being copied to a buffer of its own.
+    pub async fn new_buffered(reader: Pin<R>) -> Result<Self, CupsRasterError> {
+        Self::new_buffered_with_options(reader, ReaderOptions::default()).await
+    }
 
+    pub async fn new_buffered_with_options(
+        reader: Pin<R>,
+        options: ReaderOptions,
+    ) -> Result<Self, CupsRasterError> {
+        let CupsRasterUnifiedReader {
+            sync_word,
+            reader,
+            options,
+            ..
+        } = CupsRasterUnifiedReader::new_with_options(reader, options).await?;
+        Ok(CupsRasterUnifiedReader {
+            sync_word,
+            reader,
+            options,
+            _source: PhantomData,
+        })
+    }
+}
+
+impl<R, S> CupsRasterUnifiedReader<R, S>
+where
+    R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
+{
     pub fn sync_word(&self) -> CupsSyncWord {
         self.sync_word
     }
@@ -47,52 +115,354 @@ pub fn sync_word(&self) -> CupsSyncWord {
     pub fn byte_order(&self) -> RasterByteOrder {
         self.sync_word.byte_order()
     }
+
+    /// Turns the reader into a [`Stream`](futures::Stream) of its pages.
+    pub fn into_page_stream(self) -> RasterPageStream<Self, R> {
+        RasterPageStream::new(self)
+    }
+
+    /// Reads the header of every page, skipping the page content without decoding it.
+    ///
+    /// Fails with [`CupsRasterError::TooManyPages`] if the stream has more pages than
+    /// allowed by [`Limits::max_pages`].
+    pub async fn read_page_headers(self) -> Result<Vec<CupsPageHeaderV2>, CupsRasterError> {
+        read_page_headers(self).await
+    }
+}
+
+impl<R> CupsRasterUnifiedReader<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    /// Reads the header of every page, skipping the encoded content of each page
+    /// by following its line and block headers, without setting up a decoder.
+    ///
+    /// Unlike [`read_page_headers`](Self::read_page_headers), the options other than
+    /// [`Limits::max_pages`] are ignored.
+    pub(crate) async fn walk_page_headers(self) -> Result<Vec<CupsPageHeaderV2>, CupsRasterError> {
+        let CupsRasterUnifiedReader {
+            sync_word,
+            mut reader,
+            options,
+            ..
+        } = self;
+        let header_size = match sync_word {
+            CupsSyncWord::V1BigEndian | CupsSyncWord::V1LittleEndian => CUPS_V1_PAGE_HEADER_SIZE,
+            _ => CUPS_V2_PAGE_HEADER_SIZE,
+        };
+        let mut buffer = vec![0; header_size];
+        let mut sink = futures::io::sink();
+        let mut headers = Vec::new();
+        while read_header_bytes(&mut reader, &mut buffer).await? {
+            let max_pages = options.limits.max_pages;
+            if headers.len() as u64 >= max_pages {
+                return Err(CupsRasterError::TooManyPages { max_pages });
+            }
+            let header: CupsPageHeaderV2 = match sync_word {
+                CupsSyncWord::V1BigEndian => {
+                    CupsPageFactoryV1::<BigEndian>::header_from_bytes(&buffer)?.into()
+                }
+                CupsSyncWord::V1LittleEndian => {
+                    CupsPageFactoryV1::<LittleEndian>::header_from_bytes(&buffer)?.into()
+                }
+                CupsSyncWord::V2BigEndian => {
+                    CupsPageFactoryV2::<BigEndian>::header_from_bytes(&buffer)?
+                }
+                CupsSyncWord::V2LittleEndian => {
+                    CupsPageFactoryV2::<LittleEndian>::header_from_bytes(&buffer)?
+                }
+                CupsSyncWord::V3BigEndian => {
+                    CupsPageFactoryV3::<BigEndian>::header_from_bytes(&buffer)?
+                }
+                CupsSyncWord::V3LittleEndian => {
+                    CupsPageFactoryV3::<LittleEndian>::header_from_bytes(&buffer)?
+                }
+            };
+            let num_bytes = header.image_byte_len()?;
+            if sync_word.is_compressed() {
+                let (chunk_size, bytes_per_line) = compressed_layout(&header)?;
+                copy_compressed_content(
+                    &mut reader,
+                    &mut Pin::new(&mut sink),
+                    chunk_size,
+                    bytes_per_line,
+                    num_bytes,
+                    false,
+                )
+                .await?;
+            } else {
+                let skipped = futures::io::copy(reader.as_mut().take(num_bytes), &mut sink).await?;
+                if skipped < num_bytes {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                }
+            }
+            headers.push(header);
+        }
+        Ok(headers)
+    }
+}
+
+impl<T> CupsRasterUnifiedReader<Box<T>>
+where
+    T: AsyncRead,
+{
+    /// Creates a reader over an owned source, e.g. a `Cursor`, pinning it on the heap.
+    ///
+    /// `source` should be buffered, e.g. by `futures::io::BufReader`.
+    pub async fn from_reader(source: T) -> Result<Self, CupsRasterError> {
+        Self::new(Box::pin(source)).await
+    }
 }
 
-impl<R> RasterReader<R> for CupsRasterUnifiedReader<R>
+impl<R> CupsRasterUnifiedReader<R>
+where
+    R: DerefMut<Target: AsyncRead + AsyncSeek>,
+{
+    /// Returns a reader for page `n`, counting from 0, without decoding the pages before it.
+    ///
+    /// Uncompressed (V1 and V3) streams are expected to hold pages of the same size as
+    /// the first page, so page `n` is found by seeking past `n` pages of that size.
+    /// Compressed (V2) pages have no fixed size, so they are skipped one by one instead.
+    ///
+    /// Fails with [`CupsRasterError::PageOutOfRange`] if the stream has no page `n`.
+    pub async fn seek_to_page(
+        self,
+        n: usize,
+    ) -> Result<CupsRasterUnifiedPageReader<R>, CupsRasterError> {
+        let out_of_range = CupsRasterError::PageOutOfRange { index: n };
+        if self.sync_word.is_compressed() {
+            let mut page = self.next_page().await?.ok_or(out_of_range)?;
+            for index in 1..=n {
+                page = page
+                    .skip_page()
+                    .await?
+                    .ok_or(CupsRasterError::PageOutOfRange { index })?;
+            }
+            return Ok(page);
+        }
+        let (sync_word, options) = (self.sync_word, self.options.clone());
+        let first = self.next_page().await?.ok_or(out_of_range)?;
+        if n == 0 {
+            return Ok(first);
+        }
+        let header_size = options.header_size_override.unwrap_or(match sync_word {
+            CupsSyncWord::V1BigEndian | CupsSyncWord::V1LittleEndian => CUPS_V1_PAGE_HEADER_SIZE,
+            _ => CUPS_V2_PAGE_HEADER_SIZE,
+        }) as u64;
+        let content_size = first.header().image_byte_len()?;
+        let stride = header_size + content_size;
+        // the reader is at the start of the content of the first page
+        let offset = (n as u64 - 1)
+            .checked_mul(stride)
+            .and_then(|x| x.checked_add(content_size))
+            .and_then(|x| i64::try_from(x).ok())
+            .ok_or(CupsRasterError::DataTooLarge)?;
+        let mut reader = first.into_content().into_pin_mut();
+        reader.as_mut().seek(SeekFrom::Current(offset)).await?;
+        let mut state = CommonRasterReaderState::new(options.clone(), 4 + n as u64 * stride);
+        state.pages_read = n as u64;
+        let reader = CupsRasterUnifiedReader {
+            sync_word,
+            reader,
+            options,
+            _source: PhantomData,
+        };
+        reader
+            .next_page_with_state(state)
+            .await?
+            .ok_or(CupsRasterError::PageOutOfRange { index: n })
+    }
+}
+
+impl<R> CupsRasterUnifiedReader<R>
 where
     R: DerefMut<Target: AsyncRead>,
+    CupsRasterUnifiedDecoder<R>: Unpin,
+{
+    /// Copies all pages to `writer` and finishes it.
+    ///
+    /// When `writer` has the same sync word as the stream, only the page headers are
+    /// parsed and written again, and the encoded content of each page is copied as is.
+    /// The end of a compressed page is found by following its line and block headers.
+    /// Otherwise, the content of each page is decoded and encoded again.
+    pub async fn transcode_passthrough<W>(
+        self,
+        writer: CupsRasterUnifiedWriter<W>,
+    ) -> Result<(), CupsRasterError>
+    where
+        W: DerefMut<Target: AsyncWrite>,
+        CupsRasterUnifiedEncoder<W>: Unpin,
+    {
+        if self.sync_word != writer.sync_word() {
+            return self.reencode_pages(writer).await;
+        }
+        let sync_word = self.sync_word;
+        let sets_compression = writer.sets_compression();
+        let mut page = match self.next_page().await? {
+            Some(page) => page,
+            None => return writer.finish().await,
+        };
+        let mut writer = writer;
+        loop {
+            let (header, content, mut state) = page.into_raw_parts();
+            let (mut output, mut stats, metrics) =
+                writer.next_page(&header).await?.into_raw_parts();
+            let num_bytes = content.bytes_remaining();
+            let (input, copied) = match content {
+                CupsRasterUnifiedDecoder::Compressed(decoder) => {
+                    let (chunk_size, bytes_per_line) =
+                        (decoder.chunk_size(), decoder.bytes_per_line());
+                    let mut input = decoder.into_pin_mut();
+                    let copied = copy_compressed_content(
+                        &mut input,
+                        &mut output,
+                        chunk_size,
+                        bytes_per_line,
+                        num_bytes,
+                        false,
+                    )
+                    .await?;
+                    (input, copied)
+                }
+                CupsRasterUnifiedDecoder::Uncompressed(decoder) => {
+                    let mut input = decoder.into_pin_mut();
+                    let copied =
+                        futures::io::copy(input.as_mut().take(num_bytes), &mut output.as_mut())
+                            .await?;
+                    if copied < num_bytes {
+                        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                    }
+                    (input, copied)
+                }
+            };
+            stats.add_page(copied);
+            metrics.page_written(copied);
+            state.metrics.page_read(copied);
+            state.position += copied;
+            writer = CupsRasterUnifiedWriter::from_raw_parts(
+                sync_word,
+                output,
+                stats,
+                metrics,
+                sets_compression,
+            );
+            let reader = CupsRasterUnifiedReader {
+                sync_word,
+                reader: input,
+                options: state.options.clone(),
+                _source: PhantomData,
+            };
+            page = match reader.next_page_with_state(state).await? {
+                Some(page) => page,
+                None => break,
+            };
+        }
+        let (mut output, _) = writer.finish_with_stats().await?;
+        output.as_mut().close().await?;
+        Ok(())
+    }
+
+    async fn reencode_pages<W>(
+        self,
+        writer: CupsRasterUnifiedWriter<W>,
+    ) -> Result<(), CupsRasterError>
+    where
+        W: DerefMut<Target: AsyncWrite>,
+        CupsRasterUnifiedEncoder<W>: Unpin,
+    {
+        let mut page = match self.next_page().await? {
+            Some(page) => page,
+            None => return writer.finish().await,
+        };
+        let mut page_writer = writer.next_page(page.header()).await?;
+        loop {
+            futures::io::copy(page.content_mut(), page_writer.content_mut()).await?;
+            page = match page.next_page().await? {
+                Some(page) => page,
+                None => break,
+            };
+            page_writer = page_writer.next_page(page.header()).await?;
+        }
+        page_writer.finish().await
+    }
+}
+
+impl<R, S> RasterReader<R> for CupsRasterUnifiedReader<R, S>
+where
+    R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
 {
     type PageHeader = CupsPageHeaderV2;
-    type PageReader = CupsRasterUnifiedPageReader<R>;
+    type PageReader = CupsRasterUnifiedPageReader<R, S>;
     type Error = CupsRasterError;
-    type NextPageFuture = CupsRasterUnifiedReaderNextPage<R>;
+    type NextPageFuture = CupsRasterUnifiedReaderNextPage<R, S>;
+
+    fn next_page(self) -> CupsRasterUnifiedReaderNextPage<R, S> {
+        // the sync word has been read
+        let state = CommonRasterReaderState::new(self.options.clone(), 4);
+        self.next_page_with_state(state)
+    }
+}
 
-    fn next_page(self) -> CupsRasterUnifiedReaderNextPage<R> {
+impl<R, S> CupsRasterUnifiedReader<R, S>
+where
+    R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
+{
+    fn next_page_with_state(
+        self,
+        state: CommonRasterReaderState,
+    ) -> CupsRasterUnifiedReaderNextPage<R, S> {
         match self.sync_word {
             CupsSyncWord::V1BigEndian => CupsRasterUnifiedReaderNextPage::V1BigEndian(
-                CupsRasterUnifiedPageReaderV1BE::reader_for(self.reader, self.limits),
-            ),
-            CupsSyncWord::V1LittleEndian => CupsRasterUnifiedReaderNextPage::V1LittleEndian(
-                CupsRasterUnifiedPageReaderV1LE::reader_for(self.reader, self.limits),
+                CupsRasterUnifiedPageReaderV1BE::<R, S>::reader_for_with_state(self.reader, state),
             ),
+            CupsSyncWord::V1LittleEndian => {
+                CupsRasterUnifiedReaderNextPage::V1LittleEndian(CupsRasterUnifiedPageReaderV1LE::<
+                    R,
+                    S,
+                >::reader_for_with_state(
+                    self.reader, state
+                ))
+            }
             CupsSyncWord::V2BigEndian => CupsRasterUnifiedReaderNextPage::V2BigEndian(
-                CupsRasterUnifiedPageReaderV2BE::reader_for(self.reader, self.limits),
-            ),
-            CupsSyncWord::V2LittleEndian => CupsRasterUnifiedReaderNextPage::V2LittleEndian(
-                CupsRasterUnifiedPageReaderV2LE::reader_for(self.reader, self.limits),
+                CupsRasterUnifiedPageReaderV2BE::<R, S>::reader_for_with_state(self.reader, state),
             ),
+            CupsSyncWord::V2LittleEndian => {
+                CupsRasterUnifiedReaderNextPage::V2LittleEndian(CupsRasterUnifiedPageReaderV2LE::<
+                    R,
+                    S,
+                >::reader_for_with_state(
+                    self.reader, state
+                ))
+            }
             CupsSyncWord::V3BigEndian => CupsRasterUnifiedReaderNextPage::V3BigEndian(
-                CupsRasterUnifiedPageReaderV3BE::reader_for(self.reader, self.limits),
-            ),
-            CupsSyncWord::V3LittleEndian => CupsRasterUnifiedReaderNextPage::V3LittleEndian(
-                CupsRasterUnifiedPageReaderV3LE::reader_for(self.reader, self.limits),
+                CupsRasterUnifiedPageReaderV3BE::<R, S>::reader_for_with_state(self.reader, state),
             ),
+            CupsSyncWord::V3LittleEndian => {
+                CupsRasterUnifiedReaderNextPage::V3LittleEndian(CupsRasterUnifiedPageReaderV3LE::<
+                    R,
+                    S,
+                >::reader_for_with_state(
+                    self.reader, state
+                ))
+            }
         }
     }
 }
 
 #[pin_project(project = CupsRasterUnifiedReaderNextPageProj)]
-pub enum CupsRasterUnifiedReaderNextPage<R>
+pub enum CupsRasterUnifiedReaderNextPage<R, S = ReadAhead>
 where
     R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
 {
     V1BigEndian(
         #[pin]
         CommonRasterPageReaderFor<
             CupsPageFactoryV1<BigEndian>,
             CupsPageHeaderV2,
-            CupsRasterUnifiedDecoder<R>,
+            CupsRasterUnifiedDecoder<R, S>,
             R,
         >,
     ),
@@ -101,7 +471,7 @@ pub enum CupsRasterUnifiedReaderNextPage<R>
         CommonRasterPageReaderFor<
             CupsPageFactoryV1<LittleEndian>,
             CupsPageHeaderV2,
-            CupsRasterUnifiedDecoder<R>,
+            CupsRasterUnifiedDecoder<R, S>,
             R,
         >,
     ),
@@ -110,7 +480,7 @@ pub enum CupsRasterUnifiedReaderNextPage<R>
         CommonRasterPageReaderFor<
             CupsPageFactoryV2<BigEndian>,
             CupsPageHeaderV2,
-            CupsRasterUnifiedDecoder<R>,
+            CupsRasterUnifiedDecoder<R, S>,
             R,
         >,
     ),
@@ -119,7 +489,7 @@ pub enum CupsRasterUnifiedReaderNextPage<R>
         CommonRasterPageReaderFor<
             CupsPageFactoryV2<LittleEndian>,
             CupsPageHeaderV2,
-            CupsRasterUnifiedDecoder<R>,
+            CupsRasterUnifiedDecoder<R, S>,
             R,
         >,
     ),
@@ -128,7 +498,7 @@ pub enum CupsRasterUnifiedReaderNextPage<R>
         CommonRasterPageReaderFor<
             CupsPageFactoryV3<BigEndian>,
             CupsPageHeaderV2,
-            CupsRasterUnifiedDecoder<R>,
+            CupsRasterUnifiedDecoder<R, S>,
             R,
         >,
     ),
@@ -137,17 +507,18 @@ pub enum CupsRasterUnifiedReaderNextPage<R>
         CommonRasterPageReaderFor<
             CupsPageFactoryV3<LittleEndian>,
             CupsPageHeaderV2,
-            CupsRasterUnifiedDecoder<R>,
+            CupsRasterUnifiedDecoder<R, S>,
             R,
         >,
     ),
 }
 
-impl<R> Future for CupsRasterUnifiedReaderNextPage<R>
+impl<R, S> Future for CupsRasterUnifiedReaderNextPage<R, S>
 where
     R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
 {
-    type Output = Result<Option<CupsRasterUnifiedPageReader<R>>, CupsRasterError>;
+    type Output = Result<Option<CupsRasterUnifiedPageReader<R, S>>, CupsRasterError>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.project();
@@ -214,15 +585,9 @@ fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
             }
         }
 
-        let sync_word = match this.buffer {
-            [b'R', b'a', b'S', b't'] => CupsSyncWord::V1BigEndian,
-            [b't', b'S', b'a', b'R'] => CupsSyncWord::V1LittleEndian,
-            [b'R', b'a', b'S', b'2'] => CupsSyncWord::V2BigEndian,
-            [b'2', b'S', b'a', b'R'] => CupsSyncWord::V2LittleEndian,
-            [b'R', b'a', b'S', b'3'] => CupsSyncWord::V3BigEndian,
-            [b'3', b'S', b'a', b'R'] => CupsSyncWord::V3LittleEndian,
-            _ => return Poll::Ready(Err(CupsRasterError::InvalidSyncWord)),
-        };
-        Poll::Ready(Ok(sync_word))
+        Poll::Ready(
+            CupsSyncWord::from_bytes(this.buffer)
+                .ok_or(CupsRasterError::InvalidSyncWord(*this.buffer)),
+        )
     }
 }
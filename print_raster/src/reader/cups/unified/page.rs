@@ -1,9 +1,14 @@
+#![allow(clippy::enum_variant_names)]
+
 use crate::{
-    decode::CupsRasterUnifiedDecoder,
+    decode::{CupsRasterUnifiedDecoder, EncodedSource, ReadAhead},
     error::CupsRasterError,
     factory::{CupsPageFactoryV1, CupsPageFactoryV2, CupsPageFactoryV3},
-    model::{cups::CupsPageHeaderV2, RasterByteOrder},
-    reader::common::CommonRasterPageReader,
+    model::{
+        cups::{CupsPageHeaderV2, CupsSyncWord},
+        RasterByteOrder,
+    },
+    reader::common::{CommonRasterPageReader, CommonRasterReaderState},
     reader::RasterPageReader,
 };
 use byteorder::{BigEndian, LittleEndian};
@@ -13,80 +18,89 @@
 use pin_project::pin_project;
 use std::{future::Future, ops::DerefMut, pin::Pin};
 
-pub type CupsRasterUnifiedPageReaderV1BE<R> = CommonRasterPageReader<
+pub type CupsRasterUnifiedPageReaderV1BE<R, S = ReadAhead> = CommonRasterPageReader<
     CupsPageFactoryV1<BigEndian>,
     CupsPageHeaderV2,
-    CupsRasterUnifiedDecoder<R>,
+    CupsRasterUnifiedDecoder<R, S>,
     R,
 >;
-pub type CupsRasterUnifiedPageReaderV1LE<R> = CommonRasterPageReader<
+pub type CupsRasterUnifiedPageReaderV1LE<R, S = ReadAhead> = CommonRasterPageReader<
     CupsPageFactoryV1<LittleEndian>,
     CupsPageHeaderV2,
-    CupsRasterUnifiedDecoder<R>,
+    CupsRasterUnifiedDecoder<R, S>,
     R,
 >;
-pub type CupsRasterUnifiedPageReaderV2BE<R> = CommonRasterPageReader<
+pub type CupsRasterUnifiedPageReaderV2BE<R, S = ReadAhead> = CommonRasterPageReader<
     CupsPageFactoryV2<BigEndian>,
     CupsPageHeaderV2,
-    CupsRasterUnifiedDecoder<R>,
+    CupsRasterUnifiedDecoder<R, S>,
     R,
 >;
-pub type CupsRasterUnifiedPageReaderV2LE<R> = CommonRasterPageReader<
+pub type CupsRasterUnifiedPageReaderV2LE<R, S = ReadAhead> = CommonRasterPageReader<
     CupsPageFactoryV2<LittleEndian>,
     CupsPageHeaderV2,
-    CupsRasterUnifiedDecoder<R>,
+    CupsRasterUnifiedDecoder<R, S>,
     R,
 >;
-pub type CupsRasterUnifiedPageReaderV3BE<R> = CommonRasterPageReader<
+pub type CupsRasterUnifiedPageReaderV3BE<R, S = ReadAhead> = CommonRasterPageReader<
     CupsPageFactoryV3<BigEndian>,
     CupsPageHeaderV2,
-    CupsRasterUnifiedDecoder<R>,
+    CupsRasterUnifiedDecoder<R, S>,
     R,
 >;
-pub type CupsRasterUnifiedPageReaderV3LE<R> = CommonRasterPageReader<
+pub type CupsRasterUnifiedPageReaderV3LE<R, S = ReadAhead> = CommonRasterPageReader<
     CupsPageFactoryV3<LittleEndian>,
     CupsPageHeaderV2,
-    CupsRasterUnifiedDecoder<R>,
+    CupsRasterUnifiedDecoder<R, S>,
     R,
 >;
 
 #[derive(From)]
-pub enum CupsRasterUnifiedPageReader<R>
+pub enum CupsRasterUnifiedPageReader<R, S = ReadAhead>
 where
     R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
 {
-    V1BigEndian(CupsRasterUnifiedPageReaderV1BE<R>),
-    V1LittleEndian(CupsRasterUnifiedPageReaderV1LE<R>),
-    V2BigEndian(CupsRasterUnifiedPageReaderV2BE<R>),
-    V2LittleEndian(CupsRasterUnifiedPageReaderV2LE<R>),
-    V3BigEndian(CupsRasterUnifiedPageReaderV3BE<R>),
-    V3LittleEndian(CupsRasterUnifiedPageReaderV3LE<R>),
+    V1BigEndian(CupsRasterUnifiedPageReaderV1BE<R, S>),
+    V1LittleEndian(CupsRasterUnifiedPageReaderV1LE<R, S>),
+    V2BigEndian(CupsRasterUnifiedPageReaderV2BE<R, S>),
+    V2LittleEndian(CupsRasterUnifiedPageReaderV2LE<R, S>),
+    V3BigEndian(CupsRasterUnifiedPageReaderV3BE<R, S>),
+    V3LittleEndian(CupsRasterUnifiedPageReaderV3LE<R, S>),
 }
 
 #[pin_project(project = CupsRasterUnifiedNextPageProj)]
-pub enum CupsRasterUnifiedNextPage<R>
+pub enum CupsRasterUnifiedNextPage<R, S = ReadAhead>
 where
     R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
 {
-    V1BigEndian(#[pin] <CupsRasterUnifiedPageReaderV1BE<R> as RasterPageReader<R>>::NextPageFuture),
+    V1BigEndian(
+        #[pin] <CupsRasterUnifiedPageReaderV1BE<R, S> as RasterPageReader<R>>::NextPageFuture,
+    ),
     V1LittleEndian(
-        #[pin] <CupsRasterUnifiedPageReaderV1LE<R> as RasterPageReader<R>>::NextPageFuture,
+        #[pin] <CupsRasterUnifiedPageReaderV1LE<R, S> as RasterPageReader<R>>::NextPageFuture,
+    ),
+    V2BigEndian(
+        #[pin] <CupsRasterUnifiedPageReaderV2BE<R, S> as RasterPageReader<R>>::NextPageFuture,
     ),
-    V2BigEndian(#[pin] <CupsRasterUnifiedPageReaderV2BE<R> as RasterPageReader<R>>::NextPageFuture),
     V2LittleEndian(
-        #[pin] <CupsRasterUnifiedPageReaderV2LE<R> as RasterPageReader<R>>::NextPageFuture,
+        #[pin] <CupsRasterUnifiedPageReaderV2LE<R, S> as RasterPageReader<R>>::NextPageFuture,
+    ),
+    V3BigEndian(
+        #[pin] <CupsRasterUnifiedPageReaderV3BE<R, S> as RasterPageReader<R>>::NextPageFuture,
     ),
-    V3BigEndian(#[pin] <CupsRasterUnifiedPageReaderV3BE<R> as RasterPageReader<R>>::NextPageFuture),
     V3LittleEndian(
-        #[pin] <CupsRasterUnifiedPageReaderV3LE<R> as RasterPageReader<R>>::NextPageFuture,
+        #[pin] <CupsRasterUnifiedPageReaderV3LE<R, S> as RasterPageReader<R>>::NextPageFuture,
     ),
 }
 
-impl<R> Future for CupsRasterUnifiedNextPage<R>
+impl<R, S> Future for CupsRasterUnifiedNextPage<R, S>
 where
     R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
 {
-    type Output = Result<Option<CupsRasterUnifiedPageReader<R>>, CupsRasterError>;
+    type Output = Result<Option<CupsRasterUnifiedPageReader<R, S>>, CupsRasterError>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
         let this = self.project();
@@ -113,10 +127,13 @@ fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
     }
 }
 
-impl<R> CupsRasterUnifiedPageReader<R>
+impl<R, S> CupsRasterUnifiedPageReader<R, S>
 where
     R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
 {
+    /// Returns the byte order of the stream, which applies to both the page header
+    /// and the colors of 16 bits in the page content.
     pub fn byte_order(&self) -> RasterByteOrder {
         match self {
             CupsRasterUnifiedPageReader::V1BigEndian(_) => RasterByteOrder::BigEndian,
@@ -127,16 +144,73 @@ pub fn byte_order(&self) -> RasterByteOrder {
             CupsRasterUnifiedPageReader::V3LittleEndian(_) => RasterByteOrder::LittleEndian,
         }
     }
+
+    pub fn sync_word(&self) -> CupsSyncWord {
+        match self {
+            CupsRasterUnifiedPageReader::V1BigEndian(_) => CupsSyncWord::V1BigEndian,
+            CupsRasterUnifiedPageReader::V1LittleEndian(_) => CupsSyncWord::V1LittleEndian,
+            CupsRasterUnifiedPageReader::V2BigEndian(_) => CupsSyncWord::V2BigEndian,
+            CupsRasterUnifiedPageReader::V2LittleEndian(_) => CupsSyncWord::V2LittleEndian,
+            CupsRasterUnifiedPageReader::V3BigEndian(_) => CupsSyncWord::V3BigEndian,
+            CupsRasterUnifiedPageReader::V3LittleEndian(_) => CupsSyncWord::V3LittleEndian,
+        }
+    }
+
+    pub(crate) fn from_raw_parts(
+        sync_word: CupsSyncWord,
+        header: CupsPageHeaderV2,
+        content: CupsRasterUnifiedDecoder<R, S>,
+        state: CommonRasterReaderState,
+    ) -> Self {
+        match sync_word {
+            CupsSyncWord::V1BigEndian => {
+                CupsRasterUnifiedPageReaderV1BE::from_raw_parts(header, content, state).into()
+            }
+            CupsSyncWord::V1LittleEndian => {
+                CupsRasterUnifiedPageReaderV1LE::from_raw_parts(header, content, state).into()
+            }
+            CupsSyncWord::V2BigEndian => {
+                CupsRasterUnifiedPageReaderV2BE::from_raw_parts(header, content, state).into()
+            }
+            CupsSyncWord::V2LittleEndian => {
+                CupsRasterUnifiedPageReaderV2LE::from_raw_parts(header, content, state).into()
+            }
+            CupsSyncWord::V3BigEndian => {
+                CupsRasterUnifiedPageReaderV3BE::from_raw_parts(header, content, state).into()
+            }
+            CupsSyncWord::V3LittleEndian => {
+                CupsRasterUnifiedPageReaderV3LE::from_raw_parts(header, content, state).into()
+            }
+        }
+    }
+
+    pub(crate) fn into_raw_parts(
+        self,
+    ) -> (
+        CupsPageHeaderV2,
+        CupsRasterUnifiedDecoder<R, S>,
+        CommonRasterReaderState,
+    ) {
+        match self {
+            CupsRasterUnifiedPageReader::V1BigEndian(reader) => reader.into_raw_parts(),
+            CupsRasterUnifiedPageReader::V1LittleEndian(reader) => reader.into_raw_parts(),
+            CupsRasterUnifiedPageReader::V2BigEndian(reader) => reader.into_raw_parts(),
+            CupsRasterUnifiedPageReader::V2LittleEndian(reader) => reader.into_raw_parts(),
+            CupsRasterUnifiedPageReader::V3BigEndian(reader) => reader.into_raw_parts(),
+            CupsRasterUnifiedPageReader::V3LittleEndian(reader) => reader.into_raw_parts(),
+        }
+    }
 }
 
-impl<R> RasterPageReader<R> for CupsRasterUnifiedPageReader<R>
+impl<R, S> RasterPageReader<R> for CupsRasterUnifiedPageReader<R, S>
 where
     R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
 {
     type Header = CupsPageHeaderV2;
-    type Decoder = CupsRasterUnifiedDecoder<R>;
+    type Decoder = CupsRasterUnifiedDecoder<R, S>;
     type Error = CupsRasterError;
-    type NextPageFuture = CupsRasterUnifiedNextPage<R>;
+    type NextPageFuture = CupsRasterUnifiedNextPage<R, S>;
 
     fn next_page(self) -> Self::NextPageFuture {
         match self {
@@ -161,6 +235,29 @@ fn next_page(self) -> Self::NextPageFuture {
         }
     }
 
+    fn skip_page(self) -> Self::NextPageFuture {
+        match self {
+            CupsRasterUnifiedPageReader::V1BigEndian(reader) => {
+                CupsRasterUnifiedNextPage::V1BigEndian(reader.skip_page())
+            }
+            CupsRasterUnifiedPageReader::V1LittleEndian(reader) => {
+                CupsRasterUnifiedNextPage::V1LittleEndian(reader.skip_page())
+            }
+            CupsRasterUnifiedPageReader::V2BigEndian(reader) => {
+                CupsRasterUnifiedNextPage::V2BigEndian(reader.skip_page())
+            }
+            CupsRasterUnifiedPageReader::V2LittleEndian(reader) => {
+                CupsRasterUnifiedNextPage::V2LittleEndian(reader.skip_page())
+            }
+            CupsRasterUnifiedPageReader::V3BigEndian(reader) => {
+                CupsRasterUnifiedNextPage::V3BigEndian(reader.skip_page())
+            }
+            CupsRasterUnifiedPageReader::V3LittleEndian(reader) => {
+                CupsRasterUnifiedNextPage::V3LittleEndian(reader.skip_page())
+            }
+        }
+    }
+
     fn header(&self) -> &Self::Header {
         match self {
             CupsRasterUnifiedPageReader::V1BigEndian(reader) => reader.header(),
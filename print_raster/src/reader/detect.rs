@@ -0,0 +1,118 @@
+use crate::model::cups::CupsSyncWord;
+use crate::model::urf::URF_MAGIC;
+use futures::{AsyncRead, AsyncReadExt};
+use std::io;
+use std::ops::DerefMut;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// The format of a raster stream, as identified by its first bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RasterFormat {
+    Cups(CupsSyncWord),
+    Urf,
+}
+
+impl RasterFormat {
+    /// Identifies the format from the first bytes of a stream, of which up to 8 are used.
+    pub fn from_prefix(prefix: &[u8]) -> Option<Self> {
+        if prefix.starts_with(URF_MAGIC) {
+            return Some(RasterFormat::Urf);
+        }
+        let sync_word = prefix.get(..4)?.try_into().ok()?;
+        CupsSyncWord::from_bytes(sync_word).map(RasterFormat::Cups)
+    }
+}
+
+/// A reader that returns bytes read ahead from the start of a stream before the rest of it.
+pub struct PrefixedReader<R> {
+    prefix: [u8; 8],
+    start: usize,
+    end: usize,
+    reader: Pin<R>,
+}
+
+impl<R> PrefixedReader<R> {
+    /// Returns the bytes read ahead and not read from this reader yet.
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix[self.start..self.end]
+    }
+}
+
+impl<R> AsyncRead for PrefixedReader<R>
+where
+    R: DerefMut<Target: AsyncRead> + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        if this.start < this.end {
+            let n = buf.len().min(this.end - this.start);
+            buf[..n].copy_from_slice(&this.prefix[this.start..this.start + n]);
+            this.start += n;
+            return Poll::Ready(Ok(n));
+        }
+        this.reader.as_mut().poll_read(cx, buf)
+    }
+}
+
+/// Identifies the format of a stream by reading its first bytes.
+///
+/// The reader is handed back wrapped in a [`PrefixedReader`], which returns the bytes
+/// read here first, so the stream can be read by the reader of the detected format
+/// from the start. Fails with [`io::ErrorKind::InvalidData`] if the format is unknown.
+pub async fn detect_format<R>(mut reader: Pin<R>) -> io::Result<(RasterFormat, PrefixedReader<R>)>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    let mut prefix = [0u8; 8];
+    let mut end = 0;
+    while end < prefix.len() {
+        let n = reader.as_mut().read(&mut prefix[end..]).await?;
+        if n == 0 {
+            break;
+        }
+        end += n;
+    }
+    let format = RasterFormat::from_prefix(&prefix[..end])
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unknown raster format"))?;
+    let reader = PrefixedReader {
+        prefix,
+        start: 0,
+        end,
+        reader,
+    };
+    Ok((format, reader))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect_format, RasterFormat};
+    use crate::model::cups::CupsSyncWord;
+    use futures::AsyncReadExt;
+    use std::pin::Pin;
+
+    #[tokio::test]
+    async fn test_detect_format() {
+        let data = b"UNIRAST\0\0\0\0\x01rest";
+        let mut input = &data[..];
+        let (format, mut reader) = detect_format(Pin::new(&mut input)).await.unwrap();
+        assert_eq!(format, RasterFormat::Urf);
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).await.unwrap();
+        assert_eq!(read, data);
+
+        // a stream of the sync word only
+        let mut input = &b"3SaR"[..];
+        let (format, reader) = detect_format(Pin::new(&mut input)).await.unwrap();
+        assert_eq!(format, RasterFormat::Cups(CupsSyncWord::V3LittleEndian));
+        assert_eq!(reader.prefix(), b"3SaR");
+
+        let mut input = &b"GIF89a"[..];
+        let error = detect_format(Pin::new(&mut input)).await.err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,234 @@
+use super::{AnyPageHeader, RasterImage};
+use crate::error::ImageConversionError;
+use crate::model::cups::{CupsColorOrder, CupsColorSpace, CupsPageHeaderV2};
+use crate::model::urf::{UrfColorSpace, UrfPageHeader};
+use crate::model::RasterByteOrder;
+use ::image::{DynamicImage, ImageBuffer, Luma, Pixel, Rgb, Rgba};
+
+/// The pixel types of the `image` crate a page maps to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImageColorType {
+    Luma,
+    Rgb,
+    Rgba,
+}
+
+impl ImageColorType {
+    fn channels(self) -> u32 {
+        match self {
+            ImageColorType::Luma => 1,
+            ImageColorType::Rgb => 3,
+            ImageColorType::Rgba => 4,
+        }
+    }
+}
+
+impl RasterImage<CupsPageHeaderV2> {
+    /// Converts the page to a [`DynamicImage`].
+    ///
+    /// Only gray, RGB and RGBA pages of 8 or 16 bits per color are supported.
+    /// The padding at the end of each line is dropped. The header doesn't tell the byte
+    /// order of the stream, so colors of 16 bits fail with
+    /// [`ImageConversionError::UnknownByteOrder`], see
+    /// [`to_dynamic_image_with_byte_order`](Self::to_dynamic_image_with_byte_order).
+    pub fn to_dynamic_image(&self) -> Result<DynamicImage, ImageConversionError> {
+        cups_to_dynamic_image(&self.header, &self.data, None)
+    }
+
+    /// Converts the page to a [`DynamicImage`], reading colors of 16 bits in `byte_order`,
+    /// i.e. the byte order of the stream the page is read from.
+    pub fn to_dynamic_image_with_byte_order(
+        &self,
+        byte_order: RasterByteOrder,
+    ) -> Result<DynamicImage, ImageConversionError> {
+        cups_to_dynamic_image(&self.header, &self.data, Some(byte_order))
+    }
+}
+
+impl RasterImage<UrfPageHeader> {
+    /// Converts the page to a [`DynamicImage`].
+    ///
+    /// Only gray and RGB pages of 8 or 16 bits per color are supported.
+    pub fn to_dynamic_image(&self) -> Result<DynamicImage, ImageConversionError> {
+        urf_to_dynamic_image(&self.header, &self.data)
+    }
+}
+
+impl RasterImage<AnyPageHeader> {
+    /// Converts the page to a [`DynamicImage`].
+    ///
+    /// Like [`RasterImage::<CupsPageHeaderV2>::to_dynamic_image`], CUPS raster pages
+    /// with colors of 16 bits fail with [`ImageConversionError::UnknownByteOrder`].
+    pub fn to_dynamic_image(&self) -> Result<DynamicImage, ImageConversionError> {
+        match &self.header {
+            AnyPageHeader::Cups(header) => cups_to_dynamic_image(header, &self.data, None),
+            AnyPageHeader::Urf(header) => urf_to_dynamic_image(header, &self.data),
+        }
+    }
+
+    /// Converts the page to a [`DynamicImage`], reading colors of 16 bits of CUPS raster
+    /// in `byte_order`, i.e. the byte order of the stream the page is read from.
+    ///
+    /// URF is always big-endian, so `byte_order` is ignored for URF pages.
+    pub fn to_dynamic_image_with_byte_order(
+        &self,
+        byte_order: RasterByteOrder,
+    ) -> Result<DynamicImage, ImageConversionError> {
+        match &self.header {
+            AnyPageHeader::Cups(header) => {
+                cups_to_dynamic_image(header, &self.data, Some(byte_order))
+            }
+            AnyPageHeader::Urf(header) => urf_to_dynamic_image(header, &self.data),
+        }
+    }
+}
+
+fn cups_to_dynamic_image(
+    header: &CupsPageHeaderV2,
+    data: &[u8],
+    byte_order: Option<RasterByteOrder>,
+) -> Result<DynamicImage, ImageConversionError> {
+    let header = &header.v1;
+    // a single byte per color reads the same in either order
+    let byte_order = match byte_order {
+        Some(byte_order) => byte_order,
+        None if header.bits_per_color > 8 => {
+            return Err(ImageConversionError::UnknownByteOrder(
+                header.bits_per_color,
+            ))
+        }
+        None => RasterByteOrder::BigEndian,
+    };
+    let color_type = match header.color_space {
+        CupsColorSpace::Gray | CupsColorSpace::sGray => ImageColorType::Luma,
+        CupsColorSpace::RGB | CupsColorSpace::sRGB | CupsColorSpace::AdobeRGB => {
+            ImageColorType::Rgb
+        }
+        CupsColorSpace::RGBA => ImageColorType::Rgba,
+        color_space => return Err(ImageConversionError::UnsupportedCupsColorSpace(color_space)),
+    };
+    // the order of a single color doesn't change the layout
+    if color_type != ImageColorType::Luma && header.color_order != CupsColorOrder::Chunky {
+        return Err(ImageConversionError::UnsupportedColorOrder(
+            header.color_order,
+        ));
+    }
+    to_dynamic_image(
+        color_type,
+        header.width,
+        header.height,
+        header.bits_per_color,
+        header.bytes_per_line as u64,
+        byte_order,
+        data,
+    )
+}
+
+fn urf_to_dynamic_image(
+    header: &UrfPageHeader,
+    data: &[u8],
+) -> Result<DynamicImage, ImageConversionError> {
+    let color_type = match header.color_space {
+        UrfColorSpace::sGray | UrfColorSpace::Gray => ImageColorType::Luma,
+        UrfColorSpace::sRGB | UrfColorSpace::RGB | UrfColorSpace::AdobeRGB => ImageColorType::Rgb,
+        color_space => return Err(ImageConversionError::UnsupportedUrfColorSpace(color_space)),
+    };
+    let bits_per_color = header.bits_per_pixel as u32 / color_type.channels();
+    // lines of URF pages are not padded
+    let bytes_per_line = header.width as u64 * header.bits_per_pixel as u64 / 8;
+    to_dynamic_image(
+        color_type,
+        header.width,
+        header.height,
+        bits_per_color,
+        bytes_per_line,
+        RasterByteOrder::BigEndian,
+        data,
+    )
+}
+
+fn to_dynamic_image(
+    color_type: ImageColorType,
+    width: u32,
+    height: u32,
+    bits_per_color: u32,
+    bytes_per_line: u64,
+    byte_order: RasterByteOrder,
+    data: &[u8],
+) -> Result<DynamicImage, ImageConversionError> {
+    let bytes_per_color = match bits_per_color {
+        8 => 1,
+        16 => 2,
+        _ => {
+            return Err(ImageConversionError::UnsupportedBitsPerColor(
+                bits_per_color,
+            ))
+        }
+    };
+    let min_bytes_per_line = width as u64 * color_type.channels() as u64 * bytes_per_color;
+    if bytes_per_line < min_bytes_per_line {
+        return Err(ImageConversionError::InvalidBytesPerLine {
+            bytes_per_line,
+            min_bytes_per_line,
+        });
+    }
+    let expected = bytes_per_line * height as u64;
+    if (data.len() as u64) < expected {
+        return Err(ImageConversionError::DataTooShort {
+            expected,
+            actual: data.len() as u64,
+        });
+    }
+    // both are bounded by the length of the data now
+    let (bytes_per_line, min_bytes_per_line) =
+        (bytes_per_line as usize, min_bytes_per_line as usize);
+    let lines = (0..height as usize).map(|y| {
+        let start = y * bytes_per_line;
+        &data[start..start + min_bytes_per_line]
+    });
+    let image = if bytes_per_color == 1 {
+        let samples = lines.flatten().copied().collect();
+        match color_type {
+            ImageColorType::Luma => {
+                DynamicImage::ImageLuma8(buffer::<Luma<u8>>(width, height, samples))
+            }
+            ImageColorType::Rgb => {
+                DynamicImage::ImageRgb8(buffer::<Rgb<u8>>(width, height, samples))
+            }
+            ImageColorType::Rgba => {
+                DynamicImage::ImageRgba8(buffer::<Rgba<u8>>(width, height, samples))
+            }
+        }
+    } else {
+        let samples = lines
+            .flat_map(|line| line.chunks_exact(2))
+            .map(|sample| {
+                let sample = [sample[0], sample[1]];
+                match byte_order {
+                    RasterByteOrder::BigEndian => u16::from_be_bytes(sample),
+                    RasterByteOrder::LittleEndian => u16::from_le_bytes(sample),
+                }
+            })
+            .collect();
+        match color_type {
+            ImageColorType::Luma => {
+                DynamicImage::ImageLuma16(buffer::<Luma<u16>>(width, height, samples))
+            }
+            ImageColorType::Rgb => {
+                DynamicImage::ImageRgb16(buffer::<Rgb<u16>>(width, height, samples))
+            }
+            ImageColorType::Rgba => {
+                DynamicImage::ImageRgba16(buffer::<Rgba<u16>>(width, height, samples))
+            }
+        }
+    };
+    Ok(image)
+}
+
+fn buffer<P: Pixel>(
+    width: u32,
+    height: u32,
+    samples: Vec<P::Subpixel>,
+) -> ImageBuffer<P, Vec<P::Subpixel>> {
+    ImageBuffer::from_raw(width, height, samples).expect("samples of every pixel are copied")
+}
@@ -1,7 +1,12 @@
-use crate::decode::RasterDecoder;
-use futures::AsyncRead;
-use std::{future::Future, ops::DerefMut};
+use crate::decode::{RasterDecoder, RasterDecoderExt, RasterDecoderPeek};
+use futures::{AsyncRead, AsyncReadExt};
+use std::{future::Future, io, ops::DerefMut};
 
+/// A page of a raster stream, whose header is already read.
+///
+/// The content is decoded from the underlying stream only as it is read through
+/// [`content_mut`](Self::content_mut). Advancing to the next page consumes whatever
+/// content is left, so the content of a page can't be read after moving on.
 pub trait RasterPageReader<R>: Sized
 where
     R: DerefMut<Target: AsyncRead>,
@@ -11,11 +16,20 @@ pub trait RasterPageReader<R>: Sized
     type Error;
     type NextPageFuture: Future<Output = Result<Option<Self>, Self::Error>>;
     fn next_page(self) -> Self::NextPageFuture;
+    /// Like [`next_page`](Self::next_page), but skips the remaining content
+    /// of the current page without decoding it.
+    fn skip_page(self) -> Self::NextPageFuture;
     fn header(&self) -> &Self::Header;
     fn content_mut(&mut self) -> &mut Self::Decoder;
     fn into_content(self) -> Self::Decoder;
 }
 
+/// A reader of the pages of a raster stream.
+///
+/// Pages are read strictly in order, from a stream that may not be seekable.
+/// [`next_page`](Self::next_page) consumes the reader and reads the header of the next
+/// page, but none of its content, so the header can be inspected before deciding
+/// whether to process the page or to skip it with [`RasterPageReader::skip_page`].
 pub trait RasterReader<R>: Sized
 where
     R: DerefMut<Target: AsyncRead>,
@@ -26,3 +40,150 @@ pub trait RasterReader<R>: Sized
     type NextPageFuture: Future<Output = Result<Option<Self::PageReader>, Self::Error>>;
     fn next_page(self) -> Self::NextPageFuture;
 }
+
+/// The header and the reader of the page read by [`RasterReaderExt::next_page_header_only`].
+pub type NextPageHeaderResult<T, R> = Result<
+    Option<(
+        <T as RasterReader<R>>::PageHeader,
+        <T as RasterReader<R>>::PageReader,
+    )>,
+    <T as RasterReader<R>>::Error,
+>;
+
+pub trait RasterReaderExt<R>: RasterReader<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    /// Reads the header of the next page, without reading any of its content.
+    ///
+    /// Returns a copy of the header together with the page reader, so the decision to
+    /// process the page can be made on its header alone. Returns `None` at the end of
+    /// the stream.
+    fn next_page_header_only(self) -> impl Future<Output = NextPageHeaderResult<Self, R>>
+    where
+        Self::PageHeader: Clone,
+    {
+        async move {
+            let page = self.next_page().await?;
+            Ok(page.map(|page| (page.header().clone(), page)))
+        }
+    }
+}
+
+impl<T, R> RasterReaderExt<R> for T
+where
+    T: RasterReader<R>,
+    R: DerefMut<Target: AsyncRead>,
+{
+}
+
+/// A page with its whole content in memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RasterImage<H> {
+    pub header: H,
+    pub data: Vec<u8>,
+}
+
+/// The page read by [`RasterPageReaderExt::read_image`] and the future advancing to the next page.
+pub type ReadImageResult<P, R> = Result<
+    (
+        RasterImage<<P as RasterPageReader<R>>::Header>,
+        <P as RasterPageReader<R>>::NextPageFuture,
+    ),
+    <P as RasterPageReader<R>>::Error,
+>;
+
+pub trait RasterPageReaderExt<R>: RasterPageReader<R>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    /// Returns the next `n` bytes of the page content without consuming them,
+    /// or fewer if the content ends, e.g. to detect a payload embedded in the pixel data.
+    ///
+    /// The bytes are buffered by the decoder, so reading the content still returns them.
+    fn peek_content<'a>(&'a mut self, n: usize) -> RasterDecoderPeek<'a, Self::Decoder, R>
+    where
+        Self::Decoder: Unpin + 'a,
+        R: 'a,
+    {
+        self.content_mut().peek(n)
+    }
+
+    /// Reads the remaining content of the page into memory, failing with
+    /// [`io::ErrorKind::InvalidData`] without reading anything if it is longer than `cap` bytes.
+    ///
+    /// Unlike the [`Limits`](crate::decode::Limits) of the reader, which may be
+    /// [`NO_LIMITS`](crate::decode::Limits::NO_LIMITS), this bounds the memory used
+    /// by the caller itself.
+    fn read_content_limited(&mut self, cap: usize) -> impl Future<Output = io::Result<Vec<u8>>> + '_
+    where
+        Self::Decoder: Unpin,
+    {
+        async move {
+            let content = self.content_mut();
+            let remaining = content.bytes_remaining();
+            if remaining > cap as u64 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "page content is {} bytes, exceeding the cap of {} bytes",
+                        remaining, cap
+                    ),
+                ));
+            }
+            let mut data = vec![0; remaining as usize];
+            content.read_exact(&mut data).await?;
+            Ok(data)
+        }
+    }
+
+    /// Reads the remaining content of the page into memory.
+    ///
+    /// Returns the page as a [`RasterImage`] together with the future advancing to the
+    /// next page, so pages can still be processed one at a time. The buffer never exceeds
+    /// [`Limits::bytes_per_page`](crate::decode::Limits::bytes_per_page), which is checked
+    /// when the page is opened.
+    fn read_image(mut self) -> impl Future<Output = ReadImageResult<Self, R>>
+    where
+        Self::Header: Clone,
+        Self::Decoder: Unpin,
+        Self::Error: From<io::Error>,
+    {
+        async move {
+            let content = self.content_mut();
+            let len = usize::try_from(content.bytes_remaining())
+                .map_err(|_| io::Error::new(io::ErrorKind::OutOfMemory, "page is too large"))?;
+            let mut data = vec![0; len];
+            content.read_exact(&mut data).await?;
+            let image = RasterImage {
+                header: self.header().clone(),
+                data,
+            };
+            Ok((image, self.next_page()))
+        }
+    }
+}
+
+impl<T, R> RasterPageReaderExt<R> for T
+where
+    T: RasterPageReader<R>,
+    R: DerefMut<Target: AsyncRead>,
+{
+}
+
+/// Reads the headers of the remaining pages, skipping their content.
+pub(crate) async fn read_page_headers<T, R>(reader: T) -> Result<Vec<T::PageHeader>, T::Error>
+where
+    T: RasterReader<R>,
+    T::PageHeader: Clone,
+    T::PageReader: RasterPageReader<R, Error = T::Error>,
+    R: DerefMut<Target: AsyncRead>,
+{
+    let mut headers = Vec::new();
+    let mut page_next = reader.next_page().await?;
+    while let Some(page) = page_next {
+        headers.push(page.header().clone());
+        page_next = page.skip_page().await?;
+    }
+    Ok(headers)
+}
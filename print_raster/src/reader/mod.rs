@@ -1,5 +1,27 @@
+mod any;
+#[cfg(feature = "bytes")]
+mod bytes;
+#[cfg(feature = "bytes")]
+mod channel;
 pub mod common;
 pub mod cups;
+mod detect;
+#[cfg(feature = "image")]
+mod image;
 mod interface;
+mod options;
+mod stream;
+#[cfg(feature = "sync")]
+pub mod sync;
+#[cfg(feature = "tokio")]
+pub mod tokio;
 pub mod urf;
+#[cfg(feature = "bytes")]
+pub use self::bytes::*;
+pub use any::*;
+#[cfg(feature = "bytes")]
+pub use channel::*;
+pub use detect::*;
 pub use interface::*;
+pub use options::*;
+pub use stream::*;
@@ -0,0 +1,82 @@
+use crate::decode::Limits;
+use crate::metrics::MetricsHandle;
+#[cfg(feature = "metrics")]
+use crate::metrics::RasterMetrics;
+use std::io;
+#[cfg(feature = "metrics")]
+use std::sync::Arc;
+
+/// Options for reading raster streams.
+#[derive(Clone, Debug, Default)]
+pub struct ReaderOptions {
+    pub limits: Limits,
+    /// Reads page headers of this size instead of the size defined by the format,
+    /// for every format and version.
+    ///
+    /// This is an escape hatch for legacy files (e.g. some early CUPS Raster V1 builds)
+    /// whose headers are shorter or longer than the spec. A shorter header is padded
+    /// with zeros, the trailer of a longer header is skipped.
+    ///
+    /// The header must still hold the fields describing the page, i.e. be at least as
+    /// long as a CUPS Raster V1 page header (420 bytes) for CUPS, or 24 bytes for URF.
+    /// Creating a reader with a shorter override fails with [`io::ErrorKind::InvalidInput`].
+    pub header_size_override: Option<usize>,
+    /// The total length of the stream in bytes, including the file header, if known.
+    ///
+    /// When set, each page is checked against the remaining bytes before decoding,
+    /// so a page declaring more content than physically available fails early.
+    /// For URF, the page count of the file header is checked as well.
+    pub total_stream_len: Option<u64>,
+    /// Requires every page to have the same width, height and color space as the first page.
+    ///
+    /// Most jobs use a single geometry throughout, so a change usually means a page header
+    /// was read from a misaligned position of a corrupted stream.
+    pub expect_uniform_geometry: bool,
+    /// Fails when a page's encoded data doesn't end exactly at the end of its content,
+    /// see [`RasterDecoder::clean_page_boundary`](crate::decode::RasterDecoder::clean_page_boundary).
+    ///
+    /// By default, the next page header is read right after the last consumed byte.
+    pub require_clean_page_boundary: bool,
+    /// Fills the rest of a line on a fill-to-end block (code `0x80`) of URF content
+    /// with this byte, instead of the white of the page's color space.
+    ///
+    /// Ignored for CUPS Raster, whose compressed (V2) content has no fill-to-end block:
+    /// the code is rejected as invalid data there.
+    pub fill_byte_override: Option<u8>,
+    /// Receives the pages read and the errors raised while reading.
+    #[cfg(feature = "metrics")]
+    pub metrics: Option<Arc<dyn RasterMetrics>>,
+}
+
+impl ReaderOptions {
+    /// Rejects a [`header_size_override`](Self::header_size_override) shorter than
+    /// `min_header_size`, with which every header would be read as zeros past its end.
+    pub(crate) fn check_header_size_override(&self, min_header_size: usize) -> io::Result<()> {
+        match self.header_size_override {
+            Some(header_size) if header_size < min_header_size => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "header_size_override of {header_size} bytes is shorter than \
+                     the {min_header_size} bytes of the fields of a page header"
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn metrics(&self) -> MetricsHandle {
+        #[cfg(feature = "metrics")]
+        return MetricsHandle::new(self.metrics.clone());
+        #[cfg(not(feature = "metrics"))]
+        MetricsHandle::default()
+    }
+}
+
+impl From<Limits> for ReaderOptions {
+    fn from(limits: Limits) -> Self {
+        Self {
+            limits,
+            ..Default::default()
+        }
+    }
+}
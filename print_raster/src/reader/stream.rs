@@ -0,0 +1,242 @@
+use super::{RasterImage, RasterPageReader, RasterReader};
+use crate::decode::RasterDecoder;
+use futures::{ready, AsyncRead, AsyncReadExt, Stream, TryStreamExt};
+use pin_project::pin_project;
+use std::future::Future;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll};
+
+/// Where the page last yielded by a [`RasterPageStream`] is.
+enum PageSlot<P> {
+    /// The page is held by a [`StreamedPage`], or no page was yielded yet.
+    InUse,
+    /// The page was dropped and handed back to the stream.
+    Returned(P),
+    /// The page was taken out by [`StreamedPage::into_inner`], which ends the stream.
+    Taken,
+}
+
+type SharedPageSlot<P> = Arc<Mutex<PageSlot<P>>>;
+
+fn lock_slot<P>(slot: &SharedPageSlot<P>) -> std::sync::MutexGuard<'_, PageSlot<P>> {
+    slot.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A page yielded by [`RasterPageStream`].
+///
+/// It dereferences to the page reader, and hands the page back to the stream when dropped,
+/// so the stream can skip the rest of its content and read the next page.
+pub struct StreamedPage<P> {
+    page: Option<P>,
+    slot: SharedPageSlot<P>,
+}
+
+impl<P> StreamedPage<P> {
+    /// Takes the page reader out, which ends the stream after this page.
+    pub fn into_inner(mut self) -> P {
+        *lock_slot(&self.slot) = PageSlot::Taken;
+        self.page.take().unwrap()
+    }
+}
+
+impl<P> Deref for StreamedPage<P> {
+    type Target = P;
+
+    fn deref(&self) -> &P {
+        self.page.as_ref().unwrap()
+    }
+}
+
+impl<P> DerefMut for StreamedPage<P> {
+    fn deref_mut(&mut self) -> &mut P {
+        self.page.as_mut().unwrap()
+    }
+}
+
+impl<P> Drop for StreamedPage<P> {
+    fn drop(&mut self) {
+        if let Some(page) = self.page.take() {
+            *lock_slot(&self.slot) = PageSlot::Returned(page);
+        }
+    }
+}
+
+#[pin_project(project = RasterPageStreamStateProj)]
+enum RasterPageStreamState<RD, R>
+where
+    RD: RasterReader<R>,
+    R: DerefMut<Target: AsyncRead>,
+{
+    First(#[pin] RD::NextPageFuture),
+    Yielded,
+    Next(#[pin] <RD::PageReader as RasterPageReader<R>>::NextPageFuture),
+    Done,
+}
+
+/// A [`Stream`] of the pages of a raster reader.
+///
+/// Each page must be dropped before polling for the next one, which skips any content
+/// left unread. Pages are read one at a time, nothing is buffered.
+#[pin_project]
+pub struct RasterPageStream<RD, R>
+where
+    RD: RasterReader<R>,
+    R: DerefMut<Target: AsyncRead>,
+{
+    #[pin]
+    state: RasterPageStreamState<RD, R>,
+    slot: SharedPageSlot<RD::PageReader>,
+}
+
+impl<RD, R> RasterPageStream<RD, R>
+where
+    RD: RasterReader<R>,
+    R: DerefMut<Target: AsyncRead>,
+{
+    pub fn new(reader: RD) -> Self {
+        Self {
+            state: RasterPageStreamState::First(reader.next_page()),
+            slot: Arc::new(Mutex::new(PageSlot::InUse)),
+        }
+    }
+}
+
+impl<RD, R> Stream for RasterPageStream<RD, R>
+where
+    RD: RasterReader<R>,
+    RD::PageReader: RasterPageReader<R, Error = RD::Error>,
+    RD::Error: From<io::Error>,
+    R: DerefMut<Target: AsyncRead>,
+{
+    type Item = Result<StreamedPage<RD::PageReader>, RD::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        let result = loop {
+            match this.state.as_mut().project() {
+                RasterPageStreamStateProj::First(future) => break ready!(future.poll(cx)),
+                RasterPageStreamStateProj::Next(future) => break ready!(future.poll(cx)),
+                RasterPageStreamStateProj::Yielded => {
+                    let slot = std::mem::replace(&mut *lock_slot(this.slot), PageSlot::InUse);
+                    match slot {
+                        PageSlot::Returned(page) => this
+                            .state
+                            .set(RasterPageStreamState::Next(page.next_page())),
+                        PageSlot::Taken => {
+                            this.state.set(RasterPageStreamState::Done);
+                            return Poll::Ready(None);
+                        }
+                        PageSlot::InUse => {
+                            return Poll::Ready(Some(Err(io::Error::other(
+                                "the previous page is still in use",
+                            )
+                            .into())));
+                        }
+                    }
+                }
+                RasterPageStreamStateProj::Done => return Poll::Ready(None),
+            }
+        };
+        match result {
+            Ok(Some(page)) => {
+                this.state.set(RasterPageStreamState::Yielded);
+                Poll::Ready(Some(Ok(StreamedPage {
+                    page: Some(page),
+                    slot: this.slot.clone(),
+                })))
+            }
+            Ok(None) => {
+                this.state.set(RasterPageStreamState::Done);
+                Poll::Ready(None)
+            }
+            Err(e) => {
+                this.state.set(RasterPageStreamState::Done);
+                Poll::Ready(Some(Err(e)))
+            }
+        }
+    }
+}
+
+struct ExpandCopiesState<S, H> {
+    pages: Option<Pin<Box<S>>>,
+    copies: u32,
+    collate: bool,
+    /// An uncollated page and how many more copies of it to yield.
+    pending: Option<(RasterImage<H>, u32)>,
+    /// Every page read so far, kept for the later collated copies.
+    kept: Vec<RasterImage<H>>,
+    replayed: usize,
+}
+
+/// Reads the pages of `reader` and yields them in the physical output order of
+/// `num_copies` copies, as [`expand_copies`](crate::model::cups::expand_copies) does
+/// for pages already in memory.
+///
+/// Uncollated copies are yielded as each page is read, keeping one page in memory.
+/// Collated copies yield the first copy as the pages are read, but then have to keep
+/// every page of the stream in memory to repeat the sequence.
+pub fn read_expanded_copies<RD, R>(
+    reader: RD,
+    num_copies: u32,
+    collate: bool,
+) -> impl Stream<Item = Result<RasterImage<RD::PageHeader>, RD::Error>>
+where
+    RD: RasterReader<R>,
+    RD::PageReader: RasterPageReader<R, Error = RD::Error>,
+    <RD::PageReader as RasterPageReader<R>>::Decoder: Unpin,
+    RD::PageHeader: Clone,
+    RD::Error: From<io::Error>,
+    R: DerefMut<Target: AsyncRead>,
+{
+    let state = ExpandCopiesState {
+        pages: Some(Box::pin(RasterPageStream::new(reader))),
+        copies: num_copies.max(1),
+        collate,
+        pending: None,
+        kept: Vec::new(),
+        replayed: 0,
+    };
+    futures::stream::try_unfold(state, |mut state| async move {
+        if let Some((image, left)) = state.pending.take() {
+            if left > 1 {
+                state.pending = Some((image.clone(), left - 1));
+            }
+            return Ok(Some((image, state)));
+        }
+        if let Some(pages) = state.pages.as_mut() {
+            match pages.try_next().await? {
+                Some(mut page) => {
+                    let content = page.content_mut();
+                    let len = usize::try_from(content.bytes_remaining()).map_err(|_| {
+                        io::Error::new(io::ErrorKind::OutOfMemory, "page is too large")
+                    })?;
+                    let mut data = vec![0; len];
+                    content.read_exact(&mut data).await?;
+                    let image = RasterImage {
+                        header: page.header().clone(),
+                        data,
+                    };
+                    if state.copies > 1 {
+                        if state.collate {
+                            state.kept.push(image.clone());
+                        } else {
+                            state.pending = Some((image.clone(), state.copies - 1));
+                        }
+                    }
+                    return Ok(Some((image, state)));
+                }
+                None => state.pages = None,
+            }
+        }
+        let len = state.kept.len();
+        if state.replayed < len * (state.copies as usize - 1) {
+            let image = state.kept[state.replayed % len].clone();
+            state.replayed += 1;
+            return Ok(Some((image, state)));
+        }
+        Ok(None)
+    })
+}
@@ -0,0 +1,131 @@
+//! Blocking readers over [`std::io::Read`] sources.
+//!
+//! These wrap the async readers and drive them with [`block_on`], so they can be used
+//! on a worker thread without an async runtime.
+
+use super::cups::unified::CupsRasterUnifiedReader;
+use super::urf::UrfReader;
+use super::{RasterPageReader, RasterReader};
+use crate::error::{CupsRasterError, UrfError};
+use crate::model::cups::CupsSyncWord;
+use crate::model::urf::UrfHeader;
+use futures::executor::block_on;
+use futures::io::AllowStdIo;
+use futures::{AsyncRead, AsyncReadExt};
+use std::io::{self, Read};
+use std::marker::PhantomData;
+use std::ops::DerefMut;
+
+/// The async reader adapted from a blocking source.
+pub type StdReader<T> = Box<AllowStdIo<T>>;
+
+/// A blocking reader over any async raster reader.
+pub struct SyncRasterReader<RD, R> {
+    inner: RD,
+    _reader: PhantomData<R>,
+}
+
+pub type SyncCupsRasterReader<T> =
+    SyncRasterReader<CupsRasterUnifiedReader<StdReader<T>>, StdReader<T>>;
+pub type SyncUrfReader<T> = SyncRasterReader<UrfReader<StdReader<T>>, StdReader<T>>;
+
+impl<RD, R> SyncRasterReader<RD, R>
+where
+    RD: RasterReader<R>,
+    R: DerefMut<Target: AsyncRead>,
+{
+    pub fn from_async(inner: RD) -> Self {
+        Self {
+            inner,
+            _reader: PhantomData,
+        }
+    }
+
+    pub fn into_async(self) -> RD {
+        self.inner
+    }
+
+    /// Reads the header of the first page, blocking until it's available.
+    pub fn next_page(self) -> Result<Option<SyncRasterPage<RD::PageReader, R>>, RD::Error> {
+        Ok(block_on(self.inner.next_page())?.map(SyncRasterPage::from_async))
+    }
+}
+
+impl<T> SyncCupsRasterReader<T>
+where
+    T: Read,
+{
+    /// Creates a reader for CUPS Raster V1, V2 and V3 streams.
+    ///
+    /// As with the async reader, `source` should be buffered, e.g. by [`std::io::BufReader`].
+    pub fn new(source: T) -> Result<Self, CupsRasterError> {
+        let reader = Box::pin(AllowStdIo::new(source));
+        Ok(Self::from_async(block_on(CupsRasterUnifiedReader::new(
+            reader,
+        ))?))
+    }
+
+    pub fn sync_word(&self) -> CupsSyncWord {
+        self.inner.sync_word()
+    }
+}
+
+impl<T> SyncUrfReader<T>
+where
+    T: Read,
+{
+    /// Creates a reader for URF (Apple Raster) streams.
+    ///
+    /// As with the async reader, `source` should be buffered, e.g. by [`std::io::BufReader`].
+    pub fn new(source: T) -> Result<Self, UrfError> {
+        let reader = Box::pin(AllowStdIo::new(source));
+        Ok(Self::from_async(block_on(UrfReader::new(reader))?))
+    }
+
+    pub fn header(&self) -> &UrfHeader {
+        self.inner.header()
+    }
+}
+
+/// A blocking page reader, reading the content of the page through [`Read`].
+pub struct SyncRasterPage<P, R> {
+    inner: P,
+    _reader: PhantomData<R>,
+}
+
+impl<P, R> SyncRasterPage<P, R>
+where
+    P: RasterPageReader<R>,
+    R: DerefMut<Target: AsyncRead>,
+{
+    pub fn from_async(inner: P) -> Self {
+        Self {
+            inner,
+            _reader: PhantomData,
+        }
+    }
+
+    pub fn into_async(self) -> P {
+        self.inner
+    }
+
+    pub fn header(&self) -> &P::Header {
+        self.inner.header()
+    }
+
+    /// Skips the rest of the content and reads the header of the next page.
+    pub fn next_page(self) -> Result<Option<Self>, P::Error> {
+        Ok(block_on(self.inner.next_page())?.map(Self::from_async))
+    }
+}
+
+impl<P, R> Read for SyncRasterPage<P, R>
+where
+    P: RasterPageReader<R>,
+    P::Decoder: Unpin,
+    R: DerefMut<Target: AsyncRead>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        block_on(self.inner.content_mut().read(buf))
+    }
+}
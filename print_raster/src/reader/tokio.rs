@@ -0,0 +1,145 @@
+//! Readers over [`tokio::io::AsyncRead`] sources.
+//!
+//! These wrap the readers of this crate, adapting the source to the `futures` I/O traits,
+//! so `tokio` users don't need to go through `tokio_util::compat` themselves.
+
+use super::cups::unified::CupsRasterUnifiedReader;
+use super::urf::UrfReader as FuturesUrfReader;
+use super::{RasterPageStream, RasterReader, ReaderOptions};
+use crate::decode::Limits;
+use crate::error::{CupsRasterError, UrfError};
+use crate::model::cups::{CupsPageHeaderV2, CupsSyncWord};
+use crate::model::urf::{UrfHeader, UrfPageHeader};
+use crate::model::RasterByteOrder;
+use ::tokio::io::AsyncRead;
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
+
+/// The `futures` reader adapted from a `tokio` source.
+pub type TokioReader<T> = Box<Compat<T>>;
+
+/// A reader for CUPS Raster V1, V2 and V3 streams.
+pub struct CupsRasterReader<T> {
+    inner: CupsRasterUnifiedReader<TokioReader<T>>,
+}
+
+impl<T> CupsRasterReader<T>
+where
+    T: AsyncRead,
+{
+    /// As with the `futures` reader, `source` should be buffered,
+    /// e.g. by `tokio::io::BufReader`.
+    pub async fn new(source: T) -> Result<Self, CupsRasterError> {
+        Self::new_with_options(source, ReaderOptions::default()).await
+    }
+
+    pub async fn new_with_limits(source: T, limits: Limits) -> Result<Self, CupsRasterError> {
+        Self::new_with_options(source, limits.into()).await
+    }
+
+    pub async fn new_with_options(
+        source: T,
+        options: ReaderOptions,
+    ) -> Result<Self, CupsRasterError> {
+        let reader = Box::pin(source.compat());
+        let inner = CupsRasterUnifiedReader::new_with_options(reader, options).await?;
+        Ok(Self { inner })
+    }
+
+    pub fn sync_word(&self) -> CupsSyncWord {
+        self.inner.sync_word()
+    }
+
+    pub fn byte_order(&self) -> RasterByteOrder {
+        self.inner.byte_order()
+    }
+
+    /// Turns the reader into a [`Stream`](futures::Stream) of its pages.
+    pub fn into_page_stream(self) -> RasterPageStream<Self, TokioReader<T>> {
+        RasterPageStream::new(self)
+    }
+
+    /// Reads the header of every page, skipping the page content without decoding it.
+    pub async fn read_page_headers(self) -> Result<Vec<CupsPageHeaderV2>, CupsRasterError> {
+        self.inner.read_page_headers().await
+    }
+
+    pub fn into_inner(self) -> CupsRasterUnifiedReader<TokioReader<T>> {
+        self.inner
+    }
+}
+
+impl<T> RasterReader<TokioReader<T>> for CupsRasterReader<T>
+where
+    T: AsyncRead,
+{
+    type PageHeader = CupsPageHeaderV2;
+    type PageReader =
+        <CupsRasterUnifiedReader<TokioReader<T>> as RasterReader<TokioReader<T>>>::PageReader;
+    type Error = CupsRasterError;
+    type NextPageFuture =
+        <CupsRasterUnifiedReader<TokioReader<T>> as RasterReader<TokioReader<T>>>::NextPageFuture;
+
+    fn next_page(self) -> Self::NextPageFuture {
+        self.inner.next_page()
+    }
+}
+
+/// A reader for URF (Apple Raster) streams.
+pub struct UrfReader<T> {
+    inner: FuturesUrfReader<TokioReader<T>>,
+}
+
+impl<T> UrfReader<T>
+where
+    T: AsyncRead,
+{
+    /// As with the `futures` reader, `source` should be buffered,
+    /// e.g. by `tokio::io::BufReader`.
+    pub async fn new(source: T) -> Result<Self, UrfError> {
+        Self::new_with_options(source, ReaderOptions::default()).await
+    }
+
+    pub async fn new_with_limits(source: T, limits: Limits) -> Result<Self, UrfError> {
+        Self::new_with_options(source, limits.into()).await
+    }
+
+    pub async fn new_with_options(source: T, options: ReaderOptions) -> Result<Self, UrfError> {
+        let reader = Box::pin(source.compat());
+        let inner = FuturesUrfReader::new_with_options(reader, options).await?;
+        Ok(Self { inner })
+    }
+
+    pub fn header(&self) -> &UrfHeader {
+        self.inner.header()
+    }
+
+    /// Turns the reader into a [`Stream`](futures::Stream) of its pages.
+    pub fn into_page_stream(self) -> RasterPageStream<Self, TokioReader<T>> {
+        RasterPageStream::new(self)
+    }
+
+    /// Reads the header of every page, skipping the page content without decoding it.
+    pub async fn read_page_headers(self) -> Result<Vec<UrfPageHeader>, UrfError> {
+        self.inner.read_page_headers().await
+    }
+
+    pub fn into_inner(self) -> FuturesUrfReader<TokioReader<T>> {
+        self.inner
+    }
+}
+
+impl<T> RasterReader<TokioReader<T>> for UrfReader<T>
+where
+    T: AsyncRead,
+{
+    type PageHeader = UrfPageHeader;
+    type PageReader =
+        <FuturesUrfReader<TokioReader<T>> as RasterReader<TokioReader<T>>>::PageReader;
+    type Error = UrfError;
+    type NextPageFuture =
+        <FuturesUrfReader<TokioReader<T>> as RasterReader<TokioReader<T>>>::NextPageFuture;
+
+    fn next_page(self) -> Self::NextPageFuture {
+        self.inner.next_page()
+    }
+}
@@ -1,73 +1,215 @@
-use crate::decode::{CompressedRasterDecoder, Limits};
+use crate::decode::{BufferedSource, CompressedRasterDecoder, EncodedSource, Limits, ReadAhead};
 use crate::error::UrfError;
-use crate::factory::UrfPageFactory;
-use crate::model::urf::{UrfHeader, UrfPageHeader};
-use crate::reader::common::CommonRasterPageReader;
-use futures::AsyncRead;
+use crate::factory::{RasterPageFactory, UrfPageFactory};
+use crate::model::urf::{
+    UrfHeader, UrfPageHeader, URF_HEADER_SIZE, URF_MAGIC, URF_PAGE_HEADER_FIELDS_SIZE,
+    URF_PAGE_HEADER_SIZE,
+};
+use crate::reader::common::{read_header_bytes, CommonRasterPageReader};
+use crate::transcode::copy_compressed_content;
+use futures::{AsyncBufRead, AsyncRead};
 use pin_project::pin_project;
 use std::future::Future;
 use std::io;
+use std::marker::PhantomData;
 use std::ops::DerefMut;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use super::common::CommonRasterPageReaderFor;
-use super::RasterReader;
+use super::common::{CommonRasterPageReaderFor, CommonRasterReaderState};
+use super::{read_page_headers, RasterPageStream, RasterReader, ReaderOptions};
 
-pub struct UrfReader<R> {
+/// Reads the pages of a URF stream.
+///
+/// Pages are read until the end of the stream. The page count declared by the stream
+/// header is only checked against the limits, as some generators declare zero pages
+/// and still append page data; see [`declared_page_count`](Self::declared_page_count).
+///
+/// The encoded content is read through `S`, see [`EncodedSource`].
+pub struct UrfReader<R, S = ReadAhead> {
     reader: Pin<R>,
     header: UrfHeader,
-    limits: Limits,
+    options: ReaderOptions,
+    _source: PhantomData<S>,
 }
 
-pub type UrfPageReader<R> =
-    CommonRasterPageReader<UrfPageFactory, UrfPageHeader, CompressedRasterDecoder<R>, R>;
+pub type UrfPageReader<R, S = ReadAhead> =
+    CommonRasterPageReader<UrfPageFactory, UrfPageHeader, CompressedRasterDecoder<R, S>, R>;
 
 impl<R> UrfReader<R>
 where
     R: DerefMut<Target: AsyncRead>,
 {
     pub async fn new(reader: Pin<R>) -> Result<Self, UrfError> {
-        Self::new_with_limits(reader, Limits::default()).await
+        Self::new_with_options(reader, ReaderOptions::default()).await
     }
 
-    pub async fn new_with_limits(mut reader: Pin<R>, limits: Limits) -> Result<Self, UrfError> {
+    pub async fn new_with_limits(reader: Pin<R>, limits: Limits) -> Result<Self, UrfError> {
+        Self::new_with_options(reader, limits.into()).await
+    }
+
+    pub async fn new_with_options(
+        mut reader: Pin<R>,
+        options: ReaderOptions,
+    ) -> Result<Self, UrfError> {
+        options.check_header_size_override(URF_PAGE_HEADER_FIELDS_SIZE)?;
         let header = UrfReaderReadHeaderFuture {
-            buffer: [0; 12],
+            buffer: [0; URF_HEADER_SIZE],
             num_read: 0,
             reader: reader.as_mut(),
         }
         .await?;
+        let max_pages = options.limits.max_pages;
+        if header.page_count as u64 > max_pages {
+            return Err(UrfError::TooManyPages { max_pages });
+        }
+        if let Some(total_stream_len) = options.total_stream_len {
+            // every page takes at least its header, even without content
+            let page_header_size = options.header_size_override.unwrap_or(URF_PAGE_HEADER_SIZE);
+            let max_plausible =
+                total_stream_len.saturating_sub(URF_HEADER_SIZE as u64) / page_header_size as u64;
+            if header.page_count as u64 > max_plausible {
+                return Err(UrfError::ImplausiblePageCount {
+                    page_count: header.page_count,
+                    max_plausible,
+                });
+            }
+        }
+        Ok(UrfReader {
+            reader,
+            header,
+            options,
+            _source: PhantomData,
+        })
+    }
+}
+
+impl<R> UrfReader<R, BufferedSource>
+where
+    R: DerefMut<Target: AsyncBufRead>,
+{
+    /// Creates a reader over a buffered source, e.g. a `futures::io::BufReader`.
+    ///
+    /// The compressed content is decoded from the buffer of the source with
+    /// `poll_fill_buf` and `consume`, instead of being copied to a buffer of its own.
+    pub async fn new_buffered(reader: Pin<R>) -> Result<Self, UrfError> {
+        Self::new_buffered_with_options(reader, ReaderOptions::default()).await
+    }
+
+    pub async fn new_buffered_with_options(
+        reader: Pin<R>,
+        options: ReaderOptions,
+    ) -> Result<Self, UrfError> {
+        let UrfReader {
+            reader,
+            header,
+            options,
+            ..
+        } = UrfReader::new_with_options(reader, options).await?;
         Ok(UrfReader {
             reader,
             header,
-            limits,
+            options,
+            _source: PhantomData,
         })
     }
+}
 
+impl<R, S> UrfReader<R, S>
+where
+    R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
+    CompressedRasterDecoder<R, S>: From<CompressedRasterDecoder<R>>,
+{
     pub fn header(&self) -> &UrfHeader {
         &self.header
     }
+
+    /// Returns the page count declared by the stream header, which may differ from
+    /// the number of pages actually found in the stream.
+    pub fn declared_page_count(&self) -> u32 {
+        self.header.page_count
+    }
+
+    /// Turns the reader into a [`Stream`](futures::Stream) of its pages.
+    pub fn into_page_stream(self) -> RasterPageStream<Self, R> {
+        RasterPageStream::new(self)
+    }
+
+    /// Reads the header of every page, skipping the page content without decoding it.
+    ///
+    /// Fails with [`UrfError::TooManyPages`] if the stream has more pages than
+    /// allowed by [`Limits::max_pages`].
+    pub async fn read_page_headers(self) -> Result<Vec<UrfPageHeader>, UrfError> {
+        read_page_headers(self).await
+    }
+}
+
+impl<T> UrfReader<Box<T>>
+where
+    T: AsyncRead,
+{
+    /// Creates a reader over an owned source, e.g. a `Cursor`, pinning it on the heap.
+    ///
+    /// `source` should be buffered, e.g. by `futures::io::BufReader`.
+    pub async fn from_reader(source: T) -> Result<Self, UrfError> {
+        Self::new(Box::pin(source)).await
+    }
 }
 
-impl<R> RasterReader<R> for UrfReader<R>
+impl<R, S> RasterReader<R> for UrfReader<R, S>
 where
     R: DerefMut<Target: AsyncRead>,
+    S: EncodedSource<R>,
+    CompressedRasterDecoder<R, S>: From<CompressedRasterDecoder<R>>,
 {
     type PageHeader = UrfPageHeader;
-    type PageReader = UrfPageReader<R>;
+    type PageReader = UrfPageReader<R, S>;
     type Error = UrfError;
     type NextPageFuture =
-        CommonRasterPageReaderFor<UrfPageFactory, UrfPageHeader, CompressedRasterDecoder<R>, R>;
+        CommonRasterPageReaderFor<UrfPageFactory, UrfPageHeader, CompressedRasterDecoder<R, S>, R>;
 
     fn next_page(self) -> Self::NextPageFuture {
-        UrfPageReader::reader_for(self.reader, self.limits)
+        // the file header has been read
+        let state = CommonRasterReaderState::new(self.options, URF_HEADER_SIZE as u64);
+        UrfPageReader::<R, S>::reader_for_with_state(self.reader, state)
+    }
+}
+
+/// Counts the pages of a URF stream without decoding their pixels.
+///
+/// Returns the number of pages and the header of each page. Pages are counted as found
+/// in the stream, regardless of the page count declared by the stream header. The content
+/// of each page is skipped by following its line and block headers, without setting up
+/// a decoder.
+pub async fn count_pages<R>(reader: Pin<R>) -> Result<(usize, Vec<UrfPageHeader>), UrfError>
+where
+    R: DerefMut<Target: AsyncRead>,
+{
+    let mut reader = UrfReader::new(reader).await?.reader;
+    let mut buffer = [0; URF_PAGE_HEADER_SIZE];
+    let mut sink = futures::io::sink();
+    let mut headers = Vec::new();
+    while read_header_bytes(&mut reader, &mut buffer).await? {
+        let header = UrfPageFactory::header_from_bytes(&buffer)?;
+        let chunk_size = header.checked_bytes_per_pixel()?;
+        copy_compressed_content(
+            &mut reader,
+            &mut Pin::new(&mut sink),
+            chunk_size,
+            header.width as u64 * chunk_size as u64,
+            header.image_byte_len()?,
+            true,
+        )
+        .await?;
+        headers.push(header);
     }
+    Ok((headers.len(), headers))
 }
 
 #[pin_project]
 struct UrfReaderReadHeaderFuture<R> {
-    buffer: [u8; 12],
+    buffer: [u8; URF_HEADER_SIZE],
     num_read: usize,
     reader: Pin<R>,
 }
@@ -94,8 +236,9 @@ fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
                 Poll::Pending => return Poll::Pending,
             }
         }
-        if this.buffer[0..8] != *b"UNIRAST\0" {
-            Poll::Ready(Err(UrfError::InvalidMagic))
+        let magic: [u8; 8] = this.buffer[0..8].try_into().unwrap();
+        if magic != *URF_MAGIC {
+            Poll::Ready(Err(UrfError::InvalidMagic(magic)))
         } else {
             Poll::Ready(Ok(UrfHeader {
                 page_count: u32::from_be_bytes([
@@ -0,0 +1,256 @@
+//! Test-support adapters that exercise the partial-progress paths of readers and writers.
+//!
+//! Most in-memory readers and writers complete every call at once, so the `Poll::Pending`
+//! and short-read/short-write branches of the codecs are rarely taken in tests.
+//! The adapters here deterministically return `Poll::Pending` every other poll
+//! and move at most one byte per call.
+//!
+//! [`assert_streams_equal`] compares two streams, e.g. the output of an encoder against
+//! a reference implementation, and reports the first difference with some context.
+
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite};
+use pin_project::pin_project;
+use std::{
+    fmt, io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Returns `true` if the current poll should yield `Poll::Pending`, waking the task right away.
+fn yield_now(pending: &mut bool, cx: &mut Context<'_>) -> bool {
+    *pending = !*pending;
+    if *pending {
+        cx.waker().wake_by_ref();
+    }
+    *pending
+}
+
+/// A reader that alternates between `Poll::Pending` and reads of at most one byte.
+#[pin_project]
+pub struct PartialReader<R> {
+    #[pin]
+    inner: R,
+    pending: bool,
+}
+
+impl<R> PartialReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            pending: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R> AsyncRead for PartialReader<R>
+where
+    R: AsyncRead,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        if yield_now(this.pending, cx) {
+            return Poll::Pending;
+        }
+        this.inner.poll_read(cx, &mut buf[..1])
+    }
+}
+
+/// A writer that alternates between `Poll::Pending` and writes of at most one byte.
+///
+/// Flushing and closing are delayed the same way.
+#[pin_project]
+pub struct PartialWriter<W> {
+    #[pin]
+    inner: W,
+    pending: bool,
+}
+
+impl<W> PartialWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            pending: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W> AsyncWrite for PartialWriter<W>
+where
+    W: AsyncWrite,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        if yield_now(this.pending, cx) {
+            return Poll::Pending;
+        }
+        this.inner.poll_write(cx, &buf[..1])
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.project();
+        if yield_now(this.pending, cx) {
+            return Poll::Pending;
+        }
+        this.inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.project();
+        if yield_now(this.pending, cx) {
+            return Poll::Pending;
+        }
+        this.inner.poll_close(cx)
+    }
+}
+
+/// The number of bytes shown before and after a difference.
+const DIFF_CONTEXT: usize = 8;
+
+/// The first difference between two streams, see [`assert_streams_equal`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FirstDiff {
+    /// The offset of the first differing byte.
+    pub offset: u64,
+    /// The byte of the left stream at `offset`, or `None` if it ended before.
+    pub left: Option<u8>,
+    /// The byte of the right stream at `offset`, or `None` if it ended before.
+    pub right: Option<u8>,
+    /// The (common) bytes right before `offset`.
+    pub before: Vec<u8>,
+    /// The bytes of the left stream from `offset` on.
+    pub left_after: Vec<u8>,
+    /// The bytes of the right stream from `offset` on.
+    pub right_after: Vec<u8>,
+}
+
+impl fmt::Display for FirstDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn byte(value: Option<u8>) -> String {
+            value.map_or_else(|| "<eof>".to_string(), |b| format!("0x{:02x}", b))
+        }
+        fn line(
+            f: &mut fmt::Formatter<'_>,
+            name: &str,
+            before: &[u8],
+            after: &[u8],
+        ) -> fmt::Result {
+            write!(f, "\n  {}:", name)?;
+            for b in before {
+                write!(f, " {:02x}", b)?;
+            }
+            match after.split_first() {
+                Some((first, rest)) => {
+                    write!(f, " [{:02x}]", first)?;
+                    for b in rest {
+                        write!(f, " {:02x}", b)?;
+                    }
+                    Ok(())
+                }
+                None => write!(f, " [<eof>]"),
+            }
+        }
+        write!(
+            f,
+            "streams differ at offset {} (0x{:x}): left = {}, right = {}",
+            self.offset,
+            self.offset,
+            byte(self.left),
+            byte(self.right)
+        )?;
+        line(f, "left ", &self.before, &self.left_after)?;
+        line(f, "right", &self.before, &self.right_after)
+    }
+}
+
+impl std::error::Error for FirstDiff {}
+
+/// Reads until `buf` is full or the stream ends, returning the number of bytes read.
+async fn read_full<R>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]).await? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// Compares two streams byte by byte, returning the first difference if any.
+///
+/// Both streams are read in lockstep, so neither has to fit in memory.
+/// Up to 8 bytes around the difference are kept for [`Display`](fmt::Display).
+///
+/// # Panics
+/// Panics if reading either stream fails.
+pub async fn assert_streams_equal<A, B>(left: A, right: B) -> Result<(), FirstDiff>
+where
+    A: AsyncRead,
+    B: AsyncRead,
+{
+    let mut left = Box::pin(left);
+    let mut right = Box::pin(right);
+    let mut left_buf = vec![0u8; 4096];
+    let mut right_buf = vec![0u8; 4096];
+    let mut before = Vec::<u8>::with_capacity(DIFF_CONTEXT * 2);
+    let mut offset = 0u64;
+    loop {
+        let left_len = read_full(&mut left, &mut left_buf)
+            .await
+            .expect("failed to read the left stream");
+        let right_len = read_full(&mut right, &mut right_buf)
+            .await
+            .expect("failed to read the right stream");
+        let (l, r) = (&left_buf[..left_len], &right_buf[..right_len]);
+        let common = l.iter().zip(r).take_while(|(a, b)| a == b).count();
+        if common == left_len && common == right_len {
+            if left_len == 0 {
+                return Ok(());
+            }
+            before.extend_from_slice(&l[left_len.saturating_sub(DIFF_CONTEXT)..]);
+            before.drain(..before.len().saturating_sub(DIFF_CONTEXT));
+            offset += left_len as u64;
+            continue;
+        }
+        before.extend_from_slice(&l[common.saturating_sub(DIFF_CONTEXT)..common]);
+        before.drain(..before.len().saturating_sub(DIFF_CONTEXT));
+        let after = |data: &[u8]| data[common..data.len().min(common + DIFF_CONTEXT)].to_vec();
+        return Err(FirstDiff {
+            offset: offset + common as u64,
+            left: l.get(common).copied(),
+            right: r.get(common).copied(),
+            before,
+            left_after: after(l),
+            right_after: after(r),
+        });
+    }
+}
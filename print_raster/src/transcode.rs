@@ -0,0 +1,385 @@
+//! High-level helpers that copy pages from a reader to a writer.
+
+use crate::convert::{cmyk_pixel_to_rgb, luminance, rgb_pixel_to_cmyk};
+use crate::decode::{CompressedRasterDecoder, RasterDecoder};
+use crate::encode::{CompressedRasterEncoder, EncoderPolicy, RasterEncoder};
+use crate::error::CupsRasterError;
+use crate::model::cups::{CupsColorOrder, CupsColorSpace, CupsPageHeaderV2};
+use crate::reader::{RasterPageReader, RasterReader};
+use crate::writer::{RasterPageWriter, RasterWriter};
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use std::io;
+use std::ops::DerefMut;
+use std::pin::Pin;
+
+/// Copies all pages from `reader` to `writer`, converting the pixels of each page
+/// to `target` color space on the fly.
+///
+/// Only 8-bit chunky pages in gray, black, RGB, CMY and CMYK color spaces are supported.
+/// The color space, bits per pixel and bytes per line of the output headers are updated
+/// accordingly, and the writer is finished after the last page.
+pub async fn transcode_with_color<RD, WR, R, W>(
+    reader: RD,
+    writer: WR,
+    target: CupsColorSpace,
+) -> Result<(), CupsRasterError>
+where
+    R: DerefMut<Target: AsyncRead>,
+    W: DerefMut<Target: AsyncWrite>,
+    RD: RasterReader<R, PageHeader = CupsPageHeaderV2, Error = CupsRasterError>,
+    RD::PageReader: RasterPageReader<R, Error = CupsRasterError>,
+    <RD::PageReader as RasterPageReader<R>>::Decoder: Unpin,
+    WR: RasterWriter<W, PageHeader = CupsPageHeaderV2, Error = CupsRasterError>,
+    WR::PageWriter: RasterPageWriter<W, Error = CupsRasterError>,
+    <WR::PageWriter as RasterPageWriter<W>>::Encoder: Unpin,
+{
+    let mut page = match reader.next_page().await? {
+        Some(page) => page,
+        None => return writer.finish().await,
+    };
+    let header = convert_header(page.header(), target)?;
+    let mut page_writer = writer.next_page(&header).await?;
+    loop {
+        let source = page.header().clone();
+        convert_content(
+            &source,
+            target,
+            page.content_mut(),
+            page_writer.content_mut(),
+        )
+        .await?;
+        page = match page.next_page().await? {
+            Some(page) => page,
+            None => break,
+        };
+        let header = convert_header(page.header(), target)?;
+        page_writer = page_writer.next_page(&header).await?;
+    }
+    page_writer.finish().await
+}
+
+/// Options of the encoder of [`recompress`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecompressOptions {
+    /// The bytes per run-length chunk, which must divide the bytes per line.
+    pub chunk_size: u8,
+    /// How lines are split into blocks, see [`EncoderPolicy`].
+    pub policy: EncoderPolicy,
+}
+
+impl From<u8> for RecompressOptions {
+    fn from(chunk_size: u8) -> Self {
+        Self {
+            chunk_size,
+            policy: EncoderPolicy::default(),
+        }
+    }
+}
+
+/// Re-encodes the remaining content of `decoder` into `writer` as set by `options`,
+/// and returns `writer` once the content is fully written.
+///
+/// The content is streamed line by line, so only a single line is held in memory.
+pub async fn recompress<R, W>(
+    decoder: &mut CompressedRasterDecoder<R>,
+    writer: Pin<W>,
+    options: RecompressOptions,
+) -> io::Result<Pin<W>>
+where
+    R: DerefMut<Target: AsyncRead>,
+    W: DerefMut<Target: AsyncWrite>,
+{
+    let mut encoder = CompressedRasterEncoder::new_with_policy(
+        writer,
+        options.chunk_size,
+        decoder.bytes_per_line(),
+        decoder.bytes_remaining(),
+        options.policy,
+    )?;
+    let line_size = usize::try_from(decoder.bytes_per_line().min(decoder.bytes_remaining()))
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "bytes_per_line is too large"))?;
+    let mut line = vec![0; line_size];
+    while encoder.bytes_remaining() > 0 {
+        decoder.read_exact(&mut line).await?;
+        encoder.write_all(&line).await?;
+    }
+    encoder.flush().await?;
+    Ok(encoder.into_pin_mut())
+}
+
+/// Copies the encoded content of a compressed CUPS page of `num_bytes` bytes from
+/// `reader` to `writer` as is, and returns the number of encoded bytes copied.
+///
+/// The end of the content is found by following the line and block headers, without
+/// expanding repeated pixels or lines. `allow_fill_to_end` accepts the block code `0x80`
+/// of URF, as [`CompressedRasterDecoder::new`] does.
+pub(crate) async fn copy_compressed_content<R, W>(
+    reader: &mut Pin<R>,
+    writer: &mut Pin<W>,
+    chunk_size: u8,
+    bytes_per_line: u64,
+    num_bytes: u64,
+    allow_fill_to_end: bool,
+) -> io::Result<u64>
+where
+    R: DerefMut<Target: AsyncRead>,
+    W: DerefMut<Target: AsyncWrite>,
+{
+    let chunk_size = chunk_size as u64;
+    let mut lines_remaining = num_bytes.checked_div(bytes_per_line).unwrap_or(0);
+    let mut copied = 0;
+    let mut code = [0u8];
+    while lines_remaining > 0 {
+        reader.as_mut().read_exact(&mut code).await?;
+        writer.as_mut().write_all(&code).await?;
+        copied += 1;
+        // lines repeated past the end of the page are tolerated, as when decoding
+        lines_remaining = lines_remaining.saturating_sub(code[0] as u64 + 1);
+        let mut filled = 0;
+        while filled < bytes_per_line {
+            reader.as_mut().read_exact(&mut code).await?;
+            writer.as_mut().write_all(&code).await?;
+            copied += 1;
+            let (length, data) = match code[0] {
+                0x00..=0x7F => ((code[0] as u64 + 1) * chunk_size, chunk_size),
+                0x80 if allow_fill_to_end => (bytes_per_line - filled, 0),
+                0x80 => (0, 0),
+                _ => {
+                    let length = (!code[0] as u64 + 2) * chunk_size;
+                    (length, length)
+                }
+            };
+            if length == 0 || bytes_per_line - filled < length {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid block header",
+                ));
+            }
+            let n = futures::io::copy(reader.as_mut().take(data), &mut writer.as_mut()).await?;
+            if n < data {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "unexpected eof, more data of raster page is expected",
+                ));
+            }
+            copied += n;
+            filled += length;
+        }
+    }
+    Ok(copied)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorModel {
+    Gray,
+    Black,
+    Rgb,
+    Cmy,
+    Cmyk,
+}
+
+impl ColorModel {
+    fn of(color_space: CupsColorSpace) -> Option<Self> {
+        match color_space {
+            CupsColorSpace::Gray | CupsColorSpace::sGray => Some(Self::Gray),
+            CupsColorSpace::Black => Some(Self::Black),
+            CupsColorSpace::RGB | CupsColorSpace::sRGB | CupsColorSpace::AdobeRGB => {
+                Some(Self::Rgb)
+            }
+            CupsColorSpace::CMY => Some(Self::Cmy),
+            CupsColorSpace::CMYK => Some(Self::Cmyk),
+            _ => None,
+        }
+    }
+
+    fn num_colors(self) -> usize {
+        match self {
+            Self::Gray | Self::Black => 1,
+            Self::Rgb | Self::Cmy => 3,
+            Self::Cmyk => 4,
+        }
+    }
+
+    fn to_rgb(self, pixel: &[u8]) -> [u8; 3] {
+        match self {
+            Self::Gray => [pixel[0]; 3],
+            Self::Black => [!pixel[0]; 3],
+            Self::Rgb => [pixel[0], pixel[1], pixel[2]],
+            Self::Cmy => [!pixel[0], !pixel[1], !pixel[2]],
+            Self::Cmyk => cmyk_pixel_to_rgb([pixel[0], pixel[1], pixel[2], pixel[3]]),
+        }
+    }
+
+    fn write_rgb(self, rgb: [u8; 3], pixel: &mut [u8]) {
+        let [r, g, b] = rgb;
+        match self {
+            Self::Gray => pixel[0] = luminance(rgb),
+            Self::Black => pixel[0] = !luminance(rgb),
+            Self::Rgb => pixel.copy_from_slice(&rgb),
+            Self::Cmy => pixel.copy_from_slice(&[!r, !g, !b]),
+            Self::Cmyk => pixel.copy_from_slice(&rgb_pixel_to_cmyk(rgb)),
+        }
+    }
+}
+
+fn color_models(
+    header: &CupsPageHeaderV2,
+    target: CupsColorSpace,
+) -> Result<(ColorModel, ColorModel), CupsRasterError> {
+    let source = header.v1.color_space;
+    let unsupported = || CupsRasterError::UnsupportedColorConversion {
+        from: source,
+        to: target,
+    };
+    if header.v1.bits_per_color != 8 || header.v1.color_order != CupsColorOrder::Chunky {
+        return Err(unsupported());
+    }
+    let from = ColorModel::of(source).ok_or_else(unsupported)?;
+    let to = ColorModel::of(target).ok_or_else(unsupported)?;
+    if header.v1.bits_per_pixel != 8 * from.num_colors() as u32 {
+        return Err(CupsRasterError::DataLayoutError);
+    }
+    Ok((from, to))
+}
+
+fn convert_header(
+    header: &CupsPageHeaderV2,
+    target: CupsColorSpace,
+) -> Result<CupsPageHeaderV2, CupsRasterError> {
+    let (_, to) = color_models(header, target)?;
+    let num_colors = to.num_colors() as u32;
+    let mut header = header.clone();
+    header.v1.color_space = target;
+    header.v1.bits_per_pixel = 8 * num_colors;
+    header.v1.bytes_per_line = header
+        .v1
+        .width
+        .checked_mul(num_colors)
+        .ok_or(CupsRasterError::DataTooLarge)?;
+    header.num_colors = num_colors;
+    Ok(header)
+}
+
+async fn convert_content<D, E>(
+    header: &CupsPageHeaderV2,
+    target: CupsColorSpace,
+    decoder: &mut D,
+    encoder: &mut E,
+) -> Result<(), CupsRasterError>
+where
+    D: AsyncRead + Unpin,
+    E: AsyncWrite + Unpin,
+{
+    let (from, to) = color_models(header, target)?;
+    let width = header.v1.width as usize;
+    if (header.v1.bytes_per_line as usize) < width * from.num_colors() {
+        return Err(CupsRasterError::DataLayoutError);
+    }
+    let mut src_line = vec![0; header.v1.bytes_per_line as usize];
+    let mut dst_line = vec![0; width * to.num_colors()];
+    for _ in 0..header.v1.height {
+        decoder.read_exact(&mut src_line).await?;
+        if from == to {
+            let len = dst_line.len();
+            dst_line.copy_from_slice(&src_line[..len]);
+        } else {
+            for (src, dst) in src_line
+                .chunks_exact(from.num_colors())
+                .zip(dst_line.chunks_exact_mut(to.num_colors()))
+            {
+                to.write_rgb(from.to_rgb(src), dst);
+            }
+        }
+        encoder.write_all(&dst_line).await?;
+    }
+    encoder.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{recompress, RecompressOptions};
+    use crate::decode::{CompressedRasterDecoder, Limits};
+    use crate::encode::{CompressedRasterEncoder, EncoderPolicy};
+    use futures::{AsyncReadExt, AsyncWriteExt};
+    use std::pin::{pin, Pin};
+
+    #[tokio::test]
+    async fn test_recompress() {
+        // 4 lines of 4 RGB pixels, with a repeated line and repeated pixels
+        let mut pixels = Vec::new();
+        for line in [[0x10u8; 12], [0x10; 12], [0x20; 12]] {
+            pixels.extend_from_slice(&line);
+        }
+        pixels.extend_from_slice(&[
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x01, 0x02, 0x03, 0x07, 0x08, 0x09,
+        ]);
+
+        let mut compressed = Vec::new();
+        let mut encoder =
+            CompressedRasterEncoder::new(Pin::new(&mut compressed), 3, 12, pixels.len() as u64)
+                .unwrap();
+        encoder.write_all(&pixels).await.unwrap();
+        encoder.flush().await.unwrap();
+        drop(encoder);
+
+        let input = pin!(compressed.as_slice());
+        let mut decoder = CompressedRasterDecoder::new(
+            input,
+            &Limits::default(),
+            3,
+            12,
+            pixels.len() as u64,
+            0,
+            false,
+        )
+        .unwrap();
+        let mut recompressed = Vec::new();
+        recompress(&mut decoder, Pin::new(&mut recompressed), 1.into())
+            .await
+            .unwrap();
+        assert_ne!(recompressed, compressed);
+
+        // the policy is passed on to the encoder
+        let input = pin!(compressed.as_slice());
+        let mut decoder =
+            CompressedRasterDecoder::new(input, &Limits::default(), 3, 12, 48, 0, false).unwrap();
+        let mut cups_compatible = Vec::new();
+        let options = RecompressOptions {
+            chunk_size: 3,
+            policy: EncoderPolicy::CupsCompatible,
+        };
+        recompress(&mut decoder, Pin::new(&mut cups_compatible), options)
+            .await
+            .unwrap();
+        let mut expected = Vec::new();
+        let mut encoder = CompressedRasterEncoder::new_with_policy(
+            Pin::new(&mut expected),
+            3,
+            12,
+            48,
+            EncoderPolicy::CupsCompatible,
+        )
+        .unwrap();
+        encoder.write_all(&pixels).await.unwrap();
+        drop(encoder);
+        assert_eq!(cups_compatible, expected);
+        // the last line ends with a literal block of the last two pixels
+        assert_ne!(cups_compatible, compressed);
+
+        let input = pin!(recompressed.as_slice());
+        let mut decoder = CompressedRasterDecoder::new(
+            input,
+            &Limits::default(),
+            1,
+            12,
+            pixels.len() as u64,
+            0,
+            false,
+        )
+        .unwrap();
+        let mut output = Vec::new();
+        decoder.read_to_end(&mut output).await.unwrap();
+        assert_eq!(output, pixels);
+    }
+}
@@ -0,0 +1,79 @@
+use futures::AsyncWrite;
+use pin_project::pin_project;
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// A writer that fails once the total number of bytes written would exceed a limit.
+///
+/// A write that does not fit into the remaining budget is rejected as a whole,
+/// so the output never grows beyond the limit.
+#[pin_project]
+pub struct BoundedWriter<W> {
+    #[pin]
+    inner: W,
+    limit: u64,
+    bytes_written: u64,
+}
+
+impl<W> BoundedWriter<W> {
+    pub fn new(inner: W, limit: u64) -> Self {
+        Self {
+            inner,
+            limit,
+            bytes_written: 0,
+        }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W> AsyncWrite for BoundedWriter<W>
+where
+    W: AsyncWrite,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+        if buf.len() as u64 > *this.limit - *this.bytes_written {
+            return Poll::Ready(Err(io::Error::other(format!(
+                "output exceeds the limit of {} bytes",
+                this.limit
+            ))));
+        }
+        let n = futures::ready!(this.inner.poll_write(cx, buf))?;
+        *this.bytes_written += n as u64;
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_close(cx)
+    }
+}
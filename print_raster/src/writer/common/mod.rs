@@ -1,4 +1,6 @@
-use super::RasterPageWriter;
+use super::{RasterPageWriter, WriteStats};
+use crate::error::CommonRasterError;
+use crate::metrics::MetricsHandle;
 use crate::{encode::RasterEncoder, factory::RasterPageFactory};
 use futures::{ready, AsyncWrite};
 use pin_project::pin_project;
@@ -11,63 +13,192 @@
     task::{Context, Poll},
 };
 
-pub struct CommonRasterPageWriter<F, W>
+/// Writes the remaining bytes of `marker`, advancing `written` by the bytes written.
+fn poll_write_marker<W>(
+    writer: &mut Pin<W>,
+    cx: &mut Context,
+    marker: &[u8],
+    written: &mut usize,
+) -> Poll<io::Result<()>>
+where
+    W: DerefMut<Target: AsyncWrite>,
+{
+    while *written < marker.len() {
+        let num_written = ready!(writer.as_mut().poll_write(cx, &marker[*written..]))?;
+        if num_written == 0 {
+            return Poll::Ready(Err(io::Error::other("failed to write end-of-page marker")));
+        }
+        *written += num_written;
+    }
+    Poll::Ready(Ok(()))
+}
+
+/// A common implementation of `RasterPageWriter` for all raster formats.
+///
+/// # Type parameters
+/// - `F`: The `RasterPageFactory` implementation for the raster format.
+/// - `W`: The mutable pointer to AsyncWrite.
+/// - `ES`: The type to store the encoder.
+pub struct CommonRasterPageWriter<F, W, ES = <F as RasterPageFactory>::Encoder<W>>
 where
     F: RasterPageFactory,
     W: DerefMut<Target: AsyncWrite>,
+    ES: From<<F as RasterPageFactory>::Encoder<W>> + RasterEncoder<W>,
 {
-    content: <F as RasterPageFactory>::Encoder<W>,
+    content: ES,
+    stats: WriteStats,
+    metrics: MetricsHandle,
+    /// Rewrites the headers of the following pages before they are written.
+    rewrite_header: Option<RewriteHeader<F>>,
+    _factory: PhantomData<F>,
+    _writer: PhantomData<W>,
 }
 
-impl<F, W> CommonRasterPageWriter<F, W>
+type RewriteHeader<F> = fn(&<F as RasterPageFactory>::Header) -> <F as RasterPageFactory>::Header;
+
+impl<F, W, ES> CommonRasterPageWriter<F, W, ES>
 where
     F: RasterPageFactory,
     W: DerefMut<Target: AsyncWrite>,
+    ES: From<<F as RasterPageFactory>::Encoder<W>> + RasterEncoder<W>,
 {
     /// Writes the header of the page and returns a writer for the page content.
     pub fn writer_for(
         header: &<F as RasterPageFactory>::Header,
         writer: Pin<W>,
-    ) -> CommonRasterPageWriterFor<F, W> {
+    ) -> CommonRasterPageWriterFor<'_, F, W, ES> {
+        Self::writer_for_with_stats(
+            header,
+            writer,
+            WriteStats::default(),
+            MetricsHandle::default(),
+        )
+    }
+
+    pub(crate) fn writer_for_with_stats(
+        header: &<F as RasterPageFactory>::Header,
+        writer: Pin<W>,
+        stats: WriteStats,
+        metrics: MetricsHandle,
+    ) -> CommonRasterPageWriterFor<'_, F, W, ES> {
         CommonRasterPageWriterFor {
             header,
             writer: Some(writer),
+            stats,
+            metrics,
+            eop_marker: &[],
+            eop_marker_written: 0,
             buffer: Vec::new(),
             start: 0,
+            rewrite_header: None,
+            rewritten_header: None,
             _factory: PhantomData,
+            _encoder_storage: PhantomData,
         }
     }
+
+    /// Returns the statistics of the stream, not including the current page.
+    pub fn stats(&self) -> &WriteStats {
+        &self.stats
+    }
+
+    /// Returns the number of pages written so far, including the current page.
+    pub fn page_count(&self) -> usize {
+        self.stats.pages_written() + 1
+    }
+
+    /// Splits the writer without finishing the page, e.g. to write content that is
+    /// already encoded.
+    pub(crate) fn into_raw_parts(self) -> (Pin<W>, WriteStats, MetricsHandle) {
+        (self.content.into_pin_mut(), self.stats, self.metrics)
+    }
+
+    fn into_parts(self) -> (u64, Pin<W>, WriteStats, MetricsHandle) {
+        let mut stats = self.stats;
+        stats.add_page(self.content.bytes_emitted());
+        let bytes_remaining = self.content.bytes_remaining();
+        if bytes_remaining == 0 {
+            self.metrics.page_written(self.content.bytes_emitted());
+        }
+        (
+            bytes_remaining,
+            self.content.into_pin_mut(),
+            stats,
+            self.metrics,
+        )
+    }
 }
 
 #[pin_project]
-pub struct CommonRasterPageWriterFor<'a, F, W>
+pub struct CommonRasterPageWriterFor<'a, F, W, ES = <F as RasterPageFactory>::Encoder<W>>
 where
     F: RasterPageFactory,
     W: DerefMut<Target: AsyncWrite>,
+    ES: From<<F as RasterPageFactory>::Encoder<W>> + RasterEncoder<W>,
 {
     header: &'a <F as RasterPageFactory>::Header,
     writer: Option<Pin<W>>,
+    stats: WriteStats,
+    metrics: MetricsHandle,
+    /// The end-of-page marker of the previous page, written before the header.
+    eop_marker: &'static [u8],
+    eop_marker_written: usize,
     buffer: Vec<u8>,
     start: usize,
+    rewrite_header: Option<RewriteHeader<F>>,
+    /// The header written in place of `header`, if it is rewritten.
+    rewritten_header: Option<<F as RasterPageFactory>::Header>,
     _factory: PhantomData<F>,
+    _encoder_storage: PhantomData<ES>,
 }
 
-impl<'a, F, W> Future for CommonRasterPageWriterFor<'a, F, W>
+impl<F, W, ES> CommonRasterPageWriterFor<'_, F, W, ES>
 where
     F: RasterPageFactory,
     W: DerefMut<Target: AsyncWrite>,
-    F::Error: From<io::Error>,
+    ES: From<<F as RasterPageFactory>::Encoder<W>> + RasterEncoder<W>,
 {
-    type Output = Result<CommonRasterPageWriter<F, W>, <F as RasterPageFactory>::Error>;
+    /// Writes the header of this page and of the following pages as rewritten by `rewrite`.
+    pub(crate) fn with_rewrite_header(mut self, rewrite: Option<RewriteHeader<F>>) -> Self {
+        self.rewritten_header = rewrite.map(|rewrite| rewrite(self.header));
+        self.rewrite_header = rewrite;
+        self
+    }
+}
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+impl<'a, F, W, ES> Future for CommonRasterPageWriterFor<'a, F, W, ES>
+where
+    F: RasterPageFactory,
+    W: DerefMut<Target: AsyncWrite>,
+    ES: From<<F as RasterPageFactory>::Encoder<W>> + RasterEncoder<W>,
+    F::Error: CommonRasterError,
+{
+    type Output = Result<CommonRasterPageWriter<F, W, ES>, <F as RasterPageFactory>::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let result = ready!(self.as_mut().poll_header(cx));
+        if let Err(e) = &result {
+            self.metrics.write_error(e);
+        }
+        Poll::Ready(result)
+    }
+}
+
+impl<F, W, ES> CommonRasterPageWriterFor<'_, F, W, ES>
+where
+    F: RasterPageFactory,
+    W: DerefMut<Target: AsyncWrite>,
+    ES: From<<F as RasterPageFactory>::Encoder<W>> + RasterEncoder<W>,
+    F::Error: CommonRasterError,
+{
+    #[allow(clippy::type_complexity)]
+    fn poll_header(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<CommonRasterPageWriter<F, W, ES>, <F as RasterPageFactory>::Error>> {
         let this = self.project();
         if this.writer.is_none() {
-            return Poll::Ready(Err(io::Error::new(
-                io::ErrorKind::Other,
-                "writer is already taken",
-            )
-            .into()));
+            return Poll::Ready(Err(io::Error::other("writer is already taken").into()));
         }
         #[allow(clippy::uninit_vec)]
         if this.buffer.is_empty() {
@@ -79,9 +210,16 @@ fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
                 buffer.set_len(F::HEADER_SIZE);
                 buffer
             };
-            F::header_to_bytes(this.buffer, this.header)?;
+            let header = this.rewritten_header.as_ref().unwrap_or(this.header);
+            F::header_to_bytes(this.buffer, header)?;
         }
         let writer = this.writer.as_mut().unwrap();
+        ready!(poll_write_marker(
+            writer,
+            cx,
+            this.eop_marker,
+            this.eop_marker_written
+        ))?;
         loop {
             let buf = &mut this.buffer[*this.start..];
             let num_written = ready!(writer.as_mut().poll_write(cx, buf))?;
@@ -91,52 +229,85 @@ fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
                 break;
             }
             if num_written == 0 {
-                return Poll::Ready(Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "failed to write header",
-                )
-                .into()));
+                return Poll::Ready(Err(io::Error::other("failed to write header").into()));
             }
         }
         let writer = this.writer.take().unwrap();
+        let mut stats = std::mem::take(this.stats);
+        stats.bytes_written += (this.eop_marker.len() + F::HEADER_SIZE) as u64;
+        let header = this.rewritten_header.as_ref().unwrap_or(this.header);
         Poll::Ready(Ok(CommonRasterPageWriter {
-            content: F::encode(this.header, writer)?,
+            content: F::encode(header, writer)?.into(),
+            stats,
+            metrics: std::mem::take(this.metrics),
+            rewrite_header: *this.rewrite_header,
+            _factory: PhantomData,
+            _writer: PhantomData,
         }))
     }
 }
 
-impl<F, W> RasterPageWriter<W> for CommonRasterPageWriter<F, W>
+impl<F, W, ES> RasterPageWriter<W> for CommonRasterPageWriter<F, W, ES>
 where
     F: RasterPageFactory,
     W: DerefMut<Target: AsyncWrite>,
-    F::Error: From<io::Error>,
+    ES: From<<F as RasterPageFactory>::Encoder<W>> + RasterEncoder<W>,
+    F::Error: CommonRasterError,
 {
     type Header = F::Header;
-    type Encoder = F::Encoder<W>;
+    type Encoder = ES;
     type Error = F::Error;
-    type NextPageFuture<'a> = CommonRasterPageWriterNext<'a, F, W>
+    type NextPageFuture<'a>
+        = CommonRasterPageWriterNext<'a, F, W, ES>
     where
         Self: 'a;
     type FinishFuture = CommonRasterPageWriterFinish<W, Self::Error>;
+    type FinishWithStatsFuture = CommonRasterPageWriterFinishWithStats<W, Self::Error>;
 
     fn next_page<'a>(self, header: &'a Self::Header) -> Self::NextPageFuture<'a>
     where
         Self: 'a,
     {
-        if self.content.bytes_remaining() > 0 {
-            CommonRasterPageWriterNext::ErrorNotAllBytesWritten
+        let rewrite_header = self.rewrite_header;
+        let (bytes_remaining, writer, stats, metrics) = self.into_parts();
+        if bytes_remaining > 0 {
+            metrics.write_error(&F::Error::incomplete_page(bytes_remaining));
+            CommonRasterPageWriterNext::ErrorNotAllBytesWritten(bytes_remaining)
         } else {
-            CommonRasterPageWriterNext::NextPage(CommonRasterPageWriter::writer_for(
-                header,
-                self.into_content().into_pin_mut(),
-            ))
+            let mut future =
+                CommonRasterPageWriter::writer_for_with_stats(header, writer, stats, metrics)
+                    .with_rewrite_header(rewrite_header);
+            future.eop_marker = F::EOP_MARKER.unwrap_or_default();
+            CommonRasterPageWriterNext::NextPage(future)
         }
     }
 
     fn finish(self) -> Self::FinishFuture {
+        let bytes_remaining = self.content.bytes_remaining();
+        if bytes_remaining == 0 {
+            self.metrics.page_written(self.content.bytes_emitted());
+        }
         CommonRasterPageWriterFinish {
-            not_all_bytes_written: self.content.bytes_remaining() > 0,
+            bytes_remaining,
             writer: self.content.into_pin_mut(),
+            metrics: self.metrics,
+            eop_marker: F::EOP_MARKER.unwrap_or_default(),
+            eop_marker_written: 0,
+            _error: PhantomData,
+        }
+    }
+
+    fn finish_with_stats(self) -> Self::FinishWithStatsFuture {
+        let (bytes_remaining, writer, mut stats, metrics) = self.into_parts();
+        let eop_marker = F::EOP_MARKER.unwrap_or_default();
+        stats.bytes_written += eop_marker.len() as u64;
+        CommonRasterPageWriterFinishWithStats {
+            bytes_remaining,
+            writer: Some(writer),
+            stats,
+            metrics,
+            eop_marker,
+            eop_marker_written: 0,
             _error: PhantomData,
         }
     }
@@ -151,28 +322,31 @@ fn into_content(self) -> Self::Encoder {
 }
 
 #[pin_project(project = CommonRasterPageWriterNextProj)]
-pub enum CommonRasterPageWriterNext<'a, F, W>
+pub enum CommonRasterPageWriterNext<'a, F, W, ES = <F as RasterPageFactory>::Encoder<W>>
 where
     F: RasterPageFactory,
     W: DerefMut<Target: AsyncWrite>,
+    ES: From<<F as RasterPageFactory>::Encoder<W>> + RasterEncoder<W>,
 {
-    ErrorNotAllBytesWritten,
-    NextPage(#[pin] CommonRasterPageWriterFor<'a, F, W>),
+    /// The content of the current page is incomplete, by the given number of bytes.
+    ErrorNotAllBytesWritten(u64),
+    NextPage(#[pin] CommonRasterPageWriterFor<'a, F, W, ES>),
 }
 
-impl<'a, F, W> Future for CommonRasterPageWriterNext<'a, F, W>
+impl<'a, F, W, ES> Future for CommonRasterPageWriterNext<'a, F, W, ES>
 where
     F: RasterPageFactory,
     W: DerefMut<Target: AsyncWrite>,
-    F::Error: From<io::Error>,
+    ES: From<<F as RasterPageFactory>::Encoder<W>> + RasterEncoder<W>,
+    F::Error: CommonRasterError,
 {
-    type Output = Result<CommonRasterPageWriter<F, W>, <F as RasterPageFactory>::Error>;
+    type Output = Result<CommonRasterPageWriter<F, W, ES>, <F as RasterPageFactory>::Error>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
         match self.project() {
-            CommonRasterPageWriterNextProj::ErrorNotAllBytesWritten => Poll::Ready(Err(
-                io::Error::new(io::ErrorKind::Other, "not all bytes are written").into(),
-            )),
+            CommonRasterPageWriterNextProj::ErrorNotAllBytesWritten(bytes_remaining) => {
+                Poll::Ready(Err(F::Error::incomplete_page(*bytes_remaining)))
+            }
             CommonRasterPageWriterNextProj::NextPage(fut) => fut.poll(cx),
         }
     }
@@ -181,28 +355,122 @@ fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
 #[pin_project(project = CommonRasterPageWriterFinishProj)]
 pub struct CommonRasterPageWriterFinish<W, E> {
     writer: Pin<W>,
-    not_all_bytes_written: bool,
+    metrics: MetricsHandle,
+    bytes_remaining: u64,
+    eop_marker: &'static [u8],
+    eop_marker_written: usize,
     _error: PhantomData<E>,
 }
 
 impl<W, E> Future for CommonRasterPageWriterFinish<W, E>
 where
     W: DerefMut<Target: AsyncWrite>,
-    E: From<io::Error>,
+    E: CommonRasterError,
 {
     type Output = Result<(), E>;
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let result = ready!(self.as_mut().poll_finish(cx));
+        if let Err(e) = &result {
+            self.metrics.write_error(e);
+        }
+        Poll::Ready(result)
+    }
+}
+
+impl<W, E> CommonRasterPageWriterFinish<W, E>
+where
+    W: DerefMut<Target: AsyncWrite>,
+    E: CommonRasterError,
+{
+    fn poll_finish(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), E>> {
         let this = self.project();
+        if *this.bytes_remaining == 0 {
+            ready!(poll_write_marker(
+                this.writer,
+                cx,
+                this.eop_marker,
+                this.eop_marker_written
+            ))?;
+        }
         ready!(this.writer.as_mut().poll_close(cx))?;
-        if *this.not_all_bytes_written {
-            Poll::Ready(Err(io::Error::new(
-                io::ErrorKind::Other,
-                "not all bytes are written",
-            )
-            .into()))
+        if *this.bytes_remaining > 0 {
+            Poll::Ready(Err(E::incomplete_page(*this.bytes_remaining)))
         } else {
             Poll::Ready(Ok(()))
         }
     }
 }
+
+#[pin_project(project = CommonRasterPageWriterFinishWithStatsProj)]
+pub struct CommonRasterPageWriterFinishWithStats<W, E> {
+    writer: Option<Pin<W>>,
+    stats: WriteStats,
+    metrics: MetricsHandle,
+    bytes_remaining: u64,
+    eop_marker: &'static [u8],
+    eop_marker_written: usize,
+    _error: PhantomData<E>,
+}
+
+impl<W, E> CommonRasterPageWriterFinishWithStats<W, E> {
+    pub(crate) fn new(writer: Pin<W>, stats: WriteStats, metrics: MetricsHandle) -> Self {
+        Self {
+            writer: Some(writer),
+            stats,
+            metrics,
+            bytes_remaining: 0,
+            eop_marker: &[],
+            eop_marker_written: 0,
+            _error: PhantomData,
+        }
+    }
+}
+
+impl<W, E> Future for CommonRasterPageWriterFinishWithStats<W, E>
+where
+    W: DerefMut<Target: AsyncWrite>,
+    E: CommonRasterError,
+{
+    type Output = Result<(Pin<W>, WriteStats), E>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let result = ready!(self.as_mut().poll_finish(cx));
+        if let Err(e) = &result {
+            self.metrics.write_error(e);
+        }
+        Poll::Ready(result)
+    }
+}
+
+impl<W, E> CommonRasterPageWriterFinishWithStats<W, E>
+where
+    W: DerefMut<Target: AsyncWrite>,
+    E: CommonRasterError,
+{
+    #[allow(clippy::type_complexity)]
+    fn poll_finish(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<(Pin<W>, WriteStats), E>> {
+        let this = self.project();
+        if *this.bytes_remaining > 0 {
+            return Poll::Ready(Err(E::incomplete_page(*this.bytes_remaining)));
+        }
+        let writer = match this.writer.as_mut() {
+            Some(writer) => writer,
+            None => return Poll::Ready(Err(io::Error::other("writer is already taken").into())),
+        };
+        ready!(poll_write_marker(
+            writer,
+            cx,
+            this.eop_marker,
+            this.eop_marker_written
+        ))?;
+        ready!(writer.as_mut().poll_flush(cx))?;
+        Poll::Ready(Ok((
+            this.writer.take().unwrap(),
+            std::mem::take(this.stats),
+        )))
+    }
+}
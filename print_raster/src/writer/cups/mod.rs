@@ -1,9 +1,14 @@
-use super::common::{CommonRasterPageWriter, CommonRasterPageWriterFor};
-use super::RasterWriter;
+use super::common::{
+    CommonRasterPageWriter, CommonRasterPageWriterFinishWithStats, CommonRasterPageWriterFor,
+};
+use super::{RasterWriter, WriteStats};
 use crate::error::CupsRasterError;
 use crate::factory::{
     CupsPageFactoryV1, CupsPageFactoryV2, CupsPageFactoryV3, RasterPageFactory, WithCupsSyncWord,
 };
+use crate::metrics::MetricsHandle;
+#[cfg(feature = "metrics")]
+use crate::metrics::RasterMetrics;
 use byteorder::{BigEndian, LittleEndian};
 use futures::{ready, AsyncWrite};
 use pin_project::pin_project;
@@ -12,10 +17,15 @@
 use std::marker::PhantomData;
 use std::ops::DerefMut;
 use std::pin::Pin;
+#[cfg(feature = "metrics")]
+use std::sync::Arc;
 use std::task::{Context, Poll};
+pub mod unified;
 
 pub struct CupsRasterWriter<F, W> {
     writer: Pin<W>,
+    stats: WriteStats,
+    metrics: MetricsHandle,
     _factory: PhantomData<F>,
 }
 
@@ -26,7 +36,7 @@ impl<W, F> CupsRasterWriter<F, W>
     W: DerefMut<Target: AsyncWrite>,
 {
     pub async fn new(mut writer: Pin<W>) -> Result<Self, CupsRasterError> {
-        let buffer = (F::sync_word() as u32).to_ne_bytes();
+        let buffer = F::sync_word().to_bytes();
         CupsRasterWriterWriteSyncWord {
             buffer,
             num_written: 0,
@@ -35,9 +45,21 @@ pub async fn new(mut writer: Pin<W>) -> Result<Self, CupsRasterError> {
         .await?;
         Ok(CupsRasterWriter {
             writer,
+            stats: WriteStats {
+                bytes_written: buffer.len() as u64,
+                ..Default::default()
+            },
+            metrics: MetricsHandle::default(),
             _factory: PhantomData,
         })
     }
+
+    /// Reports the pages written to `metrics`.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: Arc<dyn RasterMetrics>) -> Self {
+        self.metrics = MetricsHandle::new(Some(metrics));
+        self
+    }
 }
 
 impl<W, F> RasterWriter<W> for CupsRasterWriter<F, W>
@@ -48,21 +70,27 @@ impl<W, F> RasterWriter<W> for CupsRasterWriter<F, W>
     type PageHeader = F::Header;
     type PageWriter = CommonRasterPageWriter<F, W>;
     type Error = CupsRasterError;
-    type NextPageFuture<'a> = CommonRasterPageWriterFor<'a, F, W>
+    type NextPageFuture<'a>
+        = CommonRasterPageWriterFor<'a, F, W>
     where
         Self: 'a;
     type FinishFuture = futures::future::Ready<Result<(), CupsRasterError>>;
+    type FinishWithStatsFuture = CommonRasterPageWriterFinishWithStats<W, CupsRasterError>;
 
     fn next_page<'a>(self, header: &'a F::Header) -> Self::NextPageFuture<'a>
     where
         Self: 'a,
     {
-        CommonRasterPageWriter::writer_for(header, self.writer)
+        CommonRasterPageWriter::writer_for_with_stats(header, self.writer, self.stats, self.metrics)
     }
 
     fn finish(self) -> Self::FinishFuture {
         futures::future::ready(Ok(()))
     }
+
+    fn finish_with_stats(self) -> Self::FinishWithStatsFuture {
+        CommonRasterPageWriterFinishWithStats::new(self.writer, self.stats, self.metrics)
+    }
 }
 
 #[pin_project]
@@ -89,11 +117,7 @@ fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
                 break;
             }
             if num_written == 0 {
-                return Poll::Ready(Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "failed to write header",
-                )
-                .into()));
+                return Poll::Ready(Err(io::Error::other("failed to write header").into()));
             }
         }
         Poll::Ready(Ok(()))
@@ -0,0 +1,303 @@
+#![allow(clippy::enum_variant_names)]
+
+use super::CupsRasterWriterWriteSyncWord;
+use crate::encode::CupsRasterUnifiedEncoder;
+use crate::error::CupsRasterError;
+use crate::factory::{CupsPageFactoryV1, CupsPageFactoryV2, CupsPageFactoryV3};
+use crate::metrics::MetricsHandle;
+#[cfg(feature = "metrics")]
+use crate::metrics::RasterMetrics;
+use crate::model::cups::{CupsPageHeaderV2, CupsSyncWord};
+use crate::model::RasterByteOrder;
+use crate::writer::common::{CommonRasterPageWriterFinishWithStats, CommonRasterPageWriterFor};
+use crate::writer::{RasterWriter, WriteStats};
+use byteorder::{BigEndian, LittleEndian};
+use futures::AsyncWrite;
+use pin_project::pin_project;
+use std::future::Future;
+use std::ops::DerefMut;
+use std::pin::Pin;
+#[cfg(feature = "metrics")]
+use std::sync::Arc;
+use std::task::{Context, Poll};
+mod page;
+pub use page::*;
+
+/// A writer of CUPS raster streams whose version and byte order are chosen at runtime.
+///
+/// Pages are described by [`CupsPageHeaderV2`], of which only the
+/// [`v1`](CupsPageHeaderV2::v1) part is written to V1 streams.
+pub struct CupsRasterUnifiedWriter<W> {
+    sync_word: CupsSyncWord,
+    writer: Pin<W>,
+    stats: WriteStats,
+    metrics: MetricsHandle,
+    /// Whether `cups_compression` is set from the sync word, see
+    /// [`new_with_compression`](Self::new_with_compression).
+    sets_compression: bool,
+}
+
+/// Returns a copy of `header` with `cups_compression` set to `COMPRESSION`.
+fn with_cups_compression<const COMPRESSION: u32>(header: &CupsPageHeaderV2) -> CupsPageHeaderV2 {
+    let mut header = header.clone();
+    header.v1.cups_compression = COMPRESSION;
+    header
+}
+
+impl<T> CupsRasterUnifiedWriter<Box<T>>
+where
+    T: AsyncWrite,
+{
+    /// Like [`new`](Self::new), writing to an owned sink, e.g. a `Vec<u8>`,
+    /// pinned on the heap.
+    pub async fn from_writer(sink: T, sync_word: CupsSyncWord) -> Result<Self, CupsRasterError> {
+        Self::new(Box::pin(sink), sync_word).await
+    }
+}
+
+impl<W> CupsRasterUnifiedWriter<W>
+where
+    W: DerefMut<Target: AsyncWrite>,
+{
+    /// Writes `sync_word` and returns a writer for the stream of that version and byte order.
+    pub async fn new(mut writer: Pin<W>, sync_word: CupsSyncWord) -> Result<Self, CupsRasterError> {
+        let buffer = sync_word.to_bytes();
+        CupsRasterWriterWriteSyncWord {
+            buffer,
+            num_written: 0,
+            writer: writer.as_mut(),
+        }
+        .await?;
+        Ok(CupsRasterUnifiedWriter {
+            sync_word,
+            writer,
+            stats: WriteStats {
+                bytes_written: buffer.len() as u64,
+                ..Default::default()
+            },
+            metrics: MetricsHandle::default(),
+            sets_compression: false,
+        })
+    }
+
+    /// Like [`new`](Self::new), choosing whether the pixel data is compressed.
+    ///
+    /// CUPS streams with V2 page headers are always compressed under the V2 sync word,
+    /// so uncompressed pixel data is written under the V3 sync word, as in CUPS.
+    ///
+    /// The [`cups_compression`](crate::model::cups::CupsPageHeaderV1::cups_compression)
+    /// field of every page is written as 1 if `compressed` and 0 otherwise, regardless of
+    /// the headers given.
+    pub async fn new_with_compression(
+        writer: Pin<W>,
+        byte_order: RasterByteOrder,
+        compressed: bool,
+    ) -> Result<Self, CupsRasterError> {
+        let mut writer = Self::new(
+            writer,
+            CupsSyncWord::with_compression(byte_order, compressed),
+        )
+        .await?;
+        writer.sets_compression = true;
+        Ok(writer)
+    }
+
+    /// Reports the pages written to `metrics`.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: Arc<dyn RasterMetrics>) -> Self {
+        self.metrics = MetricsHandle::new(Some(metrics));
+        self
+    }
+
+    /// Resumes a stream after a page whose content was written without an encoder.
+    pub(crate) fn from_raw_parts(
+        sync_word: CupsSyncWord,
+        writer: Pin<W>,
+        stats: WriteStats,
+        metrics: MetricsHandle,
+        sets_compression: bool,
+    ) -> Self {
+        CupsRasterUnifiedWriter {
+            sync_word,
+            writer,
+            stats,
+            metrics,
+            sets_compression,
+        }
+    }
+
+    pub(crate) fn sets_compression(&self) -> bool {
+        self.sets_compression
+    }
+
+    pub fn sync_word(&self) -> CupsSyncWord {
+        self.sync_word
+    }
+
+    pub fn byte_order(&self) -> RasterByteOrder {
+        self.sync_word.byte_order()
+    }
+}
+
+impl<W> RasterWriter<W> for CupsRasterUnifiedWriter<W>
+where
+    W: DerefMut<Target: AsyncWrite>,
+{
+    type PageHeader = CupsPageHeaderV2;
+    type PageWriter = CupsRasterUnifiedPageWriter<W>;
+    type Error = CupsRasterError;
+    type NextPageFuture<'a>
+        = CupsRasterUnifiedWriterNextPage<'a, W>
+    where
+        Self: 'a;
+    type FinishFuture = futures::future::Ready<Result<(), CupsRasterError>>;
+    type FinishWithStatsFuture = CommonRasterPageWriterFinishWithStats<W, CupsRasterError>;
+
+    fn next_page<'a>(self, header: &'a CupsPageHeaderV2) -> Self::NextPageFuture<'a>
+    where
+        Self: 'a,
+    {
+        let (writer, stats, metrics) = (self.writer, self.stats, self.metrics);
+        let compressed = self
+            .sets_compression
+            .then_some(with_cups_compression::<1> as fn(&_) -> _);
+        let uncompressed = self
+            .sets_compression
+            .then_some(with_cups_compression::<0> as fn(&_) -> _);
+        match self.sync_word {
+            CupsSyncWord::V1BigEndian => CupsRasterUnifiedWriterNextPage::V1BigEndian(
+                CupsRasterUnifiedPageWriterV1BE::writer_for_with_stats(
+                    &header.v1, writer, stats, metrics,
+                ),
+            ),
+            CupsSyncWord::V1LittleEndian => CupsRasterUnifiedWriterNextPage::V1LittleEndian(
+                CupsRasterUnifiedPageWriterV1LE::writer_for_with_stats(
+                    &header.v1, writer, stats, metrics,
+                ),
+            ),
+            CupsSyncWord::V2BigEndian => CupsRasterUnifiedWriterNextPage::V2BigEndian(
+                CupsRasterUnifiedPageWriterV2BE::writer_for_with_stats(
+                    header, writer, stats, metrics,
+                )
+                .with_rewrite_header(compressed),
+            ),
+            CupsSyncWord::V2LittleEndian => CupsRasterUnifiedWriterNextPage::V2LittleEndian(
+                CupsRasterUnifiedPageWriterV2LE::writer_for_with_stats(
+                    header, writer, stats, metrics,
+                )
+                .with_rewrite_header(compressed),
+            ),
+            CupsSyncWord::V3BigEndian => CupsRasterUnifiedWriterNextPage::V3BigEndian(
+                CupsRasterUnifiedPageWriterV3BE::writer_for_with_stats(
+                    header, writer, stats, metrics,
+                )
+                .with_rewrite_header(uncompressed),
+            ),
+            CupsSyncWord::V3LittleEndian => CupsRasterUnifiedWriterNextPage::V3LittleEndian(
+                CupsRasterUnifiedPageWriterV3LE::writer_for_with_stats(
+                    header, writer, stats, metrics,
+                )
+                .with_rewrite_header(uncompressed),
+            ),
+        }
+    }
+
+    fn finish(self) -> Self::FinishFuture {
+        futures::future::ready(Ok(()))
+    }
+
+    fn finish_with_stats(self) -> Self::FinishWithStatsFuture {
+        CommonRasterPageWriterFinishWithStats::new(self.writer, self.stats, self.metrics)
+    }
+}
+
+#[pin_project(project = CupsRasterUnifiedWriterNextPageProj)]
+pub enum CupsRasterUnifiedWriterNextPage<'a, W>
+where
+    W: DerefMut<Target: AsyncWrite>,
+{
+    V1BigEndian(
+        #[pin]
+        CommonRasterPageWriterFor<
+            'a,
+            CupsPageFactoryV1<BigEndian>,
+            W,
+            CupsRasterUnifiedEncoder<W>,
+        >,
+    ),
+    V1LittleEndian(
+        #[pin]
+        CommonRasterPageWriterFor<
+            'a,
+            CupsPageFactoryV1<LittleEndian>,
+            W,
+            CupsRasterUnifiedEncoder<W>,
+        >,
+    ),
+    V2BigEndian(
+        #[pin]
+        CommonRasterPageWriterFor<
+            'a,
+            CupsPageFactoryV2<BigEndian>,
+            W,
+            CupsRasterUnifiedEncoder<W>,
+        >,
+    ),
+    V2LittleEndian(
+        #[pin]
+        CommonRasterPageWriterFor<
+            'a,
+            CupsPageFactoryV2<LittleEndian>,
+            W,
+            CupsRasterUnifiedEncoder<W>,
+        >,
+    ),
+    V3BigEndian(
+        #[pin]
+        CommonRasterPageWriterFor<
+            'a,
+            CupsPageFactoryV3<BigEndian>,
+            W,
+            CupsRasterUnifiedEncoder<W>,
+        >,
+    ),
+    V3LittleEndian(
+        #[pin]
+        CommonRasterPageWriterFor<
+            'a,
+            CupsPageFactoryV3<LittleEndian>,
+            W,
+            CupsRasterUnifiedEncoder<W>,
+        >,
+    ),
+}
+
+impl<W> Future for CupsRasterUnifiedWriterNextPage<'_, W>
+where
+    W: DerefMut<Target: AsyncWrite>,
+{
+    type Output = Result<CupsRasterUnifiedPageWriter<W>, CupsRasterError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            CupsRasterUnifiedWriterNextPageProj::V1BigEndian(writer) => writer
+                .poll(cx)
+                .map(|result| result.map(CupsRasterUnifiedPageWriter::from)),
+            CupsRasterUnifiedWriterNextPageProj::V1LittleEndian(writer) => writer
+                .poll(cx)
+                .map(|result| result.map(CupsRasterUnifiedPageWriter::from)),
+            CupsRasterUnifiedWriterNextPageProj::V2BigEndian(writer) => writer
+                .poll(cx)
+                .map(|result| result.map(CupsRasterUnifiedPageWriter::from)),
+            CupsRasterUnifiedWriterNextPageProj::V2LittleEndian(writer) => writer
+                .poll(cx)
+                .map(|result| result.map(CupsRasterUnifiedPageWriter::from)),
+            CupsRasterUnifiedWriterNextPageProj::V3BigEndian(writer) => writer
+                .poll(cx)
+                .map(|result| result.map(CupsRasterUnifiedPageWriter::from)),
+            CupsRasterUnifiedWriterNextPageProj::V3LittleEndian(writer) => writer
+                .poll(cx)
+                .map(|result| result.map(CupsRasterUnifiedPageWriter::from)),
+        }
+    }
+}
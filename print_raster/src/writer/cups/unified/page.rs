@@ -0,0 +1,263 @@
+#![allow(clippy::enum_variant_names)]
+
+use crate::{
+    encode::CupsRasterUnifiedEncoder,
+    error::CupsRasterError,
+    factory::{CupsPageFactoryV1, CupsPageFactoryV2, CupsPageFactoryV3},
+    metrics::MetricsHandle,
+    model::{cups::CupsPageHeaderV2, RasterByteOrder},
+    writer::common::{
+        CommonRasterPageWriter, CommonRasterPageWriterFinish,
+        CommonRasterPageWriterFinishWithStats, CommonRasterPageWriterNext,
+    },
+    writer::{RasterPageWriter, WriteStats},
+};
+use byteorder::{BigEndian, LittleEndian};
+use derive_more::From;
+use futures::task::Poll;
+use futures::{task::Context, AsyncWrite};
+use pin_project::pin_project;
+use std::{future::Future, ops::DerefMut, pin::Pin};
+
+pub type CupsRasterUnifiedPageWriterV1BE<W> =
+    CommonRasterPageWriter<CupsPageFactoryV1<BigEndian>, W, CupsRasterUnifiedEncoder<W>>;
+pub type CupsRasterUnifiedPageWriterV1LE<W> =
+    CommonRasterPageWriter<CupsPageFactoryV1<LittleEndian>, W, CupsRasterUnifiedEncoder<W>>;
+pub type CupsRasterUnifiedPageWriterV2BE<W> =
+    CommonRasterPageWriter<CupsPageFactoryV2<BigEndian>, W, CupsRasterUnifiedEncoder<W>>;
+pub type CupsRasterUnifiedPageWriterV2LE<W> =
+    CommonRasterPageWriter<CupsPageFactoryV2<LittleEndian>, W, CupsRasterUnifiedEncoder<W>>;
+pub type CupsRasterUnifiedPageWriterV3BE<W> =
+    CommonRasterPageWriter<CupsPageFactoryV3<BigEndian>, W, CupsRasterUnifiedEncoder<W>>;
+pub type CupsRasterUnifiedPageWriterV3LE<W> =
+    CommonRasterPageWriter<CupsPageFactoryV3<LittleEndian>, W, CupsRasterUnifiedEncoder<W>>;
+
+#[derive(From)]
+pub enum CupsRasterUnifiedPageWriter<W>
+where
+    W: DerefMut<Target: AsyncWrite>,
+{
+    V1BigEndian(CupsRasterUnifiedPageWriterV1BE<W>),
+    V1LittleEndian(CupsRasterUnifiedPageWriterV1LE<W>),
+    V2BigEndian(CupsRasterUnifiedPageWriterV2BE<W>),
+    V2LittleEndian(CupsRasterUnifiedPageWriterV2LE<W>),
+    V3BigEndian(CupsRasterUnifiedPageWriterV3BE<W>),
+    V3LittleEndian(CupsRasterUnifiedPageWriterV3LE<W>),
+}
+
+#[pin_project(project = CupsRasterUnifiedPageWriterNextProj)]
+pub enum CupsRasterUnifiedPageWriterNext<'a, W>
+where
+    W: DerefMut<Target: AsyncWrite>,
+{
+    V1BigEndian(
+        #[pin]
+        CommonRasterPageWriterNext<
+            'a,
+            CupsPageFactoryV1<BigEndian>,
+            W,
+            CupsRasterUnifiedEncoder<W>,
+        >,
+    ),
+    V1LittleEndian(
+        #[pin]
+        CommonRasterPageWriterNext<
+            'a,
+            CupsPageFactoryV1<LittleEndian>,
+            W,
+            CupsRasterUnifiedEncoder<W>,
+        >,
+    ),
+    V2BigEndian(
+        #[pin]
+        CommonRasterPageWriterNext<
+            'a,
+            CupsPageFactoryV2<BigEndian>,
+            W,
+            CupsRasterUnifiedEncoder<W>,
+        >,
+    ),
+    V2LittleEndian(
+        #[pin]
+        CommonRasterPageWriterNext<
+            'a,
+            CupsPageFactoryV2<LittleEndian>,
+            W,
+            CupsRasterUnifiedEncoder<W>,
+        >,
+    ),
+    V3BigEndian(
+        #[pin]
+        CommonRasterPageWriterNext<
+            'a,
+            CupsPageFactoryV3<BigEndian>,
+            W,
+            CupsRasterUnifiedEncoder<W>,
+        >,
+    ),
+    V3LittleEndian(
+        #[pin]
+        CommonRasterPageWriterNext<
+            'a,
+            CupsPageFactoryV3<LittleEndian>,
+            W,
+            CupsRasterUnifiedEncoder<W>,
+        >,
+    ),
+}
+
+impl<W> Future for CupsRasterUnifiedPageWriterNext<'_, W>
+where
+    W: DerefMut<Target: AsyncWrite>,
+{
+    type Output = Result<CupsRasterUnifiedPageWriter<W>, CupsRasterError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        match self.project() {
+            CupsRasterUnifiedPageWriterNextProj::V1BigEndian(fut) => fut
+                .poll(cx)
+                .map(|result| result.map(CupsRasterUnifiedPageWriter::from)),
+            CupsRasterUnifiedPageWriterNextProj::V1LittleEndian(fut) => fut
+                .poll(cx)
+                .map(|result| result.map(CupsRasterUnifiedPageWriter::from)),
+            CupsRasterUnifiedPageWriterNextProj::V2BigEndian(fut) => fut
+                .poll(cx)
+                .map(|result| result.map(CupsRasterUnifiedPageWriter::from)),
+            CupsRasterUnifiedPageWriterNextProj::V2LittleEndian(fut) => fut
+                .poll(cx)
+                .map(|result| result.map(CupsRasterUnifiedPageWriter::from)),
+            CupsRasterUnifiedPageWriterNextProj::V3BigEndian(fut) => fut
+                .poll(cx)
+                .map(|result| result.map(CupsRasterUnifiedPageWriter::from)),
+            CupsRasterUnifiedPageWriterNextProj::V3LittleEndian(fut) => fut
+                .poll(cx)
+                .map(|result| result.map(CupsRasterUnifiedPageWriter::from)),
+        }
+    }
+}
+
+impl<W> CupsRasterUnifiedPageWriter<W>
+where
+    W: DerefMut<Target: AsyncWrite>,
+{
+    pub fn byte_order(&self) -> RasterByteOrder {
+        match self {
+            CupsRasterUnifiedPageWriter::V1BigEndian(_) => RasterByteOrder::BigEndian,
+            CupsRasterUnifiedPageWriter::V1LittleEndian(_) => RasterByteOrder::LittleEndian,
+            CupsRasterUnifiedPageWriter::V2BigEndian(_) => RasterByteOrder::BigEndian,
+            CupsRasterUnifiedPageWriter::V2LittleEndian(_) => RasterByteOrder::LittleEndian,
+            CupsRasterUnifiedPageWriter::V3BigEndian(_) => RasterByteOrder::BigEndian,
+            CupsRasterUnifiedPageWriter::V3LittleEndian(_) => RasterByteOrder::LittleEndian,
+        }
+    }
+
+    /// Returns the statistics of the stream, not including the current page.
+    pub fn stats(&self) -> &WriteStats {
+        match self {
+            CupsRasterUnifiedPageWriter::V1BigEndian(writer) => writer.stats(),
+            CupsRasterUnifiedPageWriter::V1LittleEndian(writer) => writer.stats(),
+            CupsRasterUnifiedPageWriter::V2BigEndian(writer) => writer.stats(),
+            CupsRasterUnifiedPageWriter::V2LittleEndian(writer) => writer.stats(),
+            CupsRasterUnifiedPageWriter::V3BigEndian(writer) => writer.stats(),
+            CupsRasterUnifiedPageWriter::V3LittleEndian(writer) => writer.stats(),
+        }
+    }
+
+    pub(crate) fn into_raw_parts(self) -> (Pin<W>, WriteStats, MetricsHandle) {
+        match self {
+            CupsRasterUnifiedPageWriter::V1BigEndian(writer) => writer.into_raw_parts(),
+            CupsRasterUnifiedPageWriter::V1LittleEndian(writer) => writer.into_raw_parts(),
+            CupsRasterUnifiedPageWriter::V2BigEndian(writer) => writer.into_raw_parts(),
+            CupsRasterUnifiedPageWriter::V2LittleEndian(writer) => writer.into_raw_parts(),
+            CupsRasterUnifiedPageWriter::V3BigEndian(writer) => writer.into_raw_parts(),
+            CupsRasterUnifiedPageWriter::V3LittleEndian(writer) => writer.into_raw_parts(),
+        }
+    }
+}
+
+impl<W> RasterPageWriter<W> for CupsRasterUnifiedPageWriter<W>
+where
+    W: DerefMut<Target: AsyncWrite>,
+{
+    type Header = CupsPageHeaderV2;
+    type Encoder = CupsRasterUnifiedEncoder<W>;
+    type Error = CupsRasterError;
+    type NextPageFuture<'a>
+        = CupsRasterUnifiedPageWriterNext<'a, W>
+    where
+        Self: 'a;
+    type FinishFuture = CommonRasterPageWriterFinish<W, CupsRasterError>;
+    type FinishWithStatsFuture = CommonRasterPageWriterFinishWithStats<W, CupsRasterError>;
+
+    /// Starts the next page, writing only the [`v1`](CupsPageHeaderV2::v1) part
+    /// of `header` to V1 streams.
+    fn next_page<'a>(self, header: &'a Self::Header) -> Self::NextPageFuture<'a>
+    where
+        Self: 'a,
+    {
+        match self {
+            CupsRasterUnifiedPageWriter::V1BigEndian(writer) => {
+                CupsRasterUnifiedPageWriterNext::V1BigEndian(writer.next_page(&header.v1))
+            }
+            CupsRasterUnifiedPageWriter::V1LittleEndian(writer) => {
+                CupsRasterUnifiedPageWriterNext::V1LittleEndian(writer.next_page(&header.v1))
+            }
+            CupsRasterUnifiedPageWriter::V2BigEndian(writer) => {
+                CupsRasterUnifiedPageWriterNext::V2BigEndian(writer.next_page(header))
+            }
+            CupsRasterUnifiedPageWriter::V2LittleEndian(writer) => {
+                CupsRasterUnifiedPageWriterNext::V2LittleEndian(writer.next_page(header))
+            }
+            CupsRasterUnifiedPageWriter::V3BigEndian(writer) => {
+                CupsRasterUnifiedPageWriterNext::V3BigEndian(writer.next_page(header))
+            }
+            CupsRasterUnifiedPageWriter::V3LittleEndian(writer) => {
+                CupsRasterUnifiedPageWriterNext::V3LittleEndian(writer.next_page(header))
+            }
+        }
+    }
+
+    fn finish(self) -> Self::FinishFuture {
+        match self {
+            CupsRasterUnifiedPageWriter::V1BigEndian(writer) => writer.finish(),
+            CupsRasterUnifiedPageWriter::V1LittleEndian(writer) => writer.finish(),
+            CupsRasterUnifiedPageWriter::V2BigEndian(writer) => writer.finish(),
+            CupsRasterUnifiedPageWriter::V2LittleEndian(writer) => writer.finish(),
+            CupsRasterUnifiedPageWriter::V3BigEndian(writer) => writer.finish(),
+            CupsRasterUnifiedPageWriter::V3LittleEndian(writer) => writer.finish(),
+        }
+    }
+
+    fn finish_with_stats(self) -> Self::FinishWithStatsFuture {
+        match self {
+            CupsRasterUnifiedPageWriter::V1BigEndian(writer) => writer.finish_with_stats(),
+            CupsRasterUnifiedPageWriter::V1LittleEndian(writer) => writer.finish_with_stats(),
+            CupsRasterUnifiedPageWriter::V2BigEndian(writer) => writer.finish_with_stats(),
+            CupsRasterUnifiedPageWriter::V2LittleEndian(writer) => writer.finish_with_stats(),
+            CupsRasterUnifiedPageWriter::V3BigEndian(writer) => writer.finish_with_stats(),
+            CupsRasterUnifiedPageWriter::V3LittleEndian(writer) => writer.finish_with_stats(),
+        }
+    }
+
+    fn content_mut(&mut self) -> &mut Self::Encoder {
+        match self {
+            CupsRasterUnifiedPageWriter::V1BigEndian(writer) => writer.content_mut(),
+            CupsRasterUnifiedPageWriter::V1LittleEndian(writer) => writer.content_mut(),
+            CupsRasterUnifiedPageWriter::V2BigEndian(writer) => writer.content_mut(),
+            CupsRasterUnifiedPageWriter::V2LittleEndian(writer) => writer.content_mut(),
+            CupsRasterUnifiedPageWriter::V3BigEndian(writer) => writer.content_mut(),
+            CupsRasterUnifiedPageWriter::V3LittleEndian(writer) => writer.content_mut(),
+        }
+    }
+
+    fn into_content(self) -> Self::Encoder {
+        match self {
+            CupsRasterUnifiedPageWriter::V1BigEndian(writer) => writer.into_content(),
+            CupsRasterUnifiedPageWriter::V1LittleEndian(writer) => writer.into_content(),
+            CupsRasterUnifiedPageWriter::V2BigEndian(writer) => writer.into_content(),
+            CupsRasterUnifiedPageWriter::V2LittleEndian(writer) => writer.into_content(),
+            CupsRasterUnifiedPageWriter::V3BigEndian(writer) => writer.into_content(),
+            CupsRasterUnifiedPageWriter::V3LittleEndian(writer) => writer.into_content(),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use futures::task::noop_waker_ref;
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll};
+
+/// Polls `future` to completion on the current thread without an executor.
+///
+/// This is meant for writers whose underlying I/O is always ready, e.g. a `Vec<u8>` or
+/// a [`futures::io::AllowStdIo`] around a blocking `std::io::Write`: their futures never
+/// wait for a wakeup, so a single poll with a no-op waker drives them to the end.
+///
+/// Returns `None` if the future is pending, i.e. the I/O is not always ready after all.
+/// The future is dropped in that case, as there is nothing that could wake it up again;
+/// use an executor (or the `sync` feature for readers) for such I/O instead.
+///
+/// # Example
+/// ```
+/// use print_raster::model::urf::{UrfColorSpace, UrfHeader, UrfPageHeader};
+/// use print_raster::writer::urf::UrfWriter;
+/// use print_raster::writer::{drive_to_completion, RasterWriter, RasterWriterExt};
+/// use print_raster::writer::RasterPageWriter;
+/// use std::pin::Pin;
+///
+/// let header = UrfPageHeader::builder()
+///     .width(4)
+///     .height(4)
+///     .bits_per_pixel(8)
+///     .color_space(UrfColorSpace::sGray)
+///     .dot_per_inch(300)
+///     .build()
+///     .unwrap();
+/// let mut output = Vec::new();
+/// drive_to_completion(async {
+///     let writer = UrfWriter::new(Pin::new(&mut output), &UrfHeader { page_count: 1 }).await?;
+///     writer.write_page(&header, &[0xff; 16]).await?.finish().await
+/// })
+/// .expect("writing to a Vec is always ready")
+/// .unwrap();
+/// assert!(output.starts_with(b"UNIRAST\0"));
+/// ```
+pub fn drive_to_completion<F>(future: F) -> Option<F::Output>
+where
+    F: Future,
+{
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(noop_waker_ref());
+    match future.as_mut().poll(&mut cx) {
+        Poll::Ready(output) => Some(output),
+        Poll::Pending => None,
+    }
+}
@@ -0,0 +1,80 @@
+use crate::error::ImageConversionError;
+use crate::model::cups::{CupsColorSpace, CupsPageHeaderV1, CupsPageHeaderV2};
+use crate::model::urf::{
+    UrfColorSpace, UrfDuplex, UrfMediaPosition, UrfMediaType, UrfPageHeader, UrfQuality,
+};
+use ::image::{ColorType, DynamicImage};
+
+/// A page header derived from an image of the `image` crate, see
+/// [`RasterWriterExt::write_image`](super::RasterWriterExt::write_image).
+///
+/// Only gray and RGB images of 8 bits per color are supported, whose pixel buffer
+/// is the page content as is.
+pub trait ImagePageHeader: Sized {
+    /// Creates the header of a page holding `image`, printed at `dpi` dots per inch
+    /// in both directions.
+    fn from_image(image: &DynamicImage, dpi: u32) -> Result<Self, ImageConversionError>;
+}
+
+/// Checks that the pixel buffer of `image` can be written as the content of a page.
+/// Returns whether the image is gray.
+fn check_image(image: &DynamicImage, dpi: u32) -> Result<bool, ImageConversionError> {
+    if dpi == 0 {
+        return Err(ImageConversionError::InvalidResolution(dpi));
+    }
+    match image.color() {
+        ColorType::L8 => Ok(true),
+        ColorType::Rgb8 => Ok(false),
+        color_type => Err(ImageConversionError::UnsupportedColorType(color_type)),
+    }
+}
+
+impl ImagePageHeader for CupsPageHeaderV1 {
+    fn from_image(image: &DynamicImage, dpi: u32) -> Result<Self, ImageConversionError> {
+        let is_gray = check_image(image, dpi)?;
+        Ok(CupsPageHeaderV1::builder()
+            .width(image.width())
+            .height(image.height())
+            .color_space(if is_gray {
+                CupsColorSpace::sGray
+            } else {
+                CupsColorSpace::sRGB
+            })
+            .resolution(dpi, dpi)
+            .page_size(points(image.width(), dpi), points(image.height(), dpi))
+            .build())
+    }
+}
+
+impl ImagePageHeader for CupsPageHeaderV2 {
+    fn from_image(image: &DynamicImage, dpi: u32) -> Result<Self, ImageConversionError> {
+        CupsPageHeaderV1::from_image(image, dpi).map(CupsPageHeaderV2::from)
+    }
+}
+
+impl ImagePageHeader for UrfPageHeader {
+    fn from_image(image: &DynamicImage, dpi: u32) -> Result<Self, ImageConversionError> {
+        let is_gray = check_image(image, dpi)?;
+        let (bits_per_pixel, color_space) = if is_gray {
+            (8, UrfColorSpace::sGray)
+        } else {
+            (24, UrfColorSpace::sRGB)
+        };
+        Ok(UrfPageHeader {
+            bits_per_pixel,
+            color_space,
+            duplex: UrfDuplex::NoDuplex,
+            quality: UrfQuality::Normal,
+            media_position: UrfMediaPosition::Auto,
+            media_type: UrfMediaType::Auto,
+            width: image.width(),
+            height: image.height(),
+            dot_per_inch: dpi,
+        })
+    }
+}
+
+/// Converts a length in pixels to points, rounded to the nearest point.
+fn points(pixels: u32, dpi: u32) -> u32 {
+    ((pixels as u64 * 72 + dpi as u64 / 2) / dpi as u64) as u32
+}
@@ -1,7 +1,20 @@
+#[cfg(feature = "image")]
+use super::ImagePageHeader;
+use super::WriteStats;
 use crate::encode::RasterEncoder;
-use futures::AsyncWrite;
-use std::{future::Future, ops::DerefMut};
+#[cfg(feature = "image")]
+use crate::error::ImageConversionError;
+use crate::model::RasterPageMetadata;
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use std::{future::Future, io, ops::DerefMut, pin::Pin};
 
+/// Writes the content of a page and starts the next page or finishes the stream.
+///
+/// A page writer must be consumed by [`next_page`](Self::next_page), [`finish`](Self::finish)
+/// or [`finish_with_stats`](Self::finish_with_stats). Dropping it instead is unsupported:
+/// the underlying writer is neither flushed nor closed, and content buffered by the encoder
+/// is lost, leaving a truncated stream. Use [`RasterPageWriterExt::abort`] to give up
+/// on a stream explicitly.
 pub trait RasterPageWriter<W>: Sized
 where
     W: DerefMut<Target: AsyncWrite>,
@@ -13,10 +26,14 @@ pub trait RasterPageWriter<W>: Sized
     where
         Self: 'a;
     type FinishFuture: Future<Output = Result<(), Self::Error>>;
+    type FinishWithStatsFuture: Future<Output = Result<(Pin<W>, WriteStats), Self::Error>>;
     fn next_page<'a>(self, header: &'a Self::Header) -> Self::NextPageFuture<'a>
     where
         Self: 'a;
     fn finish(self) -> Self::FinishFuture;
+    /// Finishes the stream without closing the underlying writer,
+    /// returning it together with the statistics of the written stream.
+    fn finish_with_stats(self) -> Self::FinishWithStatsFuture;
     fn content_mut(&mut self) -> &mut Self::Encoder;
     fn into_content(self) -> Self::Encoder;
 }
@@ -32,8 +49,251 @@ pub trait RasterWriter<W>: Sized
     where
         Self: 'a;
     type FinishFuture: Future<Output = Result<(), Self::Error>>;
+    type FinishWithStatsFuture: Future<Output = Result<(Pin<W>, WriteStats), Self::Error>>;
     fn next_page<'a>(self, header: &'a Self::PageHeader) -> Self::NextPageFuture<'a>
     where
         Self: 'a;
     fn finish(self) -> Self::FinishFuture;
+    /// Finishes the stream without closing the underlying writer,
+    /// returning it together with the statistics of the written stream.
+    fn finish_with_stats(self) -> Self::FinishWithStatsFuture;
+}
+
+/// Writes `content` as the whole content of the page just started by `page`.
+async fn write_full_content<P, W>(page: &mut P, content: &[u8]) -> io::Result<()>
+where
+    P: RasterPageWriter<W>,
+    P::Encoder: Unpin,
+    W: DerefMut<Target: AsyncWrite>,
+{
+    check_content_len(content, page.content_mut().bytes_remaining())?;
+    page.content_mut().write_all(content).await
+}
+
+fn check_content_len(content: &[u8], expected: u64) -> io::Result<()> {
+    if content.len() as u64 != expected {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "page content is {} bytes, but the header declares {} bytes",
+                content.len(),
+                expected
+            ),
+        ));
+    }
+    Ok(())
+}
+
+pub trait RasterWriterExt<W>: RasterWriter<W>
+where
+    W: DerefMut<Target: AsyncWrite>,
+{
+    /// Starts the first page with `header` and writes `content` as its whole content.
+    ///
+    /// Fails if the length of `content` doesn't match the size declared by `header`,
+    /// without writing the header as long as the size is known from
+    /// [`RasterPageMetadata::content_len`].
+    fn write_page<'a>(
+        self,
+        header: &'a Self::PageHeader,
+        content: &'a [u8],
+    ) -> impl Future<Output = Result<Self::PageWriter, Self::Error>> + 'a
+    where
+        Self: 'a,
+        Self::PageHeader: RasterPageMetadata,
+        Self::Error: From<io::Error>,
+        <Self::PageWriter as RasterPageWriter<W>>::Encoder: Unpin,
+    {
+        async move {
+            if let Some(expected) = header.content_len() {
+                check_content_len(content, expected)?;
+            }
+            let mut page = self.next_page(header).await?;
+            write_full_content(&mut page, content).await?;
+            Ok(page)
+        }
+    }
+
+    /// Starts the first page with a header derived from `image`, see [`ImagePageHeader`],
+    /// and writes the pixels of `image` as its whole content.
+    #[cfg(feature = "image")]
+    fn write_image<'a>(
+        self,
+        image: &'a ::image::DynamicImage,
+        dpi: u32,
+    ) -> impl Future<Output = Result<Self::PageWriter, Self::Error>> + 'a
+    where
+        Self: 'a,
+        Self::PageHeader: ImagePageHeader,
+        Self::Error: From<io::Error> + From<ImageConversionError>,
+        <Self::PageWriter as RasterPageWriter<W>>::Encoder: Unpin,
+    {
+        async move {
+            let header = Self::PageHeader::from_image(image, dpi)?;
+            let mut page = self.next_page(&header).await?;
+            write_full_content(&mut page, image.as_bytes()).await?;
+            Ok(page)
+        }
+    }
+}
+
+impl<T, W> RasterWriterExt<W> for T
+where
+    T: RasterWriter<W>,
+    W: DerefMut<Target: AsyncWrite>,
+{
+}
+
+pub trait RasterPageWriterExt<W>: RasterPageWriter<W>
+where
+    W: DerefMut<Target: AsyncWrite>,
+{
+    /// Starts the next page with `header` and writes `content` as its whole content.
+    ///
+    /// Fails if the length of `content` doesn't match the size declared by `header`,
+    /// without writing the header as long as the size is known from
+    /// [`RasterPageMetadata::content_len`].
+    fn write_page<'a>(
+        self,
+        header: &'a Self::Header,
+        content: &'a [u8],
+    ) -> impl Future<Output = Result<Self, Self::Error>> + 'a
+    where
+        Self: 'a,
+        Self::Header: RasterPageMetadata,
+        Self::Error: From<io::Error>,
+        Self::Encoder: Unpin,
+    {
+        async move {
+            if let Some(expected) = header.content_len() {
+                check_content_len(content, expected)?;
+            }
+            let mut page = self.next_page(header).await?;
+            write_full_content(&mut page, content).await?;
+            Ok(page)
+        }
+    }
+
+    /// Starts the next page with a header derived from `image`, see [`ImagePageHeader`],
+    /// and writes the pixels of `image` as its whole content.
+    #[cfg(feature = "image")]
+    fn write_image<'a>(
+        self,
+        image: &'a ::image::DynamicImage,
+        dpi: u32,
+    ) -> impl Future<Output = Result<Self, Self::Error>> + 'a
+    where
+        Self: 'a,
+        Self::Header: ImagePageHeader,
+        Self::Error: From<io::Error> + From<ImageConversionError>,
+        Self::Encoder: Unpin,
+    {
+        async move {
+            let header = Self::Header::from_image(image, dpi)?;
+            let mut page = self.next_page(&header).await?;
+            write_full_content(&mut page, image.as_bytes()).await?;
+            Ok(page)
+        }
+    }
+
+    /// Writes a single line of the page content.
+    ///
+    /// Fails if the length of `row` is not the line size of the encoder, see
+    /// [`RasterEncoder::bytes_per_line`], if the encoder doesn't know its line size,
+    /// or if the page has no room left for it.
+    fn write_row<'a>(
+        &'a mut self,
+        row: &'a [u8],
+    ) -> impl Future<Output = Result<(), Self::Error>> + 'a
+    where
+        Self::Error: From<io::Error>,
+        Self::Encoder: Unpin,
+    {
+        async move {
+            let content = self.content_mut();
+            let bytes_per_line = content.bytes_per_line();
+            if bytes_per_line == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the line size of the page is unknown, write the content as a whole",
+                )
+                .into());
+            }
+            if row.len() as u64 != bytes_per_line {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "row is {} bytes, but a line is {} bytes",
+                        row.len(),
+                        bytes_per_line
+                    ),
+                )
+                .into());
+            }
+            if row.len() as u64 > content.bytes_remaining() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "all lines of the page are already written",
+                )
+                .into());
+            }
+            content.write_all(row).await?;
+            Ok(())
+        }
+    }
+
+    /// Gives up on the stream, discarding the content buffered by the encoder,
+    /// and returns the underlying writer as is, e.g. to truncate or delete the output.
+    ///
+    /// The stream written so far ends with an incomplete page.
+    fn abort(self) -> Pin<W> {
+        self.into_content().into_pin_mut()
+    }
+
+    /// Copies the remaining content of the current page from `source`.
+    ///
+    /// Reads no more than the bytes the page still expects, so `source` may go on with
+    /// other data, and fails if `source` ends before the page is complete.
+    /// Returns the number of bytes copied.
+    fn write_from<'a, S>(
+        &'a mut self,
+        mut source: S,
+    ) -> impl Future<Output = Result<u64, Self::Error>> + 'a
+    where
+        S: AsyncRead + Unpin + 'a,
+        Self::Error: From<io::Error>,
+        Self::Encoder: Unpin,
+    {
+        async move {
+            let mut buf = vec![0u8; 8192];
+            let mut copied = 0u64;
+            loop {
+                let remaining = self.content_mut().bytes_remaining();
+                if remaining == 0 {
+                    return Ok(copied);
+                }
+                let len = remaining.min(buf.len() as u64) as usize;
+                let num_read = source.read(&mut buf[..len]).await?;
+                if num_read == 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!(
+                            "source ended with {} bytes of page content remaining",
+                            remaining
+                        ),
+                    )
+                    .into());
+                }
+                self.content_mut().write_all(&buf[..num_read]).await?;
+                copied += num_read as u64;
+            }
+        }
+    }
+}
+
+impl<T, W> RasterPageWriterExt<W> for T
+where
+    T: RasterPageWriter<W>,
+    W: DerefMut<Target: AsyncWrite>,
+{
 }
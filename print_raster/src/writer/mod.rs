@@ -1,5 +1,17 @@
+mod bounded;
 pub mod common;
 pub mod cups;
+mod drive;
+#[cfg(feature = "image")]
+mod image;
 mod interface;
+mod stats;
+#[cfg(feature = "tokio")]
+pub mod tokio;
 pub mod urf;
+#[cfg(feature = "image")]
+pub use self::image::*;
+pub use bounded::*;
+pub use drive::*;
 pub use interface::*;
+pub use stats::*;
@@ -0,0 +1,19 @@
+/// Statistics collected while writing a raster stream.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteStats {
+    /// Total number of bytes written, including stream and page headers.
+    pub bytes_written: u64,
+    /// Encoded size of each finished page, excluding its header.
+    pub page_sizes: Vec<u64>,
+}
+
+impl WriteStats {
+    pub fn pages_written(&self) -> usize {
+        self.page_sizes.len()
+    }
+
+    pub(crate) fn add_page(&mut self, encoded_size: u64) {
+        self.bytes_written += encoded_size;
+        self.page_sizes.push(encoded_size);
+    }
+}
@@ -0,0 +1,156 @@
+//! Writers over [`tokio::io::AsyncWrite`] sinks.
+//!
+//! These wrap the writers of this crate, adapting the sink to the `futures` I/O traits,
+//! so `tokio` users don't need to go through `tokio_util::compat` themselves.
+
+use super::cups::unified::CupsRasterUnifiedWriter;
+use super::urf::UrfWriter as FuturesUrfWriter;
+use super::RasterWriter;
+use crate::error::{CupsRasterError, UrfError};
+#[cfg(feature = "metrics")]
+use crate::metrics::RasterMetrics;
+use crate::model::cups::{CupsPageHeaderV2, CupsSyncWord};
+use crate::model::urf::{UrfHeader, UrfPageHeader};
+use crate::model::RasterByteOrder;
+use ::tokio::io::AsyncWrite;
+#[cfg(feature = "metrics")]
+use std::sync::Arc;
+use tokio_util::compat::{Compat, TokioAsyncWriteCompatExt};
+
+/// The `futures` writer adapted from a `tokio` sink.
+pub type TokioWriter<T> = Box<Compat<T>>;
+
+type CupsInner<T> = CupsRasterUnifiedWriter<TokioWriter<T>>;
+type UrfInner<T> = FuturesUrfWriter<TokioWriter<T>>;
+
+/// A writer of CUPS raster streams whose version and byte order are chosen at runtime.
+pub struct CupsRasterWriter<T> {
+    inner: CupsInner<T>,
+}
+
+impl<T> CupsRasterWriter<T>
+where
+    T: AsyncWrite,
+{
+    /// Writes `sync_word` and returns a writer for the stream of that version and byte order.
+    ///
+    /// As with the `futures` writer, `sink` should be buffered,
+    /// e.g. by `tokio::io::BufWriter`.
+    pub async fn new(sink: T, sync_word: CupsSyncWord) -> Result<Self, CupsRasterError> {
+        let writer = Box::pin(sink.compat_write());
+        let inner = CupsRasterUnifiedWriter::new(writer, sync_word).await?;
+        Ok(Self { inner })
+    }
+
+    /// Reports the pages written to `metrics`.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(self, metrics: Arc<dyn RasterMetrics>) -> Self {
+        Self {
+            inner: self.inner.with_metrics(metrics),
+        }
+    }
+
+    pub fn sync_word(&self) -> CupsSyncWord {
+        self.inner.sync_word()
+    }
+
+    pub fn byte_order(&self) -> RasterByteOrder {
+        self.inner.byte_order()
+    }
+
+    pub fn into_inner(self) -> CupsInner<T> {
+        self.inner
+    }
+}
+
+impl<T> RasterWriter<TokioWriter<T>> for CupsRasterWriter<T>
+where
+    T: AsyncWrite,
+{
+    type PageHeader = CupsPageHeaderV2;
+    type PageWriter = <CupsInner<T> as RasterWriter<TokioWriter<T>>>::PageWriter;
+    type Error = CupsRasterError;
+    type NextPageFuture<'a>
+        = <CupsInner<T> as RasterWriter<TokioWriter<T>>>::NextPageFuture<'a>
+    where
+        Self: 'a;
+    type FinishFuture = <CupsInner<T> as RasterWriter<TokioWriter<T>>>::FinishFuture;
+    type FinishWithStatsFuture =
+        <CupsInner<T> as RasterWriter<TokioWriter<T>>>::FinishWithStatsFuture;
+
+    fn next_page<'a>(self, header: &'a CupsPageHeaderV2) -> Self::NextPageFuture<'a>
+    where
+        Self: 'a,
+    {
+        self.inner.next_page(header)
+    }
+
+    fn finish(self) -> Self::FinishFuture {
+        self.inner.finish()
+    }
+
+    fn finish_with_stats(self) -> Self::FinishWithStatsFuture {
+        self.inner.finish_with_stats()
+    }
+}
+
+/// A writer of URF (Apple Raster) streams.
+pub struct UrfWriter<T> {
+    inner: UrfInner<T>,
+}
+
+impl<T> UrfWriter<T>
+where
+    T: AsyncWrite,
+{
+    /// As with the `futures` writer, `sink` should be buffered,
+    /// e.g. by `tokio::io::BufWriter`.
+    pub async fn new(sink: T, header: &UrfHeader) -> Result<Self, UrfError> {
+        let writer = Box::pin(sink.compat_write());
+        let inner = FuturesUrfWriter::new(writer, header).await?;
+        Ok(Self { inner })
+    }
+
+    /// Reports the pages written to `metrics`.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(self, metrics: Arc<dyn RasterMetrics>) -> Self {
+        Self {
+            inner: self.inner.with_metrics(metrics),
+        }
+    }
+
+    pub fn into_inner(self) -> UrfInner<T> {
+        self.inner
+    }
+}
+
+impl<T> RasterWriter<TokioWriter<T>> for UrfWriter<T>
+where
+    T: AsyncWrite,
+{
+    type PageHeader = UrfPageHeader;
+    type PageWriter = <UrfInner<T> as RasterWriter<TokioWriter<T>>>::PageWriter;
+    type Error = UrfError;
+    type NextPageFuture<'a>
+        = <UrfInner<T> as RasterWriter<TokioWriter<T>>>::NextPageFuture<'a>
+    where
+        Self: 'a;
+    type FinishFuture = <UrfInner<T> as RasterWriter<TokioWriter<T>>>::FinishFuture;
+    type FinishWithStatsFuture =
+        <UrfInner<T> as RasterWriter<TokioWriter<T>>>::FinishWithStatsFuture;
+
+    fn next_page<'a>(self, header: &'a UrfPageHeader) -> Self::NextPageFuture<'a>
+    where
+        Self: 'a,
+    {
+        self.inner.next_page(header)
+    }
+
+    fn finish(self) -> Self::FinishFuture {
+        self.inner.finish()
+    }
+
+    fn finish_with_stats(self) -> Self::FinishWithStatsFuture {
+        self.inner.finish_with_stats()
+    }
+}
@@ -1,29 +1,50 @@
-use super::common::{CommonRasterPageWriter, CommonRasterPageWriterFor};
-use super::RasterWriter;
+use super::common::{
+    CommonRasterPageWriter, CommonRasterPageWriterFinishWithStats, CommonRasterPageWriterFor,
+};
+use super::{RasterPageWriter, RasterWriter, WriteStats};
 use crate::error::UrfError;
 use crate::factory::UrfPageFactory;
-use crate::model::urf::{UrfHeader, UrfPageHeader};
-use futures::{ready, AsyncWrite};
+use crate::metrics::MetricsHandle;
+#[cfg(feature = "metrics")]
+use crate::metrics::RasterMetrics;
+use crate::model::urf::{UrfHeader, UrfPageHeader, URF_HEADER_SIZE, URF_MAGIC};
+use futures::io::SeekFrom;
+use futures::{ready, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
 use pin_project::pin_project;
 use std::future::Future;
 use std::io;
 use std::ops::DerefMut;
 use std::pin::Pin;
+#[cfg(feature = "metrics")]
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 pub struct UrfWriter<W> {
     writer: Pin<W>,
+    stats: WriteStats,
+    metrics: MetricsHandle,
 }
 
 pub type UrfPageWriter<W> = CommonRasterPageWriter<UrfPageFactory, W>;
 
+impl<T> UrfWriter<Box<T>>
+where
+    T: AsyncWrite,
+{
+    /// Like [`new`](Self::new), writing to an owned sink, e.g. a `Vec<u8>`,
+    /// pinned on the heap.
+    pub async fn from_writer(sink: T, header: &UrfHeader) -> Result<Self, UrfError> {
+        Self::new(Box::pin(sink), header).await
+    }
+}
+
 impl<W> UrfWriter<W>
 where
     W: DerefMut<Target: AsyncWrite>,
 {
     pub async fn new(mut writer: Pin<W>, header: &UrfHeader) -> Result<Self, UrfError> {
-        let mut buffer = [0u8; 12];
-        buffer[..8].copy_from_slice(b"UNIRAST\0");
+        let mut buffer = [0u8; URF_HEADER_SIZE];
+        buffer[..8].copy_from_slice(URF_MAGIC);
         buffer[8..12].copy_from_slice(&header.page_count.to_be_bytes());
         UrfWriteHeaderFuture {
             buffer,
@@ -31,7 +52,21 @@ pub async fn new(mut writer: Pin<W>, header: &UrfHeader) -> Result<Self, UrfErro
             writer: writer.as_mut(),
         }
         .await?;
-        Ok(UrfWriter { writer })
+        Ok(UrfWriter {
+            writer,
+            stats: WriteStats {
+                bytes_written: buffer.len() as u64,
+                ..Default::default()
+            },
+            metrics: MetricsHandle::default(),
+        })
+    }
+
+    /// Reports the pages written to `metrics`.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: Arc<dyn RasterMetrics>) -> Self {
+        self.metrics = MetricsHandle::new(Some(metrics));
+        self
     }
 }
 
@@ -42,26 +77,75 @@ impl<W> RasterWriter<W> for UrfWriter<W>
     type PageHeader = UrfPageHeader;
     type PageWriter = CommonRasterPageWriter<UrfPageFactory, W>;
     type Error = UrfError;
-    type NextPageFuture<'a> = CommonRasterPageWriterFor<'a, UrfPageFactory, W>
+    type NextPageFuture<'a>
+        = CommonRasterPageWriterFor<'a, UrfPageFactory, W>
     where
         Self: 'a;
     type FinishFuture = futures::future::Ready<Result<(), UrfError>>;
+    type FinishWithStatsFuture = CommonRasterPageWriterFinishWithStats<W, UrfError>;
 
     fn next_page<'a>(self, header: &'a UrfPageHeader) -> Self::NextPageFuture<'a>
     where
         Self: 'a,
     {
-        CommonRasterPageWriter::writer_for(header, self.writer)
+        CommonRasterPageWriter::writer_for_with_stats(header, self.writer, self.stats, self.metrics)
     }
 
     fn finish(self) -> Self::FinishFuture {
         futures::future::ready(Ok(()))
     }
+
+    fn finish_with_stats(self) -> Self::FinishWithStatsFuture {
+        CommonRasterPageWriterFinishWithStats::new(self.writer, self.stats, self.metrics)
+    }
+}
+
+impl<W> UrfWriter<W>
+where
+    W: DerefMut<Target: AsyncWrite + AsyncSeek>,
+{
+    /// Finishes a stream without pages, rewriting the page count of the stream header to 0.
+    pub async fn finish_with_count(self) -> Result<(), UrfError> {
+        let (writer, stats) = self.finish_with_stats().await?;
+        patch_page_count(writer, &stats).await
+    }
+}
+
+impl<W> UrfPageWriter<W>
+where
+    W: DerefMut<Target: AsyncWrite + AsyncSeek>,
+{
+    /// Finishes the stream like [`finish`](RasterPageWriter::finish), then seeks back to
+    /// rewrite the page count of the stream header with the number of pages written.
+    ///
+    /// This allows to start the stream before the number of pages is known.
+    pub async fn finish_with_count(self) -> Result<(), UrfError> {
+        let (writer, stats) = self.finish_with_stats().await?;
+        patch_page_count(writer, &stats).await
+    }
+}
+
+async fn patch_page_count<W>(mut writer: Pin<W>, stats: &WriteStats) -> Result<(), UrfError>
+where
+    W: DerefMut<Target: AsyncWrite + AsyncSeek>,
+{
+    let page_count = u32::try_from(stats.pages_written())
+        .map_err(|_| io::Error::other("too many pages for the URF page count"))?;
+    let end = writer.as_mut().seek(SeekFrom::Current(0)).await?;
+    // the stream header starts `bytes_written` bytes before the end of the stream
+    let start = end
+        .checked_sub(stats.bytes_written)
+        .ok_or_else(|| io::Error::other("stream header is before the start of the writer"))?;
+    writer.as_mut().seek(SeekFrom::Start(start + 8)).await?;
+    writer.as_mut().write_all(&page_count.to_be_bytes()).await?;
+    writer.as_mut().seek(SeekFrom::Start(end)).await?;
+    writer.as_mut().close().await?;
+    Ok(())
 }
 
 #[pin_project]
 struct UrfWriteHeaderFuture<W> {
-    buffer: [u8; 12],
+    buffer: [u8; URF_HEADER_SIZE],
     num_written: usize,
     writer: Pin<W>,
 }
@@ -83,11 +167,7 @@ fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
                 break;
             }
             if num_written == 0 {
-                return Poll::Ready(Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "failed to write header",
-                )
-                .into()));
+                return Poll::Ready(Err(io::Error::other("failed to write header").into()));
             }
         }
         Poll::Ready(Ok(()))
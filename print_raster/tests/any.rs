@@ -0,0 +1,190 @@
+use futures::{io::BufReader, AsyncReadExt};
+use print_raster::{
+    model::{
+        cups::{CupsColorSpace, CupsPageHeaderV2, CupsSyncWord},
+        urf::{UrfColorSpace, UrfPageHeader},
+        RasterPageMetadata,
+    },
+    reader::{
+        cups::unified::CupsRasterUnifiedReader, urf::UrfReader, AnyPageHeader, AnyRasterReader,
+        RasterFormat, RasterPageReader, RasterReader,
+    },
+};
+use std::{path::Path, pin::pin};
+
+async fn read_input(name: &str) -> Vec<u8> {
+    let path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("tests/test_inputs/{}.ras", name));
+    tokio::fs::read(path).await.unwrap()
+}
+
+/// Reads the header and the content of every page through [`AnyRasterReader`].
+async fn read_any(data: &[u8]) -> (RasterFormat, Vec<(AnyPageHeader, Vec<u8>)>) {
+    let file_reader = pin!(BufReader::new(data));
+    let reader = AnyRasterReader::new(file_reader).await.unwrap();
+    let format = reader.format();
+    let mut pages = Vec::new();
+    let mut page_next = reader.next_page().await.unwrap();
+    while let Some(mut page) = page_next {
+        assert_eq!(page.format(), format);
+        let mut content = Vec::new();
+        page.content_mut().read_to_end(&mut content).await.unwrap();
+        pages.push((page.header().clone(), content));
+        page_next = page.next_page().await.unwrap();
+    }
+    (format, pages)
+}
+
+#[tokio::test]
+async fn any_reader_cups() {
+    let data = read_input("pwg_sRGB").await;
+    let (format, pages) = read_any(&data).await;
+    assert_eq!(format, RasterFormat::Cups(CupsSyncWord::V2BigEndian));
+    assert!(!pages.is_empty());
+
+    let file_reader = pin!(BufReader::new(&data[..]));
+    let reader = CupsRasterUnifiedReader::new(file_reader).await.unwrap();
+    let mut page_next = reader.next_page().await.unwrap();
+    for (header, content) in pages {
+        let mut page = page_next.unwrap();
+        let expected = page.header().clone();
+        assert_eq!(header.width(), expected.v1.width);
+        assert_eq!(header.height(), expected.v1.height);
+        assert_eq!(
+            header.dpi(),
+            (
+                expected.v1.resolution.cross_feed,
+                expected.v1.resolution.feed
+            )
+        );
+        assert_eq!(header, AnyPageHeader::Cups(Box::new(expected)));
+        let mut expected_content = Vec::new();
+        page.content_mut()
+            .read_to_end(&mut expected_content)
+            .await
+            .unwrap();
+        assert_eq!(content, expected_content);
+        page_next = page.next_page().await.unwrap();
+    }
+    assert!(page_next.is_none());
+}
+
+#[tokio::test]
+async fn any_reader_urf() {
+    let data = read_input("urf_sGray").await;
+    let (format, pages) = read_any(&data).await;
+    assert_eq!(format, RasterFormat::Urf);
+    assert!(!pages.is_empty());
+
+    let file_reader = pin!(BufReader::new(&data[..]));
+    let reader = UrfReader::new(file_reader).await.unwrap();
+    let mut page_next = reader.next_page().await.unwrap();
+    for (header, content) in pages {
+        let mut page = page_next.unwrap();
+        let expected = page.header().clone();
+        assert_eq!(header.width(), expected.width);
+        assert_eq!(header.height(), expected.height);
+        assert_eq!(header.dpi(), (expected.dot_per_inch, expected.dot_per_inch));
+        assert_eq!(header, AnyPageHeader::Urf(expected));
+        let mut expected_content = Vec::new();
+        page.content_mut()
+            .read_to_end(&mut expected_content)
+            .await
+            .unwrap();
+        assert_eq!(content, expected_content);
+        page_next = page.next_page().await.unwrap();
+    }
+    assert!(page_next.is_none());
+}
+
+#[tokio::test]
+async fn any_reader_skip_page() {
+    let data = read_input("cups_v3_sRGB").await;
+    let file_reader = pin!(BufReader::new(&data[..]));
+    let reader = AnyRasterReader::new(file_reader).await.unwrap();
+    let mut count = 0;
+    let mut page_next = reader.next_page().await.unwrap();
+    while let Some(page) = page_next {
+        count += 1;
+        page_next = page.skip_page().await.unwrap();
+    }
+    let (_, pages) = read_any(&data).await;
+    assert_eq!(count, pages.len());
+}
+
+fn metadata(header: &impl RasterPageMetadata) -> (u32, u32, u32, (u32, u32), u32) {
+    (
+        header.width(),
+        header.height(),
+        header.bits_per_pixel(),
+        header.dpi(),
+        header.num_colors(),
+    )
+}
+
+#[test]
+fn page_metadata() {
+    let cups = CupsPageHeaderV2::builder()
+        .width(100)
+        .height(10)
+        .resolution(300, 600)
+        .color_space(CupsColorSpace::CMYK)
+        .build();
+    assert_eq!(metadata(&cups), (100, 10, 32, (300, 600), 4));
+    assert_eq!(
+        metadata(&AnyPageHeader::from(cups)),
+        (100, 10, 32, (300, 600), 4)
+    );
+
+    let urf = UrfPageHeader::builder()
+        .width(101)
+        .height(200)
+        .bits_per_pixel(24)
+        .color_space(UrfColorSpace::sRGB)
+        .dot_per_inch(600)
+        .build()
+        .unwrap();
+    assert_eq!(metadata(&urf), (101, 200, 24, (600, 600), 3));
+    assert_eq!(
+        metadata(&AnyPageHeader::from(urf)),
+        (101, 200, 24, (600, 600), 3)
+    );
+}
+
+#[tokio::test]
+async fn from_owned_reader() {
+    use futures::io::Cursor;
+    use print_raster::{
+        model::urf::UrfHeader,
+        writer::{urf::UrfWriter, RasterWriter},
+    };
+    use std::pin::Pin;
+
+    let data = read_input("cups_v3_sRGB").await;
+    let reader = AnyRasterReader::from_reader(Cursor::new(data.clone()))
+        .await
+        .unwrap();
+    assert_eq!(
+        reader.format(),
+        RasterFormat::Cups(CupsSyncWord::V3LittleEndian)
+    );
+    let reader = CupsRasterUnifiedReader::from_reader(Cursor::new(data))
+        .await
+        .unwrap();
+    let mut page = reader.next_page().await.unwrap().unwrap();
+    let mut content = Vec::new();
+    page.content_mut().read_to_end(&mut content).await.unwrap();
+    assert_eq!(
+        content.len() as u32,
+        page.header().height() * page.header().v1.bytes_per_line
+    );
+
+    let writer = UrfWriter::from_writer(Vec::new(), &UrfHeader { page_count: 0 })
+        .await
+        .unwrap();
+    let (sink, _) = writer.finish_with_stats().await.unwrap();
+    let data = *Pin::into_inner(sink);
+    let reader = UrfReader::from_reader(Cursor::new(data)).await.unwrap();
+    assert_eq!(reader.header().page_count, 0);
+    assert!(reader.next_page().await.unwrap().is_none());
+}
@@ -0,0 +1,149 @@
+//! Helpers shared by the integration tests.
+
+// every test crate uses only some of the helpers
+#![allow(dead_code)]
+
+use futures::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use print_raster::{
+    decode::Limits,
+    encode::{RasterEncoder, UncompressedRasterEncoder},
+    error::UrfError,
+    factory::{PageGeometry, RasterPageFactory, UrfPageFactory},
+    model::urf::{
+        UrfColorSpace, UrfDuplex, UrfHeader, UrfMediaPosition, UrfMediaType, UrfPageHeader,
+        UrfQuality,
+    },
+    writer::{urf::UrfWriter, RasterPageWriter, RasterWriter},
+};
+use std::{marker::PhantomData, ops::DerefMut, pin::Pin};
+
+pub fn urf_gray_header(width: u32, height: u32) -> UrfPageHeader {
+    UrfPageHeader {
+        bits_per_pixel: 8,
+        color_space: UrfColorSpace::sGray,
+        duplex: UrfDuplex::NoDuplex,
+        quality: UrfQuality::Default,
+        media_position: UrfMediaPosition::Auto,
+        media_type: UrfMediaType::Auto,
+        width,
+        height,
+        dot_per_inch: 300,
+    }
+}
+
+/// Writes a URF stream of `pages`, all with `header`, declaring the number of pages.
+pub async fn write_urf_pages(header: &UrfPageHeader, pages: &[&[u8]]) -> Vec<u8> {
+    let mut data = Vec::<u8>::new();
+    let page_count = pages.len() as u32;
+    let writer = UrfWriter::new(Pin::new(&mut data), &UrfHeader { page_count })
+        .await
+        .unwrap();
+    let mut pages = pages.iter();
+    let mut page = writer.next_page(header).await.unwrap();
+    page.content_mut()
+        .write_all(pages.next().expect("at least one page"))
+        .await
+        .unwrap();
+    for content in pages {
+        page = page.next_page(header).await.unwrap();
+        page.content_mut().write_all(content).await.unwrap();
+    }
+    page.finish().await.unwrap();
+    data
+}
+
+/// How a [`ForwardingUrfPageFactory`] encodes the page content.
+pub trait UrfContentEncoding {
+    type Encoder<W>: RasterEncoder<W>
+    where
+        W: DerefMut<Target: AsyncWrite>;
+    fn encode<W>(header: &UrfPageHeader, writer: Pin<W>) -> Result<Self::Encoder<W>, UrfError>
+    where
+        W: DerefMut<Target: AsyncWrite>;
+}
+
+/// The compressed content of [`UrfPageFactory`].
+pub enum Compressed {}
+
+impl UrfContentEncoding for Compressed {
+    type Encoder<W>
+        = <UrfPageFactory as RasterPageFactory>::Encoder<W>
+    where
+        W: DerefMut<Target: AsyncWrite>;
+    fn encode<W>(header: &UrfPageHeader, writer: Pin<W>) -> Result<Self::Encoder<W>, UrfError>
+    where
+        W: DerefMut<Target: AsyncWrite>,
+    {
+        UrfPageFactory::encode(header, writer)
+    }
+}
+
+/// Uncompressed content, whose line size is unknown to the writer.
+pub enum Uncompressed {}
+
+impl UrfContentEncoding for Uncompressed {
+    type Encoder<W>
+        = UncompressedRasterEncoder<W>
+    where
+        W: DerefMut<Target: AsyncWrite>;
+    fn encode<W>(header: &UrfPageHeader, writer: Pin<W>) -> Result<Self::Encoder<W>, UrfError>
+    where
+        W: DerefMut<Target: AsyncWrite>,
+    {
+        Ok(UncompressedRasterEncoder::new(
+            writer,
+            header.image_byte_len()?,
+        ))
+    }
+}
+
+/// The end-of-page marker written by a [`ForwardingUrfPageFactory`] with `EOP` set.
+pub const EOP_MARKER: &[u8] = b"EOP!";
+
+/// Forwards to [`UrfPageFactory`], except that the content is encoded by `E`
+/// and, if `EOP` is set, each page is followed by [`EOP_MARKER`].
+pub struct ForwardingUrfPageFactory<E, const EOP: bool>(PhantomData<E>);
+
+impl<E: UrfContentEncoding, const EOP: bool> RasterPageFactory
+    for ForwardingUrfPageFactory<E, EOP>
+{
+    type Header = UrfPageHeader;
+    type Error = UrfError;
+    const HEADER_SIZE: usize = UrfPageFactory::HEADER_SIZE;
+    const EOP_MARKER: Option<&'static [u8]> = if EOP { Some(EOP_MARKER) } else { None };
+    fn header_from_bytes(content: &[u8]) -> Result<Self::Header, Self::Error> {
+        UrfPageFactory::header_from_bytes(content)
+    }
+    fn header_to_bytes(target: &mut [u8], header: &Self::Header) -> Result<(), Self::Error> {
+        UrfPageFactory::header_to_bytes(target, header)
+    }
+    fn geometry(header: &Self::Header) -> PageGeometry {
+        UrfPageFactory::geometry(header)
+    }
+
+    type Decoder<R>
+        = <UrfPageFactory as RasterPageFactory>::Decoder<R>
+    where
+        R: DerefMut<Target: AsyncRead>;
+    fn decode<R>(
+        header: &Self::Header,
+        reader: Pin<R>,
+        limits: &Limits,
+    ) -> Result<Self::Decoder<R>, Self::Error>
+    where
+        R: DerefMut<Target: AsyncRead>,
+    {
+        UrfPageFactory::decode(header, reader, limits)
+    }
+
+    type Encoder<W>
+        = E::Encoder<W>
+    where
+        W: DerefMut<Target: AsyncWrite>;
+    fn encode<W>(header: &Self::Header, writer: Pin<W>) -> Result<Self::Encoder<W>, Self::Error>
+    where
+        W: DerefMut<Target: AsyncWrite>,
+    {
+        E::encode(header, writer)
+    }
+}
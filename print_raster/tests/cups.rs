@@ -1,10 +1,14 @@
 use futures::{io::BufReader, AsyncReadExt};
 use image::{ImageBuffer, Rgb};
 use print_raster::{
-    model::cups::{CupsColorOrder, CupsColorSpace},
+    error::CupsRasterError,
+    model::cups::{CupsColorOrder, CupsColorSpace, CupsPageHeaderV2, CupsSyncWord},
     reader::{cups::unified::CupsRasterUnifiedReader, RasterPageReader, RasterReader},
 };
-use std::{path::Path, pin::pin};
+use std::{
+    path::Path,
+    pin::{pin, Pin},
+};
 use tokio_util::compat::TokioAsyncReadCompatExt;
 
 async fn cups_srgb(name: &str) {
@@ -35,8 +39,8 @@ async fn cups_srgb(name: &str) {
         println!("{} bytes pixels read", data.len());
 
         let img = ImageBuffer::<Rgb<u8>, Vec<u8>>::from_vec(
-            page.header().v1.width as u32,
-            page.header().v1.height as u32,
+            page.header().v1.width,
+            page.header().v1.height,
             data,
         )
         .unwrap();
@@ -65,3 +69,1108 @@ async fn pwg_srgb() {
 async fn cups_v3_srgb() {
     cups_srgb("cups_v3_sRGB").await;
 }
+
+#[cfg(feature = "bytes")]
+#[tokio::test]
+async fn pwg_srgb_into_bytes() {
+    use print_raster::reader::read_page_into_bytes;
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_inputs/pwg_sRGB.ras");
+    let file = tokio::fs::File::open(path).await.unwrap();
+    let pinned_file_reader = pin!(BufReader::new(file.compat()));
+    let reader = CupsRasterUnifiedReader::new(pinned_file_reader)
+        .await
+        .unwrap();
+    let mut page = reader.next_page().await.unwrap().unwrap();
+    let data = read_page_into_bytes(&mut page).await.unwrap();
+    assert_eq!(
+        data.len() as u64,
+        page.header().v1.bytes_per_line as u64 * page.header().v1.height as u64
+    );
+    assert!(page.next_page().await.unwrap().is_none());
+}
+
+#[cfg(feature = "bytes")]
+#[tokio::test]
+async fn cups_v3_srgb_from_channel() {
+    use futures::{channel::mpsc, SinkExt};
+    use print_raster::reader::ChannelReader;
+
+    async fn read_pages<R>(reader: Pin<R>) -> Vec<(CupsPageHeaderV2, Vec<u8>)>
+    where
+        R: std::ops::DerefMut<Target: futures::AsyncRead>,
+    {
+        let reader = CupsRasterUnifiedReader::new(reader).await.unwrap();
+        let mut pages = Vec::new();
+        let mut page_next = reader.next_page().await.unwrap();
+        while let Some(mut page) = page_next {
+            let mut data = Vec::new();
+            page.content_mut().read_to_end(&mut data).await.unwrap();
+            pages.push((page.header().clone(), data));
+            page_next = page.next_page().await.unwrap();
+        }
+        pages
+    }
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_inputs/cups_v3_sRGB.ras");
+    let data = bytes::Bytes::from(std::fs::read(path).unwrap());
+    let mut slice = &data[..];
+    let expected = read_pages(Pin::new(&mut slice)).await;
+
+    // uneven chunk sizes, so that headers and lines straddle chunk boundaries
+    let (mut sender, receiver) = mpsc::channel(4);
+    let producer = tokio::spawn({
+        let data = data.clone();
+        async move {
+            let mut offset = 0;
+            for size in [1, 3, 0, 1000, 4093].into_iter().cycle() {
+                let end = (offset + size).min(data.len());
+                sender.send(data.slice(offset..end)).await.unwrap();
+                offset = end;
+                if offset == data.len() {
+                    break;
+                }
+            }
+        }
+    });
+    let channel_reader = pin!(ChannelReader::new(receiver));
+    assert_eq!(read_pages(channel_reader).await, expected);
+    producer.await.unwrap();
+}
+
+#[tokio::test]
+async fn header_size_override() {
+    use print_raster::reader::ReaderOptions;
+
+    async fn first_page(mut data: &[u8], options: ReaderOptions) -> (CupsPageHeaderV2, Vec<u8>) {
+        let reader = CupsRasterUnifiedReader::new_with_options(Pin::new(&mut data), options)
+            .await
+            .unwrap();
+        let mut page = reader.next_page().await.unwrap().unwrap();
+        let mut content = vec![0; 4096];
+        page.content_mut().read_exact(&mut content).await.unwrap();
+        (page.header().clone(), content)
+    }
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_inputs/pwg_sRGB.ras");
+    let data = std::fs::read(path).unwrap();
+    let expected = first_page(&data, ReaderOptions::default()).await;
+
+    // a longer header with some junk in its trailer
+    let mut longer = data[..4 + 1796].to_vec();
+    longer.extend_from_slice(&[0xaa; 8]);
+    longer.extend_from_slice(&data[4 + 1796..]);
+    let options = ReaderOptions {
+        header_size_override: Some(1796 + 8),
+        ..Default::default()
+    };
+    assert_eq!(first_page(&longer, options).await, expected);
+
+    // a shorter header, missing the (zeroed) tail of page_size_name
+    let mut shorter = data[..4 + 1796 - 8].to_vec();
+    shorter.extend_from_slice(&data[4 + 1796..]);
+    let options = ReaderOptions {
+        header_size_override: Some(1796 - 8),
+        ..Default::default()
+    };
+    assert_eq!(first_page(&shorter, options).await, expected);
+
+    // headers missing fields would be read as zeros without consuming the stream
+    for header_size in [0, 419] {
+        let options = ReaderOptions {
+            header_size_override: Some(header_size),
+            ..Default::default()
+        };
+        let err = CupsRasterUnifiedReader::new_with_options(Pin::new(&mut &data[..]), options)
+            .await
+            .err()
+            .unwrap();
+        assert!(
+            matches!(err, CupsRasterError::IoError(e) if e.kind() == std::io::ErrorKind::InvalidInput)
+        );
+    }
+    let options = ReaderOptions {
+        header_size_override: Some(0),
+        ..Default::default()
+    };
+    let urf = b"UNIRAST\0\0\0\0\0";
+    let err =
+        print_raster::reader::urf::UrfReader::new_with_options(Pin::new(&mut &urf[..]), options)
+            .await
+            .err()
+            .unwrap();
+    assert!(
+        matches!(err, print_raster::error::UrfError::IoError(e) if e.kind() == std::io::ErrorKind::InvalidInput)
+    );
+}
+
+#[tokio::test]
+async fn transcode_srgb_to_gray() {
+    use print_raster::{transcode::transcode_with_color, writer::cups::CupsRasterWriterV3LE};
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_inputs/cups_v3_sRGB.ras");
+    let data = std::fs::read(path).unwrap();
+    let mut output = Vec::<u8>::new();
+    let input = pin!(data.as_slice());
+    let reader = CupsRasterUnifiedReader::new(input).await.unwrap();
+    let writer = CupsRasterWriterV3LE::new(Pin::new(&mut output))
+        .await
+        .unwrap();
+    transcode_with_color(reader, writer, CupsColorSpace::sGray)
+        .await
+        .unwrap();
+
+    let source_reader = pin!(data.as_slice());
+    let mut source = CupsRasterUnifiedReader::new(source_reader)
+        .await
+        .unwrap()
+        .next_page()
+        .await
+        .unwrap()
+        .unwrap();
+    let gray_reader = pin!(output.as_slice());
+    let mut gray = CupsRasterUnifiedReader::new(gray_reader)
+        .await
+        .unwrap()
+        .next_page()
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(gray.header().v1.color_space, CupsColorSpace::sGray);
+    assert_eq!(gray.header().v1.bits_per_pixel, 8);
+    assert_eq!(gray.header().v1.bytes_per_line, source.header().v1.width);
+    assert_eq!(gray.header().v1.height, source.header().v1.height);
+
+    let mut rgb_line = vec![0; source.header().v1.bytes_per_line as usize];
+    let mut gray_line = vec![0; gray.header().v1.bytes_per_line as usize];
+    source
+        .content_mut()
+        .read_exact(&mut rgb_line)
+        .await
+        .unwrap();
+    gray.content_mut().read_exact(&mut gray_line).await.unwrap();
+    for (rgb, gray) in rgb_line.chunks_exact(3).zip(gray_line) {
+        let luminance = 0.299 * rgb[0] as f32 + 0.587 * rgb[1] as f32 + 0.114 * rgb[2] as f32;
+        assert!((luminance - gray as f32).abs() <= 1.0);
+    }
+}
+
+#[tokio::test]
+async fn declared_size_exceeds_stream() {
+    use print_raster::{error::CupsRasterError, reader::ReaderOptions};
+
+    async fn first_page(mut data: &[u8]) -> Result<(), CupsRasterError> {
+        let options = ReaderOptions {
+            total_stream_len: Some(data.len() as u64),
+            ..Default::default()
+        };
+        let reader =
+            CupsRasterUnifiedReader::new_with_options(Pin::new(&mut data), options).await?;
+        reader.next_page().await?.unwrap();
+        Ok(())
+    }
+
+    for name in ["pwg_sRGB", "cups_v3_sRGB"] {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("tests/test_inputs/{}.ras", name));
+        let data = std::fs::read(path).unwrap();
+        first_page(&data).await.unwrap();
+        let truncated = match name {
+            "pwg_sRGB" => &data[..4 + 1796 + 16],
+            _ => &data[..data.len() - 1],
+        };
+        assert!(matches!(
+            first_page(truncated).await,
+            Err(CupsRasterError::DeclaredSizeExceedsStream { .. })
+        ));
+    }
+}
+
+#[tokio::test]
+async fn bytes_per_line_too_short() {
+    use print_raster::error::CupsRasterError;
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_inputs/pwg_sRGB.ras");
+    let mut data = std::fs::read(path).unwrap();
+    // the first page header follows the sync word, its fields are big-endian
+    let offset = 4 + 392;
+    let bytes_per_line = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap());
+    data[offset..offset + 4].copy_from_slice(&(bytes_per_line - 3).to_be_bytes());
+
+    let mut input = data.as_slice();
+    let reader = CupsRasterUnifiedReader::new(Pin::new(&mut input))
+        .await
+        .unwrap();
+    assert!(matches!(
+        reader.next_page().await,
+        Err(CupsRasterError::DataLayoutError)
+    ));
+}
+
+#[tokio::test]
+async fn fill_byte_override_ignored() {
+    use futures::AsyncWriteExt;
+    use print_raster::reader::ReaderOptions;
+    use print_raster::writer::{
+        cups::unified::CupsRasterUnifiedWriter, RasterPageWriterExt, RasterWriter,
+    };
+
+    let header = CupsPageHeaderV2::builder()
+        .width(4)
+        .height(1)
+        .color_space(CupsColorSpace::sGray)
+        .build();
+    let write_page = |encoded: &'static [u8]| {
+        let header = header.clone();
+        async move {
+            let mut data = Vec::<u8>::new();
+            let writer =
+                CupsRasterUnifiedWriter::new(Pin::new(&mut data), CupsSyncWord::V2BigEndian)
+                    .await
+                    .unwrap();
+            let mut output = writer.next_page(&header).await.unwrap().abort();
+            output.write_all(encoded).await.unwrap();
+            data
+        }
+    };
+    let read_page = |data: Vec<u8>| async move {
+        let options = ReaderOptions {
+            fill_byte_override: Some(0x42),
+            ..Default::default()
+        };
+        let mut input = data.as_slice();
+        let reader = CupsRasterUnifiedReader::new_with_options(Pin::new(&mut input), options)
+            .await
+            .unwrap();
+        let mut page = reader.next_page().await.unwrap().unwrap();
+        let mut content = Vec::new();
+        page.content_mut()
+            .read_to_end(&mut content)
+            .await
+            .map(|_| content)
+    };
+
+    // one pixel, then a run of three
+    let data = write_page(&[0x00, 0x00, 0x10, 0x02, 0xff]).await;
+    assert_eq!(read_page(data).await.unwrap(), [0x10, 0xff, 0xff, 0xff]);
+    // one pixel, then the rest of the line filled
+    let data = write_page(&[0x00, 0x00, 0x10, 0x80]).await;
+    let error = read_page(data).await.unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[tokio::test]
+async fn resize_canvas_crop_and_pad() {
+    use print_raster::model::pixel::resize_canvas;
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_inputs/pwg_sRGB.ras");
+    let data = std::fs::read(path).unwrap();
+    let mut data = data.as_slice();
+    let reader = CupsRasterUnifiedReader::new(Pin::new(&mut data))
+        .await
+        .unwrap();
+    let page = reader.next_page().await.unwrap().unwrap();
+    let mut header = page.header().v1.clone();
+
+    // 2x2 RGB pixels with one byte of line padding
+    header.width = 2;
+    header.height = 2;
+    header.bits_per_pixel = 24;
+    header.bytes_per_line = 7;
+    let pixels = [1, 2, 3, 4, 5, 6, 0xee, 7, 8, 9, 10, 11, 12, 0xee];
+    assert_eq!(
+        resize_canvas(&pixels, &header, 3, 1, 0xff),
+        [1, 2, 3, 4, 5, 6, 0xff, 0xff, 0xff]
+    );
+    assert_eq!(
+        resize_canvas(&pixels, &header, 1, 3, 0x00),
+        [1, 2, 3, 7, 8, 9, 0, 0, 0]
+    );
+
+    // 1-bit pixels: the partial byte takes the leading pixels from the source
+    header.width = 6;
+    header.height = 1;
+    header.bits_per_pixel = 1;
+    header.bytes_per_line = 1;
+    assert_eq!(
+        resize_canvas(&[0b1010_1100], &header, 4, 1, 0x00),
+        [0b1010_0000]
+    );
+    assert_eq!(
+        resize_canvas(&[0b1010_1100], &header, 12, 1, 0xff),
+        [0b1010_1111, 0xff]
+    );
+}
+
+#[test]
+fn expand_copies_order() {
+    use print_raster::model::cups::expand_copies;
+
+    let pages = [1, 2, 3];
+    let collated = expand_copies(&pages, 2, true).copied().collect::<Vec<_>>();
+    assert_eq!(collated, [1, 2, 3, 1, 2, 3]);
+    let uncollated = expand_copies(&pages, 2, false).copied().collect::<Vec<_>>();
+    assert_eq!(uncollated, [1, 1, 2, 2, 3, 3]);
+    assert_eq!(expand_copies(&pages, 0, true).count(), 3);
+    assert_eq!(expand_copies::<u8>(&[], 5, false).count(), 0);
+}
+
+#[tokio::test]
+async fn validate_header() {
+    use print_raster::error::CupsRasterError;
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_inputs/pwg_sRGB.ras");
+    let data = std::fs::read(path).unwrap();
+    let mut data = data.as_slice();
+    let reader = CupsRasterUnifiedReader::new(Pin::new(&mut data))
+        .await
+        .unwrap();
+    let page = reader.next_page().await.unwrap().unwrap();
+    let header = page.header().clone();
+    header.validate().unwrap();
+    header.v1.validate().unwrap();
+    assert!(header.colors_consistent());
+
+    let mut invalid = header.clone();
+    invalid.num_colors = 4;
+    assert!(!invalid.colors_consistent());
+    assert!(matches!(
+        invalid.validate(),
+        Err(CupsRasterError::InvalidHeader(_))
+    ));
+
+    let mut invalid = header.clone();
+    invalid.v1.bits_per_color = 3;
+    assert!(matches!(
+        invalid.validate(),
+        Err(CupsRasterError::InvalidHeader(_))
+    ));
+
+    let mut invalid = header.clone();
+    invalid.v1.bits_per_pixel = 32;
+    assert!(matches!(
+        invalid.validate(),
+        Err(CupsRasterError::InvalidHeader(_))
+    ));
+
+    let mut invalid = header.clone();
+    invalid.v1.bytes_per_line -= 3;
+    assert!(matches!(
+        invalid.validate(),
+        Err(CupsRasterError::InvalidHeader(_))
+    ));
+
+    let mut invalid = header.clone();
+    invalid.v1.bytes_per_line += 1;
+    assert!(matches!(
+        invalid.validate(),
+        Err(CupsRasterError::InvalidHeader(_))
+    ));
+
+    // 1-bit RGB pixels are padded to 4 bits
+    let mut padded = header.clone();
+    padded.v1.bits_per_color = 1;
+    padded.v1.bits_per_pixel = 4;
+    padded.v1.bytes_per_line = padded.v1.width.div_ceil(2);
+    padded.validate().unwrap();
+
+    // a zero bytes_per_line is computed by the writers
+    let mut computed = header.clone();
+    computed.v1.bytes_per_line = 0;
+    computed.validate().unwrap();
+    CupsPageHeaderV2::builder()
+        .width(5)
+        .height(2)
+        .bits_per_color(1)
+        .color_space(CupsColorSpace::sGray)
+        .bytes_per_line(0)
+        .build()
+        .validate()
+        .unwrap();
+}
+
+#[test]
+fn header_builder() {
+    let header = CupsPageHeaderV2::builder()
+        .width(100)
+        .height(10)
+        .resolution(300, 300)
+        .page_size_name("iso_a4_210x297mm")
+        .build();
+    assert_eq!(header.v1.color_space, CupsColorSpace::sRGB);
+    assert_eq!(header.v1.color_order, CupsColorOrder::Chunky);
+    assert_eq!(header.v1.bits_per_pixel, 24);
+    assert_eq!(header.v1.bytes_per_line, 300);
+    assert_eq!(header.page_size_name, "iso_a4_210x297mm");
+    header.validate().unwrap();
+
+    // 3 colors of less than 8 bits are padded to 4
+    let header = CupsPageHeaderV2::builder()
+        .width(101)
+        .bits_per_color(1)
+        .build();
+    assert_eq!(header.v1.bits_per_pixel, 4);
+    assert_eq!(header.v1.bytes_per_line, 51);
+    header.validate().unwrap();
+
+    let header = CupsPageHeaderV2::builder()
+        .width(100)
+        .color_space(CupsColorSpace::CMYK)
+        .color_order(CupsColorOrder::Banded)
+        .build();
+    assert_eq!(header.v1.bits_per_pixel, 8);
+    assert_eq!(header.v1.bytes_per_line, 400);
+    header.validate().unwrap();
+
+    // explicit values are kept
+    let header = print_raster::model::cups::CupsPageHeaderV1::builder()
+        .width(100)
+        .color_space(CupsColorSpace::sGray)
+        .bytes_per_line(128)
+        .build();
+    assert_eq!(header.bits_per_pixel, 8);
+    assert_eq!(header.bytes_per_line, 128);
+    header.validate().unwrap();
+}
+
+#[test]
+fn display_names() {
+    use print_raster::model::{cups::CupsOrientation, urf::UrfColorSpace};
+
+    assert_eq!(CupsColorSpace::sRGB.to_string(), "sRGB");
+    assert_eq!(CupsColorSpace::CMYK.to_string(), "CMYK");
+    assert_eq!(CupsColorSpace::CIELab.to_string(), "CIE Lab");
+    assert_eq!(CupsColorSpace::Icc3.to_string(), "ICC 3");
+    assert_eq!(CupsColorSpace::DeviceF.to_string(), "Device 15");
+    assert_eq!(CupsColorOrder::Banded.to_string(), "Banded");
+    assert_eq!(
+        CupsOrientation::ReverseLandscape.to_string(),
+        "Reverse Landscape"
+    );
+    assert_eq!(UrfColorSpace::AdobeRGB.to_string(), "Adobe RGB");
+}
+
+#[test]
+fn media_position_name() {
+    let mut header = CupsPageHeaderV2::default();
+    assert_eq!(header.v1.media_position_name(), Some("auto"));
+    header.v1.media_position = 20;
+    assert_eq!(header.v1.media_position_name(), Some("tray-1"));
+    header.v1.media_position = 49;
+    assert_eq!(header.v1.media_position_name(), Some("roll-10"));
+    header.v1.media_position = 50;
+    assert_eq!(header.v1.media_position_name(), None);
+    header.v1.media_position = u32::MAX;
+    assert_eq!(header.v1.media_position_name(), None);
+}
+
+#[test]
+fn image_byte_len() {
+    let header = CupsPageHeaderV2::builder().width(5).height(3).build();
+    assert_eq!(header.image_byte_len().unwrap(), 5 * 3 * 3);
+    let planar = CupsPageHeaderV2::builder()
+        .width(5)
+        .height(3)
+        .color_order(CupsColorOrder::Planar)
+        .build();
+    assert_eq!(planar.v1.bytes_per_line, 5);
+    assert_eq!(planar.image_byte_len().unwrap(), 5 * 3 * 3);
+    assert_eq!(planar.v1.image_byte_len().unwrap(), 5 * 3 * 3);
+}
+
+#[test]
+fn bytes_per_pixel() {
+    use print_raster::model::urf::UrfColorSpace;
+
+    assert_eq!(CupsColorSpace::sRGB.bytes_per_pixel(8), Some(3));
+    assert_eq!(CupsColorSpace::CMYK.bytes_per_pixel(16), Some(8));
+    assert_eq!(CupsColorSpace::KCMYcm.bytes_per_pixel(8), Some(4));
+    assert_eq!(CupsColorSpace::DeviceF.bytes_per_pixel(16), Some(30));
+    assert_eq!(CupsColorSpace::sGray.bytes_per_pixel(1), None);
+    assert_eq!(UrfColorSpace::sGray.bytes_per_pixel(8), Some(1));
+    assert_eq!(UrfColorSpace::AdobeRGB.bytes_per_pixel(16), Some(6));
+    assert_eq!(UrfColorSpace::CMYK.bytes_per_pixel(4), None);
+}
+
+#[test]
+fn color_space_numbered_variants() {
+    assert_eq!(CupsColorSpace::Icc1.icc_color_hint(), Some(1));
+    assert_eq!(CupsColorSpace::IccF.icc_color_hint(), Some(15));
+    assert_eq!(CupsColorSpace::CIELab.icc_color_hint(), None);
+    assert_eq!(CupsColorSpace::Device1.icc_color_hint(), None);
+    assert_eq!(CupsColorSpace::Device6.device_colorant_count(), Some(6));
+    assert_eq!(CupsColorSpace::DeviceF.device_colorant_count(), Some(15));
+    assert_eq!(CupsColorSpace::IccA.device_colorant_count(), None);
+}
+
+#[test]
+fn default_header() {
+    use print_raster::model::cups::{CupsOrientation, CupsPageHeaderV1};
+
+    let v1 = CupsPageHeaderV1::default();
+    assert_eq!(v1.color_space, CupsColorSpace::sRGB);
+    assert_eq!(v1.color_order, CupsColorOrder::Chunky);
+    assert_eq!(v1.orientation, CupsOrientation::Portrait);
+    assert_eq!(v1.width, 0);
+    assert_eq!(v1.bits_per_color, 0);
+    assert!(v1.media_type.is_empty());
+    assert_eq!(CupsPageHeaderV2::default(), CupsPageHeaderV2::from(v1));
+}
+
+#[tokio::test]
+async fn planar_compressed() {
+    use futures::AsyncWriteExt;
+    use print_raster::byteorder::BigEndian;
+    use print_raster::decode::Limits;
+    use print_raster::encode::RasterEncoder;
+    use print_raster::factory::{CupsPageFactoryV2, RasterPageFactory};
+
+    let header = CupsPageHeaderV2::builder()
+        .width(2)
+        .height(2)
+        .color_order(CupsColorOrder::Planar)
+        .build();
+    // the last line of green is repeated as the first line of blue
+    const ENCODED: &[u8] = &[
+        0x01, 0xff, 1, 2, // red
+        0x00, 0xff, 3, 4, // green
+        0x01, 0xff, 5, 6, // green, blue
+        0x00, 0x01, 9, // blue
+    ];
+    const PLANES: &[u8] = &[1, 2, 1, 2, 3, 4, 5, 6, 5, 6, 9, 9];
+
+    let mut input = ENCODED;
+    let mut decoder =
+        CupsPageFactoryV2::<BigEndian>::decode(&header, Pin::new(&mut input), &Limits::default())
+            .unwrap();
+    let mut planes = Vec::new();
+    decoder.read_to_end(&mut planes).await.unwrap();
+    assert_eq!(planes, PLANES);
+
+    let mut encoded = Vec::new();
+    let mut encoder =
+        CupsPageFactoryV2::<BigEndian>::encode(&header, Pin::new(&mut encoded)).unwrap();
+    encoder.write_all(PLANES).await.unwrap();
+    assert_eq!(encoder.bytes_remaining(), 0);
+    let mut input = encoded.as_slice();
+    let mut decoder =
+        CupsPageFactoryV2::<BigEndian>::decode(&header, Pin::new(&mut input), &Limits::default())
+            .unwrap();
+    let mut planes = Vec::new();
+    decoder.read_to_end(&mut planes).await.unwrap();
+    assert_eq!(planes, PLANES);
+}
+
+#[tokio::test]
+async fn count_pages() {
+    for name in ["pwg_sRGB", "cups_v3_sRGB"] {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("tests/test_inputs/{}.ras", name));
+        let data = std::fs::read(path).unwrap();
+
+        let mut input = data.as_slice();
+        let reader = CupsRasterUnifiedReader::new(Pin::new(&mut input))
+            .await
+            .unwrap();
+        let mut headers = Vec::new();
+        let mut page_next = reader.next_page().await.unwrap();
+        while let Some(page) = page_next {
+            headers.push(page.header().clone());
+            page_next = page.next_page().await.unwrap();
+        }
+
+        let mut input = data.as_slice();
+        let (count, counted) = print_raster::reader::cups::count_pages(Pin::new(&mut input))
+            .await
+            .unwrap();
+        assert_eq!(count, headers.len());
+        assert_eq!(counted, headers);
+        assert!(input.is_empty());
+
+        let mut input = &data[..data.len() - 1];
+        let error = print_raster::reader::cups::count_pages(Pin::new(&mut input))
+            .await
+            .unwrap_err();
+        assert!(error.is_unexpected_eof());
+    }
+}
+
+#[tokio::test]
+async fn new_buffered() {
+    for name in ["pwg_sRGB", "cups_v3_sRGB"] {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("tests/test_inputs/{}.ras", name));
+        let data = std::fs::read(path).unwrap();
+
+        let mut input = data.as_slice();
+        let reader = CupsRasterUnifiedReader::new(Pin::new(&mut input))
+            .await
+            .unwrap();
+        let mut expected = Vec::new();
+        let mut page_next = reader.next_page().await.unwrap();
+        while let Some(mut page) = page_next {
+            let mut content = Vec::new();
+            page.content_mut().read_to_end(&mut content).await.unwrap();
+            expected.push((page.header().clone(), content));
+            page_next = page.next_page().await.unwrap();
+        }
+
+        // a small buffer splits the block headers across fills
+        let mut input = BufReader::with_capacity(5, data.as_slice());
+        let reader = CupsRasterUnifiedReader::new_buffered(Pin::new(&mut input))
+            .await
+            .unwrap();
+        let mut pages = Vec::new();
+        let mut page_next = reader.next_page().await.unwrap();
+        while let Some(mut page) = page_next {
+            let mut content = Vec::new();
+            page.content_mut().read_to_end(&mut content).await.unwrap();
+            pages.push((page.header().clone(), content));
+            page_next = page.next_page().await.unwrap();
+        }
+        assert_eq!(pages, expected);
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn header_serde() {
+    let mut header = CupsPageHeaderV2::builder()
+        .width(100)
+        .height(50)
+        .color_space(CupsColorSpace::CMYK)
+        .build();
+    header.vendor_str[0] = "vendor".to_string();
+    let json = serde_json::to_value(&header).unwrap();
+    assert_eq!(json["v1"]["color_space"], "CMYK");
+    assert_eq!(json["v1"]["color_order"], "Chunky");
+    assert_eq!(json["vendor_str"][0], "vendor");
+    assert_eq!(json["vendor_str"].as_array().unwrap().len(), 16);
+    let decoded: CupsPageHeaderV2 = serde_json::from_value(json).unwrap();
+    assert_eq!(decoded, header);
+}
+
+#[tokio::test]
+async fn truncated_page_header() {
+    use futures::AsyncWriteExt;
+    use print_raster::{
+        error::CupsRasterError,
+        model::cups::CupsSyncWord,
+        writer::{cups::unified::CupsRasterUnifiedWriter, RasterPageWriter, RasterWriter},
+    };
+
+    let header = CupsPageHeaderV2::builder().width(4).height(2).build();
+    let mut data = Vec::<u8>::new();
+    let writer = CupsRasterUnifiedWriter::new(Pin::new(&mut data), CupsSyncWord::V3BigEndian)
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&[0; 24]).await.unwrap();
+    page.finish().await.unwrap();
+
+    async fn count_pages(mut data: &[u8]) -> Result<usize, CupsRasterError> {
+        let reader = CupsRasterUnifiedReader::new(Pin::new(&mut data)).await?;
+        let mut pages = 0;
+        let mut page_next = reader.next_page().await?;
+        while let Some(page) = page_next {
+            pages += 1;
+            page_next = page.next_page().await?;
+        }
+        Ok(pages)
+    }
+
+    assert_eq!(count_pages(&data).await.unwrap(), 1);
+    // a part of another page header
+    let partial = data[4..104].to_vec();
+    data.extend_from_slice(&partial);
+    assert!(count_pages(&data).await.unwrap_err().is_unexpected_eof());
+}
+
+#[tokio::test]
+async fn seek_to_page() {
+    use futures::{io::Cursor, AsyncWriteExt};
+    use print_raster::{
+        error::CupsRasterError,
+        model::cups::CupsSyncWord,
+        writer::{cups::unified::CupsRasterUnifiedWriter, RasterPageWriter, RasterWriter},
+    };
+
+    let header = CupsPageHeaderV2::builder().width(4).height(2).build();
+    let pages: Vec<Vec<u8>> = (0..3u8).map(|i| vec![i * 10; 24]).collect();
+    for sync_word in [CupsSyncWord::V3LittleEndian, CupsSyncWord::V2BigEndian] {
+        let mut data = Vec::<u8>::new();
+        let writer = CupsRasterUnifiedWriter::new(Pin::new(&mut data), sync_word)
+            .await
+            .unwrap();
+        let mut page = writer.next_page(&header).await.unwrap();
+        page.content_mut().write_all(&pages[0]).await.unwrap();
+        for content in &pages[1..] {
+            page = page.next_page(&header).await.unwrap();
+            page.content_mut().write_all(content).await.unwrap();
+        }
+        page.finish().await.unwrap();
+
+        for (n, content) in pages.iter().enumerate() {
+            let mut input = Cursor::new(data.as_slice());
+            let reader = CupsRasterUnifiedReader::new(Pin::new(&mut input))
+                .await
+                .unwrap();
+            let mut page = reader.seek_to_page(n).await.unwrap();
+            let mut read = Vec::new();
+            page.content_mut().read_to_end(&mut read).await.unwrap();
+            assert_eq!(&read, content);
+            assert_eq!(page.next_page().await.unwrap().is_none(), n == 2);
+        }
+
+        let mut input = Cursor::new(data.as_slice());
+        let reader = CupsRasterUnifiedReader::new(Pin::new(&mut input))
+            .await
+            .unwrap();
+        assert!(matches!(
+            reader.seek_to_page(3).await,
+            Err(CupsRasterError::PageOutOfRange { index: 3 })
+        ));
+    }
+}
+
+#[test]
+fn sync_word_bytes() {
+    use print_raster::model::cups::CupsSyncWord;
+
+    for sync_word in [
+        CupsSyncWord::V1BigEndian,
+        CupsSyncWord::V1LittleEndian,
+        CupsSyncWord::V2BigEndian,
+        CupsSyncWord::V2LittleEndian,
+        CupsSyncWord::V3BigEndian,
+        CupsSyncWord::V3LittleEndian,
+    ] {
+        assert_eq!(
+            CupsSyncWord::from_bytes(&sync_word.to_bytes()),
+            Some(sync_word)
+        );
+    }
+    assert_eq!(CupsSyncWord::V2BigEndian.to_bytes(), *b"RaS2");
+    assert_eq!(
+        CupsSyncWord::from_bytes(b"3SaR"),
+        Some(CupsSyncWord::V3LittleEndian)
+    );
+    assert_eq!(CupsSyncWord::from_bytes(b"UNIR"), None);
+}
+
+#[test]
+fn short_header_bytes() {
+    use print_raster::byteorder::LittleEndian;
+    use print_raster::error::CupsRasterError;
+    use print_raster::factory::{CupsPageFactoryV1, CupsPageFactoryV2, RasterPageFactory};
+
+    let content = [0u8; 100];
+    assert!(matches!(
+        CupsPageFactoryV1::<LittleEndian>::header_from_bytes(&content),
+        Err(CupsRasterError::ShortHeader {
+            expected: 420,
+            actual: 100
+        })
+    ));
+    assert!(matches!(
+        CupsPageFactoryV2::<LittleEndian>::header_from_bytes(&content),
+        Err(CupsRasterError::ShortHeader {
+            expected: 1796,
+            actual: 100
+        })
+    ));
+}
+
+#[test]
+fn header_approx_eq() {
+    let header = CupsPageHeaderV2 {
+        borderless_scaling_factor: f32::NAN,
+        ..CupsPageHeaderV2::builder().width(4).height(2).build()
+    };
+    let mut other = header.clone();
+    assert_ne!(header, other);
+    assert!(header.approx_eq(&other, 0.0));
+
+    other.page_size_f32.width += 0.001;
+    other.vendor_f32[3] = 0.001;
+    assert!(header.approx_eq(&other, 0.01));
+    assert!(!header.approx_eq(&other, 0.0001));
+
+    other.vendor_u32[0] = 1;
+    assert!(!header.approx_eq(&other, 0.01));
+}
+
+#[tokio::test]
+async fn next_page_header_only() {
+    use print_raster::{
+        decode::RasterDecoder, model::cups::CUPS_V2_PAGE_HEADER_SIZE, reader::RasterReaderExt,
+    };
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_inputs/pwg_sRGB.ras");
+    let data = std::fs::read(path).unwrap();
+    let mut input = data.as_slice();
+    let reader = CupsRasterUnifiedReader::new(Pin::new(&mut input))
+        .await
+        .unwrap();
+    let (header, mut page) = reader.next_page_header_only().await.unwrap().unwrap();
+    assert_eq!(&header, page.header());
+    assert_eq!(page.content_mut().encoded_bytes_read(), 0);
+    drop(page);
+    // only the sync word and the page header are consumed
+    assert_eq!(input.len(), data.len() - 4 - CUPS_V2_PAGE_HEADER_SIZE);
+}
+
+#[tokio::test]
+async fn banded_round_trip() {
+    use futures::AsyncWriteExt;
+    use print_raster::byteorder::BigEndian;
+    use print_raster::decode::ToChunkyDecoder;
+    use print_raster::factory::{CupsPageFactoryV2, RasterPageFactory};
+    use print_raster::model::cups::CupsSyncWord;
+    use print_raster::writer::{
+        cups::unified::CupsRasterUnifiedWriter, RasterPageWriter, RasterWriter,
+    };
+
+    for bytes_per_color in [1usize, 2] {
+        let header = CupsPageHeaderV2::builder()
+            .width(3)
+            .height(2)
+            .bits_per_color(8 * bytes_per_color as u32)
+            .color_order(CupsColorOrder::Banded)
+            .build();
+        assert!(header.validate().is_ok());
+        assert_eq!(header.v1.bytes_per_line as usize, 9 * bytes_per_color);
+        // a line of 3 RGB pixels, the second line repeats the first
+        let chunky: Vec<u8> = (0..9u8)
+            .flat_map(|color| vec![color; bytes_per_color])
+            .collect();
+        let mut banded = Vec::new();
+        for band in 0..3 {
+            for pixel in 0..3 {
+                let start = (pixel * 3 + band) * bytes_per_color;
+                banded.extend_from_slice(&chunky[start..start + bytes_per_color]);
+            }
+        }
+        let banded = [banded.clone(), banded].concat();
+
+        let mut data = Vec::<u8>::new();
+        let writer = CupsRasterUnifiedWriter::new(Pin::new(&mut data), CupsSyncWord::V2BigEndian)
+            .await
+            .unwrap();
+        let mut page = writer.next_page(&header).await.unwrap();
+        page.content_mut().write_all(&banded).await.unwrap();
+        page.finish().await.unwrap();
+
+        let mut input = data.as_slice();
+        let reader = CupsRasterUnifiedReader::new(Pin::new(&mut input))
+            .await
+            .unwrap();
+        let page = reader.next_page().await.unwrap().unwrap();
+        assert_eq!(page.header(), &header);
+        let mut decoder = ToChunkyDecoder::new(page.into_content(), &header).unwrap();
+        let mut content = Vec::new();
+        decoder.read_to_end(&mut content).await.unwrap();
+        assert_eq!(content, [chunky.clone(), chunky].concat());
+    }
+
+    // a line of 10 bytes can't hold 3 bands of the same size
+    let header = CupsPageHeaderV2::builder()
+        .width(3)
+        .height(2)
+        .bytes_per_line(10)
+        .color_order(CupsColorOrder::Banded)
+        .build();
+    assert!(header.validate().is_err());
+    let mut output = Vec::new();
+    assert!(CupsPageFactoryV2::<BigEndian>::encode(&header, Pin::new(&mut output)).is_err());
+}
+
+#[cfg(feature = "image")]
+#[tokio::test]
+async fn page_to_dynamic_image() {
+    use image::{DynamicImage, Rgb};
+    use print_raster::{
+        error::ImageConversionError,
+        model::RasterByteOrder,
+        reader::{AnyPageHeader, RasterImage, RasterPageReaderExt},
+    };
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_inputs/pwg_sRGB.ras");
+    let file = tokio::fs::File::open(path).await.unwrap();
+    let pinned_file_reader = pin!(BufReader::new(file.compat()));
+    let reader = CupsRasterUnifiedReader::new(pinned_file_reader)
+        .await
+        .unwrap();
+    let page = reader.next_page().await.unwrap().unwrap();
+    let (image, _) = page.read_image().await.unwrap();
+    let expected = ImageBuffer::<Rgb<u8>, Vec<u8>>::from_vec(
+        image.header.v1.width,
+        image.header.v1.height,
+        image.data.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        image.to_dynamic_image().unwrap(),
+        DynamicImage::ImageRgb8(expected)
+    );
+
+    // the padding at the end of each line is dropped
+    let header = CupsPageHeaderV2::builder()
+        .width(2)
+        .height(2)
+        .color_space(CupsColorSpace::sGray)
+        .bits_per_color(16)
+        .bytes_per_line(6)
+        .build();
+    let image = RasterImage {
+        header,
+        data: vec![
+            0x01, 0x02, 0x03, 0x04, 0xff, 0xff, 0x05, 0x06, 0x07, 0x08, 0xff, 0xff,
+        ],
+    };
+    assert!(matches!(
+        image.to_dynamic_image(),
+        Err(ImageConversionError::UnknownByteOrder(16))
+    ));
+    let gray = image
+        .to_dynamic_image_with_byte_order(RasterByteOrder::BigEndian)
+        .unwrap()
+        .into_luma16();
+    assert_eq!(gray.into_raw(), vec![0x0102, 0x0304, 0x0506, 0x0708]);
+    let image = RasterImage {
+        header: AnyPageHeader::from(image.header),
+        data: image.data,
+    };
+    assert!(matches!(
+        image.to_dynamic_image(),
+        Err(ImageConversionError::UnknownByteOrder(16))
+    ));
+    let gray = image
+        .to_dynamic_image_with_byte_order(RasterByteOrder::LittleEndian)
+        .unwrap()
+        .into_luma16();
+    assert_eq!(gray.into_raw(), vec![0x0201, 0x0403, 0x0605, 0x0807]);
+
+    let image = RasterImage {
+        header: CupsPageHeaderV2::builder()
+            .width(2)
+            .height(2)
+            .color_space(CupsColorSpace::CMYK)
+            .build(),
+        data: vec![0; 16],
+    };
+    assert!(matches!(
+        image.to_dynamic_image(),
+        Err(ImageConversionError::UnsupportedCupsColorSpace(
+            CupsColorSpace::CMYK
+        ))
+    ));
+}
+
+#[tokio::test]
+async fn kcmycm_layout() {
+    use futures::AsyncWriteExt;
+    use print_raster::error::CupsRasterError;
+    use print_raster::model::cups::CupsSyncWord;
+    use print_raster::writer::{
+        cups::unified::CupsRasterUnifiedWriter, RasterPageWriter, RasterWriter,
+    };
+
+    // 6 colors of 1 bit are padded to a pixel per byte, counted as 4 colors by CUPS
+    let header = CupsPageHeaderV2::builder()
+        .width(5)
+        .height(2)
+        .bits_per_color(1)
+        .color_space(CupsColorSpace::KCMYcm)
+        .build();
+    assert_eq!(header.num_colors(), 4);
+    assert_eq!(header.v1.bits_per_pixel, 8);
+    assert_eq!(header.v1.bytes_per_line, 5);
+    assert_eq!(header.v1.image_byte_len().unwrap(), 10);
+    header.validate().unwrap();
+
+    // banded and planar pixels hold a band or plane per color
+    let banded = CupsPageHeaderV2::builder()
+        .width(8)
+        .bits_per_color(1)
+        .color_space(CupsColorSpace::KCMYcm)
+        .color_order(CupsColorOrder::Banded)
+        .build();
+    assert_eq!(banded.num_colors(), 6);
+    assert_eq!(banded.v1.bits_per_pixel, 1);
+    assert_eq!(banded.v1.bytes_per_line, 6);
+    banded.validate().unwrap();
+    let planar = CupsPageHeaderV2::builder()
+        .width(8)
+        .height(3)
+        .bits_per_color(2)
+        .color_space(CupsColorSpace::KCMYcm)
+        .color_order(CupsColorOrder::Planar)
+        .build();
+    assert_eq!(planar.num_colors(), 6);
+    assert_eq!(planar.v1.bits_per_pixel, 2);
+    assert_eq!(planar.v1.bytes_per_line, 2);
+    assert_eq!(planar.v1.image_byte_len().unwrap(), 2 * 3 * 6);
+    planar.validate().unwrap();
+
+    // larger colors are taken as KCMY
+    let kcmy = CupsPageHeaderV2::builder()
+        .width(5)
+        .bits_per_color(2)
+        .color_space(CupsColorSpace::KCMYcm)
+        .build();
+    assert_eq!(kcmy.num_colors(), 4);
+    assert_eq!(kcmy.v1.bits_per_pixel, 8);
+    kcmy.validate().unwrap();
+
+    // pixels of 6 bits would straddle bytes
+    let mut packed = header.clone();
+    packed.v1.bits_per_pixel = 6;
+    packed.v1.bytes_per_line = 4;
+    assert!(matches!(
+        packed.validate(),
+        Err(CupsRasterError::UnsupportedLayout(_))
+    ));
+
+    let content: Vec<u8> = (0..10).map(|i| (i * 7) as u8 & 0x3f).collect();
+    let mut data = Vec::<u8>::new();
+    let writer = CupsRasterUnifiedWriter::new(Pin::new(&mut data), CupsSyncWord::V2BigEndian)
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&content).await.unwrap();
+    assert!(matches!(
+        page.next_page(&packed).await,
+        Err(CupsRasterError::UnsupportedLayout(_))
+    ));
+
+    let mut data = Vec::<u8>::new();
+    let writer = CupsRasterUnifiedWriter::new(Pin::new(&mut data), CupsSyncWord::V2BigEndian)
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&content).await.unwrap();
+    page.finish().await.unwrap();
+    let mut input = data.as_slice();
+    let reader = CupsRasterUnifiedReader::new(Pin::new(&mut input))
+        .await
+        .unwrap();
+    let mut page = reader.next_page().await.unwrap().unwrap();
+    assert_eq!(page.header(), &header);
+    let mut decoded = Vec::new();
+    page.content_mut().read_to_end(&mut decoded).await.unwrap();
+    assert_eq!(decoded, content);
+}
+
+#[tokio::test]
+async fn invalid_sync_word() {
+    assert_eq!(CupsSyncWord::V1LittleEndian.version(), 1);
+    assert_eq!(CupsSyncWord::V2BigEndian.version(), 2);
+    assert_eq!(CupsSyncWord::V3LittleEndian.version(), 3);
+
+    let data = b"RaSxsome other content";
+    let source = pin!(BufReader::new(&data[..]));
+    let err = CupsRasterUnifiedReader::new(source).await.err().unwrap();
+    assert!(matches!(err, CupsRasterError::InvalidSyncWord(bytes) if &bytes == b"RaSx"));
+    assert_eq!(err.to_string(), "Invalid sync word: 52 61 53 78 (\"RaSx\")");
+}
@@ -0,0 +1,222 @@
+#![cfg(feature = "testing")]
+
+use futures::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use print_raster::{
+    model::{
+        cups::CupsPageHeaderV2,
+        urf::{
+            UrfColorSpace, UrfDuplex, UrfHeader, UrfMediaPosition, UrfMediaType, UrfPageHeader,
+            UrfQuality,
+        },
+    },
+    reader::{
+        cups::unified::CupsRasterUnifiedReader, urf::UrfReader, RasterPageReader, RasterReader,
+    },
+    testing::{PartialReader, PartialWriter},
+    writer::{
+        cups::{CupsRasterWriterV2BE, CupsRasterWriterV3LE},
+        urf::UrfWriter,
+        RasterPageWriter, RasterWriter,
+    },
+};
+use std::{
+    path::Path,
+    pin::{pin, Pin},
+};
+
+/// Pixel data with both repeated and distinct lines and pixels, 3 bytes per pixel.
+fn test_pixels(width: usize, height: usize) -> Vec<u8> {
+    (0..width * height * 3)
+        .map(|i| {
+            let (y, x) = (i / (width * 3), i % (width * 3) / 3);
+            if y % 3 == 1 || x < width / 2 {
+                (y / 3) as u8
+            } else {
+                (i % 251) as u8
+            }
+        })
+        .collect()
+}
+
+async fn read_cups_pages<R>(reader: R) -> Vec<(CupsPageHeaderV2, Vec<u8>)>
+where
+    R: AsyncRead,
+{
+    let reader = pin!(reader);
+    let reader = CupsRasterUnifiedReader::new(reader).await.unwrap();
+    let mut pages = Vec::new();
+    let mut page_next = reader.next_page().await.unwrap();
+    while let Some(mut page) = page_next {
+        let mut data = Vec::new();
+        page.content_mut().read_to_end(&mut data).await.unwrap();
+        pages.push((page.header().clone(), data));
+        page_next = page.next_page().await.unwrap();
+    }
+    pages
+}
+
+async fn read_urf_pages<R>(reader: R) -> (UrfHeader, Vec<(UrfPageHeader, Vec<u8>)>)
+where
+    R: AsyncRead,
+{
+    let reader = pin!(reader);
+    let reader = UrfReader::new(reader).await.unwrap();
+    let header = reader.header().clone();
+    let mut pages = Vec::new();
+    let mut page_next = reader.next_page().await.unwrap();
+    while let Some(mut page) = page_next {
+        let mut data = Vec::new();
+        page.content_mut().read_to_end(&mut data).await.unwrap();
+        pages.push((page.header().clone(), data));
+        page_next = page.next_page().await.unwrap();
+    }
+    (header, pages)
+}
+
+async fn cups_srgb_pages() -> Vec<(CupsPageHeaderV2, Vec<u8>)> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_inputs/pwg_sRGB.ras");
+    let data = std::fs::read(path).unwrap();
+    let mut data = data.as_slice();
+    let reader = CupsRasterUnifiedReader::new(Pin::new(&mut data))
+        .await
+        .unwrap();
+    let page = reader.next_page().await.unwrap().unwrap();
+    let mut header = page.header().clone();
+    header.v1.width = 24;
+    header.v1.height = 10;
+    header.v1.bytes_per_line = 24 * 3;
+    vec![
+        (header.clone(), test_pixels(24, 10)),
+        (header, vec![0xff; 24 * 10 * 3]),
+    ]
+}
+
+#[tokio::test]
+async fn cups_v2_partial_round_trip() {
+    let pages = cups_srgb_pages().await;
+
+    let mut output = PartialWriter::new(Vec::<u8>::new());
+    let writer = CupsRasterWriterV2BE::new(Pin::new(&mut output))
+        .await
+        .unwrap();
+    let mut page_writer = writer.next_page(&pages[0].0).await.unwrap();
+    page_writer
+        .content_mut()
+        .write_all(&pages[0].1)
+        .await
+        .unwrap();
+    page_writer = page_writer.next_page(&pages[1].0).await.unwrap();
+    page_writer
+        .content_mut()
+        .write_all(&pages[1].1)
+        .await
+        .unwrap();
+    page_writer.finish().await.unwrap();
+    let output = output.into_inner();
+
+    assert_eq!(read_cups_pages(output.as_slice()).await, pages);
+    assert_eq!(
+        read_cups_pages(PartialReader::new(output.as_slice())).await,
+        pages
+    );
+}
+
+#[tokio::test]
+async fn cups_v3_partial_round_trip() {
+    let pages = cups_srgb_pages().await;
+
+    let mut output = PartialWriter::new(Vec::<u8>::new());
+    let writer = CupsRasterWriterV3LE::new(Pin::new(&mut output))
+        .await
+        .unwrap();
+    let mut page_writer = writer.next_page(&pages[0].0).await.unwrap();
+    page_writer
+        .content_mut()
+        .write_all(&pages[0].1)
+        .await
+        .unwrap();
+    page_writer.finish().await.unwrap();
+    let output = output.into_inner();
+
+    assert_eq!(
+        read_cups_pages(PartialReader::new(output.as_slice())).await,
+        pages[..1]
+    );
+}
+
+#[tokio::test]
+async fn urf_partial_round_trip() {
+    let header = UrfHeader { page_count: 2 };
+    let page_header = UrfPageHeader {
+        bits_per_pixel: 24,
+        color_space: UrfColorSpace::sRGB,
+        duplex: UrfDuplex::NoDuplex,
+        quality: UrfQuality::Default,
+        media_position: UrfMediaPosition::Auto,
+        media_type: UrfMediaType::Auto,
+        width: 24,
+        height: 10,
+        dot_per_inch: 300,
+    };
+    let pages = vec![
+        (page_header.clone(), test_pixels(24, 10)),
+        (page_header, vec![0x00; 24 * 10 * 3]),
+    ];
+
+    let mut output = PartialWriter::new(Vec::<u8>::new());
+    let writer = UrfWriter::new(Pin::new(&mut output), &header)
+        .await
+        .unwrap();
+    let mut page_writer = writer.next_page(&pages[0].0).await.unwrap();
+    page_writer
+        .content_mut()
+        .write_all(&pages[0].1)
+        .await
+        .unwrap();
+    page_writer = page_writer.next_page(&pages[1].0).await.unwrap();
+    page_writer
+        .content_mut()
+        .write_all(&pages[1].1)
+        .await
+        .unwrap();
+    page_writer.finish().await.unwrap();
+    let output = output.into_inner();
+
+    assert_eq!(
+        read_urf_pages(output.as_slice()).await,
+        (header.clone(), pages.clone())
+    );
+    assert_eq!(
+        read_urf_pages(PartialReader::new(output.as_slice())).await,
+        (header, pages)
+    );
+}
+
+#[tokio::test]
+async fn streams_equal() {
+    use print_raster::testing::assert_streams_equal;
+
+    let data = test_pixels(100, 20);
+    assert_eq!(
+        assert_streams_equal(data.as_slice(), PartialReader::new(data.as_slice())).await,
+        Ok(())
+    );
+
+    let mut changed = data.clone();
+    changed[5000] ^= 0xff;
+    let diff = assert_streams_equal(data.as_slice(), PartialReader::new(changed.as_slice()))
+        .await
+        .unwrap_err();
+    assert_eq!(diff.offset, 5000);
+    assert_eq!(diff.left, Some(data[5000]));
+    assert_eq!(diff.right, Some(changed[5000]));
+    assert_eq!(diff.before, data[4992..5000]);
+    assert_eq!(diff.left_after, data[5000..5008]);
+    assert!(diff.to_string().contains("offset 5000"));
+
+    let diff = assert_streams_equal(data.as_slice(), &data[..data.len() - 1])
+        .await
+        .unwrap_err();
+    assert_eq!(diff.offset, data.len() as u64 - 1);
+    assert_eq!(diff.right, None);
+}
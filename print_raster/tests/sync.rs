@@ -0,0 +1,48 @@
+#![cfg(feature = "sync")]
+
+use print_raster::reader::sync::{SyncCupsRasterReader, SyncUrfReader};
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+fn open(name: &str) -> BufReader<File> {
+    let path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("tests/test_inputs/{}.ras", name));
+    BufReader::new(File::open(path).unwrap())
+}
+
+#[test]
+fn sync_cups_v3_srgb() {
+    let reader = SyncCupsRasterReader::new(open("cups_v3_sRGB")).unwrap();
+    let mut page_count = 0;
+    let mut page_next = reader.next_page().unwrap();
+    while let Some(mut page) = page_next {
+        let mut data = Vec::new();
+        page.read_to_end(&mut data).unwrap();
+        assert_eq!(
+            data.len() as u64,
+            page.header().v1.bytes_per_line as u64 * page.header().v1.height as u64
+        );
+        page_next = page.next_page().unwrap();
+        page_count += 1;
+    }
+    assert!(page_count > 0);
+}
+
+#[test]
+fn sync_urf_sgray() {
+    let reader = SyncUrfReader::new(open("urf_sGray")).unwrap();
+    let page_count = reader.header().page_count;
+    let mut pages = 0;
+    let mut page_next = reader.next_page().unwrap();
+    while let Some(mut page) = page_next {
+        // only read a part of the content, the rest is skipped
+        let mut data = [0u8; 100];
+        page.read_exact(&mut data).unwrap();
+        page_next = page.next_page().unwrap();
+        pages += 1;
+    }
+    assert_eq!(pages, page_count);
+}
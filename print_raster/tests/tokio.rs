@@ -0,0 +1,82 @@
+#![cfg(feature = "tokio")]
+
+use futures::{AsyncReadExt, AsyncWriteExt};
+use print_raster::{
+    model::cups::{CupsPageHeaderV2, CupsSyncWord},
+    model::urf::{UrfColorSpace, UrfHeader, UrfPageHeader},
+    reader::{
+        tokio::{CupsRasterReader, UrfReader},
+        RasterPageReader, RasterReader,
+    },
+    writer::{
+        tokio::{CupsRasterWriter, UrfWriter},
+        RasterPageWriter, RasterWriter,
+    },
+};
+use std::path::Path;
+
+#[tokio::test]
+async fn tokio_cups_v3_srgb() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_inputs/cups_v3_sRGB.ras");
+    let file = tokio::fs::File::open(path).await.unwrap();
+    let reader = CupsRasterReader::new(file).await.unwrap();
+    assert_eq!(reader.sync_word(), CupsSyncWord::V3LittleEndian);
+    let mut page_count = 0;
+    let mut page_next = reader.next_page().await.unwrap();
+    while let Some(mut page) = page_next {
+        let mut data = Vec::new();
+        page.content_mut().read_to_end(&mut data).await.unwrap();
+        assert_eq!(
+            data.len() as u64,
+            page.header().v1.bytes_per_line as u64 * page.header().v1.height as u64
+        );
+        page_next = page.next_page().await.unwrap();
+        page_count += 1;
+    }
+    assert!(page_count > 0);
+}
+
+#[tokio::test]
+async fn tokio_cups_round_trip() {
+    let header = CupsPageHeaderV2::builder().width(4).height(2).build();
+    let content: Vec<u8> = (0..24).collect();
+    let mut data = Vec::<u8>::new();
+    let writer = CupsRasterWriter::new(&mut data, CupsSyncWord::V2LittleEndian)
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&content).await.unwrap();
+    page.finish().await.unwrap();
+
+    let reader = CupsRasterReader::new(data.as_slice()).await.unwrap();
+    assert_eq!(reader.sync_word(), CupsSyncWord::V2LittleEndian);
+    let mut page = reader.next_page().await.unwrap().unwrap();
+    let mut read = Vec::new();
+    page.content_mut().read_to_end(&mut read).await.unwrap();
+    assert_eq!(read, content);
+    assert!(page.next_page().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn tokio_urf_round_trip() {
+    let header = UrfPageHeader::builder()
+        .width(4)
+        .height(2)
+        .bits_per_pixel(8)
+        .color_space(UrfColorSpace::sGray)
+        .dot_per_inch(300)
+        .build()
+        .unwrap();
+    let content: Vec<u8> = (0..8).collect();
+    let mut data = Vec::<u8>::new();
+    let writer = UrfWriter::new(&mut data, &UrfHeader { page_count: 1 })
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&content).await.unwrap();
+    page.finish().await.unwrap();
+
+    let reader = UrfReader::new(data.as_slice()).await.unwrap();
+    assert_eq!(reader.header().page_count, 1);
+    assert_eq!(reader.read_page_headers().await.unwrap(), vec![header]);
+}
@@ -1,10 +1,16 @@
+mod common;
+
+use common::{urf_gray_header, write_urf_pages};
 use futures::{io::BufReader, AsyncReadExt};
 use image::{ImageBuffer, Luma};
 use print_raster::{
     model::urf::{UrfColorSpace, UrfDuplex, UrfQuality},
     reader::{urf::UrfReader, RasterPageReader, RasterReader},
 };
-use std::{path::Path, pin::pin};
+use std::{
+    path::Path,
+    pin::{pin, Pin},
+};
 use tokio_util::compat::TokioAsyncReadCompatExt;
 
 #[tokio::test]
@@ -29,8 +35,8 @@ async fn urf_single_sgray() {
         page.content_mut().read_to_end(&mut data).await.unwrap();
 
         let img = ImageBuffer::<Luma<u8>, Vec<u8>>::from_vec(
-            page.header().width as u32,
-            page.header().height as u32,
+            page.header().width,
+            page.header().height,
             data,
         )
         .unwrap();
@@ -49,3 +55,913 @@ async fn urf_single_sgray() {
         page_index += 1;
     }
 }
+
+#[tokio::test]
+async fn urf_new_buffered() {
+    let header = urf_gray_header(16, 16);
+    let pages: Vec<Vec<u8>> = (0..3u8).map(|i| vec![i * 40; 256]).collect();
+    let page_refs: Vec<&[u8]> = pages.iter().map(Vec::as_slice).collect();
+    let data = write_urf_pages(&header, &page_refs).await;
+
+    // a small buffer splits the block headers across fills
+    let mut input = BufReader::with_capacity(3, data.as_slice());
+    let reader = UrfReader::new_buffered(Pin::new(&mut input)).await.unwrap();
+    let mut decoded = Vec::new();
+    let mut page_next = reader.next_page().await.unwrap();
+    while let Some(mut page) = page_next {
+        assert_eq!(*page.header(), header);
+        let mut content = Vec::new();
+        page.content_mut().read_to_end(&mut content).await.unwrap();
+        decoded.push(content);
+        page_next = page.next_page().await.unwrap();
+    }
+    assert_eq!(decoded, pages);
+}
+
+#[tokio::test]
+async fn urf_total_stream_len_multi_page() {
+    use print_raster::reader::ReaderOptions;
+
+    let header = urf_gray_header(16, 16);
+    let pixels = (0..16 * 16).map(|x| x as u8).collect::<Vec<_>>();
+    let data = write_urf_pages(&header, &[&[0x00; 16 * 16], &pixels]).await;
+
+    let options = ReaderOptions {
+        total_stream_len: Some(data.len() as u64),
+        ..Default::default()
+    };
+    let input = pin!(data.as_slice());
+    let reader = UrfReader::new_with_options(input, options).await.unwrap();
+    let page = reader.next_page().await.unwrap().unwrap();
+    let mut page = page.next_page().await.unwrap().unwrap();
+    let mut content = Vec::new();
+    page.content_mut().read_to_end(&mut content).await.unwrap();
+    assert_eq!(content, pixels);
+    assert!(page.next_page().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn urf_expect_uniform_geometry() {
+    use futures::AsyncWriteExt;
+    use print_raster::{
+        error::UrfError,
+        model::urf::{UrfHeader, UrfPageHeader},
+        reader::ReaderOptions,
+        writer::{urf::UrfWriter, RasterPageWriter, RasterWriter},
+    };
+    use std::pin::Pin;
+
+    let header = urf_gray_header(16, 16);
+    let shorter = UrfPageHeader {
+        height: 8,
+        ..header.clone()
+    };
+    let mut data = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut data), &UrfHeader { page_count: 3 })
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut()
+        .write_all(&[0x00; 16 * 16])
+        .await
+        .unwrap();
+    let mut page = page.next_page(&header).await.unwrap();
+    page.content_mut()
+        .write_all(&[0x80; 16 * 16])
+        .await
+        .unwrap();
+    let mut page = page.next_page(&shorter).await.unwrap();
+    page.content_mut().write_all(&[0xff; 16 * 8]).await.unwrap();
+    page.finish().await.unwrap();
+
+    // mixed geometry is accepted by default
+    let input = pin!(data.as_slice());
+    let reader = UrfReader::new(input).await.unwrap();
+    let page = reader.next_page().await.unwrap().unwrap();
+    let page = page.next_page().await.unwrap().unwrap();
+    let page = page.next_page().await.unwrap().unwrap();
+    assert_eq!(page.header().height, 8);
+
+    let options = ReaderOptions {
+        expect_uniform_geometry: true,
+        ..Default::default()
+    };
+    let input = pin!(data.as_slice());
+    let reader = UrfReader::new_with_options(input, options).await.unwrap();
+    let page = reader.next_page().await.unwrap().unwrap();
+    let page = page.next_page().await.unwrap().unwrap();
+    match page.next_page().await {
+        Err(UrfError::GeometryChanged { expected, actual }) => {
+            assert_eq!(expected.height, 16);
+            assert_eq!(actual.height, 8);
+        }
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("geometry change is not detected"),
+    }
+}
+
+#[tokio::test]
+async fn urf_page_stream() {
+    use futures::StreamExt;
+
+    let header = urf_gray_header(16, 16);
+    let data = write_urf_pages(
+        &header,
+        &[&[0x00; 16 * 16], &[0x80; 16 * 16], &[0xff; 16 * 16]],
+    )
+    .await;
+
+    let input = pin!(data.as_slice());
+    let mut stream = UrfReader::new(input).await.unwrap().into_page_stream();
+    let mut contents = Vec::new();
+    let mut page_index = 0;
+    while let Some(page) = stream.next().await {
+        let mut page = page.unwrap();
+        // leave the content of the middle page unread
+        if page_index != 1 {
+            let mut content = Vec::new();
+            page.content_mut().read_to_end(&mut content).await.unwrap();
+            contents.push(content);
+        }
+        page_index += 1;
+    }
+    assert_eq!(page_index, 3);
+    assert_eq!(contents, [[0x00; 16 * 16], [0xff; 16 * 16]]);
+
+    // the previous page must be released before advancing
+    let input = pin!(data.as_slice());
+    let mut stream = UrfReader::new(input).await.unwrap().into_page_stream();
+    let page = stream.next().await.unwrap().unwrap();
+    assert!(stream.next().await.unwrap().is_err());
+    drop(page);
+    assert!(stream.next().await.unwrap().is_ok());
+
+    // taking the page out ends the stream
+    let input = pin!(data.as_slice());
+    let mut stream = UrfReader::new(input).await.unwrap().into_page_stream();
+    let mut page = stream.next().await.unwrap().unwrap().into_inner();
+    assert!(stream.next().await.is_none());
+    assert!(stream.next().await.is_none());
+    let mut content = Vec::new();
+    page.content_mut().read_to_end(&mut content).await.unwrap();
+    assert_eq!(content, [0x00; 16 * 16]);
+}
+
+#[tokio::test]
+async fn urf_read_expanded_copies() {
+    use futures::TryStreamExt;
+    use print_raster::reader::read_expanded_copies;
+
+    let header = urf_gray_header(4, 4);
+    let data = write_urf_pages(&header, &[&[1; 16], &[2; 16], &[3; 16]]).await;
+
+    for (num_copies, collate, expected) in [
+        (2, true, &[1, 2, 3, 1, 2, 3][..]),
+        (2, false, &[1, 1, 2, 2, 3, 3][..]),
+        (0, true, &[1, 2, 3][..]),
+    ] {
+        let input = pin!(data.as_slice());
+        let reader = UrfReader::new(input).await.unwrap();
+        let images = read_expanded_copies(reader, num_copies, collate)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let fills = images.iter().map(|image| image.data[0]).collect::<Vec<_>>();
+        assert_eq!(fills, expected);
+        assert!(images.iter().all(|image| image.header == header));
+    }
+}
+
+#[tokio::test]
+async fn urf_max_pages() {
+    use futures::AsyncWriteExt;
+    use print_raster::{
+        decode::Limits,
+        error::UrfError,
+        model::urf::UrfHeader,
+        writer::{urf::UrfWriter, RasterPageWriter, RasterWriter},
+    };
+    use std::pin::Pin;
+
+    let header = urf_gray_header(4, 4);
+    // the header understates the number of pages
+    let mut data = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut data), &UrfHeader { page_count: 2 })
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&[0x00; 16]).await.unwrap();
+    for _ in 0..2 {
+        page = page.next_page(&header).await.unwrap();
+        page.content_mut().write_all(&[0x00; 16]).await.unwrap();
+    }
+    page.finish().await.unwrap();
+
+    let limits = Limits {
+        max_pages: 2,
+        ..Default::default()
+    };
+    let input = pin!(data.as_slice());
+    let reader = UrfReader::new_with_limits(input, limits.clone())
+        .await
+        .unwrap();
+    let page = reader.next_page().await.unwrap().unwrap();
+    let page = page.next_page().await.unwrap().unwrap();
+    assert!(matches!(
+        page.next_page().await,
+        Err(UrfError::TooManyPages { max_pages: 2 })
+    ));
+    let input = pin!(data.as_slice());
+    let reader = UrfReader::new_with_limits(input, limits.clone())
+        .await
+        .unwrap();
+    assert!(matches!(
+        reader.read_page_headers().await,
+        Err(UrfError::TooManyPages { max_pages: 2 })
+    ));
+    let input = pin!(data.as_slice());
+    let reader = UrfReader::new(input).await.unwrap();
+    assert_eq!(reader.read_page_headers().await.unwrap(), vec![header; 3]);
+
+    // the declared page count is checked up front
+    let limits = Limits {
+        max_pages: 1,
+        ..Default::default()
+    };
+    let input = pin!(data.as_slice());
+    assert!(matches!(
+        UrfReader::new_with_limits(input, limits).await,
+        Err(UrfError::TooManyPages { max_pages: 1 })
+    ));
+}
+
+#[tokio::test]
+async fn urf_implausible_page_count() {
+    use print_raster::{error::UrfError, reader::ReaderOptions};
+
+    // a bare file header claiming a billion pages
+    let mut data = b"UNIRAST\0".to_vec();
+    data.extend_from_slice(&1_000_000_000u32.to_be_bytes());
+    data.extend_from_slice(&[0; 64]);
+
+    let options = ReaderOptions {
+        total_stream_len: Some(data.len() as u64),
+        ..Default::default()
+    };
+    let input = pin!(data.as_slice());
+    assert!(matches!(
+        UrfReader::new_with_options(input, options).await,
+        Err(UrfError::ImplausiblePageCount {
+            page_count: 1_000_000_000,
+            max_plausible: 2
+        })
+    ));
+
+    // a larger page header leaves room for fewer pages
+    let options = ReaderOptions {
+        total_stream_len: Some(data.len() as u64),
+        header_size_override: Some(64),
+        ..Default::default()
+    };
+    let input = pin!(data.as_slice());
+    assert!(matches!(
+        UrfReader::new_with_options(input, options).await,
+        Err(UrfError::ImplausiblePageCount {
+            page_count: 1_000_000_000,
+            max_plausible: 1
+        })
+    ));
+
+    // without a known length, the header alone is accepted
+    let input = pin!(data.as_slice());
+    assert!(UrfReader::new(input).await.is_ok());
+}
+
+#[cfg(feature = "metrics")]
+#[tokio::test]
+async fn urf_metrics() {
+    use futures::AsyncWriteExt;
+    use print_raster::{
+        error::UrfError,
+        metrics::RasterMetrics,
+        model::urf::UrfHeader,
+        reader::ReaderOptions,
+        writer::{urf::UrfWriter, RasterPageWriter, RasterWriter},
+    };
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Debug, Default)]
+    struct Counters {
+        pages_read: AtomicU64,
+        bytes_read: AtomicU64,
+        pages_written: AtomicU64,
+        bytes_written: AtomicU64,
+        errors: AtomicU64,
+        write_errors: AtomicU64,
+    }
+
+    impl RasterMetrics for Counters {
+        fn page_read(&self, encoded_bytes: u64) {
+            self.pages_read.fetch_add(1, Ordering::Relaxed);
+            self.bytes_read.fetch_add(encoded_bytes, Ordering::Relaxed);
+        }
+
+        fn page_written(&self, encoded_bytes: u64) {
+            self.pages_written.fetch_add(1, Ordering::Relaxed);
+            self.bytes_written
+                .fetch_add(encoded_bytes, Ordering::Relaxed);
+        }
+
+        fn read_error(&self, error: &(dyn std::error::Error + 'static)) {
+            assert!(error.is::<UrfError>());
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn write_error(&self, error: &(dyn std::error::Error + 'static)) {
+            assert!(error.is::<UrfError>());
+            self.write_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let header = urf_gray_header(4, 4);
+    let counters = Arc::new(Counters::default());
+    let mut data = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut data), &UrfHeader { page_count: 2 })
+        .await
+        .unwrap()
+        .with_metrics(counters.clone());
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&[0x00; 16]).await.unwrap();
+    page = page.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&[0x00; 16]).await.unwrap();
+    page.finish().await.unwrap();
+    assert_eq!(counters.pages_written.load(Ordering::Relaxed), 2);
+    let bytes_written = counters.bytes_written.load(Ordering::Relaxed);
+    assert_eq!(bytes_written, (data.len() - 12 - 2 * 32) as u64);
+    assert_eq!(counters.write_errors.load(Ordering::Relaxed), 0);
+
+    // incomplete pages are reported as errors rather than as written
+    let mut output = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut output), &UrfHeader { page_count: 2 })
+        .await
+        .unwrap()
+        .with_metrics(counters.clone());
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&[0x00; 8]).await.unwrap();
+    assert!(page.next_page(&header).await.is_err());
+    let writer = UrfWriter::new(Pin::new(&mut output), &UrfHeader { page_count: 1 })
+        .await
+        .unwrap()
+        .with_metrics(counters.clone());
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&[0x00; 8]).await.unwrap();
+    assert!(page.finish().await.is_err());
+    assert_eq!(counters.pages_written.load(Ordering::Relaxed), 2);
+    assert_eq!(counters.write_errors.load(Ordering::Relaxed), 2);
+
+    let options = ReaderOptions {
+        metrics: Some(counters.clone()),
+        ..Default::default()
+    };
+    let input = pin!(data.as_slice());
+    let reader = UrfReader::new_with_options(input, options.clone())
+        .await
+        .unwrap();
+    let mut page_next = reader.next_page().await.unwrap();
+    while let Some(page) = page_next {
+        page_next = page.next_page().await.unwrap();
+    }
+    assert_eq!(counters.pages_read.load(Ordering::Relaxed), 2);
+    assert_eq!(counters.bytes_read.load(Ordering::Relaxed), bytes_written);
+    assert_eq!(counters.errors.load(Ordering::Relaxed), 0);
+
+    // a truncated page is reported as an error
+    let input = pin!(&data[..data.len() - 1]);
+    let reader = UrfReader::new_with_options(input, options).await.unwrap();
+    let page = reader.next_page().await.unwrap().unwrap();
+    let page = page.next_page().await.unwrap().unwrap();
+    assert!(page.next_page().await.is_err());
+    assert_eq!(counters.errors.load(Ordering::Relaxed), 1);
+}
+
+#[tokio::test]
+async fn urf_max_dimensions() {
+    use print_raster::{decode::Limits, error::UrfError};
+
+    let data = write_urf_pages(&urf_gray_header(8, 2), &[&[0x00; 16]]).await;
+
+    let limits = Limits {
+        max_width: 8,
+        max_height: 2,
+        ..Default::default()
+    };
+    let input = pin!(data.as_slice());
+    let reader = UrfReader::new_with_limits(input, limits).await.unwrap();
+    assert!(reader.next_page().await.unwrap().is_some());
+
+    let limits = Limits {
+        max_width: 4,
+        ..Default::default()
+    };
+    let input = pin!(data.as_slice());
+    let reader = UrfReader::new_with_limits(input, limits).await.unwrap();
+    assert!(matches!(
+        reader.next_page().await,
+        Err(UrfError::DataTooLarge)
+    ));
+}
+
+#[test]
+fn color_space_from_cups() {
+    use print_raster::model::cups::CupsColorSpace;
+
+    for cs in [
+        UrfColorSpace::sGray,
+        UrfColorSpace::sRGB,
+        UrfColorSpace::CIELab,
+        UrfColorSpace::AdobeRGB,
+        UrfColorSpace::Gray,
+        UrfColorSpace::RGB,
+        UrfColorSpace::CMYK,
+    ] {
+        assert_eq!(
+            UrfColorSpace::try_from_cups(CupsColorSpace::from_urf(cs)),
+            Some(cs)
+        );
+    }
+    assert_eq!(UrfColorSpace::try_from_cups(CupsColorSpace::KCMYcm), None);
+    assert_eq!(UrfColorSpace::try_from_cups(CupsColorSpace::Device1), None);
+}
+
+#[test]
+fn header_from_cups() {
+    use print_raster::error::UrfError;
+    use print_raster::model::cups::{CupsColorSpace, CupsPageHeaderV2};
+    use print_raster::model::urf::UrfPageHeader;
+
+    let cups = CupsPageHeaderV2::builder()
+        .width(100)
+        .height(200)
+        .resolution(300, 300)
+        .build();
+    let urf = UrfPageHeader::try_from(&cups).unwrap();
+    assert_eq!(urf.width, 100);
+    assert_eq!(urf.height, 200);
+    assert_eq!(urf.bits_per_pixel, 24);
+    assert_eq!(urf.color_space, UrfColorSpace::sRGB);
+    assert_eq!(urf.dot_per_inch, 300);
+
+    let anisotropic = CupsPageHeaderV2::builder().resolution(300, 600).build();
+    assert!(matches!(
+        UrfPageHeader::try_from(&anisotropic),
+        Err(UrfError::AnisotropicResolution {
+            cross_feed: 300,
+            feed: 600
+        })
+    ));
+    let unsupported = CupsPageHeaderV2::builder()
+        .color_space(CupsColorSpace::KCMYcm)
+        .build();
+    assert!(matches!(
+        UrfPageHeader::try_from(&unsupported),
+        Err(UrfError::UnsupportedColorSpace(CupsColorSpace::KCMYcm))
+    ));
+    for (color_space, bits_per_color) in [(CupsColorSpace::sGray, 1), (CupsColorSpace::sRGB, 4)] {
+        let packed = CupsPageHeaderV2::builder()
+            .color_space(color_space)
+            .bits_per_color(bits_per_color)
+            .resolution(300, 300)
+            .build();
+        assert!(matches!(
+            UrfPageHeader::try_from(&packed),
+            Err(UrfError::InvalidBitsPerPixel { .. })
+        ));
+    }
+    let wide = CupsPageHeaderV2::builder()
+        .color_space(CupsColorSpace::sGray)
+        .bits_per_color(16)
+        .resolution(300, 300)
+        .build();
+    assert_eq!(UrfPageHeader::try_from(&wide).unwrap().bits_per_pixel, 16);
+}
+
+#[test]
+fn header_to_cups() {
+    use print_raster::model::cups::{CupsColorOrder, CupsColorSpace, CupsPageHeaderV2};
+    use print_raster::model::urf::UrfPageHeader;
+
+    let urf = UrfPageHeader::builder()
+        .width(101)
+        .height(200)
+        .bits_per_pixel(32)
+        .color_space(UrfColorSpace::CMYK)
+        .dot_per_inch(600)
+        .duplex(UrfDuplex::ShortSide)
+        .build()
+        .unwrap();
+    let cups = CupsPageHeaderV2::from(&urf);
+    assert_eq!(cups.v1.width, 101);
+    assert_eq!(cups.v1.height, 200);
+    assert_eq!(cups.v1.bits_per_color, 8);
+    assert_eq!(cups.v1.bits_per_pixel, 32);
+    assert_eq!(cups.v1.bytes_per_line, 404);
+    assert_eq!(cups.v1.color_order, CupsColorOrder::Chunky);
+    assert_eq!(cups.v1.color_space, CupsColorSpace::CMYK);
+    assert_eq!(cups.v1.resolution.cross_feed, 600);
+    assert_eq!(cups.v1.resolution.feed, 600);
+    assert!(cups.v1.duplex);
+    assert!(cups.v1.tumble);
+    cups.validate().unwrap();
+    assert_eq!(UrfPageHeader::try_from(&cups).unwrap().bits_per_pixel, 32);
+}
+
+#[tokio::test]
+async fn urf_skip_page() {
+    let pages: Vec<Vec<u8>> = (0..3u8)
+        .map(|i| (0..64u8).map(|x| x / (i + 3)).collect())
+        .collect();
+    let data = write_urf_pages(
+        &urf_gray_header(8, 8),
+        &pages.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+    )
+    .await;
+
+    let input = pin!(data.as_slice());
+    let reader = UrfReader::new(input).await.unwrap();
+    let mut page = reader.next_page().await.unwrap().unwrap();
+    // skip a partially read page and an unread one
+    let mut buf = [0u8; 5];
+    page.content_mut().read_exact(&mut buf).await.unwrap();
+    let page = page.skip_page().await.unwrap().unwrap();
+    let mut page = page.skip_page().await.unwrap().unwrap();
+    let mut content = Vec::new();
+    page.content_mut().read_to_end(&mut content).await.unwrap();
+    assert_eq!(content, pages[2]);
+    assert!(page.skip_page().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn urf_peek_content() {
+    use print_raster::reader::RasterPageReaderExt;
+
+    let content: Vec<u8> = (0..64u8).map(|x| x / 3).collect();
+    let data = write_urf_pages(&urf_gray_header(8, 8), &[&content, &content]).await;
+
+    let input = pin!(data.as_slice());
+    let reader = UrfReader::new(input).await.unwrap();
+    let mut page = reader.next_page().await.unwrap().unwrap();
+    assert_eq!(page.peek_content(10).await.unwrap(), content[..10]);
+    assert_eq!(page.peek_content(4).await.unwrap(), content[..4]);
+    let mut buf = [0u8; 6];
+    page.content_mut().read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, content[..6]);
+    assert_eq!(page.peek_content(20).await.unwrap(), content[6..26]);
+    let mut rest = Vec::new();
+    page.content_mut().read_to_end(&mut rest).await.unwrap();
+    assert_eq!(rest, content[6..]);
+
+    // the second page starts after the first, even though it was peeked
+    let mut page = page.next_page().await.unwrap().unwrap();
+    assert_eq!(page.peek_content(100).await.unwrap(), content);
+    assert!(page.next_page().await.unwrap().is_none());
+}
+
+#[test]
+fn urf_header_builder() {
+    use print_raster::{
+        error::UrfError,
+        model::urf::{UrfMediaPosition, UrfMediaType, UrfPageHeader},
+    };
+
+    let header = UrfPageHeader::builder()
+        .width(100)
+        .height(50)
+        .bits_per_pixel(24)
+        .color_space(UrfColorSpace::sRGB)
+        .dot_per_inch(300)
+        .build()
+        .unwrap();
+    assert_eq!(
+        header,
+        UrfPageHeader {
+            bits_per_pixel: 24,
+            color_space: UrfColorSpace::sRGB,
+            duplex: UrfDuplex::NoDuplex,
+            quality: UrfQuality::Normal,
+            media_position: UrfMediaPosition::Auto,
+            media_type: UrfMediaType::Auto,
+            width: 100,
+            height: 50,
+            dot_per_inch: 300,
+        }
+    );
+
+    let builder = UrfPageHeader::builder()
+        .width(100)
+        .height(50)
+        .color_space(UrfColorSpace::sGray)
+        .dot_per_inch(300);
+    assert!(matches!(
+        builder.clone().build(),
+        Err(UrfError::InvalidHeader(_))
+    ));
+    for (color_space, bits_per_pixel) in [
+        (UrfColorSpace::sGray, 12),
+        (UrfColorSpace::sGray, 24),
+        (UrfColorSpace::sRGB, 16),
+    ] {
+        assert!(matches!(
+            builder
+                .clone()
+                .color_space(color_space)
+                .bits_per_pixel(bits_per_pixel)
+                .build(),
+            Err(UrfError::InvalidBitsPerPixel { .. })
+        ));
+    }
+    builder.bits_per_pixel(16).build().unwrap();
+}
+
+#[tokio::test]
+async fn urf_count_pages() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_inputs/urf_sGray.ras");
+    let data = std::fs::read(path).unwrap();
+
+    let mut input = data.as_slice();
+    let (count, headers) = print_raster::reader::urf::count_pages(std::pin::Pin::new(&mut input))
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+    assert_eq!(headers.len(), 1);
+    assert_eq!(headers[0].color_space, UrfColorSpace::sGray);
+    assert!(input.is_empty());
+
+    let header = urf_gray_header(4, 2);
+    let mut data = write_urf_pages(
+        &header,
+        &[&[0x10; 8], &[0x20, 0x30, 0x40, 0x50, 0, 0, 0, 0]],
+    )
+    .await;
+    // a page of one pixel per line, then a fill to the end of the line
+    data.extend_from_within(12..12 + 32);
+    data.extend_from_slice(&[0x00, 0x00, 0x10, 0x80, 0x00, 0x00, 0x20, 0x80]);
+    let mut input = data.as_slice();
+    let (count, headers) = print_raster::reader::urf::count_pages(std::pin::Pin::new(&mut input))
+        .await
+        .unwrap();
+    assert_eq!(count, 3);
+    assert!(headers.iter().all(|counted| *counted == header));
+    assert!(input.is_empty());
+
+    let mut input = &data[..data.len() - 1];
+    let error = print_raster::reader::urf::count_pages(std::pin::Pin::new(&mut input))
+        .await
+        .unwrap_err();
+    assert!(error.is_unexpected_eof());
+}
+
+#[tokio::test]
+async fn urf_read_image() {
+    use print_raster::reader::RasterPageReaderExt;
+
+    let header = urf_gray_header(8, 8);
+    let pages: Vec<Vec<u8>> = (0..2u8)
+        .map(|i| (0..64u8).map(|x| x / (i + 3)).collect())
+        .collect();
+    let data = write_urf_pages(&header, &[&pages[0], &pages[1]]).await;
+
+    let input = pin!(data.as_slice());
+    let reader = UrfReader::new(input).await.unwrap();
+    let mut page_next = reader.next_page().await.unwrap();
+    let mut images = Vec::new();
+    while let Some(page) = page_next {
+        let (image, next) = page.read_image().await.unwrap();
+        images.push(image);
+        page_next = next.await.unwrap();
+    }
+    assert_eq!(images.len(), 2);
+    for (image, content) in images.iter().zip(&pages) {
+        assert_eq!(image.header, header);
+        assert_eq!(&image.data, content);
+    }
+}
+
+#[test]
+fn urf_image_byte_len() {
+    use print_raster::model::urf::UrfPageHeader;
+
+    let header = UrfPageHeader::builder()
+        .width(100)
+        .height(20)
+        .bits_per_pixel(24)
+        .color_space(UrfColorSpace::sRGB)
+        .dot_per_inch(300)
+        .build()
+        .unwrap();
+    assert_eq!(header.image_byte_len().unwrap(), 100 * 20 * 3);
+}
+
+#[tokio::test]
+async fn urf_page_count_not_authoritative() {
+    use futures::AsyncWriteExt;
+    use print_raster::{
+        model::urf::UrfHeader,
+        writer::{urf::UrfWriter, RasterPageWriter, RasterWriter},
+    };
+    use std::pin::Pin;
+
+    let header = urf_gray_header(4, 4);
+    let mut data = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut data), &UrfHeader { page_count: 0 })
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&[0x80; 16]).await.unwrap();
+    page = page.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&[0x40; 16]).await.unwrap();
+    page.finish().await.unwrap();
+
+    let mut input = data.as_slice();
+    let reader = UrfReader::new(Pin::new(&mut input)).await.unwrap();
+    assert_eq!(reader.declared_page_count(), 0);
+    let headers = reader.read_page_headers().await.unwrap();
+    assert_eq!(headers.len(), 2);
+}
+
+#[test]
+fn urf_short_header_bytes() {
+    use print_raster::error::UrfError;
+    use print_raster::factory::{RasterPageFactory, UrfPageFactory};
+
+    assert!(matches!(
+        UrfPageFactory::header_from_bytes(&[8, 0, 0, 0]),
+        Err(UrfError::ShortHeader {
+            expected: 32,
+            actual: 4
+        })
+    ));
+}
+
+#[tokio::test]
+async fn urf_truncated_header_is_unexpected_eof() {
+    use print_raster::error::UrfError;
+
+    let mut input = &b"UNIRAST\0\0\0"[..];
+    let error = UrfReader::new(std::pin::Pin::new(&mut input))
+        .await
+        .err()
+        .unwrap();
+    assert!(error.is_unexpected_eof());
+    assert!(!UrfError::InvalidMagic([0; 8]).is_unexpected_eof());
+}
+
+#[tokio::test]
+async fn urf_fill_byte_override() {
+    use futures::AsyncWriteExt;
+    use print_raster::{
+        model::urf::UrfHeader,
+        reader::ReaderOptions,
+        writer::{urf::UrfWriter, RasterPageWriterExt, RasterWriter},
+    };
+    use std::pin::Pin;
+
+    let header = urf_gray_header(4, 1);
+    let mut data = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut data), &UrfHeader { page_count: 1 })
+        .await
+        .unwrap();
+    let mut output = writer.next_page(&header).await.unwrap().abort();
+    // one pixel, then the rest of the line filled
+    output.write_all(&[0x00, 0x00, 0x10, 0x80]).await.unwrap();
+
+    for (fill_byte_override, expected) in [
+        (None, [0x10, 0xff, 0xff, 0xff]),
+        (Some(0x42), [0x10, 0x42, 0x42, 0x42]),
+    ] {
+        let options = ReaderOptions {
+            fill_byte_override,
+            ..Default::default()
+        };
+        let input = pin!(data.as_slice());
+        let reader = UrfReader::new_with_options(input, options).await.unwrap();
+        let mut page = reader.next_page().await.unwrap().unwrap();
+        let mut content = Vec::new();
+        page.content_mut().read_to_end(&mut content).await.unwrap();
+        assert_eq!(content, expected);
+    }
+}
+
+#[test]
+fn limits_builder() {
+    use print_raster::decode::Limits;
+
+    let limits = Limits::builder().max_bytes_per_page(1 << 20).build();
+    assert_eq!(limits.bytes_per_page, 1 << 20);
+    assert_eq!(limits.bytes_per_line, u64::MAX);
+    assert_eq!(limits.max_pages, u64::MAX);
+    assert!(limits.allows_dimensions(u32::MAX, u32::MAX));
+
+    let limits = Limits::builder()
+        .max_bytes_per_line(64)
+        .max_pages(2)
+        .max_width(16)
+        .max_height(8)
+        .build();
+    assert_eq!(limits.bytes_per_line, 64);
+    assert_eq!(limits.max_pages, 2);
+    assert!(limits.allows_dimensions(16, 8));
+    assert!(!limits.allows_dimensions(16, 9));
+}
+
+#[tokio::test]
+async fn read_content_limited() {
+    use print_raster::{decode::Limits, reader::RasterPageReaderExt};
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_inputs/urf_sGray.ras");
+    let file = tokio::fs::File::open(path).await.unwrap();
+    let pinned_file_reader = pin!(BufReader::new(file.compat()));
+    let reader = UrfReader::new_with_limits(pinned_file_reader, Limits::NO_LIMITS.clone())
+        .await
+        .unwrap();
+    let mut page = reader.next_page().await.unwrap().unwrap();
+    let len = page.header().width as usize * page.header().height as usize;
+
+    let err = page.read_content_limited(len - 1).await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    // nothing is read on failure
+    let data = page.read_content_limited(len).await.unwrap();
+    assert_eq!(data.len(), len);
+    assert!(page.read_content_limited(0).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn invalid_bits_per_pixel() {
+    use futures::AsyncWriteExt;
+    use print_raster::{
+        error::UrfError,
+        model::urf::{UrfHeader, UrfPageHeader},
+        writer::{urf::UrfWriter, RasterPageWriter, RasterWriter},
+    };
+    use std::pin::Pin;
+
+    let header = UrfPageHeader::builder()
+        .width(4)
+        .height(2)
+        .bits_per_pixel(8)
+        .color_space(UrfColorSpace::sGray)
+        .dot_per_inch(300)
+        .build()
+        .unwrap();
+    let mut data = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut data), &UrfHeader { page_count: 1 })
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&[0x80; 8]).await.unwrap();
+    let rgb = UrfPageHeader {
+        color_space: UrfColorSpace::sRGB,
+        ..header.clone()
+    };
+    assert!(matches!(
+        page.next_page(&rgb).await,
+        Err(UrfError::InvalidBitsPerPixel {
+            bits_per_pixel: 8,
+            color_space: UrfColorSpace::sRGB
+        })
+    ));
+
+    let mut data = write_urf_pages(&header, &[&[0x80; 8]]).await;
+    let mut source = data.as_slice();
+    let reader = UrfReader::new(Pin::new(&mut source)).await.unwrap();
+    assert!(reader.next_page().await.unwrap().is_some());
+
+    // the first byte of the page header, after the 12 bytes of the stream header
+    data[12] = 12;
+    let mut source = data.as_slice();
+    let reader = UrfReader::new(Pin::new(&mut source)).await.unwrap();
+    assert!(matches!(
+        reader.next_page().await,
+        Err(UrfError::InvalidBitsPerPixel {
+            bits_per_pixel: 12,
+            color_space: UrfColorSpace::sGray
+        })
+    ));
+}
+
+#[tokio::test]
+async fn invalid_magic() {
+    use print_raster::error::UrfError;
+
+    let mut input = &b"RaS3\x01\x02\x03\x04\0\0\0\x01"[..];
+    let error = UrfReader::new(std::pin::Pin::new(&mut input))
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(error, UrfError::InvalidMagic(magic) if &magic == b"RaS3\x01\x02\x03\x04"));
+    assert_eq!(
+        error.to_string(),
+        "Invalid magic: 52 61 53 33 01 02 03 04 (\"RaS3....\")"
+    );
+}
@@ -0,0 +1,714 @@
+mod common;
+
+use common::urf_gray_header;
+use futures::AsyncWriteExt;
+use print_raster::{
+    model::urf::UrfHeader,
+    writer::{urf::UrfWriter, RasterPageWriter, RasterWriter},
+};
+use std::pin::Pin;
+
+#[tokio::test]
+async fn urf_finish_with_stats() {
+    let mut output = Vec::<u8>::new();
+    let header = urf_gray_header(16, 4);
+    let writer = UrfWriter::new(Pin::new(&mut output), &UrfHeader { page_count: 2 })
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&[0xff; 16 * 4]).await.unwrap();
+    let mut page = page.next_page(&header).await.unwrap();
+    page.content_mut()
+        .write_all(&(0..16 * 4).map(|x| x as u8).collect::<Vec<_>>())
+        .await
+        .unwrap();
+    let (_, stats) = page.finish_with_stats().await.unwrap();
+    assert_eq!(stats.pages_written(), 2);
+    assert_eq!(
+        stats.bytes_written,
+        12 + 2 * 32 + stats.page_sizes.iter().sum::<u64>()
+    );
+    assert!(stats.page_sizes[0] < stats.page_sizes[1]);
+    assert_eq!(stats.bytes_written, output.len() as u64);
+}
+
+#[tokio::test]
+async fn urf_finish_with_stats_no_pages() {
+    let mut output = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut output), &UrfHeader { page_count: 0 })
+        .await
+        .unwrap();
+    let (_, stats) = writer.finish_with_stats().await.unwrap();
+    assert_eq!(stats.pages_written(), 0);
+    assert_eq!(stats.bytes_written, 12);
+}
+
+#[tokio::test]
+async fn urf_bounded_writer() {
+    use print_raster::writer::BoundedWriter;
+    use std::pin::pin;
+
+    let header = urf_gray_header(16, 4);
+    let pixels = (0..16 * 4).map(|x| x as u8).collect::<Vec<_>>();
+
+    // enough room for the file header, the page header and a few bytes of content
+    let output = pin!(BoundedWriter::new(Vec::<u8>::new(), 12 + 32 + 8));
+    let writer = UrfWriter::new(output, &UrfHeader { page_count: 1 })
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    assert!(page.content_mut().write_all(&pixels).await.is_err());
+
+    let mut output = pin!(BoundedWriter::new(Vec::<u8>::new(), 1024));
+    let writer = UrfWriter::new(output.as_mut(), &UrfHeader { page_count: 1 })
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&pixels).await.unwrap();
+    page.finish().await.unwrap();
+    assert_eq!(output.bytes_written(), output.get_ref().len() as u64);
+    assert!(output.bytes_written() <= 1024);
+}
+
+#[tokio::test]
+async fn urf_write_page() {
+    use print_raster::{
+        error::UrfError,
+        writer::{RasterPageWriterExt, RasterWriterExt},
+    };
+
+    let header = urf_gray_header(16, 4);
+    let mut expected = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut expected), &UrfHeader { page_count: 2 })
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&[0x11; 16 * 4]).await.unwrap();
+    let mut page = page.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&[0x22; 16 * 4]).await.unwrap();
+    page.finish().await.unwrap();
+
+    let mut output = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut output), &UrfHeader { page_count: 2 })
+        .await
+        .unwrap();
+    let page = writer.write_page(&header, &[0x11; 16 * 4]).await.unwrap();
+    let page = page.write_page(&header, &[0x22; 16 * 4]).await.unwrap();
+    page.finish().await.unwrap();
+    assert_eq!(output, expected);
+
+    // the page header is not written for content of the wrong size
+    let mut output = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut output), &UrfHeader { page_count: 2 })
+        .await
+        .unwrap();
+    assert!(matches!(
+        writer.write_page(&header, &[0x11; 16 * 3]).await,
+        Err(UrfError::IoError(e)) if e.kind() == std::io::ErrorKind::InvalidInput
+    ));
+    assert_eq!(output.len(), 12);
+
+    let mut output = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut output), &UrfHeader { page_count: 2 })
+        .await
+        .unwrap();
+    let page = writer.write_page(&header, &[0x11; 16 * 4]).await.unwrap();
+    assert!(matches!(
+        page.write_page(&header, &[0x22; 16 * 5]).await,
+        Err(UrfError::IoError(e)) if e.kind() == std::io::ErrorKind::InvalidInput
+    ));
+}
+
+#[tokio::test]
+async fn urf_write_from() {
+    use print_raster::writer::RasterPageWriterExt;
+
+    let header = urf_gray_header(16, 4);
+    let source: Vec<u8> = (0..16 * 4 * 2).map(|x| x as u8).collect();
+    let mut expected = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut expected), &UrfHeader { page_count: 2 })
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&source[..64]).await.unwrap();
+    let mut page = page.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&source[64..]).await.unwrap();
+    page.finish().await.unwrap();
+
+    // each page takes only its own bytes from the source
+    let mut output = Vec::<u8>::new();
+    let mut input = futures::io::Cursor::new(source.as_slice());
+    let writer = UrfWriter::new(Pin::new(&mut output), &UrfHeader { page_count: 2 })
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    assert_eq!(page.write_from(&mut input).await.unwrap(), 64);
+    let mut page = page.next_page(&header).await.unwrap();
+    assert_eq!(page.write_from(&mut input).await.unwrap(), 64);
+    page.finish().await.unwrap();
+    assert_eq!(output, expected);
+
+    let mut output = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut output), &UrfHeader { page_count: 1 })
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    assert!(page.write_from(&source[..10]).await.is_err());
+}
+
+#[test]
+fn urf_drive_to_completion() {
+    use futures::io::AllowStdIo;
+    use print_raster::writer::{drive_to_completion, RasterWriterExt};
+
+    let header = urf_gray_header(16, 4);
+    let mut output = AllowStdIo::new(std::io::Cursor::new(Vec::<u8>::new()));
+    drive_to_completion(async {
+        let writer = UrfWriter::new(Pin::new(&mut output), &UrfHeader { page_count: 1 }).await?;
+        let page = writer.write_page(&header, &[0x11; 16 * 4]).await?;
+        page.finish().await
+    })
+    .unwrap()
+    .unwrap();
+    assert!(output.into_inner().into_inner().starts_with(b"UNIRAST\0"));
+
+    // a future waiting for a wakeup can't be driven
+    assert!(drive_to_completion(futures::future::pending::<()>()).is_none());
+}
+
+#[tokio::test]
+async fn cups_unified_writer() {
+    use futures::AsyncReadExt;
+    use print_raster::model::cups::{CupsPageHeaderV2, CupsSyncWord};
+    use print_raster::reader::{
+        cups::unified::CupsRasterUnifiedReader, RasterPageReader, RasterReader,
+    };
+    use print_raster::writer::{cups::unified::CupsRasterUnifiedWriter, RasterPageWriterExt};
+
+    let header = CupsPageHeaderV2::builder()
+        .width(16)
+        .height(4)
+        .resolution(300, 300)
+        .build();
+    let pixels = (0..16 * 4 * 3).map(|x| (x / 8) as u8).collect::<Vec<_>>();
+    for sync_word in [
+        CupsSyncWord::V1BigEndian,
+        CupsSyncWord::V1LittleEndian,
+        CupsSyncWord::V2BigEndian,
+        CupsSyncWord::V2LittleEndian,
+        CupsSyncWord::V3BigEndian,
+        CupsSyncWord::V3LittleEndian,
+    ] {
+        let mut output = Vec::<u8>::new();
+        let writer = CupsRasterUnifiedWriter::new(Pin::new(&mut output), sync_word)
+            .await
+            .unwrap();
+        assert_eq!(writer.sync_word(), sync_word);
+        let mut page = writer.next_page(&header).await.unwrap();
+        page.content_mut().write_all(&pixels).await.unwrap();
+        let page = page.write_page(&header, &pixels).await.unwrap();
+        let (_, stats) = page.finish_with_stats().await.unwrap();
+        assert_eq!(stats.pages_written(), 2);
+        assert_eq!(stats.bytes_written, output.len() as u64);
+
+        let mut input = output.as_slice();
+        let reader = CupsRasterUnifiedReader::new(Pin::new(&mut input))
+            .await
+            .unwrap();
+        assert_eq!(reader.sync_word(), sync_word);
+        let mut page = reader.next_page().await.unwrap().unwrap();
+        let mut pages = 0;
+        loop {
+            assert_eq!(page.header().v1.width, 16);
+            let mut content = Vec::new();
+            page.content_mut().read_to_end(&mut content).await.unwrap();
+            assert_eq!(content, pixels);
+            pages += 1;
+            match page.next_page().await.unwrap() {
+                Some(next) => page = next,
+                None => break,
+            }
+        }
+        assert_eq!(pages, 2);
+    }
+}
+
+#[tokio::test]
+async fn urf_finish_with_count() {
+    use futures::io::Cursor;
+    use print_raster::reader::urf::UrfReader;
+
+    let header = urf_gray_header(16, 4);
+    let pixels = (0..16 * 4).map(|x| x as u8).collect::<Vec<_>>();
+    let mut output = Cursor::new(Vec::<u8>::new());
+    let writer = UrfWriter::new(Pin::new(&mut output), &UrfHeader { page_count: 0 })
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    assert_eq!(page.page_count(), 1);
+    page.content_mut().write_all(&pixels).await.unwrap();
+    let mut page = page.next_page(&header).await.unwrap();
+    assert_eq!(page.page_count(), 2);
+    page.content_mut().write_all(&pixels).await.unwrap();
+    page.finish_with_count().await.unwrap();
+
+    let output = output.into_inner();
+    assert_eq!(output[8..12], 2u32.to_be_bytes());
+    let mut input = output.as_slice();
+    let reader = UrfReader::new(Pin::new(&mut input)).await.unwrap();
+    assert_eq!(reader.header().page_count, 2);
+}
+
+#[tokio::test]
+async fn eop_marker() {
+    use common::{Compressed, ForwardingUrfPageFactory, EOP_MARKER};
+    use print_raster::{factory::UrfPageFactory, writer::common::CommonRasterPageWriter};
+
+    type MarkedUrfPageFactory = ForwardingUrfPageFactory<Compressed, true>;
+
+    let header = urf_gray_header(16, 4);
+    let mut plain = Vec::<u8>::new();
+    let mut page =
+        CommonRasterPageWriter::<UrfPageFactory, _>::writer_for(&header, Pin::new(&mut plain))
+            .await
+            .unwrap();
+    page.content_mut().write_all(&[0x11; 16 * 4]).await.unwrap();
+    let mut page = page.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&[0x22; 16 * 4]).await.unwrap();
+    let (_, plain_stats) = page.finish_with_stats().await.unwrap();
+
+    let mut marked = Vec::<u8>::new();
+    let mut page = CommonRasterPageWriter::<MarkedUrfPageFactory, _>::writer_for(
+        &header,
+        Pin::new(&mut marked),
+    )
+    .await
+    .unwrap();
+    page.content_mut().write_all(&[0x11; 16 * 4]).await.unwrap();
+    let mut page = page.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&[0x22; 16 * 4]).await.unwrap();
+    let (_, stats) = page.finish_with_stats().await.unwrap();
+    assert_eq!(stats.bytes_written, marked.len() as u64);
+    assert_eq!(stats.page_sizes, plain_stats.page_sizes);
+
+    // each page is followed by the marker
+    let first_page_len = 32 + plain_stats.page_sizes[0] as usize;
+    let mut expected = plain[..first_page_len].to_vec();
+    expected.extend_from_slice(EOP_MARKER);
+    expected.extend_from_slice(&plain[first_page_len..]);
+    expected.extend_from_slice(EOP_MARKER);
+    assert_eq!(marked, expected);
+}
+
+#[tokio::test]
+async fn cups_computed_bytes_per_line() {
+    use futures::AsyncReadExt;
+    use print_raster::encode::RasterEncoder;
+    use print_raster::model::cups::{CupsPageHeaderV2, CupsSyncWord};
+    use print_raster::reader::{
+        cups::unified::CupsRasterUnifiedReader, RasterPageReader, RasterReader,
+    };
+    use print_raster::writer::cups::unified::CupsRasterUnifiedWriter;
+
+    let mut header = CupsPageHeaderV2::builder().width(5).height(2).build();
+    header.v1.bytes_per_line = 0;
+    let pixels = (0..5 * 2 * 3).map(|x| x as u8).collect::<Vec<_>>();
+    for sync_word in [
+        CupsSyncWord::V1BigEndian,
+        CupsSyncWord::V2LittleEndian,
+        CupsSyncWord::V3BigEndian,
+    ] {
+        let mut output = Vec::<u8>::new();
+        let writer = CupsRasterUnifiedWriter::new(Pin::new(&mut output), sync_word)
+            .await
+            .unwrap();
+        let mut page = writer.next_page(&header).await.unwrap();
+        assert_eq!(page.content_mut().bytes_total(), pixels.len() as u64);
+        page.content_mut().write_all(&pixels).await.unwrap();
+        page.finish().await.unwrap();
+
+        let mut input = output.as_slice();
+        let reader = CupsRasterUnifiedReader::new(Pin::new(&mut input))
+            .await
+            .unwrap();
+        let mut page = reader.next_page().await.unwrap().unwrap();
+        assert_eq!(page.header().v1.bytes_per_line, 15);
+        let mut content = Vec::new();
+        page.content_mut().read_to_end(&mut content).await.unwrap();
+        assert_eq!(content, pixels);
+    }
+}
+
+#[tokio::test]
+async fn cups_writer_compression() {
+    use futures::AsyncReadExt;
+    use print_raster::encode::RasterEncoder;
+    use print_raster::model::cups::{CupsPageHeaderV2, CupsSyncWord};
+    use print_raster::model::RasterByteOrder;
+    use print_raster::reader::{
+        cups::unified::CupsRasterUnifiedReader, RasterPageReader, RasterReader,
+    };
+    use print_raster::writer::cups::unified::CupsRasterUnifiedWriter;
+
+    let mut header = CupsPageHeaderV2::builder().width(16).height(4).build();
+    // set from the chosen compression instead
+    header.v1.cups_compression = 7;
+    let pixels = [0u8; 16 * 4 * 3];
+    let mut sizes = Vec::new();
+    for compressed in [true, false] {
+        let mut output = Vec::<u8>::new();
+        let writer = CupsRasterUnifiedWriter::new_with_compression(
+            Pin::new(&mut output),
+            RasterByteOrder::LittleEndian,
+            compressed,
+        )
+        .await
+        .unwrap();
+        assert_eq!(writer.sync_word().is_compressed(), compressed);
+        let mut page = writer.next_page(&header).await.unwrap();
+        page.content_mut().write_all(&pixels).await.unwrap();
+        assert_eq!(page.content_mut().bytes_remaining(), 0);
+        let mut page = page.next_page(&header).await.unwrap();
+        page.content_mut().write_all(&pixels).await.unwrap();
+        page.finish().await.unwrap();
+        sizes.push(output.len());
+
+        let expected = if compressed { 1 } else { 0 };
+        let mut input = output.as_slice();
+        let reader = CupsRasterUnifiedReader::new(Pin::new(&mut input))
+            .await
+            .unwrap();
+        let mut page_count = 0;
+        let mut page_next = reader.next_page().await.unwrap();
+        while let Some(mut page) = page_next {
+            assert_eq!(page.header().v1.cups_compression, expected);
+            let mut content = Vec::new();
+            page.content_mut().read_to_end(&mut content).await.unwrap();
+            assert_eq!(content, pixels);
+            page_count += 1;
+            page_next = page.next_page().await.unwrap();
+        }
+        assert_eq!(page_count, 2);
+    }
+    assert!(sizes[0] < sizes[1]);
+    assert_eq!(
+        CupsSyncWord::with_compression(RasterByteOrder::BigEndian, false),
+        CupsSyncWord::V3BigEndian
+    );
+
+    // other writers write the field as given
+    let mut output = Vec::<u8>::new();
+    let writer = CupsRasterUnifiedWriter::new(Pin::new(&mut output), CupsSyncWord::V3LittleEndian)
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&pixels).await.unwrap();
+    page.finish().await.unwrap();
+    assert_eq!(output[4 + 404..4 + 408], 7u32.to_le_bytes());
+}
+
+#[tokio::test]
+async fn cups_unified_writer_v1_round_trip() {
+    use print_raster::model::cups::{CupsPageHeaderV2, CupsSyncWord};
+    use print_raster::reader::{
+        cups::unified::CupsRasterUnifiedReader, RasterPageReader, RasterReader,
+    };
+    use print_raster::writer::cups::unified::CupsRasterUnifiedWriter;
+
+    let mut header = CupsPageHeaderV2::builder().width(4).height(2).build();
+    header.page_size_name = "iso_a4_210x297mm".to_string();
+    header.vendor_u32[0] = 42;
+    let mut output = Vec::<u8>::new();
+    let writer = CupsRasterUnifiedWriter::new(Pin::new(&mut output), CupsSyncWord::V1LittleEndian)
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&[0; 24]).await.unwrap();
+    page.finish().await.unwrap();
+    // the sync word, the V1 page header and the uncompressed content
+    assert_eq!(output.len(), 4 + 420 + 24);
+
+    let mut input = output.as_slice();
+    let reader = CupsRasterUnifiedReader::new(Pin::new(&mut input))
+        .await
+        .unwrap();
+    let page = reader.next_page().await.unwrap().unwrap();
+    assert_eq!(page.header(), &CupsPageHeaderV2::from(header.into_v1()));
+}
+
+#[tokio::test]
+async fn cups_unified_round_trip_keeps_encoding() {
+    use print_raster::reader::{
+        cups::unified::CupsRasterUnifiedReader, RasterPageReader, RasterReader,
+    };
+    use print_raster::writer::cups::unified::CupsRasterUnifiedWriter;
+    use std::path::Path;
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test_inputs/cups_v3_sRGB.ras");
+    let data = std::fs::read(path).unwrap();
+    let mut input = data.as_slice();
+    let reader = CupsRasterUnifiedReader::new(Pin::new(&mut input))
+        .await
+        .unwrap();
+    assert!(!reader.sync_word().is_compressed());
+
+    let mut output = Vec::<u8>::new();
+    let mut writer = Some(
+        CupsRasterUnifiedWriter::new(Pin::new(&mut output), reader.sync_word())
+            .await
+            .unwrap(),
+    );
+    let mut page_writer = None;
+    let mut page_next = reader.next_page().await.unwrap();
+    while let Some(mut page) = page_next {
+        let mut target = match page_writer.take() {
+            None => writer.take().unwrap().next_page(page.header()).await,
+            Some(target) => RasterPageWriter::next_page(target, page.header()).await,
+        }
+        .unwrap();
+        futures::io::copy(page.content_mut(), target.content_mut())
+            .await
+            .unwrap();
+        page_writer = Some(target);
+        page_next = page.next_page().await.unwrap();
+    }
+    page_writer.unwrap().finish().await.unwrap();
+    assert_eq!(output, data);
+}
+
+#[tokio::test]
+async fn incomplete_page() {
+    use print_raster::error::UrfError;
+
+    let header = urf_gray_header(4, 4);
+    let mut data = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut data), &UrfHeader { page_count: 2 })
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&[0; 10]).await.unwrap();
+    assert!(matches!(
+        page.next_page(&header).await,
+        Err(UrfError::IncompletePage { bytes_remaining: 6 })
+    ));
+
+    let mut data = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut data), &UrfHeader { page_count: 1 })
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    page.content_mut().write_all(&[0; 4]).await.unwrap();
+    assert!(matches!(
+        page.finish().await,
+        Err(UrfError::IncompletePage {
+            bytes_remaining: 12
+        })
+    ));
+}
+
+#[tokio::test]
+async fn write_row() {
+    use print_raster::encode::RasterEncoder;
+    use print_raster::error::{CupsRasterError, UrfError};
+    use print_raster::model::cups::{CupsPageHeaderV2, CupsSyncWord};
+    use print_raster::writer::cups::unified::CupsRasterUnifiedWriter;
+    use print_raster::writer::RasterPageWriterExt;
+
+    let header = urf_gray_header(4, 2);
+    let mut data = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut data), &UrfHeader { page_count: 1 })
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    assert_eq!(page.content_mut().bytes_per_line(), 4);
+    assert!(matches!(
+        page.write_row(&[0; 5]).await,
+        Err(UrfError::IoError(e)) if e.kind() == std::io::ErrorKind::InvalidInput
+    ));
+    page.write_row(&[1; 4]).await.unwrap();
+    page.write_row(&[2; 4]).await.unwrap();
+    assert!(page.write_row(&[3; 4]).await.is_err());
+    page.finish().await.unwrap();
+
+    // uncompressed content of 2 RGB pixels per line
+    let header = CupsPageHeaderV2::builder().width(2).height(2).build();
+    let mut data = Vec::<u8>::new();
+    let writer = CupsRasterUnifiedWriter::new(Pin::new(&mut data), CupsSyncWord::V3LittleEndian)
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    assert_eq!(page.content_mut().bytes_per_line(), 6);
+    page.write_row(&[0; 6]).await.unwrap();
+    assert!(matches!(
+        page.write_row(&[0; 4]).await,
+        Err(CupsRasterError::IoError(_))
+    ));
+    page.write_row(&[0; 6]).await.unwrap();
+    page.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn write_row_unknown_line_size() {
+    use common::{ForwardingUrfPageFactory, Uncompressed};
+    use print_raster::{
+        encode::RasterEncoder,
+        error::UrfError,
+        writer::{common::CommonRasterPageWriter, RasterPageWriterExt},
+    };
+
+    type RawUrfPageFactory = ForwardingUrfPageFactory<Uncompressed, false>;
+
+    let header = urf_gray_header(4, 2);
+    let mut data = Vec::<u8>::new();
+    let mut page =
+        CommonRasterPageWriter::<RawUrfPageFactory, _>::writer_for(&header, Pin::new(&mut data))
+            .await
+            .unwrap();
+    assert_eq!(page.content_mut().bytes_per_line(), 0);
+    assert!(matches!(
+        page.write_row(&[0; 4]).await,
+        Err(UrfError::IoError(e)) if e.kind() == std::io::ErrorKind::InvalidInput
+    ));
+    page.content_mut().write_all(&[0; 8]).await.unwrap();
+    page.finish().await.unwrap();
+}
+
+#[tokio::test]
+async fn abort_page() {
+    use print_raster::writer::RasterPageWriterExt;
+
+    let header = urf_gray_header(4, 4);
+    let mut data = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut data), &UrfHeader { page_count: 1 })
+        .await
+        .unwrap();
+    let mut page = writer.next_page(&header).await.unwrap();
+    // the first line is buffered by the encoder, waiting for repetitions
+    page.content_mut().write_all(&[0; 4]).await.unwrap();
+    let output = page.abort();
+    assert_eq!(output.len(), 12 + 32);
+}
+
+#[tokio::test]
+async fn cups_transcode_passthrough() {
+    use print_raster::model::cups::CupsSyncWord;
+    use print_raster::reader::{
+        cups::unified::CupsRasterUnifiedReader, RasterPageReader, RasterReader,
+    };
+    use print_raster::writer::cups::unified::CupsRasterUnifiedWriter;
+    use std::path::Path;
+
+    async fn pages(mut data: &[u8]) -> Vec<Vec<u8>> {
+        let reader = CupsRasterUnifiedReader::new(Pin::new(&mut data))
+            .await
+            .unwrap();
+        let mut pages = Vec::new();
+        let mut page_next = reader.next_page().await.unwrap();
+        while let Some(mut page) = page_next {
+            let mut content = Vec::new();
+            futures::io::copy(page.content_mut(), &mut content)
+                .await
+                .unwrap();
+            pages.push(content);
+            page_next = page.next_page().await.unwrap();
+        }
+        pages
+    }
+
+    for (name, other) in [
+        ("pwg_sRGB.ras", CupsSyncWord::V3BigEndian),
+        ("cups_v3_sRGB.ras", CupsSyncWord::V2LittleEndian),
+    ] {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/test_inputs")
+            .join(name);
+        let data = std::fs::read(path).unwrap();
+        let sync_word = CupsSyncWord::from_bytes(&data[..4].try_into().unwrap()).unwrap();
+        for target in [sync_word, other] {
+            let mut input = data.as_slice();
+            let reader = CupsRasterUnifiedReader::new(Pin::new(&mut input))
+                .await
+                .unwrap();
+            let mut output = Vec::<u8>::new();
+            let writer = CupsRasterUnifiedWriter::new(Pin::new(&mut output), target)
+                .await
+                .unwrap();
+            reader.transcode_passthrough(writer).await.unwrap();
+            if target == sync_word {
+                assert_eq!(output, data);
+            } else {
+                assert_eq!(pages(&output).await, pages(&data).await);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+#[tokio::test]
+async fn write_image_round_trip() {
+    use futures::io::BufReader;
+    use image::{DynamicImage, GrayImage, RgbImage, RgbaImage};
+    use print_raster::{
+        error::{ImageConversionError, UrfError},
+        model::cups::CupsSyncWord,
+        reader::{
+            cups::unified::CupsRasterUnifiedReader, urf::UrfReader, RasterPageReader,
+            RasterPageReaderExt, RasterReader,
+        },
+        writer::{cups::unified::CupsRasterUnifiedWriter, RasterPageWriterExt, RasterWriterExt},
+    };
+    use std::pin::pin;
+
+    let rgb = DynamicImage::ImageRgb8(RgbImage::from_fn(5, 3, |x, y| {
+        image::Rgb([x as u8 * 40, y as u8 * 80, 0x80])
+    }));
+    let gray = DynamicImage::ImageLuma8(GrayImage::from_fn(7, 2, |x, y| {
+        image::Luma([(x * 30 + y) as u8])
+    }));
+
+    let mut output = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut output), &UrfHeader { page_count: 2 })
+        .await
+        .unwrap();
+    let page = writer.write_image(&rgb, 300).await.unwrap();
+    let page = page.write_image(&gray, 600).await.unwrap();
+    page.finish().await.unwrap();
+
+    let source = pin!(BufReader::new(&output[..]));
+    let reader = UrfReader::new(source).await.unwrap();
+    let page = reader.next_page().await.unwrap().unwrap();
+    assert_eq!(page.header().dot_per_inch, 300);
+    let (image, next) = page.read_image().await.unwrap();
+    assert_eq!(image.to_dynamic_image().unwrap(), rgb);
+    let (image, next) = next.await.unwrap().unwrap().read_image().await.unwrap();
+    assert_eq!(image.header.dot_per_inch, 600);
+    assert_eq!(image.to_dynamic_image().unwrap(), gray);
+    assert!(next.await.unwrap().is_none());
+
+    let mut output = Vec::<u8>::new();
+    let writer = CupsRasterUnifiedWriter::new(Pin::new(&mut output), CupsSyncWord::V2BigEndian)
+        .await
+        .unwrap();
+    let page = writer.write_image(&rgb, 300).await.unwrap();
+    page.finish().await.unwrap();
+
+    let source = pin!(BufReader::new(&output[..]));
+    let reader = CupsRasterUnifiedReader::new(source).await.unwrap();
+    let page = reader.next_page().await.unwrap().unwrap();
+    // 5 pixels at 300 dpi are 1.2 points
+    assert_eq!(page.header().v1.page_size.width, 1);
+    let (image, _) = page.read_image().await.unwrap();
+    assert_eq!(image.to_dynamic_image().unwrap(), rgb);
+
+    let rgba = DynamicImage::ImageRgba8(RgbaImage::new(2, 2));
+    let mut output = Vec::<u8>::new();
+    let writer = UrfWriter::new(Pin::new(&mut output), &UrfHeader { page_count: 1 })
+        .await
+        .unwrap();
+    assert!(matches!(
+        writer.write_image(&rgba, 300).await,
+        Err(UrfError::ImageConversion(
+            ImageConversionError::UnsupportedColorType(image::ColorType::Rgba8)
+        ))
+    ));
+}
@@ -34,6 +34,7 @@ fn main() {
                     input.bytes_per_line,
                     input.data.len() as u64,
                     0,
+                    false,
                 )
                 .unwrap();
                 let mut decoded = Vec::<u8>::new();
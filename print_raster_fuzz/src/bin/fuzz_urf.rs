@@ -9,6 +9,9 @@ fn main() {
     const LIMITS: Limits = Limits {
         bytes_per_line: 8000 * 3,
         bytes_per_page: 8000 * 8000 * 3,
+        max_pages: 1000,
+        max_width: 1 << 16,
+        max_height: 1 << 16,
     };
 
     loop {